@@ -0,0 +1,68 @@
+//! Compares the fast-path parser with `IpNet::from_str`.
+//!
+//! Run with `cargo bench --features fast-parse`.
+
+#[path = "../src/fastparse.rs"]
+#[allow(unused_imports)]
+mod fastparse;
+
+use ipnet::IpNet;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const LINES: usize = 1_000_000;
+const ROUNDS: usize = 5;
+
+/// Makes a list that looks like a real feed: mostly IPv4 /24s and shorter,
+/// with some IPv6 mixed in. The generator is seeded so every run parses the
+/// same thing. It's one big buffer, like an input file would be.
+fn sample() -> String {
+    let mut state: u64 = 0x5eed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 32) as u32
+    };
+    (0..LINES)
+        .map(|i| {
+            let bits = next();
+            if i % 8 == 0 {
+                format!("2001:db8:{:x}:{:x}::/64", bits >> 16, bits & 0xffff)
+            } else {
+                let prefix = 16 + bits % 17;
+                let net = ipnet::Ipv4Net::new(bits.into(), prefix as u8).unwrap();
+                net.trunc().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the best time of several rounds of parsing every line.
+fn time<F: Fn(&str) -> Option<IpNet>>(lines: &[&str], parse: F) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let started = Instant::now();
+            for line in lines {
+                black_box(parse(black_box(line)));
+            }
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let sample = sample();
+    let lines: Vec<&str> = sample.lines().collect();
+    let slow = time(&lines, |line| line.parse().ok());
+    let fast = time(&lines, fastparse::parse);
+    let per_line = |duration: Duration| duration.as_nanos() as f64 / LINES as f64;
+    println!("IpNet::from_str   {:6.1} ns/line", per_line(slow));
+    println!("fastparse::parse  {:6.1} ns/line", per_line(fast));
+    println!(
+        "speedup           {:6.2}x",
+        slow.as_secs_f64() / fast.as_secs_f64()
+    );
+}
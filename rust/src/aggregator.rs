@@ -0,0 +1,1118 @@
+//! Aggregation with the knobs exposed.
+//!
+//! [`gather`](crate::gather) does one thing: the smallest list of networks
+//! that covers exactly the input. Sometimes that's too much. A router that
+//! can't take anything shorter than a /16 needs merging to stop there, and a
+//! list that's going to be diffed against the original may need to keep
+//! subnets it already had. [`AggregatorBuilder`] collects those choices and
+//! builds an [`Aggregator`] that applies them.
+//!
+//! [`AggregatorBuilder::on_event`] also reports each step of the way, as an
+//! [`Event`], for anything that needs to explain where a network went.
+
+use crate::source::Record;
+use crate::space::{self, Family};
+use crate::{Error, Parser, Result};
+use ipnet::IpNet;
+use std::borrow::Borrow;
+use std::fmt;
+use std::sync::Arc;
+
+/// A prefix length for each family, if one was set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct PerFamily {
+    v4: Option<u8>,
+    v6: Option<u8>,
+}
+
+impl PerFamily {
+    fn get(&self, family: Family) -> Option<u8> {
+        match family {
+            Family::V4 => self.v4,
+            Family::V6 => self.v6,
+        }
+    }
+
+    fn set(&mut self, family: Family, len: u8) {
+        match family {
+            Family::V4 => self.v4 = Some(len),
+            Family::V6 => self.v6 = Some(len),
+        }
+    }
+}
+
+/// Something aggregation did to the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A network was dropped because it was already in the list. Networks
+    /// are compared with their host bits cleared.
+    Duplicate(IpNet),
+    /// A network was dropped because another network in the list contains
+    /// it.
+    Absorbed { net: IpNet, into: IpNet },
+    /// Two halves of a network were replaced by the network.
+    Merged { halves: [IpNet; 2], into: IpNet },
+    /// Space that wasn't in the list was added, so that networks around it
+    /// could be replaced by `into`. Only the
+    /// [merge slack](AggregatorBuilder::merge_slack) does this.
+    Filled { gap: IpNet, into: IpNet },
+}
+
+/// What to do with IPv4-mapped IPv6 networks, like `::ffff:192.0.2.0/120`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mapped {
+    /// Leave them as IPv6.
+    #[default]
+    Keep,
+    /// Turn them into the IPv4 networks they stand for, so they merge with
+    /// the same space written as IPv4.
+    ToV4,
+    /// Turn every IPv4 network into its mapped IPv6 form instead.
+    ToV6,
+}
+
+/// What to do with the IPv4 space inside 6to4 and Teredo networks. See
+/// [`space::embedded_v4`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Embedded {
+    /// Leave them as they are.
+    #[default]
+    Ignore,
+    /// Add the embedded IPv4 network alongside the IPv6 one.
+    Also,
+    /// Replace the IPv6 network with the embedded IPv4 network.
+    Instead,
+}
+
+/// A function that's called with each [`Event`].
+#[derive(Clone)]
+struct Callback(Arc<dyn Fn(&Event) + Send + Sync>);
+
+impl fmt::Debug for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+/// Configures an [`Aggregator`].
+///
+/// The defaults aggregate the same way as [`gather`](crate::gather).
+///
+/// ```
+/// use ipnet::IpNet;
+/// use lfc::AggregatorBuilder;
+/// use lfc::space::Family;
+///
+/// let aggregator = AggregatorBuilder::new()
+///     .boundary(Family::V4, 24)
+///     .ipv6(false)
+///     .build()
+///     .unwrap();
+/// let nets: Vec<IpNet> = ["10.0.0.0/25", "10.0.0.128/25", "10.0.1.0/24", "2001:db8::/32"]
+///     .iter()
+///     .map(|net| net.parse().unwrap())
+///     .collect();
+/// let expected: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap(), "10.0.1.0/24".parse().unwrap()];
+/// assert_eq!(aggregator.aggregate(&nets), expected);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AggregatorBuilder {
+    merge_adjacent: bool,
+    absorb_subnets: bool,
+    max_prefix_len: PerFamily,
+    boundary: PerFamily,
+    ipv4: bool,
+    ipv6: bool,
+    mapped: Mapped,
+    embedded: Embedded,
+    merge_slack: f64,
+    regions: Vec<IpNet>,
+    parser: Parser,
+    on_event: Option<Callback>,
+}
+
+impl Default for AggregatorBuilder {
+    fn default() -> Self {
+        AggregatorBuilder {
+            merge_adjacent: true,
+            absorb_subnets: true,
+            max_prefix_len: PerFamily::default(),
+            boundary: PerFamily::default(),
+            ipv4: true,
+            ipv6: true,
+            mapped: Mapped::Keep,
+            embedded: Embedded::Ignore,
+            merge_slack: 0.0,
+            regions: Vec::new(),
+            parser: Parser::default(),
+            on_event: None,
+        }
+    }
+}
+
+impl AggregatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to combine two halves of a network into the network. On by
+    /// default.
+    pub fn merge_adjacent(mut self, merge: bool) -> Self {
+        self.merge_adjacent = merge;
+        self
+    }
+
+    /// Whether to drop networks that are inside another network in the list.
+    /// On by default. With it off, only exact duplicates are removed.
+    pub fn absorb_subnets(mut self, absorb: bool) -> Self {
+        self.absorb_subnets = absorb;
+        self
+    }
+
+    /// Drops networks of the family with prefixes longer than `len` before
+    /// aggregating.
+    pub fn max_prefix_len(mut self, family: Family, len: u8) -> Self {
+        self.max_prefix_len.set(family, len);
+        self
+    }
+
+    /// Never merges networks of the family into anything shorter than a
+    /// `/len`. Networks that were already shorter are left alone.
+    pub fn boundary(mut self, family: Family, len: u8) -> Self {
+        self.boundary.set(family, len);
+        self
+    }
+
+    /// Whether to keep IPv4 networks. On by default.
+    pub fn ipv4(mut self, keep: bool) -> Self {
+        self.ipv4 = keep;
+        self
+    }
+
+    /// Whether to keep IPv6 networks. On by default.
+    pub fn ipv6(mut self, keep: bool) -> Self {
+        self.ipv6 = keep;
+        self
+    }
+
+    /// How to treat IPv4-mapped IPv6 networks. They're converted before the
+    /// family filters, so `ipv6(false)` with [`Mapped::ToV4`] keeps them.
+    pub fn mapped(mut self, mapped: Mapped) -> Self {
+        self.mapped = mapped;
+        self
+    }
+
+    /// What to do with the IPv4 space embedded in 6to4 and Teredo networks.
+    /// Like [`mapped`](Self::mapped), this happens before the family
+    /// filters.
+    pub fn embedded(mut self, embedded: Embedded) -> Self {
+        self.embedded = embedded;
+        self
+    }
+
+    /// Also replaces networks with a supernet that they don't quite fill,
+    /// as long as no more than `slack` of it, as a fraction from 0 to 1,
+    /// wasn't already in the list. Each network gets the shortest supernet
+    /// that qualifies and holds at least one other network. The space this
+    /// adds is reported as [`Event::Filled`]. Off, at 0, by default.
+    ///
+    /// ```
+    /// use ipnet::IpNet;
+    /// use lfc::AggregatorBuilder;
+    ///
+    /// let aggregator = AggregatorBuilder::new().merge_slack(0.3).build().unwrap();
+    /// let nets: Vec<IpNet> = ["10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24"]
+    ///     .iter()
+    ///     .map(|net| net.parse().unwrap())
+    ///     .collect();
+    /// // 10.0.3.0/24 is a quarter of the /22.
+    /// let expected: Vec<IpNet> = vec!["10.0.0.0/22".parse().unwrap()];
+    /// assert_eq!(aggregator.aggregate(&nets), expected);
+    /// ```
+    pub fn merge_slack(mut self, slack: f64) -> Self {
+        self.merge_slack = slack;
+        self
+    }
+
+    /// Never merges networks into anything that's partly inside one of the
+    /// `regions` and partly outside it, like private space with the public
+    /// space next to it. Networks that were already like that are left
+    /// alone. Can be called more than once to add more regions.
+    ///
+    /// ```
+    /// use ipnet::IpNet;
+    /// use lfc::AggregatorBuilder;
+    ///
+    /// let aggregator = AggregatorBuilder::new()
+    ///     .no_merge_across(["172.16.0.0/12".parse().unwrap()])
+    ///     .build()
+    ///     .unwrap();
+    /// let nets: Vec<IpNet> = ["172.0.0.0/12", "172.16.0.0/12"]
+    ///     .iter()
+    ///     .map(|net| net.parse().unwrap())
+    ///     .collect();
+    /// assert_eq!(aggregator.aggregate(&nets), nets);
+    /// ```
+    pub fn no_merge_across<I: IntoIterator<Item = IpNet>>(mut self, regions: I) -> Self {
+        self.regions
+            .extend(regions.into_iter().map(|region| region.trunc()));
+        self.regions.sort_unstable();
+        self.regions.dedup();
+        self
+    }
+
+    /// How [`Aggregator::aggregate_records`] parses the records.
+    pub fn parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Calls `callback` with each [`Event`] as the networks are aggregated.
+    /// Events come in order for each family, IPv4 first, so replaying them
+    /// shows how every input network ended up in the result.
+    ///
+    /// ```
+    /// use ipnet::IpNet;
+    /// use lfc::{AggregatorBuilder, Event};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let seen = Arc::clone(&events);
+    /// let aggregator = AggregatorBuilder::new()
+    ///     .on_event(move |event| seen.lock().unwrap().push(*event))
+    ///     .build()
+    ///     .unwrap();
+    /// let nets: Vec<IpNet> = ["10.0.0.0/25", "10.0.0.128/25"]
+    ///     .iter()
+    ///     .map(|net| net.parse().unwrap())
+    ///     .collect();
+    /// aggregator.aggregate(&nets);
+    /// assert_eq!(
+    ///     *events.lock().unwrap(),
+    ///     vec![Event::Merged {
+    ///         halves: [nets[0], nets[1]],
+    ///         into: "10.0.0.0/24".parse().unwrap(),
+    ///     }]
+    /// );
+    /// ```
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.on_event = Some(Callback(Arc::new(callback)));
+        self
+    }
+
+    /// Checks the settings and returns an aggregator that uses them.
+    pub fn build(self) -> Result<Aggregator> {
+        if !self.ipv4 && !self.ipv6 {
+            return Err(Error::Config(
+                "both IPv4 and IPv6 are filtered out".to_string(),
+            ));
+        }
+        if !(0.0..1.0).contains(&self.merge_slack) {
+            return Err(Error::Config(format!(
+                "merge slack {} isn't from 0 up to 1",
+                self.merge_slack
+            )));
+        }
+        if self.merge_slack > 0.0 && !self.merge_adjacent {
+            return Err(Error::Config(
+                "merge slack needs adjacent networks to be merged".to_string(),
+            ));
+        }
+        for family in [Family::V4, Family::V6] {
+            for (name, len) in [
+                ("maximum prefix length", self.max_prefix_len.get(family)),
+                ("boundary", self.boundary.get(family)),
+                ("default prefix length", self.parser.default_len(family)),
+            ] {
+                if let Some(len) = len.filter(|&len| len > family.max_prefix_len()) {
+                    return Err(Error::Config(format!(
+                        "{} {} /{} is longer than /{}",
+                        family,
+                        name,
+                        len,
+                        family.max_prefix_len()
+                    )));
+                }
+            }
+        }
+        Ok(Aggregator { settings: self })
+    }
+}
+
+/// Aggregates networks according to the settings it was built with.
+#[derive(Clone, Debug, Default)]
+pub struct Aggregator {
+    settings: AggregatorBuilder,
+}
+
+impl Aggregator {
+    pub fn builder() -> AggregatorBuilder {
+        AggregatorBuilder::new()
+    }
+
+    /// Aggregates the networks. The result is sorted, with IPv4 before IPv6,
+    /// and has no host bits set.
+    pub fn aggregate<I>(&self, nets: I) -> Vec<IpNet>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<IpNet>,
+    {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+        for net in nets.into_iter().flat_map(|net| self.prepare(*net.borrow())) {
+            match Family::of(&net) {
+                Family::V4 => v4.push(net),
+                Family::V6 => v6.push(net),
+            }
+        }
+        if self.is_plain() {
+            return crate::gather(v4.into_iter().chain(v6));
+        }
+        let mut result = self.widen(Family::V4, self.reduce(Family::V4, v4));
+        result.append(&mut self.widen(Family::V6, self.reduce(Family::V6, v6)));
+        result
+    }
+
+    /// Parses records and aggregates the networks like
+    /// [`gather_records`](crate::gather_records), naming the source and line
+    /// of the first bad one.
+    pub fn aggregate_records<'a, I>(&self, records: I) -> Result<Vec<IpNet>>
+    where
+        I: IntoIterator<Item = Record<'a>>,
+    {
+        crate::until_error(self.settings.parser.clone().records(records), |nets| {
+            self.aggregate(nets)
+        })
+    }
+
+    /// The parser that [`aggregate_records`](Self::aggregate_records) uses,
+    /// for callers that parse records some other way.
+    pub fn parser(&self) -> Parser {
+        self.settings.parser.clone()
+    }
+
+    /// A copy of this aggregator without the merge slack, for aggregating
+    /// parts of a list that will be aggregated together afterward. Slack on
+    /// each part would count the space added to the parts as if it had been
+    /// in the list.
+    pub fn exact(&self) -> Aggregator {
+        let mut exact = self.clone();
+        exact.settings.merge_slack = 0.0;
+        exact
+    }
+
+    /// Converts a network the way the settings say to, and returns what's
+    /// left of it after the filters: nothing, the network, or with
+    /// [`Embedded::Also`], the network and the IPv4 space inside it. This
+    /// is the part of aggregating that looks at one network at a time, for
+    /// callers that collect networks some other way, like on disk.
+    pub fn prepare(&self, net: IpNet) -> impl Iterator<Item = IpNet> + use<> {
+        let net = match (self.settings.mapped, net) {
+            (Mapped::ToV4, IpNet::V6(v6)) => space::unmap(&v6).map_or(net, IpNet::V4),
+            (Mapped::ToV6, IpNet::V4(v4)) => IpNet::V6(space::map(&v4)),
+            _ => net,
+        };
+        let embedded = match (self.settings.embedded, net) {
+            (Embedded::Ignore, _) | (_, IpNet::V4(_)) => None,
+            (_, IpNet::V6(v6)) => space::embedded_v4(&v6).map(IpNet::V4),
+        };
+        let net = match (self.settings.embedded, embedded) {
+            (Embedded::Instead, Some(_)) => None,
+            _ => Some(net),
+        };
+        [net, embedded]
+            .map(|net| net.filter(|net| self.passes(net)))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Returns whether the network gets past the family and prefix length
+    /// filters.
+    fn passes(&self, net: &IpNet) -> bool {
+        let family = Family::of(net);
+        self.keeps(family)
+            && self
+                .settings
+                .max_prefix_len
+                .get(family)
+                .is_none_or(|len| net.prefix_len() <= len)
+    }
+
+    fn keeps(&self, family: Family) -> bool {
+        match family {
+            Family::V4 => self.settings.ipv4,
+            Family::V6 => self.settings.ipv6,
+        }
+    }
+
+    /// Returns whether this aggregates like `gather`, once the filters have
+    /// been applied. `gather` doesn't report events, so anything listening
+    /// for them needs the long way.
+    fn is_plain(&self) -> bool {
+        let settings = &self.settings;
+        settings.merge_adjacent
+            && settings.absorb_subnets
+            && settings.boundary == PerFamily::default()
+            && settings.merge_slack == 0.0
+            && settings.regions.is_empty()
+            && settings.on_event.is_none()
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(Callback(callback)) = &self.settings.on_event {
+            callback(&event);
+        }
+    }
+
+    /// Sorts the networks and removes duplicates, reporting each one.
+    fn dedup(&self, nets: &mut Vec<IpNet>) {
+        nets.sort_unstable();
+        nets.dedup_by(|net, kept| {
+            let duplicate = net == kept;
+            if duplicate {
+                self.emit(Event::Duplicate(*net));
+            }
+            duplicate
+        });
+    }
+
+    /// Aggregates networks of one family the long way, honoring every
+    /// setting.
+    fn reduce(&self, family: Family, nets: Vec<IpNet>) -> Vec<IpNet> {
+        let mut nets: Vec<IpNet> = nets.into_iter().map(|net| net.trunc()).collect();
+        self.dedup(&mut nets);
+        if self.settings.absorb_subnets {
+            // A network sorts right after any network that contains it, and
+            // before anything that comes after the container.
+            let mut kept: Vec<IpNet> = Vec::with_capacity(nets.len());
+            for net in nets {
+                match kept.last() {
+                    Some(&last) if last.contains(&net) => {
+                        self.emit(Event::Absorbed { net, into: last })
+                    }
+                    _ => kept.push(net),
+                }
+            }
+            nets = kept;
+        }
+        if !self.settings.merge_adjacent {
+            return nets;
+        }
+
+        // Merge from the longest prefixes up, so that merged networks get a
+        // chance to merge again at the next level.
+        let floor = self.settings.boundary.get(family).unwrap_or(0);
+        let mut levels = vec![Vec::new(); usize::from(family.max_prefix_len()) + 1];
+        for net in nets {
+            levels[usize::from(net.prefix_len())].push(net);
+        }
+        let mut result = Vec::new();
+        for len in (usize::from(floor) + 1..levels.len()).rev() {
+            let mut level = std::mem::take(&mut levels[len]);
+            self.dedup(&mut level);
+            let mut i = 0;
+            while i < level.len() {
+                let net = level[i];
+                // The list is sorted, so a network's other half can only be
+                // right after it.
+                match net.supernet() {
+                    Some(parent)
+                        if level.get(i + 1).is_some_and(|next| parent.contains(next))
+                            && self.may_merge(&parent) =>
+                    {
+                        self.emit(Event::Merged {
+                            halves: [net, level[i + 1]],
+                            into: parent,
+                        });
+                        levels[len - 1].push(parent);
+                        i += 2;
+                    }
+                    _ => {
+                        result.push(net);
+                        i += 1;
+                    }
+                }
+            }
+        }
+        result.extend(levels.into_iter().take(usize::from(floor) + 1).flatten());
+        self.dedup(&mut result);
+        result
+    }
+
+    /// Returns whether merging into the network is allowed, which is when
+    /// it doesn't straddle the edge of a region. Networks either nest or
+    /// don't overlap, so that's when it doesn't hold a smaller region.
+    fn may_merge(&self, net: &IpNet) -> bool {
+        let regions = &self.settings.regions;
+        let (start, end) = space::range(net);
+        let family = Family::of(net);
+        let first = regions.partition_point(|region| {
+            (Family::of(region), space::range(region).0) < (family, start)
+        });
+        !regions[first..]
+            .iter()
+            .take_while(|region| Family::of(region) == family && space::range(region).0 <= end)
+            .any(|region| region.prefix_len() > net.prefix_len())
+    }
+
+    /// Replaces networks of one family, already reduced, with the
+    /// supernets around them that the merge slack allows.
+    fn widen(&self, family: Family, nets: Vec<IpNet>) -> Vec<IpNet> {
+        let slack = self.settings.merge_slack;
+        if slack == 0.0 || nets.len() < 2 {
+            return nets;
+        }
+        let floor = self.settings.boundary.get(family).unwrap_or(0);
+        // The networks are sorted and don't overlap, so the ones under any
+        // supernet are a run of them, and running totals of their sizes
+        // give how much of the supernet they fill.
+        let ranges: Vec<(u128, u128)> = nets.iter().map(space::range).collect();
+        let mut filled = vec![0u128];
+        for (start, end) in &ranges {
+            let total = filled[filled.len() - 1];
+            filled.push(total.saturating_add(end - start).saturating_add(1));
+        }
+        let under = |supernet: &IpNet| {
+            let (start, end) = space::range(supernet);
+            let first = ranges.partition_point(|range| range.0 < start);
+            first..ranges.partition_point(|range| range.0 <= end)
+        };
+
+        let mut widened: Vec<IpNet> = nets
+            .iter()
+            .map(|net| {
+                (floor..net.prefix_len())
+                    .map(|len| IpNet::new(net.network(), len).unwrap().trunc())
+                    .filter(|supernet| self.may_merge(supernet))
+                    .find(|supernet| {
+                        let run = under(supernet);
+                        let (start, end) = space::range(supernet);
+                        let size = (end - start).saturating_add(1);
+                        let empty = size - (filled[run.end] - filled[run.start]);
+                        run.len() > 1 && empty as f64 <= slack * size as f64
+                    })
+                    .unwrap_or(*net)
+            })
+            .collect();
+        // Supernets chosen for different networks nest or don't overlap.
+        widened.sort_unstable();
+        widened.dedup();
+        let mut kept: Vec<IpNet> = Vec::with_capacity(widened.len());
+        for net in widened {
+            if !kept.last().is_some_and(|last| last.contains(&net)) {
+                kept.push(net);
+            }
+        }
+
+        for supernet in &kept {
+            let run = under(supernet);
+            if run.len() < 2 {
+                continue;
+            }
+            let (start, end) = space::range(supernet);
+            // The first address after the last network, if there is one.
+            let mut next = Some(start);
+            let mut gaps = Vec::new();
+            for (net, &(start, last)) in nets[run.clone()].iter().zip(&ranges[run]) {
+                self.emit(Event::Absorbed {
+                    net: *net,
+                    into: *supernet,
+                });
+                if let Some(next) = next.filter(|&next| next < start) {
+                    space::cover(family, next, start - 1, &mut gaps);
+                }
+                next = last.checked_add(1);
+            }
+            if let Some(next) = next.filter(|&next| next <= end) {
+                space::cover(family, next, end, &mut gaps);
+            }
+            for gap in gaps {
+                self.emit(Event::Filled {
+                    gap,
+                    into: *supernet,
+                });
+            }
+        }
+        // A supernet may be half of a network with its neighbor.
+        self.reduce(family, kept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn aggregate(builder: AggregatorBuilder, list: &[&str]) -> Vec<IpNet> {
+        builder.build().unwrap().aggregate(nets(list))
+    }
+
+    /// Makes a messy list of overlapping, adjacent, and duplicate networks.
+    fn sample() -> Vec<IpNet> {
+        let mut state: u32 = 7;
+        (0..3000)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let prefix = 18 + (state >> 8) % 15;
+                let text = if i % 4 == 0 {
+                    format!("2001:db8:{:x}::/{}", state >> 22, prefix + 22)
+                } else {
+                    format!("10.0.{}.{}/{}", (state >> 16) % 64, state >> 24, prefix)
+                };
+                text.parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_matches_gather() {
+        let sample = sample();
+        assert_eq!(
+            Aggregator::default().aggregate(&sample),
+            crate::gather(&sample)
+        );
+    }
+
+    #[test]
+    fn test_reduce_matches_gather() {
+        let sample = sample();
+        let aggregator = Aggregator::default();
+        let v4: Vec<IpNet> = sample
+            .iter()
+            .filter(|net| Family::of(net) == Family::V4)
+            .copied()
+            .collect();
+        let v6: Vec<IpNet> = sample
+            .iter()
+            .filter(|net| Family::of(net) == Family::V6)
+            .copied()
+            .collect();
+        let mut result = aggregator.reduce(Family::V4, v4);
+        result.extend(aggregator.reduce(Family::V6, v6));
+        assert_eq!(result, crate::gather(&sample));
+    }
+
+    #[test]
+    fn test_merge_adjacent() {
+        let list = [
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "10.0.0.128/25",
+            "10.0.0.128/25",
+        ];
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().merge_adjacent(false), &list),
+            nets(&["10.0.0.0/24", "10.0.1.0/24"])
+        );
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new()
+                    .merge_adjacent(false)
+                    .absorb_subnets(false),
+                &list
+            ),
+            nets(&["10.0.0.0/24", "10.0.0.128/25", "10.0.1.0/24"])
+        );
+    }
+
+    #[test]
+    fn test_absorb_subnets() {
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new().absorb_subnets(false),
+                &[
+                    "10.0.0.0/24",
+                    "10.0.1.0/24",
+                    "10.0.0.128/25",
+                    "10.0.2.0/25",
+                    "10.0.2.128/25"
+                ]
+            ),
+            nets(&["10.0.0.0/23", "10.0.0.128/25", "10.0.2.0/24"])
+        );
+    }
+
+    #[test]
+    fn test_max_prefix_len() {
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new().max_prefix_len(Family::V4, 24),
+                &[
+                    "10.0.0.0/24",
+                    "10.0.1.0/25",
+                    "10.0.1.128/25",
+                    "2001:db8::/128"
+                ]
+            ),
+            nets(&["10.0.0.0/24", "2001:db8::/128"])
+        );
+    }
+
+    #[test]
+    fn test_boundary() {
+        let list = [
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "10.0.2.0/23",
+            "10.1.0.0/16",
+            "10.2.0.0/15",
+            "2001:db8::/33",
+            "2001:db8:8000::/33",
+        ];
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().boundary(Family::V4, 23), &list),
+            nets(&[
+                "10.0.0.0/23",
+                "10.0.2.0/23",
+                "10.1.0.0/16",
+                "10.2.0.0/15",
+                "2001:db8::/32"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_slack() {
+        let list = [
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "10.0.2.0/24",
+            "10.1.0.0/24",
+            "10.1.2.0/24",
+            "2001:db8::/33",
+        ];
+        // A quarter of 10.0.0.0/22 and half of 10.1.0.0/22 are missing.
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().merge_slack(0.3), &list),
+            nets(&["10.0.0.0/22", "10.1.0.0/24", "10.1.2.0/24", "2001:db8::/33"])
+        );
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().merge_slack(0.5), &list),
+            nets(&["10.0.0.0/22", "10.1.0.0/22", "2001:db8::/33"])
+        );
+        // The boundary still holds.
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new()
+                    .merge_slack(0.5)
+                    .boundary(Family::V4, 23),
+                &list
+            ),
+            nets(&[
+                "10.0.0.0/23",
+                "10.0.2.0/24",
+                "10.1.0.0/24",
+                "10.1.2.0/24",
+                "2001:db8::/33"
+            ])
+        );
+        // Widening each part of a list would let the slack compound.
+        let aggregator = AggregatorBuilder::new().merge_slack(0.3).build().unwrap();
+        assert_eq!(
+            aggregator.exact().aggregate(nets(&list)),
+            crate::gather(nets(&list))
+        );
+    }
+
+    #[test]
+    fn test_merge_slack_events() {
+        let (result, events) = events(
+            AggregatorBuilder::new().merge_slack(0.3),
+            &["10.0.0.0/24", "10.0.1.0/24", "10.0.3.0/24"],
+        );
+        assert_eq!(result, nets(&["10.0.0.0/22"]));
+        assert_eq!(
+            events,
+            vec![
+                Event::Merged {
+                    halves: [net("10.0.0.0/24"), net("10.0.1.0/24")],
+                    into: net("10.0.0.0/23"),
+                },
+                Event::Absorbed {
+                    net: net("10.0.0.0/23"),
+                    into: net("10.0.0.0/22"),
+                },
+                Event::Absorbed {
+                    net: net("10.0.3.0/24"),
+                    into: net("10.0.0.0/22"),
+                },
+                Event::Filled {
+                    gap: net("10.0.2.0/24"),
+                    into: net("10.0.0.0/22"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_merge_across() {
+        let list = [
+            "172.0.0.0/12",
+            "172.16.0.0/12",
+            "10.0.0.0/9",
+            "10.128.0.0/9",
+            "2001:db8::/33",
+            "2001:db8:8000::/33",
+        ];
+        let builder = AggregatorBuilder::new()
+            .no_merge_across(nets(&["172.16.0.0/12", "2001:db8:8000::/48"]))
+            .no_merge_across(nets(&["10.0.0.0/8"]));
+        assert_eq!(
+            aggregate(builder.clone(), &list),
+            nets(&[
+                "10.0.0.0/8",
+                "172.0.0.0/12",
+                "172.16.0.0/12",
+                "2001:db8::/33",
+                "2001:db8:8000::/33"
+            ])
+        );
+        // The merge slack doesn't get around it either.
+        assert_eq!(
+            aggregate(
+                builder.merge_slack(0.5),
+                &["172.0.0.0/12", "172.16.0.0/13", "172.24.0.0/14"]
+            ),
+            nets(&["172.0.0.0/12", "172.16.0.0/12"])
+        );
+    }
+
+    #[test]
+    fn test_family_filters() {
+        let list = ["10.0.0.0/24", "2001:db8::/32"];
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().ipv4(false), &list),
+            nets(&["2001:db8::/32"])
+        );
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().ipv6(false), &list),
+            nets(&["10.0.0.0/24"])
+        );
+    }
+
+    /// Aggregates the list, and returns the result and every event.
+    fn events(builder: AggregatorBuilder, list: &[&str]) -> (Vec<IpNet>, Vec<Event>) {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        let result = aggregate(
+            builder.on_event(move |event| seen.lock().unwrap().push(*event)),
+            list,
+        );
+        let events = events.lock().unwrap().clone();
+        (result, events)
+    }
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_events() {
+        let (result, events) = events(
+            AggregatorBuilder::new(),
+            &[
+                "10.0.0.0/24",
+                "10.0.0.1/24",
+                "10.0.0.128/25",
+                "10.0.1.0/24",
+                "2001:db8::/33",
+                "2001:db8:8000::/33",
+            ],
+        );
+        assert_eq!(result, nets(&["10.0.0.0/23", "2001:db8::/32"]));
+        assert_eq!(
+            events,
+            vec![
+                Event::Duplicate(net("10.0.0.0/24")),
+                Event::Absorbed {
+                    net: net("10.0.0.128/25"),
+                    into: net("10.0.0.0/24"),
+                },
+                Event::Merged {
+                    halves: [net("10.0.0.0/24"), net("10.0.1.0/24")],
+                    into: net("10.0.0.0/23"),
+                },
+                Event::Merged {
+                    halves: [net("2001:db8::/33"), net("2001:db8:8000::/33")],
+                    into: net("2001:db8::/32"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_without_absorbing() {
+        // The merged /24 is a duplicate of the one already in the list.
+        let (result, events) = events(
+            AggregatorBuilder::new().absorb_subnets(false),
+            &["10.0.0.0/24", "10.0.0.0/25", "10.0.0.128/25"],
+        );
+        assert_eq!(result, nets(&["10.0.0.0/24"]));
+        assert_eq!(
+            events,
+            vec![
+                Event::Merged {
+                    halves: [net("10.0.0.0/25"), net("10.0.0.128/25")],
+                    into: net("10.0.0.0/24"),
+                },
+                Event::Duplicate(net("10.0.0.0/24")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_match_gather() {
+        let sample = sample();
+        let aggregator = AggregatorBuilder::new().on_event(|_| {}).build().unwrap();
+        assert_eq!(aggregator.aggregate(&sample), crate::gather(&sample));
+    }
+
+    #[test]
+    fn test_mapped() {
+        let list = ["::ffff:10.0.0.0/120", "10.0.1.0/24", "2001:db8::/32"];
+        assert_eq!(
+            aggregate(AggregatorBuilder::new(), &list),
+            nets(&["10.0.1.0/24", "::ffff:10.0.0.0/120", "2001:db8::/32"])
+        );
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new().mapped(Mapped::ToV4).ipv6(false),
+                &list
+            ),
+            nets(&["10.0.0.0/23"])
+        );
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().mapped(Mapped::ToV6), &list),
+            nets(&["::ffff:10.0.0.0/119", "2001:db8::/32"])
+        );
+    }
+
+    #[test]
+    fn test_embedded() {
+        let list = [
+            "2002:a00::/24",
+            "2002:a01::/32",
+            "11.0.0.0/8",
+            "2001:db8::/32",
+        ];
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().embedded(Embedded::Also), &list),
+            nets(&["10.0.0.0/7", "2001:db8::/32", "2002:a00::/24"])
+        );
+        assert_eq!(
+            aggregate(AggregatorBuilder::new().embedded(Embedded::Instead), &list),
+            nets(&["10.0.0.0/7", "2001:db8::/32"])
+        );
+        assert_eq!(
+            aggregate(
+                AggregatorBuilder::new()
+                    .embedded(Embedded::Also)
+                    .ipv4(false),
+                &list
+            ),
+            nets(&["2001:db8::/32", "2002:a00::/24"])
+        );
+    }
+
+    #[test]
+    fn test_aggregate_records() {
+        let aggregator = AggregatorBuilder::new()
+            .mapped(Mapped::ToV4)
+            .build()
+            .unwrap();
+        let records = |text: &'static str| {
+            text.lines().enumerate().map(|(index, text)| Record {
+                text,
+                provenance: crate::source::Provenance {
+                    source: None,
+                    line: index + 1,
+                },
+            })
+        };
+        assert_eq!(
+            aggregator
+                .aggregate_records(records(
+                    "::ffff:10.0.0.0/120
+10.0.1.0/24"
+                ))
+                .unwrap(),
+            nets(&["10.0.0.0/23"])
+        );
+        assert!(matches!(
+            aggregator.aggregate_records(records(
+                "10.0.0.0/8
+bogus"
+            )),
+            Err(Error::Parse { line: 2, .. })
+        ));
+        let aggregator = AggregatorBuilder::new()
+            .parser(Parser::new().default_prefix_len(Family::V4, 24))
+            .build()
+            .unwrap();
+        assert_eq!(
+            aggregator
+                .aggregate_records(records(
+                    "10.0.0.1
+10.0.1.99"
+                ))
+                .unwrap(),
+            nets(&["10.0.0.0/23"])
+        );
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(
+            AggregatorBuilder::new()
+                .ipv4(false)
+                .ipv6(false)
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "both IPv4 and IPv6 are filtered out"
+        );
+        assert_eq!(
+            AggregatorBuilder::new()
+                .boundary(Family::V4, 33)
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "IPv4 boundary /33 is longer than /32"
+        );
+        assert_eq!(
+            AggregatorBuilder::new()
+                .merge_slack(1.0)
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "merge slack 1 isn't from 0 up to 1"
+        );
+        assert!(
+            AggregatorBuilder::new()
+                .merge_slack(0.05)
+                .merge_adjacent(false)
+                .build()
+                .is_err()
+        );
+        assert_eq!(
+            AggregatorBuilder::new()
+                .parser(Parser::new().default_prefix_len(Family::V6, 129))
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "IPv6 default prefix length /129 is longer than /128"
+        );
+        assert!(
+            AggregatorBuilder::new()
+                .max_prefix_len(Family::V6, 128)
+                .build()
+                .is_ok()
+        );
+    }
+}
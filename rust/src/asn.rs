@@ -0,0 +1,192 @@
+//! Origin ASNs, for `--annotate asn` and `lfc by-asn`.
+//!
+//! Looking each network up on its own would take a round trip apiece, so
+//! the whole list goes to Team Cymru's bulk whois in one TCP session:
+//! `begin`, a line for each address, and `end`, answered with a line for
+//! each address in the same order.
+
+use ipnet::IpNet;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpStream};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+/// Team Cymru's IP to ASN mapping service.
+const CYMRU: &str = "whois.cymru.com:43";
+
+/// How long to wait for the service before giving up.
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where origin ASNs are looked up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Provider {
+    /// Team Cymru's bulk whois.
+    #[default]
+    Cymru,
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cymru" => Ok(Provider::Cymru),
+            _ => Err(format!("invalid provider '{}' (expected cymru)", s)),
+        }
+    }
+}
+
+/// The AS that originates an address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Origin {
+    pub asn: u32,
+    /// The AS's name, like `EXAMPLE-NET, US`.
+    pub name: String,
+    /// The BGP prefix that covers the address, if the provider says.
+    pub prefix: Option<IpNet>,
+}
+
+impl fmt::Display for Origin {
+    /// Formats the origin as a note, like
+    /// `AS64500 EXAMPLE-NET, US (192.0.2.0/24)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AS{} {}", self.asn, self.name)?;
+        match self.prefix {
+            Some(prefix) => write!(f, " ({})", prefix),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Provider {
+    /// Returns the origin of each network whose first address is
+    /// announced.
+    pub fn origins(self, nets: &[IpNet]) -> Result<BTreeMap<IpNet, Origin>, String> {
+        match self {
+            Provider::Cymru => cymru(nets),
+        }
+    }
+}
+
+/// Looks the networks up in one bulk whois session. The request is written
+/// from another thread, so that a long one can't fill the socket's buffers
+/// while we're not yet reading the answers.
+fn cymru(nets: &[IpNet]) -> Result<BTreeMap<IpNet, Origin>, String> {
+    if nets.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let failed = |err: io::Error| format!("{}: {}", CYMRU, err);
+    let stream = TcpStream::connect(CYMRU).map_err(failed)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(failed)?;
+    let mut writer = stream.try_clone().map_err(failed)?;
+    thread::scope(|scope| {
+        let sent = scope.spawn(move || {
+            writer.write_all(request(nets).as_bytes())?;
+            writer.flush()
+        });
+        let origins = answers(BufReader::new(&stream), nets).map_err(failed)?;
+        sent.join().unwrap().map_err(failed)?;
+        Ok(origins)
+    })
+}
+
+/// Returns the bulk request for the networks' first addresses, asking for
+/// the verbose answers that have the BGP prefix and the AS's name.
+fn request(nets: &[IpNet]) -> String {
+    let mut request = String::from("begin\nverbose\n");
+    for net in nets {
+        request += &format!("{}\n", net.network());
+    }
+    request + "end\n"
+}
+
+/// Reads the answers, like
+/// `64500 | 192.0.2.0 | 192.0.2.0/24 | US | arin | 2010-01-01 | EXAMPLE-NET, US`,
+/// and returns the origin of each network whose address has one. The first
+/// line says the service is in bulk mode, and unannounced addresses have an
+/// AS of `NA`.
+fn answers<R: BufRead>(reader: R, nets: &[IpNet]) -> io::Result<BTreeMap<IpNet, Origin>> {
+    let mut by_address: BTreeMap<IpAddr, Vec<IpNet>> = BTreeMap::new();
+    for net in nets {
+        by_address.entry(net.network()).or_default().push(*net);
+    }
+    let mut origins = BTreeMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with("Bulk mode;") {
+            continue;
+        }
+        if let Some(error) = line.strip_prefix("Error: ") {
+            return Err(io::Error::other(error.to_string()));
+        }
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [asn, address, prefix, _, _, _, name] = fields[..] else {
+            continue;
+        };
+        let (Ok(asn), Ok(address)) = (asn.parse::<u32>(), address.parse::<IpAddr>()) else {
+            continue;
+        };
+        let origin = Origin {
+            asn,
+            name: name.to_string(),
+            prefix: prefix.parse().ok(),
+        };
+        for net in by_address.get(&address).into_iter().flatten() {
+            origins.insert(*net, origin.clone());
+        }
+    }
+    Ok(origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_request() {
+        assert_eq!(
+            request(&nets(&["192.0.2.0/24", "2001:db8::/32"])),
+            "begin\nverbose\n192.0.2.0\n2001:db8::\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_answers() {
+        let nets = nets(&["1.1.1.0/24", "192.0.2.0/24", "2606:4700::/32"]);
+        let answer = "\
+Bulk mode; whois.cymru.com [2026-10-16 12:00:00 +0000]
+13335   | 1.1.1.0          | 1.1.1.0/24          | AU | apnic    | 2011-08-11 | CLOUDFLARENET, US
+NA      | 192.0.2.0        | NA                  |    | other    |            | NA
+13335   | 2606:4700::      | 2606:4700::/44      | US | arin     | 2011-11-01 | CLOUDFLARENET, US
+";
+        let origins = answers(answer.as_bytes(), &nets).unwrap();
+        assert_eq!(origins.len(), 2);
+        assert_eq!(
+            origins[&nets[0]].to_string(),
+            "AS13335 CLOUDFLARENET, US (1.1.1.0/24)"
+        );
+        assert_eq!(
+            origins[&nets[2]],
+            Origin {
+                asn: 13335,
+                name: "CLOUDFLARENET, US".to_string(),
+                prefix: Some("2606:4700::/44".parse().unwrap()),
+            }
+        );
+        let err = answers("Error: no ASN for you\n".as_bytes(), &nets).unwrap_err();
+        assert_eq!(err.to_string(), "no ASN for you");
+    }
+
+    #[test]
+    fn test_provider() {
+        assert_eq!("cymru".parse(), Ok(Provider::Cymru));
+        assert!("ripestat".parse::<Provider>().is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Special-purpose space that `--drop` and `--drop-bogons` leave out.
+//!
+//! `--drop` takes categories of blocks from the IPv4 and IPv6
+//! Special-Purpose Address Registries, like `lfc info`'s, so that each
+//! deployment can drop just the kinds that don't belong in its lists. A
+//! firewall in front of a CGNAT, for one, needs 100.64.0.0/10 kept.
+//!
+//! `--drop-bogons` takes a whole martians list instead: one of the
+//! profiles here, or a file of its own, so that exactly what's left out
+//! can be read and changed per environment.
+
+use crate::cache;
+use crate::info;
+use ipnet::IpNet;
+use lfc::source;
+use lfc::space::{self, Family};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The private blocks of RFC 1918.
+const RFC1918: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"];
+
+/// The special-purpose blocks that RFC 6890 listed when it was published,
+/// before the registries it set up grew.
+const RFC6890: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.88.99.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "240.0.0.0/4",
+    "255.255.255.255/32",
+    "::/128",
+    "::1/128",
+    "::ffff:0:0/96",
+    "64:ff9b::/96",
+    "100::/64",
+    "2001::/23",
+    "2001:db8::/32",
+    "2002::/16",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+/// Where Team Cymru publishes its fullbogons lists: the space that's
+/// special-purpose or hasn't been allocated to anyone yet.
+const FULLBOGONS: [(&str, &str); 2] = [
+    (
+        "fullbogons-ipv4.txt",
+        "https://www.team-cymru.org/Services/Bogons/fullbogons-ipv4.txt",
+    ),
+    (
+        "fullbogons-ipv6.txt",
+        "https://www.team-cymru.org/Services/Bogons/fullbogons-ipv6.txt",
+    ),
+];
+
+/// How old a fullbogons list can be before it's downloaded again. The
+/// lists change as the RIRs allocate space, so they're refreshed as often
+/// as the delegated-stats files.
+const MAX_AGE: Duration = Duration::from_secs(86_400);
+
+/// A kind of special-purpose space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Multicast,
+    Loopback,
+    LinkLocal,
+    Cgnat,
+    Doc,
+    Benchmarking,
+    Ula,
+    Private,
+    Reserved,
+}
+
+impl Category {
+    /// Every category, which is what `bogons` means.
+    const ALL: [Category; 9] = [
+        Category::Multicast,
+        Category::Loopback,
+        Category::LinkLocal,
+        Category::Cgnat,
+        Category::Doc,
+        Category::Benchmarking,
+        Category::Ula,
+        Category::Private,
+        Category::Reserved,
+    ];
+
+    /// The blocks in the category.
+    fn blocks(self) -> &'static [&'static str] {
+        match self {
+            Category::Multicast => &["224.0.0.0/4", "ff00::/8"],
+            Category::Loopback => &["127.0.0.0/8", "::1/128"],
+            Category::LinkLocal => &["169.254.0.0/16", "fe80::/10"],
+            Category::Cgnat => &["100.64.0.0/10"],
+            Category::Doc => &[
+                "192.0.2.0/24",
+                "198.51.100.0/24",
+                "203.0.113.0/24",
+                "2001:db8::/32",
+                "3fff::/20",
+            ],
+            Category::Benchmarking => &["198.18.0.0/15", "2001:2::/48"],
+            Category::Ula => &["fc00::/7"],
+            Category::Private => RFC1918,
+            Category::Reserved => &["0.0.0.0/8", "240.0.0.0/4", "::/128"],
+        }
+    }
+}
+
+/// Parses a comma-separated list of categories, where `bogons` is all of
+/// them.
+pub fn parse(text: &str) -> Result<Vec<Category>, String> {
+    let mut categories = Vec::new();
+    for name in text.split(',') {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("bogons") {
+            categories.extend(Category::ALL);
+        } else {
+            categories.push(name.parse()?);
+        }
+    }
+    Ok(categories)
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "multicast" => Ok(Category::Multicast),
+            "loopback" => Ok(Category::Loopback),
+            "link-local" => Ok(Category::LinkLocal),
+            "cgnat" => Ok(Category::Cgnat),
+            "doc" => Ok(Category::Doc),
+            "benchmarking" => Ok(Category::Benchmarking),
+            "ula" => Ok(Category::Ula),
+            "private" => Ok(Category::Private),
+            "reserved" => Ok(Category::Reserved),
+            _ => Err(format!(
+                "invalid --drop category '{}' (expected multicast, loopback, link-local, \
+                 cgnat, doc, benchmarking, ula, private, reserved, or bogons)",
+                s
+            )),
+        }
+    }
+}
+
+/// Returns the blocks in the categories.
+pub fn blocks(categories: &[Category]) -> Vec<IpNet> {
+    categories
+        .iter()
+        .flat_map(|category| category.blocks())
+        .map(|block| block.parse().unwrap())
+        .collect()
+}
+
+/// Returns the blocks a `--drop-bogons` value names: the profile `rfc1918`,
+/// `rfc6890`, `iana-special`, or `fullbogons`, or else a file or URL of
+/// networks, one per line, with `#` comments. The fullbogons lists are kept
+/// in the bogons directory of the [data directory](cache::data_dir).
+pub fn profile(spec: &str, data_dir: Option<&str>) -> Result<Vec<IpNet>, String> {
+    let parse = |list: &[&str]| list.iter().map(|net| net.parse().unwrap()).collect();
+    match spec {
+        "rfc1918" => Ok(parse(RFC1918)),
+        "rfc6890" => Ok(parse(RFC6890)),
+        "iana-special" => Ok(info::special_blocks()),
+        "fullbogons" => {
+            let dir = cache::data_dir(data_dir)?.join("bogons");
+            let mut blocks = Vec::new();
+            for (name, url) in FULLBOGONS {
+                let path = dir.join(name);
+                let text = cache::fetched(&path, url, MAX_AGE)?;
+                blocks.extend(parse_list(&path.display().to_string(), &text)?);
+            }
+            Ok(blocks)
+        }
+        _ => {
+            let input = source::open(spec).map_err(|err| err.to_string())?;
+            parse_list(spec, input.text().map_err(|err| err.to_string())?)
+        }
+    }
+}
+
+/// Parses a list of networks with `#` comments, naming the first line
+/// that isn't one.
+fn parse_list(name: &str, text: &str) -> Result<Vec<IpNet>, String> {
+    let mut nets = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let net: IpNet = line
+            .parse()
+            .map_err(|_| format!("{} line {}: '{}' isn't a network", name, index + 1, line))?;
+        nets.push(net.trunc());
+    }
+    Ok(nets)
+}
+
+/// Returns the networks without any of the space in the blocks.
+pub fn drop(nets: &[IpNet], blocks: &[IpNet]) -> Vec<IpNet> {
+    let mut kept = Vec::new();
+    for family in [Family::V4, Family::V6] {
+        let dropped = space::ranges(blocks, family);
+        for (start, end) in space::subtract(&space::ranges(nets, family), &dropped) {
+            space::cover(family, start, end, &mut kept);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("multicast, Link-Local,cgnat"),
+            Ok(vec![
+                Category::Multicast,
+                Category::LinkLocal,
+                Category::Cgnat
+            ])
+        );
+        assert_eq!(parse("bogons").unwrap().len(), Category::ALL.len());
+        assert!(parse("martians").is_err());
+        assert!(parse("doc,").is_err());
+    }
+
+    #[test]
+    fn test_blocks() {
+        for category in Category::ALL {
+            for block in category.blocks() {
+                assert!(
+                    block
+                        .parse::<IpNet>()
+                        .is_ok_and(|net| net.trunc().to_string() == *block)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_drop() {
+        assert_eq!(
+            drop(
+                &nets(&[
+                    "100.0.0.0/8",
+                    "192.0.2.0/24",
+                    "8.8.8.0/24",
+                    "fd00::/8",
+                    "2001:db8::/48"
+                ]),
+                &blocks(&[Category::Cgnat, Category::Ula])
+            ),
+            nets(&[
+                "8.8.8.0/24",
+                "100.0.0.0/10",
+                "100.128.0.0/9",
+                "192.0.2.0/24",
+                "2001:db8::/48"
+            ])
+        );
+        assert_eq!(drop(&nets(&["10.0.0.0/8"]), &[]), nets(&["10.0.0.0/8"]));
+    }
+
+    #[test]
+    fn test_profile() {
+        assert_eq!(profile("rfc1918", None).unwrap(), nets(RFC1918));
+        let rfc6890 = profile("rfc6890", None).unwrap();
+        assert!(rfc6890.iter().all(|net| *net == net.trunc()));
+        assert_eq!(rfc6890.len(), RFC6890.len());
+        assert!(profile("iana-special", None).unwrap().len() > rfc6890.len());
+
+        let dir = std::env::temp_dir().join(format!("lfc-bogons-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("bogons")).unwrap();
+        std::fs::write(
+            dir.join("bogons/fullbogons-ipv4.txt"),
+            "# last updated 1760000000 (Thu Oct  9 08:53:20 2025 GMT)\n0.0.0.0/8\n10.0.0.0/8\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("bogons/fullbogons-ipv6.txt"), "::/8\n").unwrap();
+        let fullbogons = profile("fullbogons", dir.to_str());
+        let martians = dir.join("martians.txt");
+        std::fs::write(
+            &martians,
+            "# lab space\n10.0.0.0/8\n\n198.18.0.0/15  # benchmarking\n",
+        )
+        .unwrap();
+        let file = profile(martians.to_str().unwrap(), None);
+        std::fs::write(&martians, "10.0.0.0/8\nbogus\n").unwrap();
+        let bad = profile(martians.to_str().unwrap(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            fullbogons.unwrap(),
+            nets(&["0.0.0.0/8", "10.0.0.0/8", "::/8"])
+        );
+        assert_eq!(file.unwrap(), nets(&["10.0.0.0/8", "198.18.0.0/15"]));
+        assert!(
+            bad.unwrap_err()
+                .ends_with("line 2: 'bogus' isn't a network")
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Caching results by what went into them.
+//!
+//! With `--cache-dir`, lfc hashes the normalized input along with everything
+//! else that affects the result, and saves the result under that hash. The
+//! next run over the same input finds it there and skips parsing and
+//! aggregation entirely, which makes re-running over a big feed that hasn't
+//! changed nearly instant.
+
+use crate::diag::note;
+use crate::state;
+use ipnet::IpNet;
+use lfc::source::{InputSource, UrlSource};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Returns the cache key for the lines of input.
+///
+/// Whitespace around lines and blank lines don't change the result, so they
+/// don't change the key either. `context` is everything else that affects
+/// the result, like the lfc version and any options.
+pub fn key<'a, I: IntoIterator<Item = &'a str>>(lines: I, context: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for item in context {
+        hasher.update(item.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update([0]);
+    for line in lines
+        .into_iter()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        hasher.update(line.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Returns the directory that downloaded data, like the GeoLite2 database,
+/// is kept under: `--data-dir` if there is one, or lfc's in
+/// `$XDG_CACHE_HOME` or `~/.cache` if not. It's separate from
+/// `--cache-dir`, so keeping downloads somewhere doesn't cache results.
+pub fn data_dir(data_dir: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(dir) = data_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    let env = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    match (env("XDG_CACHE_HOME"), env("HOME")) {
+        (Some(cache), _) => Ok(PathBuf::from(cache).join("lfc")),
+        (None, Some(home)) => Ok(PathBuf::from(home).join(".cache").join("lfc")),
+        (None, None) => Err("there's no data directory; give one with --data-dir".to_string()),
+    }
+}
+
+/// Returns the text of the downloaded file at `path`, downloading it from
+/// `url` first if it's missing or older than `max_age`. If an old file
+/// can't be downloaded again, it's used anyway, with a warning.
+pub fn fetched(path: &Path, url: &str, max_age: Duration) -> Result<String, String> {
+    let age = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(|modified| modified.elapsed().unwrap_or_default());
+    if age.is_none_or(|age| age > max_age) {
+        match download(url, path) {
+            Ok(()) => {}
+            Err(err) if age.is_some() => {
+                note!("warning: using {} as is: {}", path.display(), err)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Downloads the URL to the path, replacing the file there only once the
+/// download is complete.
+fn download(url: &str, path: &Path) -> Result<(), String> {
+    let download = UrlSource::fetch(url).map_err(|err| err.to_string())?;
+    let tmp = path.with_extension("tmp");
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp, download.bytes()))
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|err: io::Error| format!("{}: {}", path.display(), err))
+}
+
+fn path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.txt", key))
+}
+
+/// Returns the cached result for the key, if there is one.
+pub fn load(dir: &Path, key: &str) -> io::Result<Option<Vec<IpNet>>> {
+    state::load(&path(dir, key))
+}
+
+/// Saves the result under the key.
+pub fn save(dir: &Path, key: &str, nets: &[IpNet]) -> io::Result<()> {
+    state::save(&path(dir, key), nets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ignores_formatting() {
+        let context = vec!["lfc 0.2.0".to_string()];
+        assert_eq!(
+            key("10.0.0.0/8\n192.168.0.0/16\n".lines(), &context),
+            key("  10.0.0.0/8\n\n192.168.0.0/16  ".lines(), &context)
+        );
+    }
+
+    #[test]
+    fn test_key_depends_on_content_and_context() {
+        let context = vec!["lfc 0.2.0".to_string()];
+        let base = key("10.0.0.0/8\n".lines(), &context);
+        assert_ne!(base, key("10.0.0.0/9\n".lines(), &context));
+        assert_ne!(
+            base,
+            key("10.0.0.0/8\n".lines(), &["lfc 0.3.0".to_string()])
+        );
+        assert_ne!(base, key("10.0.0.0/8\n".lines(), &[]));
+        assert_eq!(base.len(), 64);
+    }
+
+    #[test]
+    fn test_data_dir() {
+        assert_eq!(
+            data_dir(Some("/var/cache/lfc")).unwrap(),
+            Path::new("/var/cache/lfc")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("lfc-cache-test-{}", std::process::id()));
+        assert_eq!(load(&dir, "abc").unwrap(), None);
+        let nets: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        save(&dir, "abc", &nets).unwrap();
+        assert_eq!(load(&dir, "abc").unwrap(), Some(nets));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
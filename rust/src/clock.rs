@@ -0,0 +1,167 @@
+//! Timestamps and intervals.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp, like
+/// `2025-09-19T12:34:56Z`.
+pub fn rfc3339(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+/// Parses an interval like `90s`, `15m`, `1h`, or `1d`. A bare number is
+/// in seconds.
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid interval '{}' (expected something like 15m or 1h)",
+            text
+        )
+    };
+    let (count, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(at) => text.split_at(at),
+        None => (text, "s"),
+    };
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return Err(invalid()),
+    };
+    match count.checked_mul(unit) {
+        Some(0) | None => Err(invalid()),
+        Some(secs) => Ok(Duration::from_secs(secs)),
+    }
+}
+
+/// Parses a timestamp as seconds since the Unix epoch, from either that
+/// number itself or an RFC 3339 UTC timestamp like `2025-09-19T12:34:56Z`.
+/// A date on its own, like `2025-09-19`, is the midnight it starts at.
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    if text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return text.parse().ok();
+    }
+    let (date, time) = match text.split_once(['T', 't']) {
+        Some((date, time)) => (date, Some(time.strip_suffix(['Z', 'z'])?)),
+        None => (text, None),
+    };
+    let number = |text: &str, digits: usize| {
+        (text.len() == digits && text.bytes().all(|byte| byte.is_ascii_digit()))
+            .then(|| text.parse::<u32>().ok())
+            .flatten()
+    };
+    let [year, month, day] = date.splitn(3, '-').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let (year, month, day) = (number(year, 4)?, number(month, 2)?, number(day, 2)?);
+    let days = days_from_civil(year.into(), month, day)?;
+    let secs = match time {
+        None => 0,
+        Some(time) => {
+            let [hour, minute, second] = time.splitn(3, ':').collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            let (hour, minute, second) = (number(hour, 2)?, number(minute, 2)?, number(second, 2)?);
+            if hour > 23 || minute > 59 || second > 59 {
+                return None;
+            }
+            hour * 3600 + minute * 60 + second
+        }
+    };
+    u64::try_from(days)
+        .ok()?
+        .checked_mul(86_400)?
+        .checked_add(secs.into())
+}
+
+/// Converts a date to days since 1970-01-01, the inverse of
+/// [`civil_from_days`], or returns `None` if there's no such date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    // Days past the end of the month roll over into the next one.
+    (civil_from_days(days) == (year, month, day)).then_some(days)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, using Howard
+/// Hinnant's algorithm for the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_758_285_296), "2025-09-19T12:34:56Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1758285296"), Some(1_758_285_296));
+        assert_eq!(parse_timestamp("2025-09-19T12:34:56Z"), Some(1_758_285_296));
+        assert_eq!(parse_timestamp("2000-02-29"), Some(951_782_400));
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2001-02-29"), None);
+        assert_eq!(parse_timestamp("2025-09-19T12:34:56"), None);
+        assert_eq!(parse_timestamp("2025-09-19T24:00:00Z"), None);
+        assert_eq!(parse_timestamp("1969-12-31"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+        for secs in [0, 951_782_400, 1_758_285_296, 4_102_444_800] {
+            assert_eq!(parse_timestamp(&rfc3339(secs)), Some(secs));
+        }
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_interval("2d"), Ok(Duration::from_secs(172_800)));
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("1w").is_err());
+        assert!(parse_interval("1h30m").is_err());
+    }
+}
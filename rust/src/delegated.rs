@@ -0,0 +1,267 @@
+//! The regional internet registries' delegated-stats files, for `--rir` and
+//! `--respect-allocations`.
+//!
+//! Every day, each RIR publishes a file listing every block of address
+//! space it has allocated or assigned, one per line, like
+//! `arin|US|ipv4|3.0.0.0|16777216|19880223|allocated|...`. The files are
+//! kept in the `rir` directory of the data directory, and downloaded again
+//! once they're a day old.
+
+use crate::cache;
+use ipnet::{IpNet, Ipv6Net};
+use lfc::space::{self, Family};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How old a saved file can be before it's downloaded again.
+const MAX_AGE: Duration = Duration::from_secs(86_400);
+
+/// A regional internet registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rir {
+    Afrinic,
+    Apnic,
+    Arin,
+    Lacnic,
+    Ripe,
+}
+
+impl FromStr for Rir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "afrinic" => Ok(Rir::Afrinic),
+            "apnic" => Ok(Rir::Apnic),
+            "arin" => Ok(Rir::Arin),
+            "lacnic" => Ok(Rir::Lacnic),
+            "ripe" | "ripencc" => Ok(Rir::Ripe),
+            _ => Err(format!(
+                "invalid RIR '{}' (expected afrinic, apnic, arin, lacnic, or ripe)",
+                s
+            )),
+        }
+    }
+}
+
+impl Rir {
+    /// Every RIR.
+    pub const ALL: [Rir; 5] = [Rir::Afrinic, Rir::Apnic, Rir::Arin, Rir::Lacnic, Rir::Ripe];
+
+    /// The name the RIR's file goes by.
+    fn name(self) -> &'static str {
+        match self {
+            Rir::Afrinic => "afrinic",
+            Rir::Apnic => "apnic",
+            Rir::Arin => "arin",
+            Rir::Lacnic => "lacnic",
+            Rir::Ripe => "ripencc",
+        }
+    }
+
+    /// Returns where the RIR publishes its latest extended delegated-stats
+    /// file.
+    fn url(self) -> String {
+        let host = match self {
+            Rir::Afrinic => "ftp.afrinic.net/pub/stats",
+            Rir::Apnic => "ftp.apnic.net/stats",
+            Rir::Arin => "ftp.arin.net/pub/stats",
+            Rir::Lacnic => "ftp.lacnic.net/pub/stats",
+            Rir::Ripe => "ftp.ripe.net/pub/stats",
+        };
+        format!(
+            "https://{}/{}/delegated-{}-extended-latest",
+            host,
+            self.name(),
+            self.name()
+        )
+    }
+
+    /// Returns the networks the RIR has delegated, from its file in the
+    /// data directory, downloading it first if it's missing or a day old.
+    /// If a day-old file can't be downloaded again, it's used anyway, with
+    /// a warning.
+    pub fn delegations(self, data_dir: Option<&str>) -> Result<Vec<IpNet>, String> {
+        let path = path(data_dir, self)?;
+        let text = cache::fetched(&path, &self.url(), MAX_AGE)?;
+        parse(self, &text).map_err(|line| format!("{} has a bad line: {}", path.display(), line))
+    }
+}
+
+/// Returns every network that any RIR has delegated, each a separate
+/// allocation or assignment.
+pub fn allocations(data_dir: Option<&str>) -> Result<Vec<IpNet>, String> {
+    let mut allocations = Vec::new();
+    for rir in Rir::ALL {
+        allocations.extend(rir.delegations(data_dir)?);
+    }
+    allocations.sort();
+    Ok(allocations)
+}
+
+/// Returns where the RIR's file is kept.
+fn path(data_dir: Option<&str>, rir: Rir) -> Result<PathBuf, String> {
+    Ok(cache::data_dir(data_dir)?
+        .join("rir")
+        .join(format!("delegated-{}-extended-latest", rir.name())))
+}
+
+/// Returns the networks the RIR allocated or assigned, from its file, or
+/// the first line that doesn't make sense. The version and summary lines,
+/// the records of ASNs, and space that's only available or reserved are
+/// skipped. An IPv4 record counts addresses, which needn't be a power of
+/// two, so it can take several networks to cover.
+fn parse(rir: Rir, text: &str) -> Result<Vec<IpNet>, &str> {
+    let mut nets = Vec::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.trim().split('|').collect();
+        let [registry, country, kind, start, value, _, status, ..] = fields[..] else {
+            continue;
+        };
+        if registry != rir.name() || country == "*" || !matches!(status, "allocated" | "assigned") {
+            continue;
+        }
+        match kind {
+            "ipv4" => {
+                let start = u32::from(start.parse::<Ipv4Addr>().map_err(|_| line)?);
+                let count: u32 = value.parse().map_err(|_| line)?;
+                let end = count
+                    .checked_sub(1)
+                    .and_then(|last| start.checked_add(last))
+                    .ok_or(line)?;
+                space::cover(Family::V4, start.into(), end.into(), &mut nets);
+            }
+            "ipv6" => {
+                let start: Ipv6Addr = start.parse().map_err(|_| line)?;
+                let len: u8 = value.parse().map_err(|_| line)?;
+                nets.push(IpNet::V6(
+                    Ipv6Net::new(start, len).map_err(|_| line)?.trunc(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    nets.sort();
+    Ok(nets)
+}
+
+/// Returns the parts of the networks that are inside `within`.
+pub fn intersect(nets: &[IpNet], within: &[IpNet]) -> Vec<IpNet> {
+    let mut kept = Vec::new();
+    for family in [Family::V4, Family::V6] {
+        let within = space::ranges(within, family);
+        for (start, end) in space::ranges(nets, family) {
+            let first = within.partition_point(|range| range.1 < start);
+            for range in within[first..].iter().take_while(|range| range.0 <= end) {
+                space::cover(family, start.max(range.0), end.min(range.1), &mut kept);
+            }
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const ARIN: &str = "\
+2.3|arin|1760000000000|3|19700101|20261015|-0500
+arin|*|ipv4|*|2|summary
+arin|*|ipv6|*|1|summary
+arin|US|asn|64500|1|20100101|assigned|abc
+arin|US|ipv4|192.0.2.0|256|20100101|allocated|abc
+arin|CA|ipv4|198.51.100.0|768|20100101|assigned|def
+arin||ipv4|203.0.113.0|256||available|
+arin|US|ipv6|2001:db8::|32|20100101|allocated|abc
+";
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(Rir::Arin, ARIN).unwrap(),
+            nets(&[
+                "192.0.2.0/24",
+                "198.51.100.0/23",
+                "198.51.102.0/24",
+                "2001:db8::/32"
+            ])
+        );
+        assert_eq!(parse(Rir::Ripe, ARIN).unwrap(), vec![]);
+        let bad = "arin|US|ipv4|192.0.2.0|0|20100101|allocated|abc";
+        assert_eq!(parse(Rir::Arin, bad).unwrap_err(), bad);
+    }
+
+    #[test]
+    fn test_rir() {
+        assert_eq!("ARIN".parse(), Ok(Rir::Arin));
+        assert_eq!("ripencc".parse(), Ok(Rir::Ripe));
+        assert!("iana".parse::<Rir>().is_err());
+        assert_eq!(
+            Rir::Ripe.url(),
+            "https://ftp.ripe.net/pub/stats/ripencc/delegated-ripencc-extended-latest"
+        );
+    }
+
+    #[test]
+    fn test_intersect() {
+        let within = nets(&["192.0.2.0/24", "198.51.100.0/23", "2001:db8::/32"]);
+        assert_eq!(
+            intersect(
+                &nets(&[
+                    "192.0.0.0/16",
+                    "198.51.101.128/25",
+                    "10.0.0.0/8",
+                    "2001:db8::/48"
+                ]),
+                &within
+            ),
+            nets(&["192.0.2.0/24", "198.51.101.128/25", "2001:db8::/48"])
+        );
+        assert_eq!(intersect(&nets(&["0.0.0.0/0"]), &[]), vec![]);
+    }
+
+    #[test]
+    fn test_delegations() {
+        let dir = std::env::temp_dir().join(format!("lfc-rir-{}", std::process::id()));
+        let path = path(dir.to_str(), Rir::Arin).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, ARIN).unwrap();
+        let delegations = Rir::Arin.delegations(dir.to_str());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(delegations.unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_allocations() {
+        let dir = std::env::temp_dir().join(format!("lfc-allocations-{}", std::process::id()));
+        for rir in Rir::ALL {
+            let path = path(dir.to_str(), rir).unwrap();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let text = match rir {
+                Rir::Arin => ARIN,
+                Rir::Ripe => "ripencc|NL|ipv4|192.0.3.0|256|20100101|allocated|ghi\n",
+                _ => "",
+            };
+            fs::write(&path, text).unwrap();
+        }
+        let allocations = allocations(dir.to_str());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            allocations.unwrap(),
+            nets(&[
+                "192.0.2.0/24",
+                "192.0.3.0/24",
+                "198.51.100.0/23",
+                "198.51.102.0/24",
+                "2001:db8::/32"
+            ])
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Diagnostic output.
+//!
+//! Stdout is reserved for the networks lfc produces, so that it's always safe
+//! to pipe the output into another program. Everything else - hints,
+//! warnings, stats, and progress - goes to stderr through the [`note!`] macro,
+//! which respects `-q/--quiet`. Errors that stop the program are printed with
+//! `eprintln!` directly because they should never be silenced.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses (or re-enables) all non-fatal diagnostics.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Returns true if non-fatal diagnostics are suppressed.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints a diagnostic message to stderr unless `--quiet` was given.
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if !$crate::diag::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use note;
@@ -0,0 +1,494 @@
+//! Comparing two aggregated network lists.
+
+use ipnet::IpNet;
+use lfc::space;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// When to use colored output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    /// Color if stdout is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "invalid color choice '{}' (expected auto, always, or never)",
+                s
+            )),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Decides whether to color output going to a stream, given whether that
+    /// stream is a terminal.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// A single difference between two network lists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    /// The network is in the new list but not the old one.
+    Added(IpNet),
+    /// The network is in the old list but not the new one.
+    Removed(IpNet),
+}
+
+impl Change {
+    pub fn net(&self) -> IpNet {
+        match *self {
+            Change::Added(net) | Change::Removed(net) => net,
+        }
+    }
+}
+
+/// Returns the networks that were added to or removed from `old` to get `new`,
+/// sorted by network.
+///
+/// Both lists are expected to already be aggregated, so this compares them
+/// entry by entry rather than address by address. A /24 that grew into a /23
+/// shows up as the /24 being removed and the /23 being added.
+pub fn diff(old: &[IpNet], new: &[IpNet]) -> Vec<Change> {
+    let old: BTreeSet<IpNet> = old.iter().map(IpNet::trunc).collect();
+    let new: BTreeSet<IpNet> = new.iter().map(IpNet::trunc).collect();
+    let mut changes: Vec<Change> = old
+        .difference(&new)
+        .map(|net| Change::Removed(*net))
+        .chain(new.difference(&old).map(|net| Change::Added(*net)))
+        .collect();
+    changes.sort_by_key(Change::net);
+    changes
+}
+
+/// Writes the changes as plain `+net`/`-net` lines, suitable for piping into
+/// other tools.
+pub fn render_plain<W: Write>(out: &mut W, changes: &[Change]) -> io::Result<()> {
+    for change in changes {
+        match change {
+            Change::Added(net) => writeln!(out, "+{}", net)?,
+            Change::Removed(net) => writeln!(out, "-{}", net)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the changes for a human to read: grouped into an IPv4 and an IPv6
+/// section, with the networks and their address counts lined up in columns.
+pub fn render_pretty<W: Write>(out: &mut W, changes: &[Change], color: bool) -> io::Result<()> {
+    let paint = |code: &'static str| if color { code } else { "" };
+
+    let (v4, v6): (Vec<Change>, Vec<Change>) = changes
+        .iter()
+        .partition(|change| matches!(change.net(), IpNet::V4(_)));
+
+    let mut first = true;
+    for (family, section) in [("IPv4", v4), ("IPv6", v6)] {
+        if section.is_empty() {
+            continue;
+        }
+        if !first {
+            writeln!(out)?;
+        }
+        first = false;
+
+        let added = section
+            .iter()
+            .filter(|change| matches!(change, Change::Added(_)))
+            .count();
+        writeln!(
+            out,
+            "{}{}{} ({} added, {} removed)",
+            paint(BOLD),
+            family,
+            paint(RESET),
+            added,
+            section.len() - added
+        )?;
+
+        let sizes: Vec<String> = section
+            .iter()
+            .map(|change| address_count(&change.net()))
+            .collect();
+        let net_width = section
+            .iter()
+            .map(|change| change.net().to_string().len())
+            .max()
+            .unwrap_or(0);
+        let size_width = sizes.iter().map(String::len).max().unwrap_or(0);
+
+        for (change, size) in section.iter().zip(sizes) {
+            let (sign, code) = match change {
+                Change::Added(_) => ('+', GREEN),
+                Change::Removed(_) => ('-', RED),
+            };
+            writeln!(
+                out,
+                "  {}{} {:<net_width$}  {:>size_width$} addresses{}",
+                paint(code),
+                sign,
+                change.net().to_string(),
+                size,
+                paint(RESET),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the number of addresses in the network.
+fn address_count(net: &IpNet) -> String {
+    space::Count(space::subnet_count(net, net.max_prefix_len())).to_string()
+}
+
+/// One step of an edit script turning one list of lines into another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Returns the shortest edit script that turns `old` into `new`, using Myers'
+/// O(ND) algorithm.
+///
+/// Only the part of each round's furthest-reaching paths that could have been
+/// touched is saved for the backtrack, so memory is O(D^2) rather than
+/// O((N+M)D). Lists that were both produced by lfc tend to be nearly
+/// identical, which keeps D small.
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // trace[d] holds v[-d..=d] as it was at the start of round d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, saved) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| saved[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            (at(prev_k), at(prev_k) - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Formats a unified diff hunk range. Empty ranges point at the line before
+/// them, per the format's convention.
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Writes a standard unified diff from `old` to `new`, with `context` lines of
+/// context around each change. Nothing is written if the lists are identical.
+///
+/// Both headers name `path`, so `patch -p0 < diff` updates that file in place.
+pub fn write_unified<W: Write>(
+    out: &mut W,
+    path: &str,
+    old: &[&str],
+    new: &[&str],
+    context: usize,
+) -> io::Result<()> {
+    let edits = edit_script(old, new);
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| **edit != Edit::Keep)
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    // Group changes that are close enough for their context to touch.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    writeln!(out, "--- {}", path)?;
+    writeln!(out, "+++ {}", path)?;
+
+    // Line positions in old and new at the start of each edit.
+    let (mut old_pos, mut new_pos) = (0, 0);
+    let mut positions = Vec::with_capacity(edits.len());
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Keep => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+
+    for (start, end) in hunks {
+        let span = &edits[start..end];
+        let old_len = span.iter().filter(|edit| **edit != Edit::Insert).count();
+        let new_len = span.iter().filter(|edit| **edit != Edit::Delete).count();
+        let (old_start, new_start) = positions[start];
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        )?;
+        for (offset, edit) in span.iter().enumerate() {
+            let (o, n) = positions[start + offset];
+            match edit {
+                Edit::Keep => writeln!(out, " {}", old[o])?,
+                Edit::Delete => writeln!(out, "-{}", old[o])?,
+                Edit::Insert => writeln!(out, "+{}", new[n])?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_diff_added_and_removed() {
+        let old = nets(&["10.0.0.0/24", "192.168.0.0/16"]);
+        let new = nets(&["10.0.0.0/23", "192.168.0.0/16", "2001:db8::/32"]);
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::Added("10.0.0.0/23".parse().unwrap()),
+                Change::Removed("10.0.0.0/24".parse().unwrap()),
+                Change::Added("2001:db8::/32".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let list = nets(&["10.0.0.0/8", "2001:db8::/32"]);
+        assert!(diff(&list, &list).is_empty());
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let changes = diff(&nets(&["10.0.0.0/24"]), &nets(&["10.0.1.0/24"]));
+        let mut out = Vec::new();
+        render_plain(&mut out, &changes).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "-10.0.0.0/24\n+10.0.1.0/24\n"
+        );
+    }
+
+    #[test]
+    fn test_render_pretty_sections_and_alignment() {
+        let changes = diff(
+            &nets(&["10.0.0.0/24", "2001:db8::/32"]),
+            &nets(&["10.0.0.0/8"]),
+        );
+        let mut out = Vec::new();
+        render_pretty(&mut out, &changes, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+IPv4 (1 added, 1 removed)
+  + 10.0.0.0/8   16777216 addresses
+  - 10.0.0.0/24       256 addresses
+
+IPv6 (0 added, 1 removed)
+  - 2001:db8::/32  79228162514264337593543950336 addresses
+"
+        );
+    }
+
+    #[test]
+    fn test_render_pretty_color() {
+        let changes = diff(&[], &nets(&["10.0.0.0/8"]));
+        let mut out = Vec::new();
+        render_pretty(&mut out, &changes, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b[32m+ 10.0.0.0/8"));
+    }
+
+    #[test]
+    fn test_address_count_ipv6_default_route() {
+        assert_eq!(
+            address_count(&"::/0".parse().unwrap()),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(address_count(&"0.0.0.0/0".parse().unwrap()), "4294967296");
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert_eq!("always".parse(), Ok(ColorChoice::Always));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    fn unified(old: &[&str], new: &[&str]) -> String {
+        let mut out = Vec::new();
+        write_unified(&mut out, "list.txt", old, new, 3).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_edit_script() {
+        use Edit::*;
+        assert_eq!(
+            edit_script(&["a", "b", "c"], &["a", "c", "d"]),
+            vec![Keep, Delete, Keep, Insert]
+        );
+        assert_eq!(edit_script::<&str>(&[], &[]), vec![]);
+        assert_eq!(edit_script(&[], &["a"]), vec![Insert]);
+        assert_eq!(edit_script(&["a"], &[]), vec![Delete]);
+    }
+
+    #[test]
+    fn test_unified_identical() {
+        assert_eq!(unified(&["a", "b"], &["a", "b"]), "");
+    }
+
+    #[test]
+    fn test_unified_single_hunk() {
+        let old = ["1", "2", "3", "4", "5", "6", "7", "8"];
+        let new = ["1", "2", "3", "4", "x", "6", "7", "8"];
+        assert_eq!(
+            unified(&old, &new),
+            "\
+--- list.txt
++++ list.txt
+@@ -2,7 +2,7 @@
+ 2
+ 3
+ 4
+-5
++x
+ 6
+ 7
+ 8
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_separate_hunks() {
+        let old: Vec<String> = (1..=20).map(|i| i.to_string()).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let mut new = old.clone();
+        new.remove(0);
+        new.push("21");
+        assert_eq!(
+            unified(&old, &new),
+            "\
+--- list.txt
++++ list.txt
+@@ -1,4 +1,3 @@
+-1
+ 2
+ 3
+ 4
+@@ -18,3 +17,4 @@
+ 18
+ 19
+ 20
++21
+"
+        );
+    }
+
+    #[test]
+    fn test_unified_from_empty() {
+        assert_eq!(
+            unified(&[], &["10.0.0.0/8"]),
+            "--- list.txt\n+++ list.txt\n@@ -0,0 +1 @@\n+10.0.0.0/8\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Errors from the library.
+
+use crate::source::{Provenance, Record};
+use std::io;
+use std::net::IpAddr;
+
+/// Everything that can go wrong in the library.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A line of input isn't an IP network.
+    #[error(
+        "Unable to parse {text:?} as an IP network on {}, column {column}: {reason}.",
+        location(.input, *.line)
+    )]
+    Parse {
+        /// The name of the source the line came from, if it came from one.
+        input: Option<String>,
+        /// The line number, counting from 1.
+        line: usize,
+        /// The character on the line where the problem starts, counting
+        /// from 1.
+        column: usize,
+        /// The text that was parsed, without surrounding whitespace.
+        text: String,
+        /// What's wrong with it.
+        reason: String,
+    },
+
+    /// Reading or writing failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A setting doesn't make sense, by itself or with the others.
+    #[error("{0}")]
+    Config(String),
+}
+
+/// Returns where a bad line came from, for the message.
+fn location(input: &Option<String>, line: usize) -> Provenance<'_> {
+    Provenance {
+        source: input.as_deref(),
+        line,
+    }
+}
+
+/// A result with the library's [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Describes why `text`, found in the record, isn't an IP network.
+    pub(crate) fn parse(record: &Record, text: &str) -> Error {
+        let (offset, reason) = diagnose(text);
+        Error::parse_because(record, text, offset, reason)
+    }
+
+    /// Says that `text`, found in the record, was rejected for `reason`,
+    /// starting `offset` bytes into it.
+    pub(crate) fn parse_because(
+        record: &Record,
+        text: &str,
+        offset: usize,
+        reason: String,
+    ) -> Error {
+        // `text` is a slice of the record, so this is where it starts on the
+        // line.
+        let start = text.as_ptr() as usize - record.text.as_ptr() as usize;
+        Error::Parse {
+            input: record.provenance.source.map(str::to_string),
+            line: record.provenance.line,
+            column: record.text[..start + offset].chars().count() + 1,
+            text: text.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Works out what's wrong with a network that didn't parse, and the byte
+/// offset in it where the problem starts.
+fn diagnose(text: &str) -> (usize, String) {
+    if let Some(at) = text.find('%') {
+        return (at, "IPv6 zone IDs aren't allowed".to_string());
+    }
+    let Some((address, prefix)) = text.rsplit_once('/') else {
+        return match text.parse::<IpAddr>() {
+            Ok(_) => (text.len(), "missing the /prefix length".to_string()),
+            Err(_) => (0, "not an IP address".to_string()),
+        };
+    };
+    let Ok(parsed) = address.parse::<IpAddr>() else {
+        return (0, "not an IP address".to_string());
+    };
+    let longest = if parsed.is_ipv4() { 32 } else { 128 };
+    let offset = address.len() + 1;
+    match prefix.parse::<u32>() {
+        Ok(len) if len > longest => (
+            offset,
+            format!("the prefix length is longer than /{}", longest),
+        ),
+        _ => (offset, "not a prefix length".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(source: Option<&'static str>, line: usize, text: &'static str) -> Record<'static> {
+        Record {
+            text,
+            provenance: Provenance { source, line },
+        }
+    }
+
+    fn parse_error(line: &'static str) -> (usize, String) {
+        match Error::parse(&record(None, 1, line), line.trim()) {
+            Error::Parse { column, reason, .. } => (column, reason),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(parse_error("bogus"), (1, "not an IP address".to_string()));
+        assert_eq!(
+            parse_error("  10.0.0.1"),
+            (11, "missing the /prefix length".to_string())
+        );
+        assert_eq!(
+            parse_error("10.0.0.0/33"),
+            (10, "the prefix length is longer than /32".to_string())
+        );
+        assert_eq!(
+            parse_error("\t2001:db8::/x"),
+            (13, "not a prefix length".to_string())
+        );
+        assert_eq!(
+            parse_error("10.0.0.300/8"),
+            (1, "not an IP address".to_string())
+        );
+        assert_eq!(
+            parse_error("fe80::1%eth0/64"),
+            (8, "IPv6 zone IDs aren't allowed".to_string())
+        );
+        // Columns count characters, not bytes.
+        assert_eq!(
+            parse_error("\u{3000}10.0.0.1"),
+            (10, "missing the /prefix length".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        assert_eq!(
+            Error::parse(&record(None, 3, "10.0.0.1"), "10.0.0.1").to_string(),
+            "Unable to parse \"10.0.0.1\" as an IP network on line 3, column 9: \
+             missing the /prefix length."
+        );
+        assert_eq!(
+            Error::parse(&record(Some("list.txt"), 3, "10.0.0.1"), "10.0.0.1").to_string(),
+            "Unable to parse \"10.0.0.1\" as an IP network on list.txt line 3, column 9: \
+             missing the /prefix length."
+        );
+    }
+}
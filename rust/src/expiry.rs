@@ -0,0 +1,44 @@
+//! Entries that expire, for self-cleaning dynamic lists.
+//!
+//! A line can have a timestamp after its network, like
+//! `192.0.2.0/24 2025-09-19T12:34:56Z` or
+//! `192.0.2.0/24 expires=1758285296`, and is left out of the input once
+//! that time has passed. With `--expire-after`, the timestamp is when the
+//! entry was added instead, and it expires that long afterward.
+
+/// Splits a line into its network and when it expires, if it has a
+/// timestamp after the network. `after` is how many seconds after its
+/// timestamp an entry expires. Returns `None` if the line doesn't have one,
+/// so that it's parsed as it is, and any mistake in it is reported as a bad
+/// network.
+pub fn split(line: &str, after: u64) -> Option<(&str, u64)> {
+    let (net, timestamp) = line.trim().split_once(char::is_whitespace)?;
+    let timestamp = timestamp.trim_start();
+    let timestamp = timestamp.strip_prefix("expires=").unwrap_or(timestamp);
+    let expires = crate::clock::parse_timestamp(timestamp)?;
+    Some((net, expires.saturating_add(after)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("192.0.2.0/24 2025-09-19T12:34:56Z", 0),
+            Some(("192.0.2.0/24", 1_758_285_296))
+        );
+        assert_eq!(
+            split("  2001:db8::/32\texpires=1758285296 ", 0),
+            Some(("2001:db8::/32", 1_758_285_296))
+        );
+        assert_eq!(
+            split("192.0.2.0/24 2025-09-19", 86_400),
+            Some(("192.0.2.0/24", 1_758_326_400))
+        );
+        assert_eq!(split("192.0.2.0/24", 0), None);
+        assert_eq!(split("192.0.2.0/24 tomorrow", 0), None);
+        assert_eq!(split("192.0.2.0/24 1758285296 extra", 0), None);
+    }
+}
@@ -0,0 +1,204 @@
+//! A fast path for parsing networks.
+//!
+//! On big inputs, most of lfc's time goes to `IpNet::from_str`, which is
+//! general and careful and goes through several layers of parser
+//! combinators per address. Nearly every line of a real list is a plain
+//! `a.b.c.d/n` or `x:y::z/n`, so this handles exactly those with straight
+//! loops over the bytes, and returns `None` for anything else so the caller
+//! can fall back to the real parser. It never accepts anything that
+//! `IpNet::from_str` rejects, and never parses anything differently.
+//!
+//! Finding the lines in the first place is already vectorized: the input
+//! scanner splits them with `memchr`.
+//!
+//! This is behind the `fast-parse` feature. `cargo bench --features
+//! fast-parse` compares the two.
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Parses a network in its common form, or returns `None` to let
+/// `IpNet::from_str` decide.
+pub fn parse(text: &str) -> Option<IpNet> {
+    let bytes = text.as_bytes();
+    if let Some((address, prefix)) = parse_v4(bytes) {
+        return Ipv4Net::new(address, parse_prefix(prefix)?)
+            .ok()
+            .map(IpNet::V4);
+    }
+    let slash = bytes.iter().rposition(|&byte| byte == b'/')?;
+    let address = parse_v6(&bytes[..slash])?;
+    Ipv6Net::new(address, parse_prefix(&bytes[slash + 1..])?)
+        .ok()
+        .map(IpNet::V6)
+}
+
+/// Parses a prefix length of one to three digits without leading zeros.
+fn parse_prefix(bytes: &[u8]) -> Option<u8> {
+    if bytes.is_empty() || bytes.len() > 3 || (bytes.len() > 1 && bytes[0] == b'0') {
+        return None;
+    }
+    let mut value: u32 = 0;
+    for &byte in bytes {
+        let digit = byte.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        value = value * 10 + digit as u32;
+    }
+    u8::try_from(value).ok()
+}
+
+/// Parses the IPv4 address at the start of `bytes` in one pass, and returns
+/// it with whatever follows the `/` after it.
+fn parse_v4(bytes: &[u8]) -> Option<(Ipv4Addr, &[u8])> {
+    let mut address: u32 = 0;
+    let mut octets = 0;
+    let mut value: u32 = 0;
+    let mut digits = 0;
+    for (index, &byte) in bytes.iter().enumerate() {
+        let digit = byte.wrapping_sub(b'0');
+        if digit <= 9 {
+            // The standard parser rejects leading zeros, since some tools
+            // would read them as octal.
+            if digits == 3 || (digits == 1 && value == 0) {
+                return None;
+            }
+            value = value * 10 + digit as u32;
+            digits += 1;
+            continue;
+        }
+        if digits == 0 || value > 255 {
+            return None;
+        }
+        address = address << 8 | value;
+        octets += 1;
+        match (byte, octets) {
+            (b'.', 1..=3) => {}
+            (b'/', 4) => return Some((Ipv4Addr::from(address), &bytes[index + 1..])),
+            _ => return None,
+        }
+        value = 0;
+        digits = 0;
+    }
+    None
+}
+
+/// Parses up to `limit` colon-separated hex groups into `groups`, and
+/// returns how many there were.
+fn parse_groups(bytes: &[u8], groups: &mut [u16], limit: usize) -> Option<usize> {
+    if bytes.is_empty() {
+        return Some(0);
+    }
+    let mut count = 0;
+    for group in bytes.split(|&byte| byte == b':') {
+        if group.is_empty() || group.len() > 4 || count == limit {
+            return None;
+        }
+        let mut value: u16 = 0;
+        for &byte in group {
+            let digit = match byte {
+                b'0'..=b'9' => byte - b'0',
+                b'a'..=b'f' => byte - b'a' + 10,
+                b'A'..=b'F' => byte - b'A' + 10,
+                _ => return None,
+            };
+            value = value << 4 | digit as u16;
+        }
+        groups[count] = value;
+        count += 1;
+    }
+    Some(count)
+}
+
+/// Parses an IPv6 address made of hex groups and at most one `::`. Anything
+/// fancier, like an embedded IPv4 address, is left to the real parser.
+fn parse_v6(bytes: &[u8]) -> Option<Ipv6Addr> {
+    let mut groups = [0u16; 8];
+    match bytes.windows(2).position(|pair| pair == b"::") {
+        None => {
+            if parse_groups(bytes, &mut groups, 8)? != 8 {
+                return None;
+            }
+        }
+        Some(gap) => {
+            let (head, tail) = (&bytes[..gap], &bytes[gap + 2..]);
+            // "::" has to stand in for at least one group.
+            let head_count = parse_groups(head, &mut groups, 7)?;
+            let mut tail_groups = [0u16; 7];
+            let tail_count = parse_groups(tail, &mut tail_groups, 7 - head_count)?;
+            groups[8 - tail_count..].copy_from_slice(&tail_groups[..tail_count]);
+        }
+    }
+    Some(Ipv6Addr::from(groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_common_forms() {
+        for text in [
+            "0.0.0.0/0",
+            "10.0.0.0/8",
+            "192.168.1.0/24",
+            "255.255.255.255/32",
+            "10.1.2.3/8",
+            "::/0",
+            "::1/128",
+            "2001:db8::/32",
+            "2001:DB8:0:0:0:0:0:1/128",
+            "fe80::1:2/64",
+            "1:2:3:4:5:6:7::/112",
+            "::2:3:4:5:6:7:8/128",
+        ] {
+            assert_eq!(parse(text), Some(text.parse().unwrap()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_never_disagrees() {
+        for text in [
+            "",
+            "/",
+            "10.0.0.0",
+            "10.0.0.0/",
+            "10.0.0.0/33",
+            "10.0.0.0/08",
+            "10.0.0.0/256",
+            "10.0.0.01/8",
+            "10.0.0.256/8",
+            "10.0.0/8",
+            "10.0.0.0.0/8",
+            "10..0.0/8",
+            ".10.0.0/8",
+            "1000.0.0.0/8",
+            "10.0.0.0/+8",
+            " 10.0.0.0/8",
+            "::/129",
+            ":::/0",
+            "1::2::3/64",
+            "1:2:3:4:5:6:7:8::/128",
+            "1:2:3:4:5:6:7/112",
+            "1:2:3:4:5:6:7:8:9/128",
+            "12345::/16",
+            "g::/16",
+            ":1::/16",
+            "1::2:/16",
+            "::ffff:10.0.0.1/128",
+            "1:2:3:4:5:6:7::8/128",
+        ] {
+            if let Some(net) = parse(text) {
+                assert_eq!(Ok(net), text.parse::<IpNet>(), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_falls_back() {
+        assert_eq!(parse("10.0.0.01/8"), None);
+        assert_eq!(parse("::ffff:10.0.0.1/128"), None);
+        assert_eq!(parse("1:2:3:4:5:6:7::8/128"), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! Ranges that services publish as JSON, for `lfc fetch`.
+//!
+//! Each feed is downloaded like any other URL source, and its networks are
+//! picked out of the JSON into a list, one per line, that's aggregated like
+//! any other input. That's what crawler and webhook allowlists are built
+//! from. RIPEstat's announced prefixes are a feed too, but of whichever
+//! ASes are asked about.
+
+use lfc::Parser;
+use lfc::source::{self, MemorySource};
+use serde_json::Value;
+
+/// A service's published ranges.
+pub struct Feed {
+    /// The name `lfc fetch` knows it by.
+    pub name: &'static str,
+    pub url: &'static str,
+    /// Returns the networks in the JSON.
+    networks: fn(&Value) -> Vec<String>,
+}
+
+/// The feeds `lfc fetch` knows.
+pub const FEEDS: &[Feed] = &[
+    Feed {
+        name: "github-meta",
+        url: "https://api.github.com/meta",
+        networks: github_meta,
+    },
+    Feed {
+        name: "googlebot",
+        url: "https://developers.google.com/static/search/apis/ipranges/googlebot.json",
+        networks: googlebot,
+    },
+];
+
+/// The feed of the prefixes that ASes announce, from RIPEstat.
+pub const RIPESTAT: &str = "ripestat";
+
+/// Returns the feed with the name.
+pub fn find(name: &str) -> Option<&'static Feed> {
+    FEEDS.iter().find(|feed| feed.name == name)
+}
+
+/// Returns the networks from GitHub's meta API: those of every service,
+/// like hooks, actions, and pages. The API has other things too, like SSH
+/// keys, so only strings that are networks are kept.
+fn github_meta(meta: &Value) -> Vec<String> {
+    let parser = Parser::new();
+    meta.as_object()
+        .into_iter()
+        .flat_map(|meta| meta.values())
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter(|text| parser.parse(text).is_some())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the networks from one of Google's crawler range lists, where
+/// each prefix is either an `ipv4Prefix` or an `ipv6Prefix`.
+fn googlebot(ranges: &Value) -> Vec<String> {
+    ranges["prefixes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|prefix| {
+            prefix["ipv4Prefix"]
+                .as_str()
+                .or_else(|| prefix["ipv6Prefix"].as_str())
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the URL of the prefixes RIPEstat saw the AS announce, between
+/// the times if there are any, or in the last two weeks if not.
+fn ripestat_url(asn: u32, since: Option<&str>, until: Option<&str>) -> String {
+    let mut url = format!(
+        "https://stat.ripe.net/data/announced-prefixes/data.json?resource=AS{}&sourceapp=lfc",
+        asn
+    );
+    if let Some(since) = since {
+        url += &format!("&starttime={}", since);
+    }
+    if let Some(until) = until {
+        url += &format!("&endtime={}", until);
+    }
+    url
+}
+
+/// Returns the prefixes in RIPEstat's announced-prefixes data.
+fn ripestat_prefixes(data: &Value) -> Vec<String> {
+    data["data"]["prefixes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|prefix| prefix["prefix"].as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Downloads the prefixes that RIPEstat saw each AS announce, between the
+/// times if there are any, as a list for each AS named by its URL.
+pub fn ripestat(
+    asns: &[u32],
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<Vec<MemorySource>, String> {
+    asns.iter()
+        .map(|&asn| {
+            let url = ripestat_url(asn, since, until);
+            let download = source::open(&url).map_err(|err| err.to_string())?;
+            let json: Value = serde_json::from_slice(download.bytes())
+                .map_err(|err| format!("{} isn't valid JSON: {}", url, err))?;
+            let prefixes = ripestat_prefixes(&json);
+            if prefixes.is_empty() {
+                return Err(format!(
+                    "RIPEstat didn't see AS{} announce any prefixes",
+                    asn
+                ));
+            }
+            Ok(MemorySource::new(&url, prefixes.join("\n")))
+        })
+        .collect()
+}
+
+/// Checks a time for `--since` or `--until`: a date, a date and time, or
+/// seconds since the Unix epoch, like `2024-01-31`, `2024-01-31T12:00:00Z`,
+/// or `1706702400`.
+pub fn parse_time(text: &str) -> Option<&str> {
+    let mut chars = text.chars();
+    let plain = chars.next().is_some_and(|c| c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_digit() || "-:TZ".contains(c));
+    plain.then_some(text)
+}
+
+impl Feed {
+    /// Downloads the feed and returns its networks as a list named by the
+    /// feed's URL.
+    pub fn fetch(&self) -> Result<MemorySource, String> {
+        let download = source::open(self.url).map_err(|err| err.to_string())?;
+        let json: Value = serde_json::from_slice(download.bytes())
+            .map_err(|err| format!("{} isn't valid JSON: {}", self.url, err))?;
+        let networks = (self.networks)(&json);
+        if networks.is_empty() {
+            return Err(format!("{} doesn't list any networks", self.url));
+        }
+        Ok(MemorySource::new(self.url, networks.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_github_meta() {
+        let meta = json!({
+            "verifiable_password_authentication": false,
+            "ssh_keys": ["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl"],
+            "hooks": ["192.30.252.0/22", "2a0a:a440::/29"],
+            "actions": ["4.148.0.0/16"],
+            "domains": {"website": ["*.github.com"]},
+        });
+        let mut networks = github_meta(&meta);
+        networks.sort();
+        assert_eq!(
+            networks,
+            vec!["192.30.252.0/22", "2a0a:a440::/29", "4.148.0.0/16"]
+        );
+    }
+
+    #[test]
+    fn test_googlebot() {
+        let ranges = json!({
+            "creationTime": "2025-09-19T12:34:56.000000",
+            "prefixes": [
+                {"ipv6Prefix": "2001:4860:4801:10::/64"},
+                {"ipv4Prefix": "66.249.64.0/27"},
+            ],
+        });
+        assert_eq!(
+            googlebot(&ranges),
+            vec!["2001:4860:4801:10::/64", "66.249.64.0/27"]
+        );
+    }
+
+    #[test]
+    fn test_ripestat() {
+        let data = json!({
+            "status": "ok",
+            "data": {
+                "prefixes": [
+                    {"prefix": "193.0.0.0/21", "timelines": [{"starttime": "2024-01-01T00:00:00"}]},
+                    {"prefix": "2001:67c:2e8::/48", "timelines": []},
+                ],
+                "resource": "3333",
+            },
+        });
+        assert_eq!(
+            ripestat_prefixes(&data),
+            vec!["193.0.0.0/21", "2001:67c:2e8::/48"]
+        );
+        assert!(ripestat_prefixes(&json!({"status": "error"})).is_empty());
+        assert_eq!(
+            ripestat_url(3333, Some("2024-01-01"), Some("2024-02-01T12:00:00Z")),
+            "https://stat.ripe.net/data/announced-prefixes/data.json?resource=AS3333&sourceapp=lfc&starttime=2024-01-01&endtime=2024-02-01T12:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("2024-01-31"), Some("2024-01-31"));
+        assert_eq!(
+            parse_time("2024-01-31T12:00:00Z"),
+            Some("2024-01-31T12:00:00Z")
+        );
+        assert_eq!(parse_time("1706702400"), Some("1706702400"));
+        assert_eq!(parse_time("last week"), None);
+        assert_eq!(parse_time("2024-01-31&x=1"), None);
+        assert_eq!(parse_time(""), None);
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("googlebot").unwrap().name, "googlebot");
+        assert!(find("bingbot").is_none());
+    }
+}
@@ -0,0 +1,354 @@
+//! Output formats for aggregated lists.
+//!
+//! Each format is a [`Formatter`] in its own module under `format/`, and a
+//! [`Registry`] maps format names to them. [`Registry::default`] has the
+//! built-in formats, and programs using lfc as a library can register their
+//! own alongside them.
+//!
+//! Formats write networks through [`Style::display`], so that the IPv6 form
+//! and case the user asked for apply to all of them.
+
+mod arrow;
+mod bind;
+mod bits;
+mod bpf;
+mod bsdroute;
+mod codegen;
+mod dnsmasq;
+mod dot;
+mod envoy;
+mod firewalld;
+mod frr;
+mod html;
+mod intrange;
+mod iproute;
+mod istio;
+mod json;
+mod markdown;
+mod msgpack;
+mod openbgpd;
+mod protobuf;
+mod text;
+mod toml;
+mod tree;
+mod unbound;
+mod windows;
+mod xml;
+mod yaml;
+
+use crate::{Error, Result};
+use ipnet::IpNet;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+pub use arrow::Arrow;
+pub use bind::BindAcl;
+pub use bits::Bits;
+pub use bpf::BpfMap;
+pub use bsdroute::BsdRoute;
+pub use codegen::Codegen;
+pub use dnsmasq::Dnsmasq;
+pub use dot::Dot;
+pub use envoy::Envoy;
+pub use firewalld::Firewalld;
+pub use frr::Frr;
+pub use html::HtmlReport;
+pub use intrange::IntRange;
+pub use iproute::IpRoute;
+pub use istio::Istio;
+pub use json::Json;
+pub use markdown::Markdown;
+pub use msgpack::MsgPack;
+pub use openbgpd::OpenBgpd;
+pub use protobuf::{PROTO, Protobuf};
+pub use text::Text;
+pub use toml::Toml;
+pub use tree::Tree;
+pub use unbound::Unbound;
+pub use windows::WindowsFirewall;
+pub use xml::Xml;
+pub use yaml::Yaml;
+
+/// Writes a list of networks in some format.
+pub trait Formatter: Send + Sync {
+    /// The name that selects the format, like `text`.
+    fn name(&self) -> &str;
+
+    /// A short description of the format, for listing them.
+    fn description(&self) -> &str;
+
+    /// Writes the networks, which are sorted and aggregated, in the style.
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()>;
+}
+
+/// How IPv6 addresses are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ipv6Form {
+    /// The RFC 5952 form, like `2001:db8::/32`.
+    #[default]
+    Compressed,
+    /// All eight groups of four digits, like
+    /// `2001:0db8:0000:0000:0000:0000:0000:0000/32`.
+    Expanded,
+}
+
+/// The case of hex digits in IPv6 addresses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HexCase {
+    /// Lowercase, as RFC 5952 recommends.
+    #[default]
+    Lower,
+    Upper,
+}
+
+/// How networks are written. IPv4 networks always look the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub ipv6_form: Ipv6Form,
+    pub hex_case: HexCase,
+}
+
+impl Style {
+    /// Returns the network, ready to be formatted in this style.
+    pub fn display<'a>(&self, net: &'a IpNet) -> Styled<'a> {
+        Styled { net, style: *self }
+    }
+
+    /// Returns a single address formatted in this style, without a prefix
+    /// length.
+    pub fn address(&self, address: IpAddr) -> String {
+        let mut text = self.display(&IpNet::from(address)).to_string();
+        text.truncate(text.rfind('/').unwrap_or(text.len()));
+        text
+    }
+}
+
+/// A network that formats in a [`Style`].
+pub struct Styled<'a> {
+    net: &'a IpNet,
+    style: Style,
+}
+
+impl fmt::Display for Styled<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let IpNet::V6(net) = self.net else {
+            return write!(f, "{}", self.net);
+        };
+        let text = match self.style.ipv6_form {
+            Ipv6Form::Compressed => net.to_string(),
+            Ipv6Form::Expanded => {
+                let groups: Vec<String> = net
+                    .addr()
+                    .segments()
+                    .iter()
+                    .map(|group| format!("{:04x}", group))
+                    .collect();
+                format!("{}/{}", groups.join(":"), net.prefix_len())
+            }
+        };
+        match self.style.hex_case {
+            HexCase::Lower => f.write_str(&text),
+            HexCase::Upper => f.write_str(&text.to_uppercase()),
+        }
+    }
+}
+
+/// The formats that can be selected by name.
+pub struct Registry {
+    formatters: Vec<Box<dyn Formatter>>,
+}
+
+impl Default for Registry {
+    /// Returns a registry with the built-in formats.
+    fn default() -> Self {
+        Registry {
+            formatters: vec![
+                Box::new(Text),
+                Box::new(Json),
+                Box::new(Tree),
+                Box::new(Dot::default()),
+                Box::new(HtmlReport::default()),
+                Box::new(Markdown::default()),
+                Box::new(Yaml),
+                Box::new(Toml::default()),
+                Box::new(Xml::default()),
+                Box::new(MsgPack),
+                Box::new(Protobuf),
+                Box::new(Arrow),
+                Box::new(IntRange::default()),
+                Box::new(Bits::default()),
+                Box::new(Dnsmasq::default()),
+                Box::new(Unbound::default()),
+                Box::new(BindAcl::default()),
+                Box::new(Envoy::default()),
+                Box::new(Istio::default()),
+                Box::new(BpfMap::default()),
+                Box::new(Codegen::default()),
+                Box::new(Firewalld::default()),
+                Box::new(WindowsFirewall::default()),
+                Box::new(IpRoute::default()),
+                Box::new(BsdRoute::default()),
+                Box::new(Frr::default()),
+                Box::new(OpenBgpd::default()),
+            ],
+        }
+    }
+}
+
+impl Registry {
+    /// Returns a registry without any formats.
+    pub fn empty() -> Registry {
+        Registry {
+            formatters: Vec::new(),
+        }
+    }
+
+    /// Adds a format. Its name can't already be taken.
+    pub fn register(&mut self, formatter: Box<dyn Formatter>) -> Result<()> {
+        if self.get(formatter.name()).is_some() {
+            return Err(Error::Config(format!(
+                "there's already a format named {:?}",
+                formatter.name()
+            )));
+        }
+        self.formatters.push(formatter);
+        Ok(())
+    }
+
+    /// Looks up a format by name.
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        self.formatters
+            .iter()
+            .find(|formatter| formatter.name() == name)
+            .map(Box::as_ref)
+    }
+
+    /// Returns the formats in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Formatter> {
+        self.formatters.iter().map(Box::as_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shout;
+
+    impl Formatter for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn description(&self) -> &str {
+            "Networks in capital letters"
+        }
+
+        fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+            for net in nets {
+                writeln!(out, "{}!", style.display(net).to_string().to_uppercase())?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_builtins() {
+        let registry = Registry::default();
+        let names: Vec<&str> = registry.iter().map(Formatter::name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "text",
+                "json",
+                "tree",
+                "dot",
+                "html-report",
+                "markdown",
+                "yaml",
+                "toml",
+                "xml",
+                "msgpack",
+                "protobuf",
+                "arrow",
+                "intrange",
+                "bits",
+                "dnsmasq",
+                "unbound",
+                "bind-acl",
+                "envoy",
+                "istio",
+                "bpf-map",
+                "codegen",
+                "firewalld",
+                "windows-firewall",
+                "iproute",
+                "bsd-route",
+                "frr",
+                "openbgpd"
+            ]
+        );
+        assert!(registry.get("nope").is_none());
+    }
+
+    #[test]
+    fn test_style() {
+        let nets: Vec<IpNet> = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8:a::/48".parse().unwrap(),
+            "::ffff:192.0.2.0/120".parse().unwrap(),
+        ];
+        let show = |style: Style| -> Vec<String> {
+            nets.iter()
+                .map(|net| style.display(net).to_string())
+                .collect()
+        };
+        assert_eq!(
+            show(Style::default()),
+            vec!["10.0.0.0/8", "2001:db8:a::/48", "::ffff:192.0.2.0/120"]
+        );
+        assert_eq!(
+            show(Style {
+                ipv6_form: Ipv6Form::Expanded,
+                hex_case: HexCase::Lower,
+            }),
+            vec![
+                "10.0.0.0/8",
+                "2001:0db8:000a:0000:0000:0000:0000:0000/48",
+                "0000:0000:0000:0000:0000:ffff:c000:0200/120"
+            ]
+        );
+        assert_eq!(
+            show(Style {
+                ipv6_form: Ipv6Form::Compressed,
+                hex_case: HexCase::Upper,
+            }),
+            vec!["10.0.0.0/8", "2001:DB8:A::/48", "::FFFF:192.0.2.0/120"]
+        );
+        let upper = Style {
+            ipv6_form: Ipv6Form::Compressed,
+            hex_case: HexCase::Upper,
+        };
+        assert_eq!(upper.address("2001:db8::a".parse().unwrap()), "2001:DB8::A");
+        assert_eq!(upper.address("192.0.2.1".parse().unwrap()), "192.0.2.1");
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = Registry::default();
+        registry.register(Box::new(Shout)).unwrap();
+        let mut out = Vec::new();
+        let nets = vec!["2001:db8::/32".parse().unwrap()];
+        registry
+            .get("shout")
+            .unwrap()
+            .write(&mut out, &nets, &Style::default())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "2001:DB8::/32!\n");
+        assert_eq!(
+            registry.register(Box::new(Shout)).unwrap_err().to_string(),
+            "there's already a format named \"shout\""
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Apache Arrow's IPC file format, for loading into dataframes.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// An Arrow IPC file, also known as Feather version 2, with one record
+/// batch holding a row for each network:
+///
+/// | Column | Type | Value |
+/// | --- | --- | --- |
+/// | `network` | Utf8 | The network, as the text format writes it |
+/// | `first` | FixedSizeBinary(16) | The network's first address |
+/// | `last` | FixedSizeBinary(16) | The network's last address |
+/// | `prefixlen` | UInt8 | The prefix length |
+/// | `family` | UInt8 | 4 or 6 |
+///
+/// IPv4 addresses are written as IPv4-mapped IPv6 addresses, like
+/// `::ffff:10.0.0.0`, so that both families fit in one column and sort
+/// together by their bytes. None of the columns have nulls.
+pub struct Arrow;
+
+/// The start of an Arrow file, padded to 8 bytes.
+const MAGIC: &[u8; 8] = b"ARROW1\0\0";
+
+/// `MetadataVersion.V5`.
+const VERSION: i16 = 4;
+
+/// A column's type.
+enum Column {
+    Utf8,
+    FixedSizeBinary(i32),
+    UInt(i32),
+}
+
+const COLUMNS: [(&str, Column); 5] = [
+    ("network", Column::Utf8),
+    ("first", Column::FixedSizeBinary(16)),
+    ("last", Column::FixedSizeBinary(16)),
+    ("prefixlen", Column::UInt(8)),
+    ("family", Column::UInt(8)),
+];
+
+/// Builds a FlatBuffer from its end back to its start, the way the
+/// FlatBuffers library does, so that everything is written before the
+/// offsets that point forward to it. The bytes are kept in reverse, and
+/// positions are counted back from the end.
+#[derive(Default)]
+struct FlatBuilder {
+    reversed: Vec<u8>,
+    min_align: usize,
+    /// The slot and position of each field in the table being built.
+    fields: Vec<(usize, usize)>,
+    table_start: usize,
+}
+
+impl FlatBuilder {
+    fn len(&self) -> usize {
+        self.reversed.len()
+    }
+
+    /// Pads so that the position is a multiple of `align` after
+    /// `additional` more bytes.
+    fn prep(&mut self, align: usize, additional: usize) {
+        self.min_align = self.min_align.max(align);
+        let padding = (align - (self.len() + additional) % align) % align;
+        self.reversed.resize(self.len() + padding, 0);
+    }
+
+    /// Adds a scalar from its big-endian bytes, which come out
+    /// little-endian once the buffer is reversed.
+    fn scalar(&mut self, bytes: &[u8]) {
+        self.prep(bytes.len(), 0);
+        self.reversed.extend_from_slice(bytes);
+    }
+
+    /// Adds an offset to something already written.
+    fn offset(&mut self, target: usize) {
+        self.prep(4, 0);
+        let relative = (self.len() + 4 - target) as u32;
+        self.reversed.extend_from_slice(&relative.to_be_bytes());
+    }
+
+    fn string(&mut self, text: &str) -> usize {
+        self.prep(4, text.len() + 1);
+        self.reversed.push(0);
+        self.reversed.extend(text.bytes().rev());
+        self.reversed
+            .extend_from_slice(&(text.len() as u32).to_be_bytes());
+        self.len()
+    }
+
+    /// Adds a vector of offsets to tables.
+    fn tables(&mut self, targets: &[usize]) -> usize {
+        self.prep(4, 4 * targets.len());
+        for &target in targets.iter().rev() {
+            self.offset(target);
+        }
+        self.reversed
+            .extend_from_slice(&(targets.len() as u32).to_be_bytes());
+        self.len()
+    }
+
+    /// Adds a vector of structs, each already laid out in little-endian
+    /// order and aligned to 8 bytes.
+    fn structs<const N: usize>(&mut self, structs: &[[u8; N]]) -> usize {
+        self.prep(8, N * structs.len());
+        for bytes in structs.iter().rev() {
+            self.reversed.extend(bytes.iter().rev());
+        }
+        self.reversed
+            .extend_from_slice(&(structs.len() as u32).to_be_bytes());
+        self.len()
+    }
+
+    fn start_table(&mut self) {
+        self.fields.clear();
+        self.table_start = self.len();
+    }
+
+    fn field(&mut self, slot: usize, bytes: &[u8]) {
+        self.scalar(bytes);
+        self.fields.push((slot, self.len()));
+    }
+
+    fn field_offset(&mut self, slot: usize, target: usize) {
+        self.offset(target);
+        self.fields.push((slot, self.len()));
+    }
+
+    /// Finishes the table with its vtable, and returns its position.
+    fn end_table(&mut self) -> usize {
+        self.scalar(&0i32.to_be_bytes());
+        let table = self.len();
+        let slots = self.fields.iter().map(|&(slot, _)| slot + 1).max();
+        let mut entries = vec![0u16; slots.unwrap_or(0)];
+        for &(slot, position) in &self.fields {
+            entries[slot] = (table - position) as u16;
+        }
+        for entry in entries.iter().rev() {
+            self.reversed.extend_from_slice(&entry.to_be_bytes());
+        }
+        let size = (table - self.table_start) as u16;
+        self.reversed.extend_from_slice(&size.to_be_bytes());
+        let size = (4 + 2 * entries.len()) as u16;
+        self.reversed.extend_from_slice(&size.to_be_bytes());
+        // The table starts with how far back its vtable is.
+        let vtable = (self.len() - table) as i32;
+        self.reversed[table - 4..table].copy_from_slice(&vtable.to_be_bytes());
+        table
+    }
+
+    fn finish(mut self, root: usize) -> Vec<u8> {
+        self.prep(self.min_align.max(4), 4);
+        self.offset(root);
+        self.reversed.reverse();
+        self.reversed
+    }
+}
+
+/// Returns two numbers as a `FieldNode` or `Buffer` struct.
+fn pair(first: usize, second: usize) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&(first as i64).to_le_bytes());
+    bytes[8..].copy_from_slice(&(second as i64).to_le_bytes());
+    bytes
+}
+
+/// Adds a `Schema` table.
+fn schema(builder: &mut FlatBuilder) -> usize {
+    let mut fields = Vec::new();
+    for (name, column) in &COLUMNS {
+        let name = builder.string(name);
+        let children = builder.tables(&[]);
+        builder.start_table();
+        // The tag in the `Type` union.
+        let tag = match column {
+            Column::Utf8 => 5,
+            Column::FixedSizeBinary(width) => {
+                builder.field(0, &width.to_be_bytes());
+                15
+            }
+            Column::UInt(bits) => {
+                builder.field(0, &bits.to_be_bytes());
+                builder.field(1, &[0]);
+                2
+            }
+        };
+        let kind = builder.end_table();
+        builder.start_table();
+        builder.field_offset(0, name);
+        builder.field_offset(3, kind);
+        builder.field_offset(5, children);
+        builder.field(1, &[0]);
+        builder.field(2, &[tag]);
+        fields.push(builder.end_table());
+    }
+    let fields = builder.tables(&fields);
+    builder.start_table();
+    builder.field_offset(1, fields);
+    // Little-endian.
+    builder.field(0, &0i16.to_be_bytes());
+    builder.end_table()
+}
+
+/// Returns a `Message` with the header that `header` adds, which is a
+/// `MessageHeader` union member with the tag `tag`.
+fn message(tag: u8, body: usize, header: impl FnOnce(&mut FlatBuilder) -> usize) -> Vec<u8> {
+    let mut builder = FlatBuilder::default();
+    let header = header(&mut builder);
+    builder.start_table();
+    builder.field(3, &(body as i64).to_be_bytes());
+    builder.field_offset(2, header);
+    builder.field(0, &VERSION.to_be_bytes());
+    builder.field(1, &[tag]);
+    let message = builder.end_table();
+    builder.finish(message)
+}
+
+/// Writes a message and its body, and returns the length of everything
+/// before the body.
+fn write_message(out: &mut dyn Write, metadata: &[u8], body: &[u8]) -> io::Result<usize> {
+    let padding = (8 - metadata.len() % 8) % 8;
+    out.write_all(&[0xff; 4])?;
+    out.write_all(&((metadata.len() + padding) as i32).to_le_bytes())?;
+    out.write_all(metadata)?;
+    out.write_all(&[0; 8][..padding])?;
+    out.write_all(body)?;
+    Ok(8 + metadata.len() + padding)
+}
+
+/// A record batch's body, and the `Buffer`s that locate its parts.
+#[derive(Default)]
+struct Body {
+    bytes: Vec<u8>,
+    buffers: Vec<[u8; 16]>,
+}
+
+impl Body {
+    fn add(&mut self, data: &[u8]) {
+        self.buffers.push(pair(self.bytes.len(), data.len()));
+        self.bytes.extend_from_slice(data);
+        self.bytes.resize(self.bytes.len().next_multiple_of(8), 0);
+    }
+}
+
+/// Returns the address as 16 bytes.
+fn octets(addr: std::net::IpAddr) -> [u8; 16] {
+    match addr {
+        std::net::IpAddr::V4(addr) => addr.to_ipv6_mapped().octets(),
+        std::net::IpAddr::V6(addr) => addr.octets(),
+    }
+}
+
+impl Formatter for Arrow {
+    fn name(&self) -> &str {
+        "arrow"
+    }
+
+    fn description(&self) -> &str {
+        "An Apache Arrow IPC file with a row for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let mut text = String::new();
+        let mut offsets = 0i32.to_le_bytes().to_vec();
+        let mut first = Vec::with_capacity(16 * nets.len());
+        let mut last = Vec::with_capacity(16 * nets.len());
+        let mut prefixlen = Vec::with_capacity(nets.len());
+        let mut family = Vec::with_capacity(nets.len());
+        for net in nets {
+            use std::fmt::Write as _;
+            write!(text, "{}", style.display(net)).expect("writing to a String");
+            let offset = i32::try_from(text.len()).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "too many networks for Arrow")
+            })?;
+            offsets.extend_from_slice(&offset.to_le_bytes());
+            first.extend_from_slice(&octets(net.network()));
+            last.extend_from_slice(&octets(net.broadcast()));
+            prefixlen.push(net.prefix_len());
+            family.push(match net {
+                IpNet::V4(_) => 4,
+                IpNet::V6(_) => 6,
+            });
+        }
+        // Each column has an empty validity buffer, since there are no
+        // nulls, and then its values.
+        let mut body = Body::default();
+        body.add(&[]);
+        body.add(&offsets);
+        body.add(text.as_bytes());
+        for values in [&first, &last, &prefixlen, &family] {
+            body.add(&[]);
+            body.add(values);
+        }
+
+        out.write_all(MAGIC)?;
+        let schema_message = message(1, 0, schema);
+        let batch = MAGIC.len() + write_message(out, &schema_message, &[])?;
+        let batch_message = message(3, body.bytes.len(), |builder| {
+            let nodes = vec![pair(nets.len(), 0); COLUMNS.len()];
+            let nodes = builder.structs(&nodes);
+            let buffers = builder.structs(&body.buffers);
+            builder.start_table();
+            builder.field(0, &(nets.len() as i64).to_be_bytes());
+            builder.field_offset(1, nodes);
+            builder.field_offset(2, buffers);
+            builder.end_table()
+        });
+        let metadata = write_message(out, &batch_message, &body.bytes)?;
+        // The end of the stream.
+        out.write_all(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0])?;
+
+        let mut builder = FlatBuilder::default();
+        let schema = schema(&mut builder);
+        let dictionaries = builder.structs::<24>(&[]);
+        let mut block = [0; 24];
+        block[..8].copy_from_slice(&(batch as i64).to_le_bytes());
+        block[8..12].copy_from_slice(&(metadata as i32).to_le_bytes());
+        block[16..].copy_from_slice(&(body.bytes.len() as i64).to_le_bytes());
+        let batches = builder.structs(&[block]);
+        builder.start_table();
+        builder.field_offset(1, schema);
+        builder.field_offset(2, dictionaries);
+        builder.field_offset(3, batches);
+        builder.field(0, &VERSION.to_be_bytes());
+        let footer = builder.end_table();
+        let footer = builder.finish(footer);
+        out.write_all(&footer)?;
+        out.write_all(&(footer.len() as i32).to_le_bytes())?;
+        out.write_all(&MAGIC[..6])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_builder() {
+        let mut builder = FlatBuilder::default();
+        let name = builder.string("ab");
+        builder.start_table();
+        builder.field_offset(1, name);
+        builder.field(0, &7i16.to_be_bytes());
+        let table = builder.end_table();
+        // The offset to the table.
+        let mut expected = vec![12, 0, 0, 0];
+        // The vtable: its size, the table's size, and where the fields are.
+        expected.extend([8, 0, 12, 0, 6, 0, 8, 0]);
+        // The table: how far back the vtable is, padding, the number, and
+        // the offset to the name.
+        expected.extend([8, 0, 0, 0, 0, 0, 7, 0, 4, 0, 0, 0]);
+        expected.extend([2, 0, 0, 0, b'a', b'b', 0, 0]);
+        assert_eq!(builder.finish(table), expected);
+    }
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        Arrow.write(&mut out, &nets, &Style::default()).unwrap();
+        assert!(out.starts_with(b"ARROW1\0\0\xff\xff\xff\xff"));
+        assert!(out.ends_with(b"ARROW1"));
+        let footer = i32::from_le_bytes(out[out.len() - 10..out.len() - 6].try_into().unwrap());
+        let footer = out.len() - 10 - footer as usize;
+        assert_eq!(footer % 8, 0);
+        assert_eq!(
+            out[footer - 8..footer],
+            [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]
+        );
+
+        // The body is at the end of the stream, with the columns' values
+        // each padded to 8 bytes.
+        let mut body = Vec::new();
+        body.extend([0, 0, 0, 0, 10, 0, 0, 0, 23, 0, 0, 0, 0, 0, 0, 0]);
+        body.extend(b"10.0.0.0/82001:db8::/32\0");
+        body.extend([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 10, 0, 0, 0]);
+        body.extend([0x20, 1, 0xd, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        body.extend([
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 10, 0xff, 0xff, 0xff,
+        ]);
+        body.extend([0x20, 1, 0xd, 0xb8, 0xff, 0xff, 0xff, 0xff]);
+        body.extend([0xff; 8]);
+        body.extend([8, 32, 0, 0, 0, 0, 0, 0, 4, 6, 0, 0, 0, 0, 0, 0]);
+        assert!(out[..footer - 8].ends_with(&body));
+    }
+}
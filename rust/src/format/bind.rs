@@ -0,0 +1,127 @@
+//! A BIND ACL, for name servers that match clients against it.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The ACLs BIND always has, which can't be redefined.
+const BUILTIN: [&str; 4] = ["any", "none", "localhost", "localnets"];
+
+/// An `acl` statement for `named.conf`, with an element for each network.
+///
+/// With [exceptions](BindAcl::except), the networks to leave out are
+/// negated with `!` and come first, since BIND uses the first element that
+/// matches a client.
+#[derive(Debug)]
+pub struct BindAcl {
+    name: String,
+    except: Vec<IpNet>,
+}
+
+impl Default for BindAcl {
+    /// Returns the format with an ACL named `lfc`.
+    fn default() -> Self {
+        BindAcl {
+            name: "lfc".to_string(),
+            except: Vec::new(),
+        }
+    }
+}
+
+impl BindAcl {
+    /// Names the ACL. Returns `None` for names that can't be quoted, or that
+    /// belong to one of BIND's built-in ACLs.
+    pub fn name(mut self, name: &str) -> Option<Self> {
+        if name.is_empty()
+            || BUILTIN.contains(&name)
+            || name
+                .chars()
+                .any(|c| c == '"' || c == '\\' || c.is_control())
+        {
+            return None;
+        }
+        self.name = name.to_string();
+        Some(self)
+    }
+
+    /// Negates these networks, which are inside the list's networks, so the
+    /// ACL doesn't match them.
+    pub fn except(mut self, nets: Vec<IpNet>) -> Self {
+        self.except = nets;
+        self
+    }
+}
+
+impl Formatter for BindAcl {
+    fn name(&self) -> &str {
+        "bind-acl"
+    }
+
+    fn description(&self) -> &str {
+        "A BIND acl statement with the networks"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        writeln!(out, "acl \"{}\" {{", self.name)?;
+        for net in &self.except {
+            writeln!(out, "    !{};", style.display(net))?;
+        }
+        for net in nets {
+            writeln!(out, "    {};", style.display(net))?;
+        }
+        writeln!(out, "}};")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        BindAcl::default()
+            .name("blocked")
+            .unwrap()
+            .write(
+                &mut out,
+                &[net("192.0.2.0/24"), net("2001:db8::/32")],
+                &Style::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+acl \"blocked\" {
+    192.0.2.0/24;
+    2001:db8::/32;
+};
+"
+        );
+    }
+
+    #[test]
+    fn test_except() {
+        let mut out = Vec::new();
+        BindAcl::default()
+            .except(vec![net("192.0.2.128/25")])
+            .write(&mut out, &[net("192.0.2.0/24")], &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "acl \"lfc\" {\n    !192.0.2.128/25;\n    192.0.2.0/24;\n};\n"
+        );
+    }
+
+    #[test]
+    fn test_name() {
+        assert!(BindAcl::default().name("any").is_none());
+        assert!(BindAcl::default().name("a\"b").is_none());
+        assert!(BindAcl::default().name("").is_none());
+        assert!(BindAcl::default().name("blocked-v2").is_some());
+    }
+}
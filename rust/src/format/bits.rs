@@ -0,0 +1,116 @@
+//! The networks' addresses in binary or hexadecimal, to see how they line
+//! up.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// Each network next to its address in binary, with a `|` between the
+/// network bits and the host bits. IPv4 bits are grouped by octet, and
+/// IPv6 bits in groups of 16, like the address's text.
+///
+/// In [hexadecimal](Bits::hex), the `|` goes after the last digit with any
+/// network bits in it, so that a prefix length that isn't a multiple of 4
+/// has some host bits to the left of the `|`.
+#[derive(Debug, Default)]
+pub struct Bits {
+    hex: bool,
+}
+
+impl Bits {
+    /// Writes the addresses in hexadecimal instead of binary.
+    pub fn hex(mut self, hex: bool) -> Self {
+        self.hex = hex;
+        self
+    }
+
+    /// Returns the network's address with its boundary marked.
+    fn digits(&self, net: &IpNet) -> String {
+        let (value, group, separator) = match net {
+            IpNet::V4(net) => (u32::from(net.network()).into(), 8, '.'),
+            IpNet::V6(net) => (u128::from(net.network()), 16, ':'),
+        };
+        let bits = usize::from(net.max_prefix_len());
+        let unit = if self.hex { 4 } else { 1 };
+        let boundary = usize::from(net.prefix_len()).div_ceil(unit);
+        let mut text = String::new();
+        for i in 0..bits / unit {
+            if i == boundary {
+                text.push('|');
+            } else if i > 0 && i % (group / unit) == 0 {
+                text.push(separator);
+            }
+            let digit = (value >> (bits - unit * (i + 1))) as u32 & ((1 << unit) - 1);
+            text.push(char::from_digit(digit, 16).expect("a digit under 16"));
+        }
+        if boundary == bits / unit {
+            text.push('|');
+        }
+        text
+    }
+}
+
+impl Formatter for Bits {
+    fn name(&self) -> &str {
+        "bits"
+    }
+
+    fn description(&self) -> &str {
+        "Each network's address in binary, marking where the prefix ends"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let names: Vec<String> = nets
+            .iter()
+            .map(|net| style.display(net).to_string())
+            .collect();
+        let width = names.iter().map(String::len).max().unwrap_or(0);
+        for (net, name) in nets.iter().zip(&names) {
+            writeln!(out, "{:width$}  {}", name, self.digits(net))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: Bits, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            write(
+                Bits::default(),
+                &["10.0.0.0/8", "10.16.0.0/12", "192.0.2.1/32", "0.0.0.0/0"]
+            ),
+            "\
+10.0.0.0/8    00001010|00000000.00000000.00000000
+10.16.0.0/12  00001010.0001|0000.00000000.00000000
+192.0.2.1/32  11000000.00000000.00000010.00000001|
+0.0.0.0/0     |00000000.00000000.00000000.00000000
+"
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(
+            write(
+                Bits::default().hex(true),
+                &["10.16.0.0/12", "10.24.0.0/13", "2001:db8::/32"]
+            ),
+            "\
+10.16.0.0/12   0a.1|0.00.00
+10.24.0.0/13   0a.18|00.00
+2001:db8::/32  2001:0db8|0000:0000:0000:0000:0000:0000
+"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! bpftool commands, for loading XDP and tc filters' LPM trie maps.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A `bpftool map update` command for each network, adding it to a pinned
+/// `BPF_MAP_TYPE_LPM_TRIE` map. A trie's keys are all one size, so IPv4
+/// networks go into the map pinned at `PIN_v4` and IPv6 ones into `PIN_v6`.
+///
+/// Each key is a `struct bpf_lpm_trie_key`: the prefix length as a
+/// little-endian `__u32`, for x86-64 and arm64 hosts, then the network's
+/// address in network byte order. Every network gets the same value.
+#[derive(Debug)]
+pub struct BpfMap {
+    pin: String,
+    value: Vec<u8>,
+}
+
+impl Default for BpfMap {
+    /// Returns the format with maps pinned at `/sys/fs/bpf/lfc_v4` and
+    /// `/sys/fs/bpf/lfc_v6`, and a `__u32` 1 for each value.
+    fn default() -> Self {
+        BpfMap {
+            pin: "/sys/fs/bpf/lfc".to_string(),
+            value: vec![1, 0, 0, 0],
+        }
+    }
+}
+
+impl BpfMap {
+    /// Pins the maps at `PIN_v4` and `PIN_v6`. Returns `None` if the path
+    /// has anything but letters, digits, and `_./-`, which the commands
+    /// would have to quote.
+    pub fn pin(mut self, pin: &str) -> Option<Self> {
+        let plain = |c: char| c.is_ascii_alphanumeric() || "_./-".contains(c);
+        self.pin = (!pin.is_empty() && pin.chars().all(plain)).then(|| pin.to_string())?;
+        Some(self)
+    }
+
+    /// Uses another value for each network, in hex, like `01000000` or
+    /// `01 00 00 00`. Returns `None` if it isn't whole bytes of hex.
+    pub fn value(mut self, hex: &str) -> Option<Self> {
+        let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        if digits.is_empty()
+            || !digits.len().is_multiple_of(2)
+            || !digits.iter().all(u8::is_ascii_hexdigit)
+        {
+            return None;
+        }
+        let nibble = |digit: u8| (digit as char).to_digit(16).unwrap() as u8;
+        self.value = digits
+            .chunks(2)
+            .map(|pair| nibble(pair[0]) << 4 | nibble(pair[1]))
+            .collect();
+        Some(self)
+    }
+}
+
+/// Writes bytes as `hex` arguments for bpftool.
+fn hex(out: &mut dyn Write, bytes: &[u8]) -> io::Result<()> {
+    write!(out, "hex")?;
+    for byte in bytes {
+        write!(out, " {:02x}", byte)?;
+    }
+    Ok(())
+}
+
+impl Formatter for BpfMap {
+    fn name(&self) -> &str {
+        "bpf-map"
+    }
+
+    fn description(&self) -> &str {
+        "bpftool commands that add each network to an LPM trie map"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], _style: &Style) -> io::Result<()> {
+        for net in nets {
+            let mut key = u32::from(net.prefix_len()).to_le_bytes().to_vec();
+            let family = match net {
+                IpNet::V4(net) => {
+                    key.extend(net.network().octets());
+                    "v4"
+                }
+                IpNet::V6(net) => {
+                    key.extend(net.network().octets());
+                    "v6"
+                }
+            };
+            write!(
+                out,
+                "bpftool map update pinned {}_{} key ",
+                self.pin, family
+            )?;
+            hex(out, &key)?;
+            write!(out, " value ")?;
+            hex(out, &self.value)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bpf(format: &BpfMap, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            bpf(&BpfMap::default(), &["192.0.2.0/24", "2001:db8::/32"]),
+            "\
+bpftool map update pinned /sys/fs/bpf/lfc_v4 key hex 18 00 00 00 c0 00 02 00 value hex 01 00 00 00
+bpftool map update pinned /sys/fs/bpf/lfc_v6 key hex 20 00 00 00 20 01 0d b8 00 00 00 00 00 00 00 00 00 00 00 00 value hex 01 00 00 00
+"
+        );
+        assert_eq!(bpf(&BpfMap::default(), &[]), "");
+    }
+
+    #[test]
+    fn test_options() {
+        let format = BpfMap::default()
+            .pin("/sys/fs/bpf/xdp/deny")
+            .unwrap()
+            .value("ff 00")
+            .unwrap();
+        assert_eq!(
+            bpf(&format, &["10.0.0.0/8"]),
+            "bpftool map update pinned /sys/fs/bpf/xdp/deny_v4 key hex 08 00 00 00 0a 00 00 00 value hex ff 00\n"
+        );
+        assert!(BpfMap::default().pin("/sys/fs/bpf/my map").is_none());
+        assert!(BpfMap::default().pin("").is_none());
+        assert!(BpfMap::default().value("1").is_none());
+        assert!(BpfMap::default().value("zz").is_none());
+        assert!(BpfMap::default().value("+1").is_none());
+        assert!(BpfMap::default().value("").is_none());
+        assert_eq!(BpfMap::default().value("0A0b").unwrap().value, vec![10, 11]);
+    }
+}
@@ -0,0 +1,94 @@
+//! route(8) commands, for null-routing networks on FreeBSD and OpenBSD.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The route flags that drop traffic.
+const ACTIONS: [&str; 2] = ["blackhole", "reject"];
+
+/// A `route add` command for each network, through the loopback address
+/// with `-blackhole`, which drops the network's packets quietly, or with
+/// `-reject`, which answers them with an ICMP unreachable.
+#[derive(Debug)]
+pub struct BsdRoute {
+    action: &'static str,
+}
+
+impl Default for BsdRoute {
+    /// Returns the format with blackhole routes.
+    fn default() -> Self {
+        BsdRoute { action: ACTIONS[0] }
+    }
+}
+
+impl BsdRoute {
+    /// Uses another flag: blackhole or reject. Returns `None` for any other
+    /// flag.
+    pub fn action(mut self, name: &str) -> Option<Self> {
+        self.action = ACTIONS.into_iter().find(|action| *action == name)?;
+        Some(self)
+    }
+}
+
+impl Formatter for BsdRoute {
+    fn name(&self) -> &str {
+        "bsd-route"
+    }
+
+    fn description(&self) -> &str {
+        "BSD route commands that blackhole each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        for net in nets {
+            let (family, gateway) = match net {
+                IpNet::V4(_) => ("", "127.0.0.1"),
+                IpNet::V6(_) => ("-inet6 ", "::1"),
+            };
+            writeln!(
+                out,
+                "route add {}-net {} {} -{}",
+                family,
+                style.display(net),
+                gateway,
+                self.action
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(format: &BsdRoute, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            route(&BsdRoute::default(), &["192.0.2.0/24", "2001:db8::/32"]),
+            "\
+route add -net 192.0.2.0/24 127.0.0.1 -blackhole
+route add -inet6 -net 2001:db8::/32 ::1 -blackhole
+"
+        );
+        assert_eq!(route(&BsdRoute::default(), &[]), "");
+    }
+
+    #[test]
+    fn test_action() {
+        let format = BsdRoute::default().action("reject").unwrap();
+        assert_eq!(
+            route(&format, &["10.0.0.0/8"]),
+            "route add -net 10.0.0.0/8 127.0.0.1 -reject\n"
+        );
+        assert!(BsdRoute::default().action("unreachable").is_none());
+    }
+}
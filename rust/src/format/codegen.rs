@@ -0,0 +1,395 @@
+//! Source code with the networks built in, for firmware and agents that
+//! can't load a list at run time.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The languages the code can be in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Lang {
+    C,
+    Rust,
+}
+
+/// A C header or Rust module with a sorted table of each family's networks
+/// and a function that finds the longest one containing an address.
+///
+/// Each entry has the index of the innermost entry containing it, so the
+/// lookup is a binary search for the last network starting at or before
+/// the address, then a walk out through the networks containing that one.
+/// Aggregated networks don't contain each other, so the walk is only ever
+/// one step, but `--keep-structure` lists still work.
+#[derive(Debug)]
+pub struct Codegen {
+    lang: Lang,
+    prefix: String,
+}
+
+impl Default for Codegen {
+    /// Returns the format for C, with names starting with `lfc`.
+    fn default() -> Self {
+        Codegen {
+            lang: Lang::C,
+            prefix: "lfc".to_string(),
+        }
+    }
+}
+
+impl Codegen {
+    /// Writes code in another language: `c` or `rust`. Returns `None` for
+    /// any other language.
+    pub fn lang(mut self, name: &str) -> Option<Self> {
+        self.lang = match name {
+            "c" => Lang::C,
+            "rust" => Lang::Rust,
+            _ => return None,
+        };
+        Some(self)
+    }
+
+    /// Starts the tables' and functions' names with the prefix, so more
+    /// than one list can be in the same program. Returns `None` if the
+    /// prefix isn't an identifier.
+    pub fn prefix(mut self, prefix: &str) -> Option<Self> {
+        let mut chars = prefix.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        self.prefix = valid.then(|| prefix.to_string())?;
+        Some(self)
+    }
+}
+
+/// A network in a table: its first address, prefix length, the index of
+/// the innermost network containing it, and the network itself.
+type Entry = (u128, u8, Option<usize>, IpNet);
+
+/// Returns one family's networks, sorted, as a table.
+fn table(nets: &[IpNet], v4: bool) -> Vec<Entry> {
+    let mut family: Vec<IpNet> = nets
+        .iter()
+        .filter(|net| matches!(net, IpNet::V4(_)) == v4)
+        .map(IpNet::trunc)
+        .collect();
+    family.sort_unstable();
+    family.dedup();
+    let mut table: Vec<Entry> = Vec::with_capacity(family.len());
+    let mut open: Vec<usize> = Vec::new();
+    for net in family {
+        while open.last().is_some_and(|&i| !table[i].3.contains(&net)) {
+            open.pop();
+        }
+        let first = match net.network() {
+            std::net::IpAddr::V4(address) => u128::from(u32::from(address)),
+            std::net::IpAddr::V6(address) => u128::from(address),
+        };
+        table.push((first, net.prefix_len(), open.last().copied(), net));
+        open.push(table.len() - 1);
+    }
+    table
+}
+
+impl Codegen {
+    fn write_c(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let p = &self.prefix;
+        writeln!(
+            out,
+            "/* Longest-prefix match over {} networks, generated by lfc. */",
+            nets.len()
+        )?;
+        writeln!(out)?;
+        writeln!(out, "#include <stddef.h>")?;
+        writeln!(out, "#include <stdint.h>")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "\
+/* A network: its first address, its prefix length, and the index of the
+   innermost network containing it, or -1. */
+struct {p}_v4_net {{
+    uint32_t first;
+    uint8_t prefix_len;
+    int32_t parent;
+}};
+
+struct {p}_v6_net {{
+    uint64_t first_hi;
+    uint64_t first_lo;
+    uint8_t prefix_len;
+    int32_t parent;
+}};"
+        )?;
+
+        let v4 = table(nets, true);
+        writeln!(out)?;
+        if v4.is_empty() {
+            writeln!(
+                out,
+                "\
+/* There are no IPv4 networks. */
+static inline int32_t {p}_lookup_v4(const uint8_t addr[4]) {{
+    (void)addr;
+    return -1;
+}}"
+            )?;
+        } else {
+            writeln!(out, "static const struct {p}_v4_net {p}_v4[] = {{")?;
+            for (first, len, parent, net) in &v4 {
+                writeln!(
+                    out,
+                    "    {{0x{:08x}u, {}, {}}}, /* {} */",
+                    first,
+                    len,
+                    parent.map_or(-1, |i| i as i64),
+                    style.display(net)
+                )?;
+            }
+            writeln!(out, "}};")?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "\
+/* Returns the index in {p}_v4 of the longest network containing the
+   address, in network byte order, or -1 if none does. */
+static inline int32_t {p}_lookup_v4(const uint8_t addr[4]) {{
+    uint32_t a = (uint32_t)addr[0] << 24 | (uint32_t)addr[1] << 16 |
+                 (uint32_t)addr[2] << 8 | addr[3];
+    size_t lo = 0, hi = sizeof {p}_v4 / sizeof {p}_v4[0];
+    int32_t i;
+    while (lo < hi) {{
+        size_t mid = lo + (hi - lo) / 2;
+        if ({p}_v4[mid].first <= a)
+            lo = mid + 1;
+        else
+            hi = mid;
+    }}
+    for (i = (int32_t)lo - 1; i >= 0; i = {p}_v4[i].parent) {{
+        uint8_t len = {p}_v4[i].prefix_len;
+        uint32_t mask = len ? UINT32_MAX << (32 - len) : 0;
+        if ((a & mask) == {p}_v4[i].first)
+            return i;
+    }}
+    return -1;
+}}"
+            )?;
+        }
+
+        let v6 = table(nets, false);
+        writeln!(out)?;
+        if v6.is_empty() {
+            writeln!(
+                out,
+                "\
+/* There are no IPv6 networks. */
+static inline int32_t {p}_lookup_v6(const uint8_t addr[16]) {{
+    (void)addr;
+    return -1;
+}}"
+            )?;
+        } else {
+            writeln!(out, "static const struct {p}_v6_net {p}_v6[] = {{")?;
+            for (first, len, parent, net) in &v6 {
+                writeln!(
+                    out,
+                    "    {{UINT64_C(0x{:016x}), UINT64_C(0x{:016x}), {}, {}}}, /* {} */",
+                    first >> 64,
+                    first & u128::from(u64::MAX),
+                    len,
+                    parent.map_or(-1, |i| i as i64),
+                    style.display(net)
+                )?;
+            }
+            writeln!(out, "}};")?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "\
+/* Returns the index in {p}_v6 of the longest network containing the
+   address, in network byte order, or -1 if none does. */
+static inline int32_t {p}_lookup_v6(const uint8_t addr[16]) {{
+    uint64_t a_hi = 0, a_lo = 0;
+    size_t lo = 0, hi = sizeof {p}_v6 / sizeof {p}_v6[0], k;
+    int32_t i;
+    for (k = 0; k < 8; k++) {{
+        a_hi = a_hi << 8 | addr[k];
+        a_lo = a_lo << 8 | addr[k + 8];
+    }}
+    while (lo < hi) {{
+        size_t mid = lo + (hi - lo) / 2;
+        if ({p}_v6[mid].first_hi < a_hi ||
+            ({p}_v6[mid].first_hi == a_hi && {p}_v6[mid].first_lo <= a_lo))
+            lo = mid + 1;
+        else
+            hi = mid;
+    }}
+    for (i = (int32_t)lo - 1; i >= 0; i = {p}_v6[i].parent) {{
+        uint8_t len = {p}_v6[i].prefix_len;
+        uint64_t mask_hi = len >= 64 ? UINT64_MAX : len ? UINT64_MAX << (64 - len) : 0;
+        uint64_t mask_lo = len <= 64 ? 0 : UINT64_MAX << (128 - len);
+        if ((a_hi & mask_hi) == {p}_v6[i].first_hi && (a_lo & mask_lo) == {p}_v6[i].first_lo)
+            return i;
+    }}
+    return -1;
+}}"
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_rust(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        writeln!(
+            out,
+            "// Longest-prefix match over {} networks, generated by lfc.",
+            nets.len()
+        )?;
+        for (family, bits, int, v4) in [("v4", 32, "u32", true), ("v6", 128, "u128", false)] {
+            let table_name = format!("{}_{}", self.prefix, family).to_uppercase();
+            let function = format!("{}_lookup_{}", self.prefix, family);
+            let addr = if v4 { "Ipv4Addr" } else { "Ipv6Addr" };
+            writeln!(out)?;
+            writeln!(
+                out,
+                "\
+/// The IP{family} networks, sorted: each one's first address, prefix length,
+/// and the index of the innermost network containing it."
+            )?;
+            writeln!(
+                out,
+                "pub static {table_name}: &[({int}, u8, Option<usize>)] = &["
+            )?;
+            for (first, len, parent, net) in table(nets, v4) {
+                let parent = parent.map_or("None".to_string(), |i| format!("Some({})", i));
+                let first = if v4 {
+                    format!("0x{:08x}", first)
+                } else {
+                    format!("0x{:032x}", first)
+                };
+                writeln!(
+                    out,
+                    "    ({}, {}, {}), // {}",
+                    first,
+                    len,
+                    parent,
+                    style.display(&net)
+                )?;
+            }
+            writeln!(out, "];")?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "\
+/// Returns the index in `{table_name}` of the longest network containing the
+/// address, if any does.
+pub fn {function}(addr: std::net::{addr}) -> Option<usize> {{
+    let addr = {int}::from(addr);
+    let mut at = {table_name}
+        .partition_point(|net| net.0 <= addr)
+        .checked_sub(1);
+    while let Some(i) = at {{
+        let (first, len, parent) = {table_name}[i];
+        if addr & {int}::MAX.checked_shl({bits} - u32::from(len)).unwrap_or(0) == first {{
+            return Some(i);
+        }}
+        at = parent;
+    }}
+    None
+}}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for Codegen {
+    fn name(&self) -> &str {
+        "codegen"
+    }
+
+    fn description(&self) -> &str {
+        "C or Rust code with a longest-prefix match over the networks"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        match self.lang {
+            Lang::C => self.write_c(out, nets, style),
+            Lang::Rust => self.write_rust(out, nets, style),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn code(format: &Codegen, list: &[&str]) -> String {
+        let mut out = Vec::new();
+        format
+            .write(&mut out, &nets(list), &Style::default())
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_table() {
+        let list = nets(&[
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "10.1.2.0/24",
+            "10.2.0.0/16",
+            "11.0.0.0/8",
+        ]);
+        let parents: Vec<Option<usize>> = table(&list, true).iter().map(|entry| entry.2).collect();
+        assert_eq!(parents, vec![None, Some(0), Some(1), Some(0), None]);
+        assert_eq!(table(&list, false), vec![]);
+        assert_eq!(table(&list, true)[1].0, 0x0a01_0000);
+    }
+
+    #[test]
+    fn test_c() {
+        let c = code(&Codegen::default(), &["192.0.2.0/24", "2001:db8::/32"]);
+        assert!(c.starts_with("/* Longest-prefix match over 2 networks, generated by lfc. */\n"));
+        assert!(c.contains("    {0xc0000200u, 24, -1}, /* 192.0.2.0/24 */\n"));
+        assert!(c.contains(
+            "    {UINT64_C(0x20010db800000000), UINT64_C(0x0000000000000000), 32, -1}, \
+             /* 2001:db8::/32 */\n"
+        ));
+        assert!(c.contains("static inline int32_t lfc_lookup_v4(const uint8_t addr[4]) {\n"));
+        let empty = code(&Codegen::default(), &[]);
+        assert!(empty.contains("/* There are no IPv4 networks. */\n"));
+        assert!(!empty.contains("lfc_v4[]"));
+    }
+
+    #[test]
+    fn test_rust() {
+        let format = Codegen::default()
+            .lang("rust")
+            .unwrap()
+            .prefix("deny")
+            .unwrap();
+        let rust = code(&format, &["10.0.0.0/8", "10.1.0.0/16"]);
+        assert!(rust.contains(
+            "pub static DENY_V4: &[(u32, u8, Option<usize>)] = &[\n    \
+             (0x0a000000, 8, None), // 10.0.0.0/8\n    \
+             (0x0a010000, 16, Some(0)), // 10.1.0.0/16\n];\n"
+        ));
+        assert!(
+            rust.contains("pub fn deny_lookup_v4(addr: std::net::Ipv4Addr) -> Option<usize> {\n")
+        );
+        assert!(rust.contains("pub static DENY_V6: &[(u128, u8, Option<usize>)] = &[\n];\n"));
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(Codegen::default().lang("go").is_none());
+        assert!(Codegen::default().prefix("deny_list2").is_some());
+        assert!(Codegen::default().prefix("2deny").is_none());
+        assert!(Codegen::default().prefix("deny-list").is_none());
+        assert!(Codegen::default().prefix("").is_none());
+    }
+}
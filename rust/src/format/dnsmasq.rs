@@ -0,0 +1,91 @@
+//! dnsmasq configuration, for routers that filter DNS answers with it.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The dnsmasq options that take a network.
+const OPTIONS: [&str; 2] = ["ignore-address", "bogus-nxdomain"];
+
+/// A dnsmasq option for each network, like `ignore-address=192.0.2.0/24`,
+/// which drops DNS answers with addresses in the network.
+///
+/// dnsmasq's `ipset=`, `nftset=`, and `address=` options are keyed by
+/// domain, not by network, so they can't come from a list of networks.
+#[derive(Debug)]
+pub struct Dnsmasq {
+    option: &'static str,
+}
+
+impl Default for Dnsmasq {
+    /// Returns the format with `ignore-address` options.
+    fn default() -> Self {
+        Dnsmasq { option: OPTIONS[0] }
+    }
+}
+
+impl Dnsmasq {
+    /// Uses another option: `ignore-address`, or `bogus-nxdomain` to turn
+    /// answers with the networks' addresses into NXDOMAIN. Returns `None`
+    /// for any other option.
+    pub fn option(mut self, name: &str) -> Option<Self> {
+        self.option = OPTIONS.into_iter().find(|option| *option == name)?;
+        Some(self)
+    }
+}
+
+impl Formatter for Dnsmasq {
+    fn name(&self) -> &str {
+        "dnsmasq"
+    }
+
+    fn description(&self) -> &str {
+        "dnsmasq ignore-address options for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        for net in nets {
+            writeln!(out, "{}={}", self.option, style.display(net))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets() -> Vec<IpNet> {
+        vec![
+            "192.0.2.0/24".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        Dnsmasq::default()
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ignore-address=192.0.2.0/24\nignore-address=2001:db8::/32\n"
+        );
+    }
+
+    #[test]
+    fn test_option() {
+        let mut out = Vec::new();
+        Dnsmasq::default()
+            .option("bogus-nxdomain")
+            .unwrap()
+            .write(&mut out, &nets()[..1], &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "bogus-nxdomain=192.0.2.0/24\n"
+        );
+        assert!(Dnsmasq::default().option("ipset").is_none());
+    }
+}
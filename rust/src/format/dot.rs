@@ -0,0 +1,129 @@
+//! A Graphviz graph of how the networks were aggregated.
+
+use super::{Formatter, Style};
+use crate::Event;
+use ipnet::IpNet;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// A Graphviz digraph with the result's networks at the top and the input
+/// networks they were made from as leaves under them. The graph comes from
+/// the aggregator's [`Event`]s, so without any, it's only the result.
+///
+/// Merged halves hang from the network they were merged into, and networks
+/// inside another network hang from it by dotted edges. Space that
+/// [merge slack](crate::AggregatorBuilder::merge_slack) added hangs by
+/// dashed edges, and is dashed itself, since it wasn't in the input.
+#[derive(Debug, Default)]
+pub struct Dot {
+    events: Vec<Event>,
+}
+
+impl Dot {
+    /// Returns a graph of the events, in the order they happened.
+    pub fn new(events: Vec<Event>) -> Dot {
+        Dot { events }
+    }
+}
+
+impl Formatter for Dot {
+    fn name(&self) -> &str {
+        "dot"
+    }
+
+    fn description(&self) -> &str {
+        "A Graphviz graph of how the input networks were merged"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let name = |net: &IpNet| format!("\"{}\"", style.display(net));
+        writeln!(out, "digraph lfc {{")?;
+        writeln!(out, "    node [shape=box];")?;
+        for net in nets {
+            writeln!(out, "    {} [style=bold];", name(net))?;
+        }
+        // The same edge can come up more than once when inputs are
+        // aggregated separately and then together.
+        let mut seen = HashSet::new();
+        for event in &self.events {
+            let (into, children, attributes) = match event {
+                Event::Duplicate(_) => continue,
+                Event::Merged { halves, into } => (into, &halves[..], ""),
+                Event::Absorbed { net, into } => {
+                    (into, std::slice::from_ref(net), " [style=dotted]")
+                }
+                Event::Filled { gap, into } => {
+                    writeln!(out, "    {} [style=dashed];", name(gap))?;
+                    (into, std::slice::from_ref(gap), " [style=dashed]")
+                }
+            };
+            for child in children {
+                if seen.insert((*into, *child)) {
+                    writeln!(out, "    {} -> {}{};", name(into), name(child), attributes)?;
+                }
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        let events = vec![
+            Event::Duplicate(net("10.0.0.0/25")),
+            Event::Merged {
+                halves: [net("10.0.0.0/25"), net("10.0.0.128/25")],
+                into: net("10.0.0.0/24"),
+            },
+            Event::Absorbed {
+                net: net("10.0.1.0/25"),
+                into: net("10.0.1.0/24"),
+            },
+            Event::Merged {
+                halves: [net("10.0.0.0/24"), net("10.0.1.0/24")],
+                into: net("10.0.0.0/23"),
+            },
+            Event::Merged {
+                halves: [net("10.0.0.0/24"), net("10.0.1.0/24")],
+                into: net("10.0.0.0/23"),
+            },
+            Event::Filled {
+                gap: net("10.0.3.0/24"),
+                into: net("10.0.0.0/22"),
+            },
+        ];
+        let mut out = Vec::new();
+        Dot::new(events)
+            .write(
+                &mut out,
+                &[net("10.0.0.0/22"), net("2001:db8::/32")],
+                &Style::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+digraph lfc {
+    node [shape=box];
+    \"10.0.0.0/22\" [style=bold];
+    \"2001:db8::/32\" [style=bold];
+    \"10.0.0.0/24\" -> \"10.0.0.0/25\";
+    \"10.0.0.0/24\" -> \"10.0.0.128/25\";
+    \"10.0.1.0/24\" -> \"10.0.1.0/25\" [style=dotted];
+    \"10.0.0.0/23\" -> \"10.0.0.0/24\";
+    \"10.0.0.0/23\" -> \"10.0.1.0/24\";
+    \"10.0.3.0/24\" [style=dashed];
+    \"10.0.0.0/22\" -> \"10.0.3.0/24\" [style=dashed];
+}
+"
+        );
+    }
+}
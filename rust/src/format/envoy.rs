@@ -0,0 +1,108 @@
+//! Envoy RBAC principals, for edge proxies that block or allow networks.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use serde_json::json;
+use std::io::{self, Write};
+
+/// The RBAC principals that match a client's address.
+const PRINCIPALS: [&str; 3] = ["source_ip", "direct_remote_ip", "remote_ip"];
+
+/// A JSON object with the `principals` of an Envoy RBAC policy: one
+/// `source_ip` principal with a CidrRange for each network. JSON is also
+/// YAML, so the object can go into either kind of Envoy config.
+#[derive(Debug)]
+pub struct Envoy {
+    principal: &'static str,
+}
+
+impl Default for Envoy {
+    /// Returns the format with `source_ip` principals.
+    fn default() -> Self {
+        Envoy {
+            principal: PRINCIPALS[0],
+        }
+    }
+}
+
+impl Envoy {
+    /// Uses another principal: `direct_remote_ip`, which newer Envoys use
+    /// instead of `source_ip`, or `remote_ip`, which takes the address from
+    /// `X-Forwarded-For`. Returns `None` for any other principal.
+    pub fn principal(mut self, name: &str) -> Option<Self> {
+        self.principal = PRINCIPALS
+            .into_iter()
+            .find(|principal| *principal == name)?;
+        Some(self)
+    }
+}
+
+impl Formatter for Envoy {
+    fn name(&self) -> &str {
+        "envoy"
+    }
+
+    fn description(&self) -> &str {
+        "Envoy RBAC principals with a CidrRange for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let principals: Vec<_> = nets
+            .iter()
+            .map(|net| {
+                json!({
+                    self.principal: {
+                        "address_prefix": style.address(net.network()),
+                        "prefix_len": net.prefix_len(),
+                    }
+                })
+            })
+            .collect();
+        serde_json::to_writer(&mut *out, &json!({ "principals": principals }))?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets() -> Vec<IpNet> {
+        vec![
+            "192.0.2.0/24".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        Envoy::default()
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "{\"principals\":[",
+                "{\"source_ip\":{\"address_prefix\":\"192.0.2.0\",\"prefix_len\":24}},",
+                "{\"source_ip\":{\"address_prefix\":\"2001:db8::\",\"prefix_len\":32}}",
+                "]}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_principal() {
+        let mut out = Vec::new();
+        Envoy::default()
+            .principal("remote_ip")
+            .unwrap()
+            .write(&mut out, &nets()[..1], &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"principals\":[{\"remote_ip\":{\"address_prefix\":\"192.0.2.0\",\"prefix_len\":24}}]}\n"
+        );
+        assert!(Envoy::default().principal("header").is_none());
+    }
+}
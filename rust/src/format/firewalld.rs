@@ -0,0 +1,141 @@
+//! firewalld ipsets, for RHEL-family hosts.
+
+use super::{Formatter, Style};
+use crate::space::Family;
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// How many entries a `hash:net` set holds unless it says otherwise.
+const MAXELEM: usize = 65_536;
+
+/// A `hash:net` ipset document, for `/etc/firewalld/ipsets/NAME.xml`, where
+/// the file name names the set. An ipset holds one family, which is the
+/// networks' own unless [`family`](Firewalld::family) picks one.
+#[derive(Debug, Default)]
+pub struct Firewalld {
+    family: Option<Family>,
+}
+
+impl Firewalld {
+    /// Makes an `inet` or `inet6` set, leaving out the networks of the
+    /// other family. Returns `None` for any other family.
+    pub fn family(mut self, name: &str) -> Option<Self> {
+        self.family = Some(match name {
+            "inet" => Family::V4,
+            "inet6" => Family::V6,
+            _ => return None,
+        });
+        Some(self)
+    }
+}
+
+impl Formatter for Firewalld {
+    fn name(&self) -> &str {
+        "firewalld"
+    }
+
+    fn description(&self) -> &str {
+        "A firewalld hash:net ipset with an entry for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let family = match self.family {
+            Some(family) => family,
+            None => {
+                let mut families = nets.iter().map(Family::of);
+                let first = families.next().unwrap_or(Family::V4);
+                if families.any(|family| family != first) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "a firewalld ipset holds only IPv4 or only IPv6 networks",
+                    ));
+                }
+                first
+            }
+        };
+        let entries: Vec<&IpNet> = nets
+            .iter()
+            .filter(|net| Family::of(net) == family)
+            .collect();
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
+        writeln!(out, "<ipset type=\"hash:net\">")?;
+        if family == Family::V6 {
+            writeln!(out, "  <option name=\"family\" value=\"inet6\"/>")?;
+        }
+        if entries.len() > MAXELEM {
+            writeln!(
+                out,
+                "  <option name=\"maxelem\" value=\"{}\"/>",
+                entries.len()
+            )?;
+        }
+        for net in entries {
+            writeln!(out, "  <entry>{}</entry>", style.display(net))?;
+        }
+        writeln!(out, "</ipset>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn firewalld(format: &Firewalld, nets: &[&str]) -> io::Result<String> {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            firewalld(&Firewalld::default(), &["192.0.2.0/24", "198.51.100.7/32"]).unwrap(),
+            "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<ipset type=\"hash:net\">
+  <entry>192.0.2.0/24</entry>
+  <entry>198.51.100.7/32</entry>
+</ipset>
+"
+        );
+        assert_eq!(
+            firewalld(&Firewalld::default(), &["2001:db8::/32"]).unwrap(),
+            "\
+<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<ipset type=\"hash:net\">
+  <option name=\"family\" value=\"inet6\"/>
+  <entry>2001:db8::/32</entry>
+</ipset>
+"
+        );
+        assert!(
+            firewalld(&Firewalld::default(), &[])
+                .unwrap()
+                .contains("<ipset type=\"hash:net\">\n</ipset>")
+        );
+    }
+
+    #[test]
+    fn test_family() {
+        let mixed = ["192.0.2.0/24", "2001:db8::/32"];
+        assert!(firewalld(&Firewalld::default(), &mixed).is_err());
+        let v6 = firewalld(&Firewalld::default().family("inet6").unwrap(), &mixed).unwrap();
+        assert!(v6.contains("<entry>2001:db8::/32</entry>"));
+        assert!(!v6.contains("192.0.2.0"));
+        assert!(Firewalld::default().family("ipv4").is_none());
+    }
+
+    #[test]
+    fn test_maxelem() {
+        let nets: Vec<IpNet> = (0..=MAXELEM as u32)
+            .map(|i| IpNet::from(std::net::IpAddr::from(std::net::Ipv4Addr::from(i << 8))))
+            .collect();
+        let mut out = Vec::new();
+        Firewalld::default()
+            .write(&mut out, &nets, &Style::default())
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("  <option name=\"maxelem\" value=\"65537\"/>\n"));
+    }
+}
@@ -0,0 +1,157 @@
+//! FRR and Quagga prefix lists, for filtering BGP announcements.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// How far apart the sequence numbers are, leaving room to insert entries
+/// by hand.
+const STEP: usize = 5;
+
+/// An `ip prefix-list` line for each IPv4 network and an `ipv6 prefix-list`
+/// line for each IPv6 one, permitting them, numbered in steps of five for
+/// each family, to paste into vtysh or load with `vtysh -f`.
+///
+/// With [`ge`](Frr::ge) or [`le`](Frr::le), each entry also matches the
+/// longer networks inside it, down to those lengths. A length is only
+/// written where it's longer than the entry's own and no longer than the
+/// family's addresses, since FRR rejects any other.
+#[derive(Debug)]
+pub struct Frr {
+    name: String,
+    ge: Option<u8>,
+    le: Option<u8>,
+}
+
+impl Default for Frr {
+    /// Returns the format with exact entries in a list named `lfc`.
+    fn default() -> Self {
+        Frr {
+            name: "lfc".to_string(),
+            ge: None,
+            le: None,
+        }
+    }
+}
+
+impl Frr {
+    /// Names the prefix lists. Returns `None` if the name has anything but
+    /// letters, digits, and `_.-`.
+    pub fn name(mut self, name: &str) -> Option<Self> {
+        let plain = |c: char| c.is_ascii_alphanumeric() || "_.-".contains(c);
+        if name.is_empty() || !name.chars().all(plain) {
+            return None;
+        }
+        self.name = name.to_string();
+        Some(self)
+    }
+
+    /// Matches only the networks at least this long inside each entry.
+    pub fn ge(mut self, len: u8) -> Self {
+        self.ge = Some(len);
+        self
+    }
+
+    /// Matches the networks up to this long inside each entry.
+    pub fn le(mut self, len: u8) -> Self {
+        self.le = Some(len);
+        self
+    }
+}
+
+impl Formatter for Frr {
+    fn name(&self) -> &str {
+        "frr"
+    }
+
+    fn description(&self) -> &str {
+        "FRR prefix-list lines that permit each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let (mut v4, mut v6) = (0, 0);
+        for net in nets {
+            let (command, seq) = match net {
+                IpNet::V4(_) => ("ip", &mut v4),
+                IpNet::V6(_) => ("ipv6", &mut v6),
+            };
+            *seq += STEP;
+            write!(
+                out,
+                "{} prefix-list {} seq {} permit {}",
+                command,
+                self.name,
+                seq,
+                style.display(net)
+            )?;
+            let fits = |len: &u8| *len > net.prefix_len() && *len <= net.max_prefix_len();
+            if let Some(ge) = self.ge.filter(fits) {
+                write!(out, " ge {}", ge)?;
+            }
+            if let Some(le) = self.le.filter(fits) {
+                write!(out, " le {}", le)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frr(format: &Frr, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            frr(
+                &Frr::default().name("AS64500-IN").unwrap(),
+                &["192.0.2.0/24", "198.51.100.0/24", "2001:db8::/32"]
+            ),
+            "\
+ip prefix-list AS64500-IN seq 5 permit 192.0.2.0/24
+ip prefix-list AS64500-IN seq 10 permit 198.51.100.0/24
+ipv6 prefix-list AS64500-IN seq 5 permit 2001:db8::/32
+"
+        );
+        assert_eq!(frr(&Frr::default(), &[]), "");
+        assert!(Frr::default().name("AS 64500").is_none());
+        assert!(Frr::default().name("").is_none());
+    }
+
+    #[test]
+    fn test_lengths() {
+        let format = Frr::default().ge(20).le(24);
+        assert_eq!(
+            frr(
+                &format,
+                &[
+                    "10.0.0.0/8",
+                    "192.0.2.0/22",
+                    "198.51.100.0/24",
+                    "2001:db8::/32"
+                ]
+            ),
+            "\
+ip prefix-list lfc seq 5 permit 10.0.0.0/8 ge 20 le 24
+ip prefix-list lfc seq 10 permit 192.0.2.0/22 le 24
+ip prefix-list lfc seq 15 permit 198.51.100.0/24
+ipv6 prefix-list lfc seq 5 permit 2001:db8::/32
+"
+        );
+        assert_eq!(
+            frr(&Frr::default().le(48), &["10.0.0.0/8", "2001:db8::/32"]),
+            "\
+ip prefix-list lfc seq 5 permit 10.0.0.0/8
+ipv6 prefix-list lfc seq 5 permit 2001:db8::/32 le 48
+"
+        );
+    }
+}
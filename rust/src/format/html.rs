@@ -0,0 +1,374 @@
+//! A self-contained HTML report on the result.
+
+use super::{Formatter, Style};
+use crate::space::{self, Family};
+use ipnet::IpNet;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// A single HTML page with everything inline, for attaching to a change
+/// ticket: how big the result is, charts of its prefix lengths, and the
+/// networks themselves. With a [baseline](HtmlReport::baseline), it also
+/// shows what changed from it, and with [sources](HtmlReport::sources),
+/// which sources each network came from.
+#[derive(Debug)]
+pub struct HtmlReport {
+    title: String,
+    baseline: Option<(String, Vec<IpNet>)>,
+    sources: Vec<(String, Vec<IpNet>)>,
+}
+
+impl Default for HtmlReport {
+    fn default() -> Self {
+        HtmlReport {
+            title: "lfc report".to_string(),
+            baseline: None,
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl HtmlReport {
+    /// Sets the page's title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Compares the result to an earlier, aggregated one, called `name`.
+    pub fn baseline(mut self, name: impl Into<String>, nets: Vec<IpNet>) -> Self {
+        self.baseline = Some((name.into(), nets));
+        self
+    }
+
+    /// Names the sources that were aggregated, each with its own networks,
+    /// so that each network in the result can list the sources it came
+    /// from.
+    pub fn sources(mut self, sources: Vec<(String, Vec<IpNet>)>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Returns the indexes of the sources with networks inside each
+    /// network.
+    fn provenance(&self, nets: &[IpNet]) -> Vec<BTreeSet<usize>> {
+        let mut found = vec![BTreeSet::new(); nets.len()];
+        for (index, (_, source)) in self.sources.iter().enumerate() {
+            for net in source {
+                // The network holding it is the last one that sorts before
+                // it, or the most specific one if networks hold others.
+                let at = nets.partition_point(|held| held <= net);
+                if let Some(held) = at.checked_sub(1).filter(|&at| nets[at].contains(net)) {
+                    found[held].insert(index);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Escapes text for HTML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns an SVG bar chart of how many of the family's networks have each
+/// prefix length, from the shortest to the longest there is, or `None` if
+/// there aren't any.
+fn histogram(nets: &[IpNet], family: Family) -> Option<String> {
+    let mut counts = vec![0usize; usize::from(family.max_prefix_len()) + 1];
+    for net in nets.iter().filter(|net| Family::of(net) == family) {
+        counts[usize::from(net.prefix_len())] += 1;
+    }
+    let first = counts.iter().position(|&count| count > 0)?;
+    let last = counts.iter().rposition(|&count| count > 0)?;
+    let most = counts[first..=last].iter().copied().max().unwrap();
+    let (bar, height) = (18, 120);
+    let width = (last - first + 1) * bar;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">\n",
+        width,
+        height + 20
+    );
+    for (i, &count) in counts[first..=last].iter().enumerate() {
+        let len = first + i;
+        let tall = if count == 0 {
+            0
+        } else {
+            (count * height).div_ceil(most)
+        };
+        let x = i * bar;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"><title>/{}: {}</title></rect>",
+            x + 1,
+            height - tall,
+            bar - 2,
+            tall,
+            len,
+            count
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\">{}</text>",
+            x + bar / 2,
+            height + 14,
+            len
+        );
+    }
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+td.number { text-align: right; }
+tr.added td { background: #e6ffec; }
+tr.removed td { background: #ffebe9; }
+svg rect { fill: #4a79a5; }
+svg text { font-size: 10px; text-anchor: middle; }
+";
+
+impl Formatter for HtmlReport {
+    fn name(&self) -> &str {
+        "html-report"
+    }
+
+    fn description(&self) -> &str {
+        "A self-contained HTML page with stats, charts, and the networks"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let title = escape(&self.title);
+        writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">\n<title>{}</title>", title)?;
+        writeln!(out, "<style>\n{}</style>\n</head>\n<body>", STYLE)?;
+        writeln!(out, "<h1>{}</h1>", title)?;
+
+        writeln!(out, "<h2>Summary</h2>\n<table>")?;
+        writeln!(out, "<tr><th></th><th>IPv4</th><th>IPv6</th></tr>")?;
+        let families = [Family::V4, Family::V6].map(|family| {
+            let of: Vec<&IpNet> = nets
+                .iter()
+                .filter(|net| Family::of(net) == family)
+                .collect();
+            let lens = of.iter().map(|net| net.prefix_len());
+            let len = |len: Option<u8>| len.map_or("-".to_string(), |len| format!("/{}", len));
+            [
+                of.len().to_string(),
+                space::Count(space::size(&space::ranges(nets, family))).to_string(),
+                len(lens.clone().min()),
+                len(lens.max()),
+            ]
+        });
+        let rows = ["Networks", "Addresses", "Largest", "Smallest"];
+        for (row, name) in rows.iter().enumerate() {
+            writeln!(
+                out,
+                "<tr><th>{}</th><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                name, families[0][row], families[1][row]
+            )?;
+        }
+        writeln!(out, "</table>")?;
+
+        writeln!(out, "<h2>Prefix lengths</h2>")?;
+        for family in [Family::V4, Family::V6] {
+            if let Some(svg) = histogram(nets, family) {
+                write!(out, "<h3>{}</h3>\n{}", family, svg)?;
+            }
+        }
+
+        if let Some((name, baseline)) = &self.baseline {
+            writeln!(out, "<h2>Changes from {}</h2>", escape(name))?;
+            let old: HashSet<&IpNet> = baseline.iter().collect();
+            let new: HashSet<&IpNet> = nets.iter().collect();
+            let mut changes: Vec<(&IpNet, bool)> = baseline
+                .iter()
+                .filter(|net| !new.contains(net))
+                .map(|net| (net, false))
+                .chain(
+                    nets.iter()
+                        .filter(|net| !old.contains(net))
+                        .map(|net| (net, true)),
+                )
+                .collect();
+            changes.sort();
+            let mut summary = format!(
+                "+{} / -{} networks",
+                changes.iter().filter(|(_, added)| *added).count(),
+                changes.iter().filter(|(_, added)| !*added).count()
+            );
+            for family in [Family::V4, Family::V6] {
+                let old = space::ranges(baseline, family);
+                let new = space::ranges(nets, family);
+                let common = space::overlap_size(&old, &new);
+                let _ = write!(
+                    summary,
+                    ", {} +{} / -{} addresses",
+                    family,
+                    space::Count(space::size(&new) - common),
+                    space::Count(space::size(&old) - common)
+                );
+            }
+            writeln!(out, "<p>{}</p>", summary)?;
+            if !changes.is_empty() {
+                writeln!(out, "<table>\n<tr><th></th><th>Network</th></tr>")?;
+                for (net, added) in changes {
+                    let (class, sign) = if added {
+                        ("added", '+')
+                    } else {
+                        ("removed", '-')
+                    };
+                    writeln!(
+                        out,
+                        "<tr class=\"{}\"><td>{}</td><td>{}</td></tr>",
+                        class,
+                        sign,
+                        style.display(net)
+                    )?;
+                }
+                writeln!(out, "</table>")?;
+            }
+        }
+
+        writeln!(out, "<h2>Networks</h2>\n<table>")?;
+        let provenance = self.provenance(nets);
+        if self.sources.is_empty() {
+            writeln!(out, "<tr><th>Network</th><th>Addresses</th></tr>")?;
+        } else {
+            writeln!(
+                out,
+                "<tr><th>Network</th><th>Addresses</th><th>Sources</th></tr>"
+            )?;
+        }
+        for (net, sources) in nets.iter().zip(&provenance) {
+            write!(
+                out,
+                "<tr><td>{}</td><td class=\"number\">{}</td>",
+                style.display(net),
+                space::Count(space::subnet_count(net, net.max_prefix_len()))
+            )?;
+            if !self.sources.is_empty() {
+                let names: Vec<String> = sources
+                    .iter()
+                    .map(|&index| escape(&self.sources[index].0))
+                    .collect();
+                write!(out, "<td>{}</td>", names.join(", "))?;
+            }
+            writeln!(out, "</tr>")?;
+        }
+        writeln!(out, "</table>\n</body>\n</html>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn report(report: &HtmlReport, list: &[&str]) -> String {
+        let mut out = Vec::new();
+        report
+            .write(&mut out, &nets(list), &Style::default())
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        let html = report(
+            &HtmlReport::default().title("blocklist <prod>"),
+            &["10.0.0.0/8", "192.168.0.0/24", "2001:db8::/32"],
+        );
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("<title>blocklist &lt;prod&gt;</title>"));
+        assert!(html.contains(
+            "<tr><th>Networks</th><td class=\"number\">2</td><td class=\"number\">1</td></tr>"
+        ));
+        assert!(html.contains(
+            "<tr><th>Largest</th><td class=\"number\">/8</td><td class=\"number\">/32</td></tr>"
+        ));
+        assert!(html.contains("<tr><td>192.168.0.0/24</td><td class=\"number\">256</td></tr>"));
+        assert!(!html.contains("Changes from"));
+        assert!(!html.contains("<th>Sources</th>"));
+
+        let html = report(&HtmlReport::default(), &["::/0"]);
+        let all = "340282366920938463463374607431768211456";
+        assert!(html.contains(&format!(
+            "<tr><th>Addresses</th><td class=\"number\">0</td><td class=\"number\">{}</td></tr>",
+            all
+        )));
+        assert!(html.contains(&format!(
+            "<tr><td>::/0</td><td class=\"number\">{}</td></tr>",
+            all
+        )));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let svg = histogram(
+            &nets(&["10.0.0.0/8", "11.0.0.0/8", "12.0.0.0/10"]),
+            Family::V4,
+        )
+        .unwrap();
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains("height=\"120\"><title>/8: 2</title>"));
+        assert!(svg.contains("height=\"0\"><title>/9: 0</title>"));
+        assert!(svg.contains("height=\"60\"><title>/10: 1</title>"));
+        assert_eq!(histogram(&nets(&["10.0.0.0/8"]), Family::V6), None);
+    }
+
+    #[test]
+    fn test_baseline() {
+        let html = report(
+            &HtmlReport::default().baseline("old.txt", nets(&["10.0.0.0/24", "10.0.2.0/24"])),
+            &["10.0.0.0/23", "10.0.2.0/24"],
+        );
+        assert!(html.contains("<h2>Changes from old.txt</h2>"));
+        assert!(
+            html.contains(
+                "<p>+1 / -1 networks, IPv4 +256 / -0 addresses, IPv6 +0 / -0 addresses</p>"
+            )
+        );
+        assert!(html.contains("<tr class=\"removed\"><td>-</td><td>10.0.0.0/24</td></tr>"));
+        assert!(html.contains("<tr class=\"added\"><td>+</td><td>10.0.0.0/23</td></tr>"));
+    }
+
+    #[test]
+    fn test_sources() {
+        let html = report(
+            &HtmlReport::default().sources(vec![
+                ("a.txt".to_string(), nets(&["10.0.0.0/24"])),
+                (
+                    "b&c.txt".to_string(),
+                    nets(&["10.0.1.0/24", "192.0.2.0/24"]),
+                ),
+            ]),
+            &["10.0.0.0/23", "192.0.2.0/24", "198.51.100.0/24"],
+        );
+        assert!(html.contains(
+            "<td>10.0.0.0/23</td><td class=\"number\">512</td><td>a.txt, b&amp;c.txt</td>"
+        ));
+        assert!(
+            html.contains("<td>192.0.2.0/24</td><td class=\"number\">256</td><td>b&amp;c.txt</td>")
+        );
+        assert!(html.contains("<td>198.51.100.0/24</td><td class=\"number\">256</td><td></td>"));
+    }
+}
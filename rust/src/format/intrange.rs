@@ -0,0 +1,101 @@
+//! Numeric address ranges, for lookup tables in databases.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A line for each network with its first and last addresses as unsigned
+/// integers, separated by a comma. IPv4 addresses fit in 32 bits, and IPv6
+/// addresses are written as whole 128-bit numbers, or, with
+/// [`split`](IntRange::split), as two 64-bit halves for databases without
+/// 128-bit integers.
+#[derive(Debug, Default)]
+pub struct IntRange {
+    split: bool,
+}
+
+impl IntRange {
+    /// Writes each IPv6 address as its high and low 64 bits, so IPv6 lines
+    /// have four numbers: the first address's halves, then the last's.
+    pub fn split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+}
+
+impl Formatter for IntRange {
+    fn name(&self) -> &str {
+        "intrange"
+    }
+
+    fn description(&self) -> &str {
+        "Each network's first and last addresses as integers"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], _style: &Style) -> io::Result<()> {
+        for net in nets {
+            match net {
+                IpNet::V4(net) => writeln!(
+                    out,
+                    "{},{}",
+                    u32::from(net.network()),
+                    u32::from(net.broadcast())
+                )?,
+                IpNet::V6(net) => {
+                    let first = u128::from(net.network());
+                    let last = u128::from(net.broadcast());
+                    if self.split {
+                        writeln!(
+                            out,
+                            "{},{},{},{}",
+                            (first >> 64) as u64,
+                            first as u64,
+                            (last >> 64) as u64,
+                            last as u64
+                        )?
+                    } else {
+                        writeln!(out, "{},{}", first, last)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: IntRange) -> String {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            write(IntRange::default()),
+            "\
+167772160,184549375
+42540766411282592856903984951653826560,42540766490510755371168322545197776895
+"
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            write(IntRange::default().split(true)),
+            "\
+167772160,184549375
+2306139568115548160,0,2306139572410515455,18446744073709551615
+"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! iproute2 commands, for null-routing networks on Linux hosts.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The route types that drop traffic.
+const ACTIONS: [&str; 3] = ["blackhole", "unreachable", "prohibit"];
+
+/// A shell script of `ip route` commands that route each network to a
+/// blackhole, or another type of route that drops its traffic. The script
+/// first flushes the table's routes of that type, for both families, then
+/// adds one for each network with `ip route replace`, so running a newer
+/// script leaves the table holding just the newer list.
+#[derive(Debug)]
+pub struct IpRoute {
+    action: &'static str,
+    table: Option<String>,
+}
+
+impl Default for IpRoute {
+    /// Returns the format with blackhole routes in the main table.
+    fn default() -> Self {
+        IpRoute {
+            action: ACTIONS[0],
+            table: None,
+        }
+    }
+}
+
+impl IpRoute {
+    /// Uses another type of route: blackhole, unreachable, or prohibit.
+    /// Returns `None` for any other type.
+    pub fn action(mut self, name: &str) -> Option<Self> {
+        self.action = ACTIONS.into_iter().find(|action| *action == name)?;
+        Some(self)
+    }
+
+    /// Puts the routes in a table, by number or by a name from
+    /// `/etc/iproute2/rt_tables`. Returns `None` if the table has anything
+    /// but letters, digits, `_`, and `-`.
+    pub fn table(mut self, table: &str) -> Option<Self> {
+        let plain = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if table.is_empty() || !table.chars().all(plain) {
+            return None;
+        }
+        self.table = Some(table.to_string());
+        Some(self)
+    }
+}
+
+impl Formatter for IpRoute {
+    fn name(&self) -> &str {
+        "iproute"
+    }
+
+    fn description(&self) -> &str {
+        "ip route commands that blackhole each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let table = match &self.table {
+            Some(table) => format!(" table {}", table),
+            None => String::new(),
+        };
+        // Without a family, `ip route flush` only flushes IPv4 routes.
+        writeln!(out, "ip -4 route flush{} type {}", table, self.action)?;
+        writeln!(out, "ip -6 route flush{} type {}", table, self.action)?;
+        for net in nets {
+            let family = match net {
+                IpNet::V4(_) => "",
+                IpNet::V6(_) => " -6",
+            };
+            writeln!(
+                out,
+                "ip{} route replace {} {}{}",
+                family,
+                self.action,
+                style.display(net),
+                table
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iproute(format: &IpRoute, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            iproute(&IpRoute::default(), &["192.0.2.0/24", "2001:db8::/32"]),
+            "\
+ip -4 route flush type blackhole
+ip -6 route flush type blackhole
+ip route replace blackhole 192.0.2.0/24
+ip -6 route replace blackhole 2001:db8::/32
+"
+        );
+        assert_eq!(
+            iproute(&IpRoute::default(), &[]),
+            "ip -4 route flush type blackhole\nip -6 route flush type blackhole\n"
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let format = IpRoute::default()
+            .action("unreachable")
+            .unwrap()
+            .table("100")
+            .unwrap();
+        assert_eq!(
+            iproute(&format, &["10.0.0.0/8"]),
+            "\
+ip -4 route flush table 100 type unreachable
+ip -6 route flush table 100 type unreachable
+ip route replace unreachable 10.0.0.0/8 table 100
+"
+        );
+        assert!(IpRoute::default().action("drop").is_none());
+        assert!(IpRoute::default().action("Blackhole").is_none());
+        assert!(IpRoute::default().table("100; reboot").is_none());
+        assert!(IpRoute::default().table("").is_none());
+    }
+}
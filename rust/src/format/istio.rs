@@ -0,0 +1,214 @@
+//! An Istio AuthorizationPolicy, for meshes that block or allow networks.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The actions a policy can take without an external authorizer.
+const ACTIONS: [&str; 3] = ["DENY", "ALLOW", "AUDIT"];
+
+/// A YAML AuthorizationPolicy manifest with one rule matching the networks
+/// as `ipBlocks`, or as `remoteIpBlocks`, which Istio takes from
+/// `X-Forwarded-For`.
+///
+/// Without any networks, the policy has no rules, so it matches nothing.
+/// An empty `ipBlocks` would match every client instead.
+#[derive(Debug)]
+pub struct Istio {
+    name: String,
+    action: &'static str,
+    labels: Vec<(String, String)>,
+    remote: bool,
+}
+
+impl Default for Istio {
+    /// Returns the format with a DENY policy named `lfc`, for every
+    /// workload in its namespace.
+    fn default() -> Self {
+        Istio {
+            name: "lfc".to_string(),
+            action: ACTIONS[0],
+            labels: Vec::new(),
+            remote: false,
+        }
+    }
+}
+
+/// Returns the text as a double-quoted YAML string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Istio {
+    /// Names the policy. Returns `None` if Kubernetes wouldn't accept the
+    /// name.
+    pub fn name(mut self, name: &str) -> Option<Self> {
+        let valid = |label: &str| {
+            label.starts_with(|c: char| c.is_ascii_alphanumeric())
+                && label.ends_with(|c: char| c.is_ascii_alphanumeric())
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        };
+        if name.len() > 253 || !name.split('.').all(valid) {
+            return None;
+        }
+        self.name = name.to_string();
+        Some(self)
+    }
+
+    /// Uses another action: ALLOW, DENY, or AUDIT, in any case. Returns
+    /// `None` for any other action.
+    pub fn action(mut self, name: &str) -> Option<Self> {
+        self.action = ACTIONS
+            .into_iter()
+            .find(|action| action.eq_ignore_ascii_case(name))?;
+        Some(self)
+    }
+
+    /// Applies the policy only to workloads with this label. Returns `None`
+    /// if the label's name is empty.
+    pub fn label(mut self, name: &str, value: &str) -> Option<Self> {
+        if name.is_empty() {
+            return None;
+        }
+        self.labels.push((name.to_string(), value.to_string()));
+        Some(self)
+    }
+
+    /// Matches the networks as `remoteIpBlocks` instead of `ipBlocks`.
+    pub fn remote(mut self, remote: bool) -> Self {
+        self.remote = remote;
+        self
+    }
+}
+
+impl Formatter for Istio {
+    fn name(&self) -> &str {
+        "istio"
+    }
+
+    fn description(&self) -> &str {
+        "An Istio AuthorizationPolicy that denies the networks"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        writeln!(out, "apiVersion: security.istio.io/v1")?;
+        writeln!(out, "kind: AuthorizationPolicy")?;
+        writeln!(out, "metadata:")?;
+        writeln!(out, "  name: {}", self.name)?;
+        writeln!(out, "spec:")?;
+        if !self.labels.is_empty() {
+            writeln!(out, "  selector:")?;
+            writeln!(out, "    matchLabels:")?;
+            for (name, value) in &self.labels {
+                writeln!(out, "      {}: {}", quote(name), quote(value))?;
+            }
+        }
+        writeln!(out, "  action: {}", self.action)?;
+        if nets.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "  rules:")?;
+        writeln!(out, "  - from:")?;
+        writeln!(out, "    - source:")?;
+        let blocks = if self.remote {
+            "remoteIpBlocks"
+        } else {
+            "ipBlocks"
+        };
+        writeln!(out, "        {}:", blocks)?;
+        for net in nets {
+            writeln!(out, "        - \"{}\"", style.display(net))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets() -> Vec<IpNet> {
+        vec![
+            "192.0.2.0/24".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        Istio::default()
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+apiVersion: security.istio.io/v1
+kind: AuthorizationPolicy
+metadata:
+  name: lfc
+spec:
+  action: DENY
+  rules:
+  - from:
+    - source:
+        ipBlocks:
+        - \"192.0.2.0/24\"
+        - \"2001:db8::/32\"
+"
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let istio = Istio::default()
+            .name("edge-allow")
+            .unwrap()
+            .action("allow")
+            .unwrap()
+            .label("app", "ingress\"gw")
+            .unwrap()
+            .remote(true);
+        let mut out = Vec::new();
+        istio
+            .write(&mut out, &nets()[..1], &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+apiVersion: security.istio.io/v1
+kind: AuthorizationPolicy
+metadata:
+  name: edge-allow
+spec:
+  selector:
+    matchLabels:
+      \"app\": \"ingress\\\"gw\"
+  action: ALLOW
+  rules:
+  - from:
+    - source:
+        remoteIpBlocks:
+        - \"192.0.2.0/24\"
+"
+        );
+        let mut out = Vec::new();
+        istio.write(&mut out, &[], &Style::default()).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .ends_with("  action: ALLOW\n")
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(Istio::default().name("Blocked").is_none());
+        assert!(Istio::default().name("-lfc").is_none());
+        assert!(Istio::default().name("lfc.blocked").is_some());
+        assert!(Istio::default().action("CUSTOM").is_none());
+        assert!(Istio::default().label("", "x").is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! A JSON object, shaped like the REST API's responses.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use serde_json::json;
+use std::io::{self, Write};
+
+/// A JSON object with a `networks` array of strings.
+pub struct Json;
+
+impl Formatter for Json {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn description(&self) -> &str {
+        "A JSON object with a \"networks\" array"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let networks: Vec<String> = nets
+            .iter()
+            .map(|net| style.display(net).to_string())
+            .collect();
+        serde_json::to_writer(&mut *out, &json!({ "networks": networks }))?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        Json.write(&mut out, &nets, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"networks\":[\"10.0.0.0/8\",\"2001:db8::/32\"]}\n"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! A Markdown table, for wikis and pull request descriptions.
+
+use super::{Formatter, Style};
+use crate::space::{self, Family};
+use ipnet::IpNet;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// A Markdown table of the networks, with how many addresses each has and
+/// its family. With [notes](Markdown::notes), there's a column for them
+/// too.
+#[derive(Debug, Default)]
+pub struct Markdown {
+    notes: Option<BTreeMap<IpNet, String>>,
+}
+
+impl Markdown {
+    /// Adds a Notes column, with the note for each network that has one.
+    pub fn notes(mut self, notes: BTreeMap<IpNet, String>) -> Self {
+        self.notes = Some(notes);
+        self
+    }
+}
+
+impl Formatter for Markdown {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn description(&self) -> &str {
+        "A Markdown table with each network's addresses and family"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        match &self.notes {
+            None => writeln!(
+                out,
+                "| Network | Addresses | Family |\n| --- | ---: | --- |"
+            )?,
+            Some(_) => writeln!(
+                out,
+                "| Network | Addresses | Family | Notes |\n| --- | ---: | --- | --- |"
+            )?,
+        }
+        for net in nets {
+            write!(
+                out,
+                "| {} | {} | {} |",
+                style.display(net),
+                space::Count(space::subnet_count(net, net.max_prefix_len())),
+                Family::of(net)
+            )?;
+            if let Some(notes) = &self.notes {
+                let note = notes.get(net).map_or(String::new(), |note| {
+                    note.replace('|', "\\|").replace('\n', " ")
+                });
+                write!(out, " {} |", note)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets() -> Vec<IpNet> {
+        vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/126".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        Markdown::default()
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+| Network | Addresses | Family |
+| --- | ---: | --- |
+| 10.0.0.0/8 | 16777216 | IPv4 |
+| 2001:db8::/126 | 4 | IPv6 |
+"
+        );
+    }
+
+    #[test]
+    fn test_default_route() {
+        let mut out = Vec::new();
+        Markdown::default()
+            .write(&mut out, &["::/0".parse().unwrap()], &Style::default())
+            .unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("| ::/0 | 340282366920938463463374607431768211456 | IPv6 |\n")
+        );
+    }
+
+    #[test]
+    fn test_notes() {
+        let notes = BTreeMap::from([(nets()[0], "Private-Use | RFC 1918".to_string())]);
+        let mut out = Vec::new();
+        Markdown::default()
+            .notes(notes)
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+| Network | Addresses | Family | Notes |
+| --- | ---: | --- | --- |
+| 10.0.0.0/8 | 16777216 | IPv4 | Private-Use \\| RFC 1918 |
+| 2001:db8::/126 | 4 | IPv6 |  |
+"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! MessagePack, shaped like the JSON format.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A MessagePack map with a `networks` array of strings: the JSON format's
+/// structure in a compact binary form.
+pub struct MsgPack;
+
+/// Writes a string's header and bytes.
+fn write_str(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    let len = text.len();
+    match len {
+        0..=31 => out.write_all(&[0xa0 | len as u8])?,
+        32..=0xff => out.write_all(&[0xd9, len as u8])?,
+        0x100..=0xffff => {
+            out.write_all(&[0xda])?;
+            out.write_all(&(len as u16).to_be_bytes())?;
+        }
+        _ => {
+            out.write_all(&[0xdb])?;
+            out.write_all(&(len as u32).to_be_bytes())?;
+        }
+    }
+    out.write_all(text.as_bytes())
+}
+
+/// Writes the header of an array with `len` items.
+fn write_array_header(out: &mut dyn Write, len: usize) -> io::Result<()> {
+    match len {
+        0..=15 => out.write_all(&[0x90 | len as u8]),
+        16..=0xffff => {
+            out.write_all(&[0xdc])?;
+            out.write_all(&(len as u16).to_be_bytes())
+        }
+        _ => {
+            let len = u32::try_from(len).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "too many networks for MessagePack",
+                )
+            })?;
+            out.write_all(&[0xdd])?;
+            out.write_all(&len.to_be_bytes())
+        }
+    }
+}
+
+impl Formatter for MsgPack {
+    fn name(&self) -> &str {
+        "msgpack"
+    }
+
+    fn description(&self) -> &str {
+        "MessagePack with the JSON format's structure"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        // A map with one entry.
+        out.write_all(&[0x81])?;
+        write_str(out, "networks")?;
+        write_array_header(out, nets.len())?;
+        for net in nets {
+            write_str(out, &style.display(net).to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Ipv6Form;
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        MsgPack.write(&mut out, &nets, &Style::default()).unwrap();
+        let mut expected = vec![0x81, 0xa8];
+        expected.extend(b"networks");
+        expected.extend([0x92, 0xaa]);
+        expected.extend(b"10.0.0.0/8");
+        expected.push(0xad);
+        expected.extend(b"2001:db8::/32");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_lengths() {
+        // Expanded IPv6 networks are too long for the shortest strings.
+        let style = Style {
+            ipv6_form: Ipv6Form::Expanded,
+            ..Style::default()
+        };
+        let nets = vec!["2001:db8::/128".parse().unwrap(); 16];
+        let mut out = Vec::new();
+        MsgPack.write(&mut out, &nets, &style).unwrap();
+        assert_eq!(&out[10..16], &[0xdc, 0, 16, 0xd9, 43, b'2']);
+        assert_eq!(out.len(), 10 + 3 + 16 * 45);
+
+        let mut out = Vec::new();
+        MsgPack.write(&mut out, &[], &Style::default()).unwrap();
+        assert_eq!(out[10..], [0x90]);
+    }
+}
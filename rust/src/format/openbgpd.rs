@@ -0,0 +1,129 @@
+//! OpenBGPD prefix sets, for bgpd.conf on OpenBSD route servers.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A `prefix-set` block with an entry for each network, to include in
+/// bgpd.conf and match with `prefix-set NAME` in filter rules.
+///
+/// With [`lengths`](OpenBgpd::lengths), each entry also matches the longer
+/// networks inside it, with a `prefixlen` range. The range is cut to the
+/// lengths between the entry's own and its family's addresses, which is all
+/// bgpd takes, and an entry it leaves nothing of stays exact.
+#[derive(Debug)]
+pub struct OpenBgpd {
+    name: String,
+    lengths: Option<(u8, u8)>,
+}
+
+impl Default for OpenBgpd {
+    /// Returns the format with exact entries in a set named `lfc`.
+    fn default() -> Self {
+        OpenBgpd {
+            name: "lfc".to_string(),
+            lengths: None,
+        }
+    }
+}
+
+impl OpenBgpd {
+    /// Names the set. Returns `None` if the name has anything but letters,
+    /// digits, and `_.-`.
+    pub fn name(mut self, name: &str) -> Option<Self> {
+        let plain = |c: char| c.is_ascii_alphanumeric() || "_.-".contains(c);
+        if name.is_empty() || !name.chars().all(plain) {
+            return None;
+        }
+        self.name = name.to_string();
+        Some(self)
+    }
+
+    /// Matches the networks from `low` to `high` long, inclusive, inside
+    /// each entry.
+    pub fn lengths(mut self, low: u8, high: u8) -> Self {
+        self.lengths = Some((low, high));
+        self
+    }
+}
+
+impl Formatter for OpenBgpd {
+    fn name(&self) -> &str {
+        "openbgpd"
+    }
+
+    fn description(&self) -> &str {
+        "An OpenBGPD prefix-set with an entry for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        writeln!(out, "prefix-set {} {{", self.name)?;
+        for net in nets {
+            write!(out, "\t{}", style.display(net))?;
+            if let Some((low, high)) = self.lengths {
+                let low = low.max(net.prefix_len());
+                let high = high.min(net.max_prefix_len());
+                if high > net.prefix_len() && low <= high {
+                    write!(out, " prefixlen {} - {}", low, high)?;
+                }
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openbgpd(format: &OpenBgpd, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        format.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            openbgpd(
+                &OpenBgpd::default().name("customers").unwrap(),
+                &["192.0.2.0/24", "2001:db8::/32"]
+            ),
+            "\
+prefix-set customers {
+\t192.0.2.0/24
+\t2001:db8::/32
+}
+"
+        );
+        assert_eq!(openbgpd(&OpenBgpd::default(), &[]), "prefix-set lfc {\n}\n");
+        assert!(OpenBgpd::default().name("my set").is_none());
+        assert!(OpenBgpd::default().name("").is_none());
+    }
+
+    #[test]
+    fn test_lengths() {
+        let format = OpenBgpd::default().lengths(20, 48);
+        assert_eq!(
+            openbgpd(
+                &format,
+                &[
+                    "10.0.0.0/8",
+                    "192.0.2.0/22",
+                    "198.51.100.7/32",
+                    "2001:db8::/32"
+                ]
+            ),
+            "\
+prefix-set lfc {
+\t10.0.0.0/8 prefixlen 20 - 32
+\t192.0.2.0/22 prefixlen 22 - 32
+\t198.51.100.7/32
+\t2001:db8::/32 prefixlen 32 - 48
+}
+"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Protocol Buffers, with the messages in `lfc.proto`.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The schema for the output, for generating code to read it.
+pub const PROTO: &str = include_str!("../lfc.proto");
+
+/// A serialized `lfc.v1.Result` message, as described by [`PROTO`].
+pub struct Protobuf;
+
+/// Appends a varint.
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Returns a serialized `Prefix` message for the network.
+fn prefix(net: &IpNet) -> Vec<u8> {
+    let address = match net {
+        IpNet::V4(net) => net.addr().octets().to_vec(),
+        IpNet::V6(net) => net.addr().octets().to_vec(),
+    };
+    let mut message = Vec::with_capacity(22);
+    // Field 1, length-delimited.
+    message.push(1 << 3 | 2);
+    varint(&mut message, address.len() as u64);
+    message.extend(address);
+    // Field 2, a varint, which proto3 leaves out when it's 0.
+    if net.prefix_len() > 0 {
+        message.push(2 << 3);
+        varint(&mut message, net.prefix_len().into());
+    }
+    message
+}
+
+impl Formatter for Protobuf {
+    fn name(&self) -> &str {
+        "protobuf"
+    }
+
+    fn description(&self) -> &str {
+        "A serialized lfc.v1.Result Protocol Buffers message"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], _style: &Style) -> io::Result<()> {
+        let mut field = Vec::new();
+        for net in nets {
+            let message = prefix(net);
+            field.clear();
+            // Result field 1, each a length-delimited Prefix.
+            field.push(1 << 3 | 2);
+            varint(&mut field, message.len() as u64);
+            out.write_all(&field)?;
+            out.write_all(&message)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "0.0.0.0/0".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        Protobuf.write(&mut out, &nets, &Style::default()).unwrap();
+        let mut expected = vec![0x0a, 8, 0x0a, 4, 10, 0, 0, 0, 0x10, 8];
+        expected.extend([0x0a, 6, 0x0a, 4, 0, 0, 0, 0]);
+        expected.extend([0x0a, 20, 0x0a, 16, 0x20, 0x01, 0x0d, 0xb8]);
+        expected.extend([0; 12]);
+        expected.extend([0x10, 32]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut out = Vec::new();
+        varint(&mut out, 300);
+        assert_eq!(out, [0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_proto() {
+        // The encoding above has to match the schema.
+        assert!(PROTO.contains("package lfc.v1;"));
+        assert!(PROTO.contains("bytes address = 1;"));
+        assert!(PROTO.contains("uint32 length = 2;"));
+        assert!(PROTO.contains("repeated Prefix prefixes = 1;"));
+    }
+}
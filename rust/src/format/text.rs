@@ -0,0 +1,44 @@
+//! One network per line, the way lfc reads them.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// Plain text, one network per line.
+pub struct Text;
+
+impl Formatter for Text {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn description(&self) -> &str {
+        "One network per line"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        for net in nets {
+            writeln!(out, "{}", style.display(net))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        Text.write(&mut out, &nets, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "10.0.0.0/8\n2001:db8::/32\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! A TOML document, for dropping into application config files.
+
+use super::{Formatter, Style};
+use crate::space::{self, Family};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A TOML array of network strings under a configurable key, optionally
+/// with a table of stats next to it.
+#[derive(Debug)]
+pub struct Toml {
+    table: Vec<String>,
+    key: String,
+    stats: bool,
+}
+
+impl Default for Toml {
+    /// Returns the format with the networks in a top-level `networks` key.
+    fn default() -> Self {
+        Toml {
+            table: Vec::new(),
+            key: "networks".to_string(),
+            stats: false,
+        }
+    }
+}
+
+impl Toml {
+    /// Puts the networks under a dotted key path, like
+    /// `firewall.blocklist.networks`: everything before the last dot names
+    /// the table, and the last part is the key in it. Returns `None` if
+    /// any part is empty.
+    pub fn key(mut self, path: &str) -> Option<Self> {
+        let mut parts: Vec<String> = path.split('.').map(str::to_string).collect();
+        if parts.iter().any(String::is_empty) {
+            return None;
+        }
+        self.key = parts.pop().unwrap();
+        self.table = parts;
+        Some(self)
+    }
+
+    /// Whether to add a `stats` table with how many networks and addresses
+    /// there are in each family. Off by default.
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+}
+
+/// Writes a key bare if TOML allows it, and quoted if not.
+fn key(key: &str) -> String {
+    let bare = key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+/// Writes a count as a TOML integer, or as a string if it's too big for
+/// one, as IPv6 address counts usually are.
+fn count(count: u128) -> String {
+    match i64::try_from(count) {
+        Ok(count) => count.to_string(),
+        Err(_) => format!("\"{}\"", space::Count(count)),
+    }
+}
+
+impl Formatter for Toml {
+    fn name(&self) -> &str {
+        "toml"
+    }
+
+    fn description(&self) -> &str {
+        "A TOML array of networks, under a key that --toml-key sets"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let table: Vec<String> = self.table.iter().map(|part| key(part)).collect();
+        if !table.is_empty() {
+            writeln!(out, "[{}]", table.join("."))?;
+        }
+        if nets.is_empty() {
+            writeln!(out, "{} = []", key(&self.key))?;
+        } else {
+            writeln!(out, "{} = [", key(&self.key))?;
+            for net in nets {
+                writeln!(out, "    \"{}\",", style.display(net))?;
+            }
+            writeln!(out, "]")?;
+        }
+        if self.stats {
+            let stats: Vec<String> = table.into_iter().chain(["stats".to_string()]).collect();
+            writeln!(out, "\n[{}]", stats.join("."))?;
+            for (name, family) in [("v4", Family::V4), ("v6", Family::V6)] {
+                let networks = nets.iter().filter(|net| Family::of(net) == family).count();
+                let addresses = space::size(&space::ranges(nets, family));
+                writeln!(out, "networks_{} = {}", name, networks)?;
+                writeln!(out, "addresses_{} = {}", name, count(addresses))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toml(toml: &Toml, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        toml.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            toml(&Toml::default(), &["10.0.0.0/8", "2001:db8::/32"]),
+            "networks = [\n    \"10.0.0.0/8\",\n    \"2001:db8::/32\",\n]\n"
+        );
+        assert_eq!(toml(&Toml::default(), &[]), "networks = []\n");
+    }
+
+    #[test]
+    fn test_key() {
+        let format = Toml::default().key("firewall.block list.cidrs").unwrap();
+        assert_eq!(
+            toml(&format, &["10.0.0.0/8"]),
+            "[firewall.\"block list\"]\ncidrs = [\n    \"10.0.0.0/8\",\n]\n"
+        );
+        assert!(Toml::default().key("firewall..cidrs").is_none());
+        assert!(Toml::default().key("").is_none());
+    }
+
+    #[test]
+    fn test_stats() {
+        let format = Toml::default().key("acl.allow").unwrap().stats(true);
+        assert_eq!(
+            toml(&format, &["10.0.0.0/8", "2001:db8::/32"]),
+            "\
+[acl]
+allow = [
+    \"10.0.0.0/8\",
+    \"2001:db8::/32\",
+]
+
+[acl.stats]
+networks_v4 = 1
+addresses_v4 = 16777216
+networks_v6 = 1
+addresses_v6 = \"79228162514264337593543950336\"
+"
+        );
+        assert_eq!(
+            toml(&Toml::default().stats(true), &[]),
+            "networks = []\n\n[stats]\nnetworks_v4 = 0\naddresses_v4 = 0\nnetworks_v6 = 0\naddresses_v6 = 0\n"
+        );
+        assert!(
+            toml(&Toml::default().stats(true), &["::/0"])
+                .contains("addresses_v6 = \"340282366920938463463374607431768211456\"\n")
+        );
+    }
+}
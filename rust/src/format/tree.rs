@@ -0,0 +1,115 @@
+//! The networks as a tree, with each one under the network that holds it.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// An ASCII tree of the networks. Aggregated networks never hold each
+/// other, so they're a flat list unless the output keeps networks inside
+/// other networks, as `--keep-structure` does.
+pub struct Tree;
+
+impl Formatter for Tree {
+    fn name(&self) -> &str {
+        "tree"
+    }
+
+    fn description(&self) -> &str {
+        "Networks indented under the networks that hold them"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        // A network sorts right after the network that holds it, or after
+        // its siblings under that network, so its parent is the nearest
+        // network before it that holds it.
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); nets.len()];
+        let mut roots = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        for (index, net) in nets.iter().enumerate() {
+            while open.last().is_some_and(|&last| !nets[last].contains(net)) {
+                open.pop();
+            }
+            match open.last() {
+                Some(&parent) => children[parent].push(index),
+                None => roots.push(index),
+            }
+            open.push(index);
+        }
+        for root in roots {
+            writeln!(out, "{}", style.display(&nets[root]))?;
+            write_children(out, nets, &children, root, "", style)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the children of the network, each line starting with `indent`.
+fn write_children(
+    out: &mut dyn Write,
+    nets: &[IpNet],
+    children: &[Vec<usize>],
+    parent: usize,
+    indent: &str,
+    style: &Style,
+) -> io::Result<()> {
+    let count = children[parent].len();
+    for (i, &child) in children[parent].iter().enumerate() {
+        let (branch, below) = if i + 1 == count {
+            ("`-- ", "    ")
+        } else {
+            ("|-- ", "|   ")
+        };
+        writeln!(out, "{}{}{}", indent, branch, style.display(&nets[child]))?;
+        write_children(
+            out,
+            nets,
+            children,
+            child,
+            &format!("{}{}", indent, below),
+            style,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        Tree.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            tree(&["10.0.0.0/8", "2001:db8::/32"]),
+            "10.0.0.0/8\n2001:db8::/32\n"
+        );
+        assert_eq!(
+            tree(&[
+                "10.0.0.0/8",
+                "10.0.0.0/16",
+                "10.0.0.0/24",
+                "10.0.5.0/24",
+                "10.2.0.0/16",
+                "192.168.0.0/16",
+                "2001:db8::/32",
+                "2001:db8:1::/48",
+            ]),
+            "\
+10.0.0.0/8
+|-- 10.0.0.0/16
+|   |-- 10.0.0.0/24
+|   `-- 10.0.5.0/24
+`-- 10.2.0.0/16
+192.168.0.0/16
+2001:db8::/32
+`-- 2001:db8:1::/48
+"
+        );
+    }
+}
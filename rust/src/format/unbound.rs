@@ -0,0 +1,106 @@
+//! Unbound access control, for resolvers that should ignore some clients.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The actions Unbound's `access-control` takes.
+const ACTIONS: [&str; 8] = [
+    "refuse",
+    "deny",
+    "allow",
+    "allow_setrd",
+    "allow_snoop",
+    "allow_cookie",
+    "deny_non_local",
+    "refuse_non_local",
+];
+
+/// A `server:` clause with an `access-control` line for each network, for
+/// an Unbound config file to include.
+#[derive(Debug)]
+pub struct Unbound {
+    action: &'static str,
+}
+
+impl Default for Unbound {
+    /// Returns the format with the `refuse` action.
+    fn default() -> Self {
+        Unbound { action: ACTIONS[0] }
+    }
+}
+
+impl Unbound {
+    /// Uses another action, like `deny` or `allow`. Returns `None` if
+    /// Unbound doesn't have the action.
+    pub fn action(mut self, name: &str) -> Option<Self> {
+        self.action = ACTIONS.into_iter().find(|action| *action == name)?;
+        Some(self)
+    }
+}
+
+impl Formatter for Unbound {
+    fn name(&self) -> &str {
+        "unbound"
+    }
+
+    fn description(&self) -> &str {
+        "Unbound access-control lines that refuse each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        writeln!(out, "server:")?;
+        for net in nets {
+            writeln!(
+                out,
+                "    access-control: {} {}",
+                style.display(net),
+                self.action
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets() -> Vec<IpNet> {
+        vec![
+            "192.0.2.0/24".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+        Unbound::default()
+            .write(&mut out, &nets(), &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+server:
+    access-control: 192.0.2.0/24 refuse
+    access-control: 2001:db8::/32 refuse
+"
+        );
+    }
+
+    #[test]
+    fn test_action() {
+        let mut out = Vec::new();
+        Unbound::default()
+            .action("deny")
+            .unwrap()
+            .write(&mut out, &nets()[..1], &Style::default())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "server:\n    access-control: 192.0.2.0/24 deny\n"
+        );
+        assert!(Unbound::default().action("drop").is_none());
+    }
+}
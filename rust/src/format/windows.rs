@@ -0,0 +1,187 @@
+//! Windows Defender Firewall rules, for Windows server fleets.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The most remote addresses in one rule. Windows takes more, but rules
+/// with more than about a thousand get slow to change and to match.
+const CHUNK: usize = 1000;
+
+/// The actions a rule can take.
+const ACTIONS: [&str; 2] = ["Block", "Allow"];
+
+/// The directions a rule can match.
+const DIRECTIONS: [&str; 2] = ["Inbound", "Outbound"];
+
+/// A PowerShell script that keeps firewall rules matching the networks as
+/// their `-RemoteAddress`, a thousand networks to a rule. The rules are
+/// named `NAME-1`, `NAME-2`, and so on, so running a newer script updates
+/// the rules it finds with `Set-NetFirewallRule`, creates the rest with
+/// `New-NetFirewallRule`, and removes the ones a shorter list doesn't need.
+#[derive(Debug)]
+pub struct WindowsFirewall {
+    name: String,
+    action: &'static str,
+    direction: &'static str,
+}
+
+impl Default for WindowsFirewall {
+    /// Returns the format with inbound rules named `lfc-N` that block the
+    /// networks.
+    fn default() -> Self {
+        WindowsFirewall {
+            name: "lfc".to_string(),
+            action: ACTIONS[0],
+            direction: DIRECTIONS[0],
+        }
+    }
+}
+
+impl WindowsFirewall {
+    /// Names the rules `NAME-1`, `NAME-2`, and so on. Returns `None` if the
+    /// name has anything but letters, digits, `_`, and `-`.
+    pub fn name(mut self, name: &str) -> Option<Self> {
+        let plain = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        self.name = (!name.is_empty() && name.chars().all(plain)).then(|| name.to_string())?;
+        Some(self)
+    }
+
+    /// Uses another action: Block or Allow, in any case. Returns `None`
+    /// for any other action.
+    pub fn action(mut self, name: &str) -> Option<Self> {
+        self.action = ACTIONS
+            .into_iter()
+            .find(|action| action.eq_ignore_ascii_case(name))?;
+        Some(self)
+    }
+
+    /// Matches another direction: Inbound or Outbound, in any case. Returns
+    /// `None` for any other direction.
+    pub fn direction(mut self, name: &str) -> Option<Self> {
+        self.direction = DIRECTIONS
+            .into_iter()
+            .find(|direction| direction.eq_ignore_ascii_case(name))?;
+        Some(self)
+    }
+}
+
+impl Formatter for WindowsFirewall {
+    fn name(&self) -> &str {
+        "windows-firewall"
+    }
+
+    fn description(&self) -> &str {
+        "A PowerShell script keeping Windows Firewall rules for the networks"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        let (name, action, direction) = (&self.name, self.action, self.direction);
+        writeln!(
+            out,
+            "# {} rules for {} networks, generated by lfc.",
+            direction,
+            nets.len()
+        )?;
+        writeln!(out, "$ErrorActionPreference = 'Stop'")?;
+        let chunks = nets.chunks(CHUNK);
+        let count = chunks.len();
+        for (i, chunk) in chunks.enumerate() {
+            let rule = format!("{}-{}", name, i + 1);
+            writeln!(out)?;
+            writeln!(out, "$addresses = @(")?;
+            for net in chunk {
+                writeln!(out, "    '{}'", style.display(net))?;
+            }
+            writeln!(out, ")")?;
+            writeln!(
+                out,
+                "\
+if (Get-NetFirewallRule -Name '{rule}' -ErrorAction SilentlyContinue) {{
+    Set-NetFirewallRule -Name '{rule}' -Direction {direction} -Action {action} -RemoteAddress $addresses
+}} else {{
+    New-NetFirewallRule -Name '{rule}' -DisplayName '{rule}' -Direction {direction} -Action {action} -RemoteAddress $addresses | Out-Null
+}}"
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(
+            out,
+            "\
+# Remove the rules that a longer list needed.
+Get-NetFirewallRule -Name '{name}-*' -ErrorAction SilentlyContinue |
+    Where-Object {{ $_.Name -match '^{name}-([0-9]+)$' -and [int]$Matches[1] -gt {count} }} |
+    Remove-NetFirewallRule"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(format: &WindowsFirewall, nets: &[IpNet]) -> String {
+        let mut out = Vec::new();
+        format.write(&mut out, nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        let nets: Vec<IpNet> = vec![
+            "192.0.2.0/24".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        assert_eq!(
+            script(&WindowsFirewall::default(), &nets),
+            "\
+# Inbound rules for 2 networks, generated by lfc.
+$ErrorActionPreference = 'Stop'
+
+$addresses = @(
+    '192.0.2.0/24'
+    '2001:db8::/32'
+)
+if (Get-NetFirewallRule -Name 'lfc-1' -ErrorAction SilentlyContinue) {
+    Set-NetFirewallRule -Name 'lfc-1' -Direction Inbound -Action Block -RemoteAddress $addresses
+} else {
+    New-NetFirewallRule -Name 'lfc-1' -DisplayName 'lfc-1' -Direction Inbound -Action Block -RemoteAddress $addresses | Out-Null
+}
+
+# Remove the rules that a longer list needed.
+Get-NetFirewallRule -Name 'lfc-*' -ErrorAction SilentlyContinue |
+    Where-Object { $_.Name -match '^lfc-([0-9]+)$' -and [int]$Matches[1] -gt 1 } |
+    Remove-NetFirewallRule
+"
+        );
+    }
+
+    #[test]
+    fn test_chunks() {
+        let nets: Vec<IpNet> = (0..2500u32)
+            .map(|i| IpNet::from(std::net::IpAddr::from(std::net::Ipv4Addr::from(i << 8))))
+            .collect();
+        let format = WindowsFirewall::default()
+            .name("feeds")
+            .unwrap()
+            .action("allow")
+            .unwrap()
+            .direction("OUTBOUND")
+            .unwrap();
+        let out = script(&format, &nets);
+        assert_eq!(out.matches("$addresses = @(").count(), 3);
+        assert!(out.contains("New-NetFirewallRule -Name 'feeds-3' -DisplayName 'feeds-3' -Direction Outbound -Action Allow"));
+        assert!(out.contains("[int]$Matches[1] -gt 3 }"));
+        let empty = script(&WindowsFirewall::default(), &[]);
+        assert!(!empty.contains("$addresses"));
+        assert!(empty.contains("[int]$Matches[1] -gt 0 }"));
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(WindowsFirewall::default().name("it's").is_none());
+        assert!(WindowsFirewall::default().name("").is_none());
+        assert!(WindowsFirewall::default().action("deny").is_none());
+        assert!(WindowsFirewall::default().direction("both").is_none());
+    }
+}
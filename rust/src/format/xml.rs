@@ -0,0 +1,145 @@
+//! An XML document, for appliances and APIs that only take XML.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// An XML document with an element for each network, inside a root
+/// element. The names are configurable, and the network can be an
+/// attribute instead of the element's text.
+#[derive(Debug)]
+pub struct Xml {
+    root: String,
+    element: String,
+    attribute: Option<String>,
+}
+
+impl Default for Xml {
+    /// Returns the format with `<network>` elements in a `<networks>` root.
+    fn default() -> Self {
+        Xml {
+            root: "networks".to_string(),
+            element: "network".to_string(),
+            attribute: None,
+        }
+    }
+}
+
+/// Returns whether the name is a valid XML name. Only ASCII names are
+/// accepted, which is all any of the consumers need.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || "_:.-".contains(c))
+}
+
+impl Xml {
+    /// Names the root element. Returns `None` if the name isn't valid XML.
+    pub fn root(mut self, name: &str) -> Option<Self> {
+        self.root = is_name(name).then(|| name.to_string())?;
+        Some(self)
+    }
+
+    /// Names the element for each network. Returns `None` if the name
+    /// isn't valid XML.
+    pub fn element(mut self, name: &str) -> Option<Self> {
+        self.element = is_name(name).then(|| name.to_string())?;
+        Some(self)
+    }
+
+    /// Puts each network in an attribute with this name instead of in the
+    /// element's text. Returns `None` if the name isn't valid XML.
+    pub fn attribute(mut self, name: &str) -> Option<Self> {
+        self.attribute = Some(is_name(name).then(|| name.to_string())?);
+        Some(self)
+    }
+}
+
+impl Formatter for Xml {
+    fn name(&self) -> &str {
+        "xml"
+    }
+
+    fn description(&self) -> &str {
+        "An XML document with an element for each network"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        // Networks never need escaping, and the names were checked.
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        if nets.is_empty() {
+            return writeln!(out, "<{}/>", self.root);
+        }
+        writeln!(out, "<{}>", self.root)?;
+        for net in nets {
+            match &self.attribute {
+                Some(attribute) => writeln!(
+                    out,
+                    "  <{} {}=\"{}\"/>",
+                    self.element,
+                    attribute,
+                    style.display(net)
+                )?,
+                None => writeln!(out, "  <{0}>{1}</{0}>", self.element, style.display(net))?,
+            }
+        }
+        writeln!(out, "</{}>", self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xml(xml: &Xml, nets: &[&str]) -> String {
+        let nets: Vec<IpNet> = nets.iter().map(|net| net.parse().unwrap()).collect();
+        let mut out = Vec::new();
+        xml.write(&mut out, &nets, &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write() {
+        assert_eq!(
+            xml(&Xml::default(), &["10.0.0.0/8", "2001:db8::/32"]),
+            "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<networks>
+  <network>10.0.0.0/8</network>
+  <network>2001:db8::/32</network>
+</networks>
+"
+        );
+        assert_eq!(
+            xml(&Xml::default(), &[]),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<networks/>\n"
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let format = Xml::default()
+            .root("acl:list")
+            .unwrap()
+            .element("entry")
+            .unwrap()
+            .attribute("prefix")
+            .unwrap();
+        assert_eq!(
+            xml(&format, &["10.0.0.0/8"]),
+            "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<acl:list>
+  <entry prefix=\"10.0.0.0/8\"/>
+</acl:list>
+"
+        );
+        assert!(is_name("_a-1.b"));
+        assert!(!is_name(""));
+        assert!(!is_name("1st"));
+        assert!(!is_name("a b"));
+        assert!(Xml::default().element("<bad>").is_none());
+    }
+}
@@ -0,0 +1,52 @@
+//! A YAML document, shaped like the JSON format.
+
+use super::{Formatter, Style};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// A YAML mapping with a `networks` sequence of strings. The networks are
+/// quoted, since IPv6 networks are full of colons.
+pub struct Yaml;
+
+impl Formatter for Yaml {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn description(&self) -> &str {
+        "A YAML mapping with a \"networks\" sequence"
+    }
+
+    fn write(&self, out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+        if nets.is_empty() {
+            return writeln!(out, "networks: []");
+        }
+        writeln!(out, "networks:")?;
+        for net in nets {
+            writeln!(out, "  - \"{}\"", style.display(net))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let nets = vec![
+            "10.0.0.0/8".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
+        ];
+        let mut out = Vec::new();
+        Yaml.write(&mut out, &nets, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "networks:\n  - \"10.0.0.0/8\"\n  - \"2001:db8::/32\"\n"
+        );
+        let mut out = Vec::new();
+        Yaml.write(&mut out, &[], &Style::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "networks: []\n");
+    }
+}
@@ -0,0 +1,509 @@
+//! GeoLite2 country data, for `lfc geoip` and `lfc by-country`.
+//!
+//! MaxMind's GeoLite2 Country database is free, but only with a license
+//! key, so it can't ship with lfc. `lfc geoip update` downloads the CSV
+//! edition with the key, joins each block to its country's code, and saves
+//! the blocks in the data directory as `NETWORK CC` lines that are quick
+//! to load. Country data drifts as address space changes hands, so a copy
+//! more than 30 days old is refused rather than quietly trusted.
+
+use crate::cache;
+use flate2::read::DeflateDecoder;
+use ipnet::IpNet;
+use lfc::space::{self, Family};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where MaxMind's databases are downloaded from.
+const DOWNLOAD: &str = "https://download.maxmind.com/app/geoip_download";
+
+/// The edition `lfc geoip update` downloads.
+const EDITION: &str = "GeoLite2-Country-CSV";
+
+/// The file the blocks are saved in, in the GeoIP directory.
+const FILE: &str = "GeoLite2-Country.txt";
+
+/// Where MaxMind's geoipupdate keeps its license key, and where it's read
+/// from when neither `--geoip-config` nor the environment has one.
+pub const CONFIG: &str = "/etc/GeoIP.conf";
+
+/// How old a saved database can be before it's refused.
+const MAX_AGE: Duration = Duration::from_secs(30 * 86_400);
+
+/// Returns the directory the database is kept in: `geoip` in the
+/// [data directory](cache::data_dir).
+pub fn dir(data_dir: Option<&str>) -> Result<PathBuf, String> {
+    Ok(cache::data_dir(data_dir)?.join("geoip"))
+}
+
+/// Returns the MaxMind license key from MAXMIND_LICENSE_KEY, or else from
+/// the `LicenseKey` line of the GeoIP.conf at `config`.
+pub fn license_key(config: &str) -> Result<String, String> {
+    if let Ok(key) = std::env::var("MAXMIND_LICENSE_KEY")
+        && !key.is_empty()
+    {
+        return Ok(key);
+    }
+    let text = fs::read_to_string(config).map_err(|err| {
+        format!(
+            "set MAXMIND_LICENSE_KEY in the environment, or LicenseKey in {} ({})",
+            config, err
+        )
+    })?;
+    config_key(&text).ok_or_else(|| format!("{} doesn't have a LicenseKey", config))
+}
+
+/// Returns the value of the `LicenseKey` line in a GeoIP.conf.
+fn config_key(text: &str) -> Option<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| line.trim().strip_prefix("LicenseKey"))
+        .find_map(|key| {
+            let key = key.trim();
+            (!key.is_empty()).then(|| key.to_string())
+        })
+}
+
+/// Downloads the database with the license key and saves its blocks in the
+/// directory, replacing any already there. Returns how many it saved.
+pub fn update(dir: &Path, key: &str) -> Result<usize, String> {
+    let zip = download(key)?;
+    let locations = unzip(&zip, "GeoLite2-Country-Locations-en.csv")?;
+    let v4 = unzip(&zip, "GeoLite2-Country-Blocks-IPv4.csv")?;
+    let v6 = unzip(&zip, "GeoLite2-Country-Blocks-IPv6.csv")?;
+    let text = |bytes: Vec<u8>| {
+        String::from_utf8(bytes).map_err(|_| format!("{} has a CSV that isn't text", EDITION))
+    };
+    let blocks = join(&text(locations)?, &[&text(v4)?, &text(v6)?])?;
+    let mut contents = String::new();
+    for (net, country) in &blocks {
+        contents += &format!("{} {}\n", net, country);
+    }
+    let path = dir.join(FILE);
+    let tmp = path.with_extension("txt.tmp");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&tmp, contents))
+        .and_then(|_| fs::rename(&tmp, &path))
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(blocks.len())
+}
+
+/// Downloads the zipped database. The URL has the license key in it, so
+/// errors don't mention it.
+fn download(key: &str) -> Result<Vec<u8>, String> {
+    let failed = |err: ureq::Error| match err {
+        ureq::Error::StatusCode(401) => "MaxMind didn't accept the license key".to_string(),
+        err => format!("unable to download {}: {}", EDITION, err),
+    };
+    ureq::get(DOWNLOAD)
+        .query("edition_id", EDITION)
+        .query("license_key", key)
+        .query("suffix", "zip")
+        .call()
+        .map_err(failed)?
+        .body_mut()
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()
+        .map_err(failed)
+}
+
+/// Returns the contents of the file in the zip archive whose name ends with
+/// `name`. MaxMind puts the files in a directory named for the release.
+fn unzip(zip: &[u8], name: &str) -> Result<Vec<u8>, String> {
+    let bad = || format!("the {} download isn't a zip archive", EDITION);
+    let u16_at = |at: usize| -> Result<usize, String> {
+        let bytes = zip.get(at..at + 2).ok_or_else(bad)?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    let u32_at = |at: usize| -> Result<usize, String> {
+        let bytes = zip.get(at..at + 4).ok_or_else(bad)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    // The end of central directory record is last, after a comment of up
+    // to 64 KiB.
+    let end = (0..zip.len().saturating_sub(21))
+        .rev()
+        .take(65_536 + 22)
+        .find(|&at| zip[at..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(bad)?;
+    let mut at = u32_at(end + 16)?;
+    for _ in 0..u16_at(end + 10)? {
+        if !zip.get(at..).ok_or_else(bad)?.starts_with(b"PK\x01\x02") {
+            return Err(bad());
+        }
+        let method = u16_at(at + 10)?;
+        let size = u32_at(at + 20)?;
+        let name_len = u16_at(at + 28)?;
+        let entry_name = zip.get(at + 46..at + 46 + name_len).ok_or_else(bad)?;
+        let local = u32_at(at + 42)?;
+        at += 46 + name_len + u16_at(at + 30)? + u16_at(at + 32)?;
+        if !entry_name.ends_with(name.as_bytes()) {
+            continue;
+        }
+        let start = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let data = zip.get(start..start + size).ok_or_else(bad)?;
+        return match method {
+            0 => Ok(data.to_vec()),
+            8 => {
+                let mut contents = Vec::new();
+                DeflateDecoder::new(data)
+                    .read_to_end(&mut contents)
+                    .map_err(|err: io::Error| format!("{} in {}: {}", name, EDITION, err))?;
+                Ok(contents)
+            }
+            _ => Err(format!("{} in {} is compressed oddly", name, EDITION)),
+        };
+    }
+    Err(format!("the {} download doesn't have {}", EDITION, name))
+}
+
+/// Joins the blocks CSVs to the locations CSV, and returns each block with
+/// its country's code, sorted. A block's country is where it's used, or
+/// where it's registered if MaxMind doesn't know that, and blocks with
+/// neither, like those only placed on a continent, are left out.
+fn join(locations: &str, blocks: &[&str]) -> Result<Vec<(IpNet, String)>, String> {
+    let mut countries = HashMap::new();
+    for line in locations.lines().skip(1) {
+        let fields: Vec<&str> = line.splitn(6, ',').collect();
+        if let [id, _, _, _, country, ..] = fields[..]
+            && !country.is_empty()
+        {
+            countries.insert(id, country.trim_matches('"'));
+        }
+    }
+    let mut joined = Vec::new();
+    for line in blocks.iter().flat_map(|blocks| blocks.lines().skip(1)) {
+        let fields: Vec<&str> = line.splitn(4, ',').collect();
+        let [net, id, registered, ..] = fields[..] else {
+            continue;
+        };
+        let net: IpNet = net
+            .parse()
+            .map_err(|_| format!("'{}' in {} isn't a network", net, EDITION))?;
+        if let Some(country) = countries.get(id).or_else(|| countries.get(registered)) {
+            joined.push((net, country.to_string()));
+        }
+    }
+    joined.sort();
+    Ok(joined)
+}
+
+/// The saved blocks, each with its country's code.
+pub struct Database {
+    blocks: Vec<(IpNet, String)>,
+    age: Duration,
+}
+
+impl Database {
+    /// Loads the blocks saved in the directory. It's an error, with a hint
+    /// to run `lfc geoip update`, if there aren't any or they're more than
+    /// 30 days old.
+    pub fn open(dir: &Path) -> Result<Database, String> {
+        let path = dir.join(FILE);
+        let failed = |err: io::Error| format!("{}: {}", path.display(), err);
+        let age = match fs::metadata(&path) {
+            Ok(metadata) => metadata
+                .modified()
+                .map_err(failed)?
+                .elapsed()
+                .unwrap_or_default(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(format!(
+                    "there's no GeoLite2 database in {}; run `lfc geoip update` to download one",
+                    dir.display()
+                ));
+            }
+            Err(err) => return Err(failed(err)),
+        };
+        Database::check_age(dir, age)?;
+        let text = fs::read_to_string(&path).map_err(failed)?;
+        let blocks = parse(&text).map_err(|line| {
+            format!(
+                "{} has '{}', which isn't a block; run `lfc geoip update` to download it again",
+                path.display(),
+                line
+            )
+        })?;
+        Ok(Database { blocks, age })
+    }
+
+    /// Refuses a database that's too old to trust.
+    fn check_age(dir: &Path, age: Duration) -> Result<(), String> {
+        if age <= MAX_AGE {
+            return Ok(());
+        }
+        Err(format!(
+            "the GeoLite2 database in {} is {} days old; run `lfc geoip update` to refresh it",
+            dir.display(),
+            age.as_secs() / 86_400
+        ))
+    }
+
+    /// Returns how many blocks there are.
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns how long ago the database was downloaded.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    /// Splits the network at the edges of the blocks it overlaps, and
+    /// returns each part with the code of its country, or `None` for the
+    /// parts no block places.
+    pub fn split(&self, net: &IpNet) -> Vec<(IpNet, Option<&str>)> {
+        let family = Family::of(net);
+        let (start, end) = space::range(net);
+        // The blocks don't overlap, so they're in order of their last
+        // addresses too.
+        let first = self.blocks.partition_point(|(block, _)| {
+            (Family::of(block), space::range(block).1) < (family, start)
+        });
+        let mut parts = Vec::new();
+        let unknown = |from: u128, to: u128, parts: &mut Vec<(IpNet, Option<&str>)>| {
+            let mut nets = Vec::new();
+            space::cover(family, from, to, &mut nets);
+            parts.extend(nets.into_iter().map(|net| (net, None)));
+        };
+        let mut next = start;
+        for (block, country) in &self.blocks[first..] {
+            let (block_start, block_end) = space::range(block);
+            if Family::of(block) != family || block_start > end {
+                break;
+            }
+            let (part_start, part_end) = (block_start.max(start), block_end.min(end));
+            if next < part_start {
+                unknown(next, part_start - 1, &mut parts);
+            }
+            let part = if block.contains(net) { net } else { block };
+            parts.push((*part, Some(country.as_str())));
+            match part_end.checked_add(1) {
+                Some(after) => next = after,
+                None => return parts,
+            }
+        }
+        if next <= end {
+            unknown(next, end, &mut parts);
+        }
+        parts
+    }
+}
+
+/// Parses saved `NETWORK CC` lines, or returns the first line that isn't
+/// one.
+fn parse(text: &str) -> Result<Vec<(IpNet, String)>, &str> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (net, country) = line.split_once(' ').ok_or(line)?;
+            let net = net.parse::<IpNet>().map_err(|_| line)?;
+            Ok((net, country.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    const LOCATIONS: &str = "\
+geoname_id,locale_code,continent_code,continent_name,country_iso_code,country_name,is_in_european_union
+2077456,en,OC,Oceania,AU,Australia,0
+1835841,en,AS,Asia,KR,\"Korea, Republic of\",0
+6255148,en,EU,Europe,,,0
+6252001,en,NA,\"North America\",US,\"United States\",0
+";
+
+    const V4: &str = "\
+network,geoname_id,registered_country_geoname_id,represented_country_geoname_id,is_anonymous_proxy,is_satellite_provider,is_anycast
+1.0.0.0/24,2077456,2077456,,0,0,
+1.11.0.0/16,1835841,1835841,,0,0,
+2.16.0.0/24,6255148,6255148,,0,0,
+8.8.8.0/24,,6252001,,0,0,1
+";
+
+    const V6: &str = "\
+network,geoname_id,registered_country_geoname_id,represented_country_geoname_id,is_anonymous_proxy,is_satellite_provider,is_anycast
+2001:db8::/32,6252001,6252001,,0,0,
+";
+
+    /// Returns a zip archive of the files, the first stored and the rest
+    /// deflated.
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for (i, (name, contents)) in files.iter().enumerate() {
+            let (method, data) = if i == 0 {
+                (0u16, contents.as_bytes().to_vec())
+            } else {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents.as_bytes()).unwrap();
+                (8, encoder.finish().unwrap())
+            };
+            let sizes = [
+                &(data.len() as u32).to_le_bytes()[..],
+                &(contents.len() as u32).to_le_bytes(),
+                &(name.len() as u16).to_le_bytes(),
+                &[0, 0],
+            ]
+            .concat();
+            directory.extend(b"PK\x01\x02");
+            directory.extend([0; 6]);
+            directory.extend(method.to_le_bytes());
+            directory.extend([0; 8]);
+            directory.extend(&sizes);
+            directory.extend([0; 10]);
+            directory.extend((zip.len() as u32).to_le_bytes());
+            directory.extend(name.as_bytes());
+            zip.extend(b"PK\x03\x04");
+            zip.extend([0; 4]);
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 8]);
+            zip.extend(&sizes);
+            zip.extend(name.as_bytes());
+            zip.extend(&data);
+        }
+        let offset = zip.len() as u32;
+        zip.extend(&directory);
+        zip.extend(b"PK\x05\x06");
+        zip.extend([0; 4]);
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(offset.to_le_bytes());
+        zip.extend([0; 2]);
+        zip
+    }
+
+    #[test]
+    fn test_unzip() {
+        let zip = zip(&[
+            ("GeoLite2-Country-CSV_20261013/COPYRIGHT.txt", "(c) MaxMind"),
+            (
+                "GeoLite2-Country-CSV_20261013/GeoLite2-Country-Blocks-IPv4.csv",
+                V4,
+            ),
+        ]);
+        assert_eq!(unzip(&zip, "COPYRIGHT.txt").unwrap(), b"(c) MaxMind");
+        assert_eq!(
+            unzip(&zip, "GeoLite2-Country-Blocks-IPv4.csv").unwrap(),
+            V4.as_bytes()
+        );
+        assert!(unzip(&zip, "GeoLite2-Country-Blocks-IPv6.csv").is_err());
+        assert!(unzip(b"<html>Invalid license key</html>", "COPYRIGHT.txt").is_err());
+    }
+
+    #[test]
+    fn test_join() {
+        let blocks = join(LOCATIONS, &[V4, V6]).unwrap();
+        let blocks: Vec<String> = blocks
+            .iter()
+            .map(|(net, country)| format!("{} {}", net, country))
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![
+                "1.0.0.0/24 AU",
+                "1.11.0.0/16 KR",
+                "8.8.8.0/24 US",
+                "2001:db8::/32 US"
+            ]
+        );
+        assert!(join(LOCATIONS, &["network\nnope,1,1\n"]).is_err());
+    }
+
+    #[test]
+    fn test_config_key() {
+        let config = "\
+# GeoIP.conf file for geoipupdate
+AccountID 123456
+LicenseKey 0123abcd  # from the account page
+EditionIDs GeoLite2-Country
+";
+        assert_eq!(config_key(config).as_deref(), Some("0123abcd"));
+        assert_eq!(config_key("# LicenseKey 0123abcd\nLicenseKey\n"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let blocks = parse("1.0.0.0/24 AU\n\n2001:db8::/32 US\n").unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                ("1.0.0.0/24".parse().unwrap(), "AU".to_string()),
+                ("2001:db8::/32".parse().unwrap(), "US".to_string())
+            ]
+        );
+        assert_eq!(parse("1.0.0.0/24\n").unwrap_err(), "1.0.0.0/24");
+    }
+
+    #[test]
+    fn test_split() {
+        let database = Database {
+            blocks: parse("1.0.0.0/24 AU\n1.0.1.0/24 CN\n1.0.4.0/22 AU\n2001:db8::/32 US\n")
+                .unwrap(),
+            age: Duration::ZERO,
+        };
+        let split = |net: &str| -> Vec<String> {
+            database
+                .split(&net.parse().unwrap())
+                .into_iter()
+                .map(|(net, country)| format!("{} {}", net, country.unwrap_or("-")))
+                .collect()
+        };
+        assert_eq!(
+            split("1.0.0.0/21"),
+            vec![
+                "1.0.0.0/24 AU",
+                "1.0.1.0/24 CN",
+                "1.0.2.0/23 -",
+                "1.0.4.0/22 AU"
+            ]
+        );
+        assert_eq!(split("1.0.5.128/25"), vec!["1.0.5.128/25 AU"]);
+        assert_eq!(split("1.0.8.0/24"), vec!["1.0.8.0/24 -"]);
+        let everything = split("0.0.0.0/0");
+        assert_eq!(everything.len(), 25);
+        assert_eq!(everything[..2], ["0.0.0.0/8 -", "1.0.0.0/24 AU"]);
+        assert_eq!(everything[24], "128.0.0.0/1 -");
+        assert_eq!(split("2001:db8::1/128"), vec!["2001:db8::1/128 US"]);
+        let everything = split("::/0");
+        assert!(everything.contains(&"2001:db8::/32 US".to_string()));
+        assert_eq!(everything.last().unwrap(), "8000::/1 -");
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = std::env::temp_dir().join(format!("lfc-geoip-{}", std::process::id()));
+        let err = Database::open(&dir).err().unwrap();
+        assert!(
+            err.contains("run `lfc geoip update` to download one"),
+            "{}",
+            err
+        );
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(FILE), "1.0.0.0/24 AU\n").unwrap();
+        let database = Database::open(&dir).unwrap();
+        assert_eq!(database.len(), 1);
+        assert!(database.age() < MAX_AGE);
+        fs::remove_dir_all(&dir).unwrap();
+        let err = Database::check_age(&dir, MAX_AGE + Duration::from_secs(86_400)).unwrap_err();
+        assert!(err.contains("is 31 days old"), "{}", err);
+    }
+
+    #[test]
+    fn test_dir() {
+        assert_eq!(
+            dir(Some("/var/cache/lfc")).unwrap(),
+            Path::new("/var/cache/lfc/geoip")
+        );
+    }
+}
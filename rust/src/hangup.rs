@@ -0,0 +1,66 @@
+//! Reloading on SIGHUP, the way other long-running network daemons do.
+//!
+//! `lfc serve` and `lfc --refresh` read their sources again when they're
+//! sent SIGHUP, instead of the signal ending them.
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Returns a channel that gets a message each time the process is sent
+/// SIGHUP.
+#[cfg(unix)]
+pub fn hangups() -> io::Result<Receiver<()>> {
+    use signal_hook::consts::SIGHUP;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGHUP])?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            if sender.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+/// Returns a channel that never gets a message, since there's no SIGHUP
+/// off Unix.
+#[cfg(not(unix))]
+pub fn hangups() -> io::Result<Receiver<()>> {
+    let (sender, receiver) = mpsc::channel();
+    // Keep the channel open, so waiting on it waits.
+    std::mem::forget(sender);
+    Ok(receiver)
+}
+
+/// Waits for SIGHUP, for up to `timeout` if there is one, and returns
+/// whether it came. Several that came together count as one.
+pub fn wait(hangups: &Receiver<()>, timeout: Option<Duration>) -> bool {
+    let came = match timeout {
+        Some(timeout) => match hangups.recv_timeout(timeout) {
+            Ok(()) => true,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => false,
+        },
+        None => hangups.recv().is_ok(),
+    };
+    while hangups.try_recv().is_ok() {}
+    came
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait() {
+        let (sender, receiver) = mpsc::channel();
+        assert!(!wait(&receiver, Some(Duration::from_millis(10))));
+        sender.send(()).unwrap();
+        sender.send(()).unwrap();
+        assert!(wait(&receiver, None));
+        assert!(!wait(&receiver, Some(Duration::ZERO)));
+    }
+}
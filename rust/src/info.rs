@@ -0,0 +1,331 @@
+//! What `lfc info` says about a network.
+//!
+//! It's the same thing ipcalc does: the network's edges, its size, its
+//! mask in a few forms, and whether IANA set it aside for something
+//! special, from the IPv4 and IPv6 Special-Purpose Address Registries.
+//! Multicast space has registries of its own, but is listed here too.
+
+use ipnet::IpNet;
+use lfc::format::Style;
+use lfc::space::{self, Family};
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Blocks that IANA set aside, and what for.
+const SPECIAL: &[(&str, &str)] = &[
+    ("0.0.0.0/8", "\"This network\""),
+    ("0.0.0.0/32", "\"This host on this network\""),
+    ("10.0.0.0/8", "Private-Use"),
+    ("100.64.0.0/10", "Shared Address Space"),
+    ("127.0.0.0/8", "Loopback"),
+    ("169.254.0.0/16", "Link Local"),
+    ("172.16.0.0/12", "Private-Use"),
+    ("192.0.0.0/24", "IETF Protocol Assignments"),
+    ("192.0.0.0/29", "IPv4 Service Continuity Prefix"),
+    ("192.0.0.8/32", "IPv4 dummy address"),
+    ("192.0.0.9/32", "Port Control Protocol Anycast"),
+    ("192.0.0.10/32", "Traversal Using Relays around NAT Anycast"),
+    ("192.0.0.170/32", "NAT64/DNS64 Discovery"),
+    ("192.0.0.171/32", "NAT64/DNS64 Discovery"),
+    ("192.0.2.0/24", "Documentation (TEST-NET-1)"),
+    ("192.31.196.0/24", "AS112-v4"),
+    ("192.52.193.0/24", "AMT"),
+    ("192.88.99.0/24", "Deprecated (6to4 Relay Anycast)"),
+    ("192.168.0.0/16", "Private-Use"),
+    ("192.175.48.0/24", "Direct Delegation AS112 Service"),
+    ("198.18.0.0/15", "Benchmarking"),
+    ("198.51.100.0/24", "Documentation (TEST-NET-2)"),
+    ("203.0.113.0/24", "Documentation (TEST-NET-3)"),
+    ("224.0.0.0/4", "Multicast"),
+    ("240.0.0.0/4", "Reserved"),
+    ("255.255.255.255/32", "Limited Broadcast"),
+    ("::/128", "Unspecified Address"),
+    ("::1/128", "Loopback Address"),
+    ("::ffff:0:0/96", "IPv4-mapped Address"),
+    ("64:ff9b::/96", "IPv4-IPv6 Translation"),
+    ("64:ff9b:1::/48", "IPv4-IPv6 Translation (local use)"),
+    ("100::/64", "Discard-Only Address Block"),
+    ("2001::/23", "IETF Protocol Assignments"),
+    ("2001::/32", "TEREDO"),
+    ("2001:1::1/128", "Port Control Protocol Anycast"),
+    ("2001:1::2/128", "Traversal Using Relays around NAT Anycast"),
+    ("2001:2::/48", "Benchmarking"),
+    ("2001:3::/32", "AMT"),
+    ("2001:4:112::/48", "AS112-v6"),
+    ("2001:10::/28", "Deprecated (previously ORCHID)"),
+    ("2001:20::/28", "ORCHIDv2"),
+    ("2001:db8::/32", "Documentation"),
+    ("2002::/16", "6to4"),
+    ("2620:4f:8000::/48", "Direct Delegation AS112 Service"),
+    ("3fff::/20", "Documentation"),
+    ("5f00::/16", "Segment Routing (SRv6) SIDs"),
+    ("fc00::/7", "Unique-Local"),
+    ("fe80::/10", "Link-Local Unicast"),
+    ("ff00::/8", "Multicast"),
+];
+
+/// A special-purpose block and what it's for.
+type Block = (IpNet, &'static str);
+
+/// Returns the most specific special-purpose block that holds the network,
+/// and the blocks inside the network.
+pub fn special(net: &IpNet) -> (Option<Block>, Vec<Block>) {
+    let blocks = SPECIAL
+        .iter()
+        .map(|(block, name)| (block.parse::<IpNet>().unwrap(), *name));
+    let mut holder: Option<Block> = None;
+    let mut inside = Vec::new();
+    for (block, name) in blocks {
+        if block.contains(net) {
+            if holder.is_none_or(|(held, _)| block.prefix_len() > held.prefix_len()) {
+                holder = Some((block, name));
+            }
+        } else if net.contains(&block) {
+            inside.push((block, name));
+        }
+    }
+    (holder, inside)
+}
+
+/// Returns every special-purpose block.
+pub fn special_blocks() -> Vec<IpNet> {
+    SPECIAL
+        .iter()
+        .map(|(block, _)| block.parse().unwrap())
+        .collect()
+}
+
+/// Returns what IANA set the network aside for, or the special-purpose
+/// blocks inside it, or `None` if there's nothing special about it.
+pub fn describe(net: &IpNet) -> Option<String> {
+    match special(net) {
+        (Some((_, name)), _) => Some(name.to_string()),
+        (None, inside) if inside.is_empty() => None,
+        (None, inside) => {
+            let names: Vec<String> = inside
+                .iter()
+                .map(|(block, name)| format!("{} ({})", name, block))
+                .collect();
+            Some(format!("includes {}", names.join(", ")))
+        }
+    }
+}
+
+/// The networks that are in, or include, a special-purpose block, and
+/// whether they include it.
+type Classified = Vec<(IpNet, bool)>;
+
+/// Sorts the networks by the special-purpose blocks they're in or include,
+/// in the registries' order, leaving out blocks that none of them touch. A
+/// network is put with the most specific block that holds it, and with
+/// every block inside it.
+pub fn classify(nets: &[IpNet]) -> Vec<(Block, Classified)> {
+    let specials: Vec<_> = nets.iter().map(|net| (*net, special(net))).collect();
+    SPECIAL
+        .iter()
+        .map(|(block, name)| (block.parse::<IpNet>().unwrap(), *name))
+        .filter_map(|block| {
+            let members: Classified = specials
+                .iter()
+                .filter_map(|(net, (holder, inside))| {
+                    if *holder == Some(block) {
+                        Some((*net, false))
+                    } else {
+                        inside.contains(&block).then_some((*net, true))
+                    }
+                })
+                .collect();
+            (!members.is_empty()).then_some((block, members))
+        })
+        .collect()
+}
+
+/// Writes which networks are in, or include, each special-purpose block,
+/// and how many of them there are.
+pub fn write_report(out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+    let classified = classify(nets);
+    let mut special: Vec<IpNet> = Vec::new();
+    for ((block, name), members) in &classified {
+        writeln!(out, "{} ({}):", name, style.display(block))?;
+        for (net, includes) in members {
+            if *includes {
+                writeln!(out, "    {}, which includes it", style.display(net))?;
+            } else {
+                writeln!(out, "    {}", style.display(net))?;
+            }
+            special.push(*net);
+        }
+    }
+    special.sort_unstable();
+    special.dedup();
+    writeln!(
+        out,
+        "{} of {} networks in the result are or include special-purpose space",
+        special.len(),
+        nets.len()
+    )
+}
+
+/// Writes the address's bits, with a space after the network part.
+fn binary(net: &IpNet) -> String {
+    let (bits, group, separator) = match Family::of(net) {
+        Family::V4 => (32, 8, '.'),
+        Family::V6 => (128, 16, ':'),
+    };
+    let address = space::range(&IpNet::from(net.addr())).0;
+    let mut text = String::with_capacity(bits + bits / group + 1);
+    for bit in 0..bits {
+        if bit == usize::from(net.prefix_len()) {
+            text.push(' ');
+        } else if bit > 0 && bit % group == 0 {
+            text.push(separator);
+        }
+        text.push(if address >> (bits - 1 - bit) & 1 == 1 {
+            '1'
+        } else {
+            '0'
+        });
+    }
+    text
+}
+
+/// Writes everything `lfc info` says about the network.
+pub fn write(out: &mut dyn Write, net: &IpNet, style: &Style) -> io::Result<()> {
+    let address = |address: IpAddr| style.address(address);
+    let network = net.trunc();
+    let addresses = space::subnet_count(net, net.max_prefix_len());
+    // Only IPv4 networks bigger than a /31 lose their first and last
+    // addresses to the network and broadcast addresses.
+    let hosts = match net {
+        IpNet::V4(v4) if v4.prefix_len() < 31 => (
+            IpAddr::V4(Ipv4Addr::from(u32::from(v4.network()) + 1)),
+            IpAddr::V4(Ipv4Addr::from(u32::from(v4.broadcast()) - 1)),
+        ),
+        _ => (net.network(), net.broadcast()),
+    };
+    if network != *net {
+        writeln!(out, "address:    {}", address(net.addr()))?;
+    }
+    writeln!(out, "network:    {}", style.display(&network))?;
+    writeln!(out, "addresses:  {}", space::Count(addresses))?;
+    match net {
+        IpNet::V4(_) => {
+            writeln!(out, "first:      {}", address(net.network()))?;
+            writeln!(out, "broadcast:  {}", address(net.broadcast()))?;
+        }
+        IpNet::V6(_) => {
+            writeln!(out, "first:      {}", address(net.network()))?;
+            writeln!(out, "last:       {}", address(net.broadcast()))?;
+        }
+    }
+    writeln!(out, "first host: {}", address(hosts.0))?;
+    writeln!(out, "last host:  {}", address(hosts.1))?;
+    writeln!(out, "netmask:    {}", address(net.netmask()))?;
+    writeln!(out, "wildcard:   {}", address(net.hostmask()))?;
+    writeln!(out, "binary:     {}", binary(net))?;
+    let (holder, inside) = special(net);
+    match holder {
+        Some((block, name)) => writeln!(out, "special:    {} ({})", name, style.display(&block))?,
+        None if inside.is_empty() => writeln!(out, "special:    no")?,
+        None => {}
+    }
+    for (block, name) in inside {
+        writeln!(out, "includes:   {} ({})", name, style.display(&block))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(net: &str) -> String {
+        let mut out = Vec::new();
+        write(&mut out, &net.parse().unwrap(), &Style::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_ipv4() {
+        assert_eq!(
+            info("203.0.113.0/26"),
+            "\
+network:    203.0.113.0/26
+addresses:  64
+first:      203.0.113.0
+broadcast:  203.0.113.63
+first host: 203.0.113.1
+last host:  203.0.113.62
+netmask:    255.255.255.192
+wildcard:   0.0.0.63
+binary:     11001011.00000000.01110001.00 000000
+special:    Documentation (TEST-NET-3) (203.0.113.0/24)
+"
+        );
+        let text = info("198.18.7.9/31");
+        assert!(text.starts_with("address:    198.18.7.9\nnetwork:    198.18.7.8/31\n"));
+        assert!(text.contains("first host: 198.18.7.8\nlast host:  198.18.7.9\n"));
+    }
+
+    #[test]
+    fn test_ipv6() {
+        let text = info("2001:db8::/126");
+        assert!(text.contains("first:      2001:db8::\nlast:       2001:db8::3\n"));
+        assert!(text.contains("first host: 2001:db8::\nlast host:  2001:db8::3\n"));
+        assert!(text.contains("netmask:    ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffc\n"));
+        assert!(text.contains("wildcard:   ::3\n"));
+        assert!(text.contains(":0000000000000000:00000000000000 00\n"));
+        assert!(text.ends_with("special:    Documentation (2001:db8::/32)\n"));
+    }
+
+    #[test]
+    fn test_count() {
+        assert!(info("::/0").contains("addresses:  340282366920938463463374607431768211456\n"));
+        assert!(info("::/1").contains("addresses:  170141183460469231731687303715884105728\n"));
+    }
+
+    #[test]
+    fn test_special() {
+        let net = |text: &str| text.parse::<IpNet>().unwrap();
+        assert_eq!(
+            special(&net("192.0.0.9/32")).0,
+            Some((net("192.0.0.9/32"), "Port Control Protocol Anycast"))
+        );
+        assert_eq!(special(&net("8.8.8.0/24")), (None, Vec::new()));
+        let (holder, inside) = special(&net("192.0.0.0/16"));
+        assert_eq!(holder, None);
+        assert_eq!(inside.len(), 8);
+        assert!(info("8.8.8.0/24").ends_with("special:    no\n"));
+        assert_eq!(
+            describe(&net("10.1.0.0/16")).as_deref(),
+            Some("Private-Use")
+        );
+        assert_eq!(
+            describe(&net("198.51.0.0/16")).as_deref(),
+            Some("includes Documentation (TEST-NET-2) (198.51.100.0/24)")
+        );
+        assert_eq!(describe(&net("8.8.8.0/24")), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let nets: Vec<IpNet> = ["8.8.8.0/24", "10.1.0.0/16", "10.2.0.0/16", "198.51.0.0/16"]
+            .iter()
+            .map(|net| net.parse().unwrap())
+            .collect();
+        let mut out = Vec::new();
+        write_report(&mut out, &nets, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Private-Use (10.0.0.0/8):
+    10.1.0.0/16
+    10.2.0.0/16
+Documentation (TEST-NET-2) (198.51.100.0/24):
+    198.51.0.0/16, which includes it
+3 of 4 networks in the result are or include special-purpose space
+"
+        );
+        assert!(classify(&nets[..1]).is_empty());
+    }
+}
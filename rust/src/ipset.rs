@@ -0,0 +1,178 @@
+//! Reading and updating Linux ipsets.
+
+use crate::diff::Change;
+use ipnet::IpNet;
+use lfc::space::Family;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+/// The members of an existing ipset.
+#[derive(Debug, Default, PartialEq)]
+pub struct CurrentSet {
+    /// The set's name, if the input said.
+    pub name: Option<String>,
+    /// The set's address family, if the input said.
+    pub family: Option<Family>,
+    pub members: Vec<IpNet>,
+}
+
+/// Parses the members of an ipset.
+///
+/// This understands the output of `ipset list NAME`, the output of
+/// `ipset save NAME`, and plain lists with one network per line. Bare
+/// addresses are treated as /32 or /128 networks, since that's how ipset
+/// prints them. Anything after the address on a member line, like a timeout
+/// or comment, is ignored.
+pub fn parse_current(text: &str) -> Result<CurrentSet, String> {
+    let mut set = CurrentSet::default();
+    let is_listing = text.lines().any(|line| line.trim() == "Members:");
+    let mut in_members = !is_listing;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if !in_members {
+            if let Some(name) = line.strip_prefix("Name:") {
+                set.name = Some(name.trim().to_string());
+            } else if let Some(header) = line.strip_prefix("Header:") {
+                set.family = parse_family(header);
+            } else if line == "Members:" {
+                in_members = true;
+            }
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let member = match words.next() {
+            Some("create") => {
+                set.name = words.next().map(str::to_string);
+                set.family = parse_family(line);
+                continue;
+            }
+            Some("add") => {
+                set.name = words.next().map(str::to_string);
+                words.next()
+            }
+            word => word,
+        };
+        if let Some(member) = member {
+            set.members.push(parse_member(member)?);
+        }
+    }
+    Ok(set)
+}
+
+/// Finds the family in an ipset header like `family inet hashsize 1024`.
+fn parse_family(header: &str) -> Option<Family> {
+    let mut words = header.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "family" {
+            return match words.next() {
+                Some("inet") => Some(Family::V4),
+                Some("inet6") => Some(Family::V6),
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+fn parse_member(member: &str) -> Result<IpNet, String> {
+    member
+        .parse::<IpNet>()
+        .or_else(|_| member.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("unable to parse ipset member {:?}", member))
+}
+
+/// Writes the ipset commands that apply the changes to the named set, in the
+/// format read by `ipset restore`.
+///
+/// All additions come before any deletions. When a /24 grows into a /23, the
+/// /23 is added before the /24 is removed, so there's never a moment when
+/// addresses that should stay in the set are missing from it.
+pub fn write_delta<W: Write>(out: &mut W, name: &str, changes: &[Change]) -> io::Result<()> {
+    for change in changes {
+        if let Change::Added(net) = change {
+            writeln!(out, "add {} {}", name, net)?;
+        }
+    }
+    for change in changes {
+        if let Change::Removed(net) = change {
+            writeln!(out, "del {} {}", name, net)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff;
+
+    const LISTING: &str = "\
+Name: blocklist
+Type: hash:net
+Revision: 7
+Header: family inet hashsize 1024 maxelem 65536
+Size in memory: 1234
+References: 0
+Number of entries: 3
+Members:
+10.0.0.0/24
+192.0.2.7 timeout 300
+10.0.1.0/24
+";
+
+    #[test]
+    fn test_parse_listing() {
+        let set = parse_current(LISTING).unwrap();
+        assert_eq!(set.name.as_deref(), Some("blocklist"));
+        assert_eq!(set.family, Some(Family::V4));
+        assert_eq!(
+            set.members,
+            vec![
+                "10.0.0.0/24".parse::<IpNet>().unwrap(),
+                "192.0.2.7/32".parse().unwrap(),
+                "10.0.1.0/24".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_save() {
+        let set = parse_current(
+            "create v6list hash:net family inet6 hashsize 1024\nadd v6list 2001:db8::/32\n",
+        )
+        .unwrap();
+        assert_eq!(set.name.as_deref(), Some("v6list"));
+        assert_eq!(set.family, Some(Family::V6));
+        assert_eq!(set.members, vec!["2001:db8::/32".parse::<IpNet>().unwrap()]);
+    }
+
+    #[test]
+    fn test_parse_plain_list() {
+        let set = parse_current("10.0.0.0/8\n\n192.0.2.1\n").unwrap();
+        assert_eq!(set.name, None);
+        assert_eq!(set.members.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_bad_member() {
+        assert!(parse_current("Members:\nnonsense\n").is_err());
+    }
+
+    #[test]
+    fn test_write_delta_adds_before_deleting() {
+        let current = parse_current(LISTING).unwrap().members;
+        let desired: Vec<IpNet> = vec!["10.0.0.0/23".parse().unwrap()];
+        let mut out = Vec::new();
+        write_delta(&mut out, "blocklist", &diff(&current, &desired)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+add blocklist 10.0.0.0/23
+del blocklist 10.0.0.0/24
+del blocklist 10.0.1.0/24
+del blocklist 192.0.2.7/32
+"
+        );
+    }
+}
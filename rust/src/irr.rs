@@ -0,0 +1,259 @@
+//! IRR route objects, for `lfc irr-check`.
+//!
+//! The route and route6 objects registered for an origin are asked for
+//! over IRRd's whois protocol, which RADb and most other IRR mirrors
+//! speak: `!g` and `!6` list an origin's IPv4 and IPv6 routes, and `!s`
+//! picks which databases, like RADB or RIPE, they come from.
+
+use ipnet::IpNet;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// The IRR server to ask when `--irr-server` doesn't say.
+pub const SERVER: &str = "whois.radb.net";
+
+/// The port IRR servers listen on when the address doesn't say.
+const PORT: u16 = 43;
+
+/// How long to wait for the server before giving up.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How a network compares with an origin's route objects.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Check {
+    /// A route object matches it exactly.
+    Registered,
+    /// It's inside this route object, but no route object matches it.
+    Inside(IpNet),
+    /// It covers these route objects, but no route object matches it.
+    Covers(Vec<IpNet>),
+    /// No route object overlaps it at all.
+    Unregistered,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Registered => f.write_str("registered"),
+            Check::Inside(route) => write!(f, "mismatched, inside route {}", route),
+            Check::Covers(routes) => {
+                f.write_str("mismatched, covers route")?;
+                if routes.len() > 1 {
+                    f.write_str("s")?;
+                }
+                for (i, route) in routes.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{}", separator, route)?;
+                }
+                Ok(())
+            }
+            Check::Unregistered => f.write_str("unregistered"),
+        }
+    }
+}
+
+/// Returns how the network compares with the route objects.
+pub fn check(net: &IpNet, routes: &[IpNet]) -> Check {
+    if routes.contains(net) {
+        return Check::Registered;
+    }
+    if let Some(route) = routes
+        .iter()
+        .filter(|route| route.contains(net))
+        .max_by_key(|route| route.prefix_len())
+    {
+        return Check::Inside(*route);
+    }
+    let covered: Vec<IpNet> = routes
+        .iter()
+        .filter(|route| net.contains(*route))
+        .copied()
+        .collect();
+    if covered.is_empty() {
+        Check::Unregistered
+    } else {
+        Check::Covers(covered)
+    }
+}
+
+/// Returns the route and route6 objects registered for the origin, from
+/// the sources, or from every source the server mirrors if there are none,
+/// asking the IRR server at `HOST[:PORT]`.
+pub fn routes(server: &str, origin: u32, sources: &[String]) -> Result<Vec<IpNet>, String> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, PORT)
+    };
+    let failed = |err: io::Error| format!("{}: {}", address, err);
+    let stream = TcpStream::connect(&address).map_err(failed)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(failed)?;
+    query(stream, origin, sources).map_err(|err| format!("{}: {}", address, err))
+}
+
+/// Asks for the origin's routes in one session: `!!` keeps it open for
+/// every query, and `!q` closes it.
+fn query<S: Read + Write>(
+    mut stream: S,
+    origin: u32,
+    sources: &[String],
+) -> Result<Vec<IpNet>, String> {
+    let mut queries = String::from("!!\n");
+    if !sources.is_empty() {
+        queries += &format!("!s{}\n", sources.join(","));
+    }
+    queries += &format!("!gAS{}\n!6AS{}\n!q\n", origin, origin);
+    stream
+        .write_all(queries.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(stream);
+    if !sources.is_empty() {
+        answer(&mut reader)?;
+    }
+    let mut routes = Vec::new();
+    for _ in 0..2 {
+        for route in answer(&mut reader)?.split_whitespace() {
+            routes.push(
+                route
+                    .parse::<IpNet>()
+                    .map_err(|_| format!("'{}' isn't a route", route))?
+                    .trunc(),
+            );
+        }
+    }
+    routes.sort();
+    routes.dedup();
+    Ok(routes)
+}
+
+/// Reads the answer to one query: `A` and a length, then that much data
+/// and `C`; `C` alone for success without data; `D` for nothing found; or
+/// `F` and an error.
+fn answer<R: BufRead>(reader: &mut R) -> Result<String, String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+        return Err("the server closed the connection".to_string());
+    }
+    let line = line.trim_end();
+    match line.split_at_checked(1) {
+        Some(("A", length)) => {
+            let length: usize = length
+                .parse()
+                .map_err(|_| format!("bad answer '{}'", line))?;
+            let mut data = vec![0; length];
+            reader
+                .read_exact(&mut data)
+                .map_err(|err| err.to_string())?;
+            answer(reader)?;
+            String::from_utf8(data).map_err(|_| "the answer isn't text".to_string())
+        }
+        Some(("C" | "D", _)) => Ok(String::new()),
+        Some(("F", error)) => Err(error.trim().to_string()),
+        _ => Err(format!("bad answer '{}'", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A server that answers from a script and keeps what it's sent.
+    struct Server {
+        answer: io::Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Server {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.answer.read(buf)
+        }
+    }
+
+    impl Write for Server {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn server(answer: &str) -> Server {
+        Server {
+            answer: io::Cursor::new(answer.as_bytes().to_vec()),
+            sent: Vec::new(),
+        }
+    }
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let mut stream = server("C\nA29\n192.0.2.0/24 198.51.100.0/23\nC\nA14\n2001:db8::/32\nC\n");
+        let routes = query(
+            &mut stream,
+            64500,
+            &["RADB".to_string(), "RIPE".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(stream.sent).unwrap(),
+            "!!\n!sRADB,RIPE\n!gAS64500\n!6AS64500\n!q\n"
+        );
+        assert_eq!(
+            routes,
+            nets(&["192.0.2.0/24", "198.51.100.0/23", "2001:db8::/32"])
+        );
+        let routes = query(&mut server("D\nA14\n2001:db8::/32\nC\n"), 64500, &[]).unwrap();
+        assert_eq!(routes, nets(&["2001:db8::/32"]));
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let err = query(
+            &mut server("F Invalid source NOPE\n"),
+            64500,
+            &["NOPE".to_string()],
+        );
+        assert_eq!(err.unwrap_err(), "Invalid source NOPE");
+        assert!(query(&mut server("C\n"), 64500, &[]).is_err());
+        assert!(query(&mut server("A5\nnope\nC\nD\n"), 64500, &[]).is_err());
+        assert!(query(&mut server("Z\n"), 64500, &[]).is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let routes = nets(&[
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "192.0.2.0/25",
+            "192.0.2.128/25",
+        ]);
+        let check = |net: &str| super::check(&net.parse().unwrap(), &routes);
+        assert_eq!(check("10.0.0.0/8"), Check::Registered);
+        assert_eq!(
+            check("10.1.2.0/24"),
+            Check::Inside("10.1.0.0/16".parse().unwrap())
+        );
+        assert_eq!(
+            check("192.0.2.0/24"),
+            Check::Covers(nets(&["192.0.2.0/25", "192.0.2.128/25"]))
+        );
+        assert_eq!(check("203.0.113.0/24"), Check::Unregistered);
+        assert_eq!(check("2001:db8::/32"), Check::Unregistered);
+        assert_eq!(
+            check("192.0.2.0/24").to_string(),
+            "mismatched, covers routes 192.0.2.0/25, 192.0.2.128/25"
+        );
+        assert_eq!(
+            check("10.1.2.0/24").to_string(),
+            "mismatched, inside route 10.1.0.0/16"
+        );
+    }
+}
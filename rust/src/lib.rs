@@ -0,0 +1,462 @@
+//! The library behind lfc.
+//!
+//! Everything the `lfc` command does to a list of networks starts here:
+//! [`nets`] and friends parse lines of text, and [`gather`] aggregates the
+//! result the way the command does by default. For anything else, like
+//! leaving neighbors unmerged or only looking at one family, configure an
+//! [`Aggregator`] with [`AggregatorBuilder`].
+//!
+//! # no_std
+//!
+//! The aggregation core, [`gather`] and the interval arithmetic in
+//! [`space`], only needs `alloc`. Build with `default-features = false` to
+//! leave out the `std` feature and everything else, for targets without an
+//! operating system underneath.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod aggregator;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "fast-parse")]
+mod fastparse;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "std")]
+mod parser;
+#[cfg(feature = "std")]
+pub mod setfile;
+#[cfg(feature = "std")]
+pub mod source;
+pub mod space;
+#[cfg(feature = "std")]
+pub mod spill;
+
+#[cfg(feature = "std")]
+pub use aggregator::{Aggregator, AggregatorBuilder, Embedded, Event, Mapped};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+#[cfg(feature = "std")]
+pub use error::{Error, Result};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+#[cfg(feature = "std")]
+pub use parser::{HostBits, Parser};
+#[cfg(feature = "std")]
+use source::{Provenance, Record};
+
+/// Parses lines of text into IP networks in CIDR notation.
+///
+/// Each line should contain a single IP network (e.g., "192.168.1.0/24").
+/// Empty lines and surrounding whitespace are ignored. If any line cannot
+/// be parsed as a valid IP network, the whole list is rejected with an
+/// [`Error::Parse`] for the first bad line, to prevent silent failures that
+/// could lead to incorrect firewall rules or other security issues.
+///
+/// # Examples
+///
+/// ```
+/// use lfc::parse_nets;
+///
+/// let input = "192.168.1.0/24\n10.0.0.0/8";
+/// let nets = parse_nets(input.lines()).unwrap();
+/// assert_eq!(nets.len(), 2);
+/// ```
+#[cfg(feature = "std")]
+pub fn parse_nets<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Result<Vec<IpNet>> {
+    nets(lines).collect()
+}
+
+/// Returns an iterator that parses each line as it's reached. The lines are
+/// borrowed straight from the input, so nothing is allocated per line unless
+/// it's bad.
+#[cfg(feature = "std")]
+pub fn nets<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> impl Iterator<Item = Result<IpNet>> {
+    record_nets(lines.into_iter().enumerate().map(|(index, text)| Record {
+        text,
+        provenance: Provenance {
+            source: None,
+            line: index + 1,
+        },
+    }))
+}
+
+/// Parses records from an [`InputSource`](source::InputSource) like
+/// [`nets`], naming the source and line of any that are bad.
+#[cfg(feature = "std")]
+pub fn record_nets<'a, I: IntoIterator<Item = Record<'a>>>(
+    records: I,
+) -> impl Iterator<Item = Result<IpNet>> {
+    // Fail if any line is invalid because that could give unexpected
+    // results. Imagine this is creating firewall rules and the user
+    // accidentally typed an IP address instead of a CIDR. Then we might be
+    // outputting a set of blocked addresses without the one the user
+    // explicitly wanted to block! That's not good. It's better here to say,
+    // hey, there's a problem with your input that you need to fix before we
+    // can help you. A [`Parser`] can be told to take bare addresses, when
+    // that's what the input means.
+    Parser::default().records(records)
+}
+
+/// Parses a single network, trying the fast parser first if it's built in.
+#[cfg(feature = "std")]
+fn parse_net(text: &str) -> Option<IpNet> {
+    #[cfg(feature = "fast-parse")]
+    if let Some(net) = fastparse::parse(text) {
+        return Some(net);
+    }
+    text.parse().ok()
+}
+
+/// Aggregates and merges IP networks to their minimal representation.
+///
+/// Takes a collection of IP networks and combines adjacent or overlapping networks
+/// into larger CIDR blocks where possible. This process:
+/// - Removes duplicate networks
+/// - Merges overlapping networks (subnets absorbed by supernets)
+/// - Combines adjacent networks that align on CIDR boundaries
+/// - Preserves networks that cannot be aggregated
+///
+/// The networks can come from anything that iterates over them, so callers
+/// don't have to build a list first. Each one goes straight into the list for
+/// its family, which is what the aggregator wants anyway.
+///
+/// # Examples
+///
+/// ```
+/// use ipnet::IpNet;
+/// use lfc::gather;
+///
+/// // Adjacent networks merge into a larger block
+/// let nets: Vec<IpNet> = vec!["192.168.0.0/24".parse().unwrap(), "192.168.1.0/24".parse().unwrap()];
+/// let result = gather(&nets);
+/// assert_eq!(result, vec!["192.168.0.0/23".parse::<IpNet>().unwrap()]);
+///
+/// // Overlapping networks are reduced to the supernet
+/// let nets: Vec<IpNet> = vec!["10.0.0.0/16".parse().unwrap(), "10.0.1.0/24".parse().unwrap()];
+/// let result = gather(&nets);
+/// assert_eq!(result, vec!["10.0.0.0/16".parse::<IpNet>().unwrap()]);
+/// ```
+pub fn gather<I>(nets: I) -> Vec<IpNet>
+where
+    I: IntoIterator,
+    I::Item: Borrow<IpNet>,
+{
+    let mut v4 = Vec::new();
+    let mut v6 = Vec::new();
+    for net in nets {
+        match *net.borrow() {
+            IpNet::V4(net) => v4.push(net),
+            IpNet::V6(net) => v6.push(net),
+        }
+    }
+    let v4 = Ipv4Net::aggregate(&v4).into_iter().map(IpNet::V4);
+    let v6 = Ipv6Net::aggregate(&v6).into_iter().map(IpNet::V6);
+    v4.chain(v6).collect()
+}
+
+/// Parses lines of text and aggregates the networks, like [`gather`] on the
+/// result of [`parse_nets`] but without building the whole parsed list
+/// first.
+#[cfg(feature = "std")]
+pub fn gather_lines<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Result<Vec<IpNet>> {
+    gather_parsed(nets(lines))
+}
+
+/// Parses records and aggregates the networks like [`gather_lines`], naming
+/// the source and line of the first bad one.
+#[cfg(feature = "std")]
+pub fn gather_records<'a, I: IntoIterator<Item = Record<'a>>>(records: I) -> Result<Vec<IpNet>> {
+    gather_parsed(record_nets(records))
+}
+
+/// Aggregates networks as they're parsed, stopping at the first error.
+#[cfg(feature = "std")]
+fn gather_parsed<I: Iterator<Item = Result<IpNet>>>(nets: I) -> Result<Vec<IpNet>> {
+    until_error(nets, |nets| gather(nets))
+}
+
+/// Hands the networks to `f` as they're parsed, stopping at the first
+/// error, and returns the error if there was one.
+#[cfg(feature = "std")]
+fn until_error<I, T, F>(nets: I, f: F) -> Result<T>
+where
+    I: Iterator<Item = Result<IpNet>>,
+    F: FnOnce(&mut dyn Iterator<Item = IpNet>) -> T,
+{
+    let mut error = None;
+    let result = f(&mut nets.map_while(|net| net.map_err(|err| error = Some(err)).ok()));
+    match error {
+        Some(err) => Err(err),
+        None => Ok(result),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nets_single_network() {
+        let input = "192.168.1.0/24";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+    }
+
+    #[test]
+    fn test_parse_nets_multiple_networks() {
+        let input = "192.168.1.0/24\n10.0.0.0/8\n172.16.0.0/12";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+        assert_eq!(result[1].to_string(), "10.0.0.0/8");
+        assert_eq!(result[2].to_string(), "172.16.0.0/12");
+    }
+
+    #[test]
+    fn test_parse_nets_with_whitespace() {
+        let input = "  192.168.1.0/24  \n  10.0.0.0/8  ";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+        assert_eq!(result[1].to_string(), "10.0.0.0/8");
+    }
+
+    #[test]
+    fn test_parse_nets_with_empty_lines() {
+        let input = "192.168.1.0/24\n\n10.0.0.0/8\n\n\n172.16.0.0/12";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_nets_ipv6() {
+        let input = "2001:db8::/32\nfe80::/10";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].to_string(), "2001:db8::/32");
+        assert_eq!(result[1].to_string(), "fe80::/10");
+    }
+
+    #[test]
+    fn test_parse_nets_mixed_ipv4_ipv6() {
+        let input = "192.168.1.0/24\n2001:db8::/32";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+        assert_eq!(result[1].to_string(), "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_parse_nets_invalid_input() {
+        let input = "not-an-ip-address";
+        assert!(matches!(
+            parse_nets(input.lines()),
+            Err(Error::Parse {
+                line: 1,
+                column: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_nets_ip_without_cidr() {
+        let input = "192.168.1.1";
+        assert!(matches!(
+            parse_nets(input.lines()),
+            Err(Error::Parse {
+                line: 1,
+                column: 12,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_nets_empty_input() {
+        let input = "";
+        let result = parse_nets(input.lines()).unwrap();
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test]
+    fn test_gather_overlapping_networks() {
+        let nets = vec![
+            "10.0.0.0/16".parse().unwrap(),
+            "10.0.1.0/24".parse().unwrap(),
+            "10.0.2.0/24".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "10.0.0.0/16");
+    }
+
+    #[test]
+    fn test_gather_adjacent_networks() {
+        let nets = vec![
+            "192.168.0.0/24".parse().unwrap(),
+            "192.168.1.0/24".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "192.168.0.0/23");
+    }
+
+    #[test]
+    fn test_gather_four_adjacent_networks() {
+        let nets = vec![
+            "10.0.0.0/24".parse().unwrap(),
+            "10.0.1.0/24".parse().unwrap(),
+            "10.0.2.0/24".parse().unwrap(),
+            "10.0.3.0/24".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "10.0.0.0/22");
+    }
+
+    #[test]
+    fn test_gather_non_adjacent_networks() {
+        let nets = vec![
+            "192.168.1.0/24".parse().unwrap(),
+            "192.168.3.0/24".parse().unwrap(),
+            "10.0.0.0/8".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 3);
+        assert!(result.contains(&"10.0.0.0/8".parse().unwrap()));
+        assert!(result.contains(&"192.168.1.0/24".parse().unwrap()));
+        assert!(result.contains(&"192.168.3.0/24".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_gather_duplicate_networks() {
+        let nets = vec![
+            "192.168.1.0/24".parse().unwrap(),
+            "192.168.1.0/24".parse().unwrap(),
+            "192.168.1.0/24".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+    }
+
+    #[test]
+    fn test_gather_ipv6_adjacent() {
+        let nets = vec![
+            "2001:db8::/33".parse().unwrap(),
+            "2001:db8:8000::/33".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_gather_ipv6_non_adjacent() {
+        let nets = vec![
+            "2001:db8::/32".parse().unwrap(),
+            "2001:dba::/32".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn test_gather_mixed_ipv4_ipv6() {
+        let nets = vec![
+            "192.168.0.0/24".parse().unwrap(),
+            "192.168.1.0/24".parse().unwrap(),
+            "2001:db8::/33".parse().unwrap(),
+            "2001:db8:8000::/33".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&"192.168.0.0/23".parse().unwrap()));
+        assert!(result.contains(&"2001:db8::/32".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_gather_empty() {
+        let nets = vec![];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test]
+    fn test_gather_single_network() {
+        let nets = vec!["10.0.0.0/8".parse().unwrap()];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "10.0.0.0/8");
+    }
+
+    #[test]
+    fn test_gather_complex_aggregation() {
+        let nets = vec![
+            "192.168.0.0/25".parse().unwrap(),
+            "192.168.0.128/25".parse().unwrap(),
+            "192.168.1.0/25".parse().unwrap(),
+            "192.168.1.128/25".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_string(), "192.168.0.0/23");
+    }
+
+    #[test]
+    fn test_gather_alignment_issues() {
+        let nets = vec![
+            "30.0.33.0/24".parse().unwrap(),
+            "30.0.34.0/24".parse().unwrap(),
+            "10.0.7.0/24".parse().unwrap(),
+            "10.0.8.0/24".parse().unwrap(),
+            "10.0.9.0/24".parse().unwrap(),
+            "20.0.14.0/23".parse().unwrap(),
+            "20.0.16.0/23".parse().unwrap(),
+            "20.0.18.0/23".parse().unwrap(),
+            "20.0.20.0/23".parse().unwrap(),
+            "30.0.32.0/20".parse().unwrap(),
+        ];
+        let result = gather(&nets);
+        assert_eq!(result.len(), 6);
+        assert!(result.contains(&"10.0.7.0/24".parse().unwrap()));
+        assert!(result.contains(&"10.0.8.0/23".parse().unwrap()));
+        assert!(result.contains(&"20.0.14.0/23".parse().unwrap()));
+        assert!(result.contains(&"20.0.16.0/22".parse().unwrap()));
+        assert!(result.contains(&"20.0.20.0/23".parse().unwrap()));
+        assert!(result.contains(&"30.0.32.0/20".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_nets_error() {
+        assert_eq!(
+            parse_nets("10.0.0.0/8\n\n  bogus".lines())
+                .unwrap_err()
+                .to_string(),
+            "Unable to parse \"bogus\" as an IP network on line 3, column 3: not an IP address."
+        );
+    }
+
+    #[test]
+    fn test_gather_lines_matches_gather() {
+        let input = "2001:db8:1::/48\n10.0.1.0/24\n\n  10.0.0.0/24\n2001:db8::/48\n192.168.0.0/16";
+        assert_eq!(
+            gather_lines(input.lines()).unwrap(),
+            gather(parse_nets(input.lines()).unwrap())
+        );
+        assert_eq!(gather_lines("".lines()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_gather_lines_error() {
+        assert!(matches!(
+            gather_lines("10.0.0.0/8\nbogus".lines()),
+            Err(Error::Parse { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,362 @@
+//! What `lfc lint` finds suspicious in a list.
+//!
+//! Nothing here stops a list from aggregating; these are the mistakes that
+//! aggregate just fine and then block far too much, or nothing at all, or
+//! make the next diff noisy.
+
+use crate::info;
+use ipnet::IpNet;
+use lfc::source::Record;
+use lfc::space::{self, Family};
+use lfc::{Error, Parser};
+use serde_json::{Value, json};
+use std::fmt;
+use std::io::{self, Write};
+
+/// How bad a finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Harmless, but not how lfc writes networks.
+    Notice,
+    /// Probably a mistake.
+    Warning,
+    /// Certainly a mistake.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Notice => "notice",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Something suspicious about one line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The name of the source the line came from, if it came from one.
+    pub source: Option<String>,
+    /// The line number, counting from 1.
+    pub line: usize,
+    /// The line, without surrounding whitespace.
+    pub text: String,
+    pub message: String,
+}
+
+/// Checks every record, and returns what's suspicious about them in the
+/// order they're listed. `parser` is how they're parsed; any differences
+/// from how strict parsing would take them are notices, or warnings if the
+/// host bits are set. Networks shorter than `shortest` for their family are
+/// warnings.
+pub fn lint(records: &[Record], parser: &Parser, shortest: [u8; 2]) -> Vec<Finding> {
+    let strict = parser.clone().strict(true);
+    let mut findings: Vec<(usize, Finding)> = Vec::new();
+    let mut nets = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        let mut find = |severity, message| {
+            findings.push((index, finding(record, severity, message)));
+        };
+        let net = match parse(parser, record) {
+            None => continue,
+            Some(Err(reason)) => {
+                find(Severity::Error, reason);
+                continue;
+            }
+            Some(Ok(net)) => net,
+        };
+        if let Some(Err(reason)) = parse(&strict, record) {
+            let severity = if net.trunc() == net {
+                Severity::Notice
+            } else {
+                Severity::Warning
+            };
+            find(severity, reason);
+        }
+        let net = net.trunc();
+        nets.push((index, net));
+        if net.prefix_len() == 0 {
+            find(Severity::Error, "matches every address".to_string());
+            continue;
+        }
+        let shortest = match Family::of(&net) {
+            Family::V4 => shortest[0],
+            Family::V6 => shortest[1],
+        };
+        if net.prefix_len() < shortest {
+            find(Severity::Warning, format!("shorter than /{}", shortest));
+        }
+        if let Some(special) = info::describe(&net) {
+            find(
+                Severity::Warning,
+                format!("special-purpose space: {}", special),
+            );
+        }
+    }
+    for (index, message) in overlaps(records, &mut nets) {
+        findings.push((index, finding(&records[index], Severity::Warning, message)));
+    }
+    // Sorting is stable, so each line's findings stay in the order above.
+    findings.sort_by_key(|(index, _)| *index);
+    findings.into_iter().map(|(_, finding)| finding).collect()
+}
+
+/// Parses a record, returning `None` if it's blank, or why it doesn't
+/// parse.
+fn parse(parser: &Parser, record: &Record) -> Option<Result<IpNet, String>> {
+    let result = parser.clone().records([*record]).next()?;
+    Some(result.map_err(|err| match err {
+        Error::Parse { reason, .. } => reason,
+        err => err.to_string(),
+    }))
+}
+
+/// Returns the networks that are inside, or the same as, another one in the
+/// list, along with which one. Each is reported once, against the smallest
+/// network holding it, or the first time it was listed.
+fn overlaps(records: &[Record], nets: &mut [(usize, IpNet)]) -> Vec<(usize, String)> {
+    nets.sort_by_key(|(index, net)| {
+        (
+            Family::of(net),
+            space::range(net).0,
+            net.prefix_len(),
+            *index,
+        )
+    });
+    let mut overlaps = Vec::new();
+    let mut holders: Vec<(usize, IpNet)> = Vec::new();
+    for &(index, net) in nets.iter() {
+        while holders
+            .last()
+            .is_some_and(|(_, holder)| !holder.contains(&net))
+        {
+            holders.pop();
+        }
+        if let Some(&(holder_index, holder)) = holders.last() {
+            let relation = if holder == net {
+                "duplicates"
+            } else {
+                "is inside"
+            };
+            overlaps.push((
+                index,
+                format!(
+                    "{} {} on {}",
+                    relation, holder, records[holder_index].provenance
+                ),
+            ));
+        }
+        holders.push((index, net));
+    }
+    overlaps
+}
+
+fn finding(record: &Record, severity: Severity, message: String) -> Finding {
+    Finding {
+        severity,
+        source: record.provenance.source.map(str::to_string),
+        line: record.provenance.line,
+        text: record.text.trim().to_string(),
+        message,
+    }
+}
+
+/// Returns how many findings there are of each severity, from notices to
+/// errors.
+pub fn counts(findings: &[Finding]) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for finding in findings {
+        counts[finding.severity as usize] += 1;
+    }
+    counts
+}
+
+/// Writes a line for each finding, like a compiler's diagnostics.
+pub fn write_text<W: Write>(out: &mut W, findings: &[Finding]) -> io::Result<()> {
+    for finding in findings {
+        if let Some(source) = &finding.source {
+            write!(out, "{} ", source)?;
+        }
+        writeln!(
+            out,
+            "line {}: {}: {}: {}",
+            finding.line, finding.severity, finding.text, finding.message
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the findings as a JSON object, with how many there are of each
+/// severity.
+pub fn write_json<W: Write>(out: &mut W, findings: &[Finding]) -> io::Result<()> {
+    let [notices, warnings, errors] = counts(findings);
+    let findings: Vec<Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "severity": finding.severity.to_string(),
+                "source": finding.source,
+                "line": finding.line,
+                "text": finding.text,
+                "message": finding.message,
+            })
+        })
+        .collect();
+    let report = json!({
+        "errors": errors,
+        "warnings": warnings,
+        "notices": notices,
+        "findings": findings,
+    });
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lfc::source::Provenance;
+
+    fn records(text: &str) -> Vec<Record<'_>> {
+        text.lines()
+            .enumerate()
+            .map(|(index, text)| Record {
+                text,
+                provenance: Provenance {
+                    source: Some("feed.txt"),
+                    line: index + 1,
+                },
+            })
+            .collect()
+    }
+
+    fn lint_text(text: &str) -> Vec<(Severity, usize, String)> {
+        lint(&records(text), &Parser::new(), [8, 32])
+            .into_iter()
+            .map(|finding| (finding.severity, finding.line, finding.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let findings = lint_text(
+            "\
+0.0.0.0/0
+8.8.8.0/24
+
+2.0.0.0/7
+10.1.0.0/16
+2001:DB8:0::/48
+192.0.2.77/24
+not a network
+8.8.8.0/24
+2600::/12
+",
+        );
+        assert_eq!(
+            findings,
+            vec![
+                (Severity::Error, 1, "matches every address".to_string()),
+                (
+                    Severity::Warning,
+                    2,
+                    "is inside 0.0.0.0/0 on feed.txt line 1".to_string()
+                ),
+                (Severity::Warning, 4, "shorter than /8".to_string()),
+                (
+                    Severity::Warning,
+                    4,
+                    "is inside 0.0.0.0/0 on feed.txt line 1".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    5,
+                    "special-purpose space: Private-Use".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    5,
+                    "is inside 0.0.0.0/0 on feed.txt line 1".to_string()
+                ),
+                (
+                    Severity::Notice,
+                    6,
+                    "not in RFC 5952 form; write it as 2001:db8::/48".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    6,
+                    "special-purpose space: Documentation".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    7,
+                    "host bits are set; the network is 192.0.2.0/24".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    7,
+                    "special-purpose space: Documentation (TEST-NET-1)".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    7,
+                    "is inside 0.0.0.0/0 on feed.txt line 1".to_string()
+                ),
+                (Severity::Error, 8, "not an IP address".to_string()),
+                (
+                    Severity::Warning,
+                    9,
+                    "duplicates 8.8.8.0/24 on feed.txt line 2".to_string()
+                ),
+                (Severity::Warning, 10, "shorter than /32".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let findings = lint_text("10.0.0.0/8\n10.1.0.0/16\n10.1.2.0/24\n11.0.0.0/8\n");
+        let overlaps: Vec<_> = findings
+            .iter()
+            .filter(|(_, _, message)| !message.starts_with("special"))
+            .collect();
+        assert_eq!(
+            overlaps,
+            [
+                &(
+                    Severity::Warning,
+                    2,
+                    "is inside 10.0.0.0/8 on feed.txt line 1".to_string()
+                ),
+                &(
+                    Severity::Warning,
+                    3,
+                    "is inside 10.1.0.0/16 on feed.txt line 2".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let findings = lint(&records("0.0.0.0/0\n"), &Parser::new(), [8, 32]);
+        assert_eq!(counts(&findings), [0, 0, 1]);
+        let mut out = Vec::new();
+        write_text(&mut out, &findings).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "feed.txt line 1: error: 0.0.0.0/0: matches every address\n"
+        );
+        let mut out = Vec::new();
+        write_json(&mut out, &findings).unwrap();
+        let report: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(report["errors"], 1);
+        assert_eq!(report["findings"][0]["severity"], "error");
+        assert_eq!(report["findings"][0]["source"], "feed.txt");
+        assert_eq!(report["findings"][0]["line"], 1);
+    }
+}
@@ -1,6 +1,6 @@
 //! Little Fluffy Clouds (lfc) - IP Network Aggregation Tool
 //!
-//! This tool reads IP networks in CIDR notation from stdin and outputs an aggregated,
+//! This tool reads IP networks in CIDR notation from files or stdin and outputs an aggregated,
 //! minimized list of networks. It combines adjacent networks that can be merged into
 //! larger CIDR blocks and removes overlapping or duplicate entries.
 //!
@@ -8,8 +8,15 @@
 //!
 //! ```bash
 //! cat networks.txt | lfc
+//! lfc networks.txt more-networks.txt
 //! ```
 //!
+//! Networks are read from the files named on the command line, or from stdin if
+//! there aren't any. If stdin is a terminal, lfc prints a short hint to stderr
+//! before reading so that it doesn't look like it's hanging. Pass `--stdin` to
+//! skip the hint. Names starting with `http://` or `https://` are downloaded,
+//! and gzipped input is decompressed, wherever it came from.
+//!
 //! # Input Format
 //!
 //! Each line should contain a single IP network in CIDR notation:
@@ -38,74 +45,66 @@
 //! 192.168.0.0/23
 //! ```
 
-use ipnet::IpNet;
-use std::{env, io, str};
-
-/// Parses lines of text into IP networks in CIDR notation.
-///
-/// Each line should contain a single IP network (e.g., "192.168.1.0/24").
-/// Empty lines and surrounding whitespace are ignored. If any line cannot
-/// be parsed as a valid IP network, the function panics to prevent silent
-/// failures that could lead to incorrect firewall rules or other security issues.
-///
-/// # Panics
-///
-/// Panics if any non-empty line cannot be parsed as a valid IP network.
-///
-/// # Examples
-///
-/// ```
-/// let input = "192.168.1.0/24\n10.0.0.0/8";
-/// let nets = parse_nets(input.lines());
-/// assert_eq!(nets.len(), 2);
-/// ```
-fn parse_nets(lines: str::Lines) -> Vec<IpNet> {
-    lines
-        // Remove any surrounding whitespace from each line.
-        .map(|line| line.trim()) //.to_string())
-        // Skip empty lines.
-        .filter(|line| !line.is_empty())
-        // Parse each line as an IP network. Fail if any line is invalid because
-        // that could give unexpected results. Imagine this is creating firewall
-        // rules and the user accidentally typed an IP address instead of a
-        // CIDR. Then we might be outputting a set of blocked addresses without
-        // the one the user explicitly wanted to block! That's not good. It's
-        // better here to say, hey, there's a problem with your input that you
-        // need to fix before we can help you.
-        .map(|line| match line.parse::<IpNet>() {
-            Ok(net) => net,
-            Err(_) => {
-                panic!("Unable to parse {:?} as an IP network.", line)
-            }
-        })
-        .collect()
-}
+mod asn;
+mod bogons;
+mod cache;
+mod clock;
+mod delegated;
+mod diag;
+mod diff;
+mod expiry;
+mod fetch;
+mod geoip;
+mod hangup;
+mod info;
+mod ipset;
+mod irr;
+mod lint;
+mod metrics;
+mod output;
+mod plan;
+mod ptr;
+mod push;
+mod regions;
+mod resolve;
+mod rest;
+mod rollup;
+mod rpki;
+mod sample;
+mod script;
+mod selftest;
+mod serve;
+mod slaac;
+mod state;
+mod synth;
+mod syslog;
+mod systemd;
+mod tui;
+mod webhook;
+mod zones;
 
-/// Aggregates and merges IP networks to their minimal representation.
-///
-/// Takes a collection of IP networks and combines adjacent or overlapping networks
-/// into larger CIDR blocks where possible. This process:
-/// - Removes duplicate networks
-/// - Merges overlapping networks (subnets absorbed by supernets)
-/// - Combines adjacent networks that align on CIDR boundaries
-/// - Preserves networks that cannot be aggregated
-///
-/// # Examples
-///
-/// ```
-/// // Adjacent networks merge into a larger block
-/// let nets = vec!["192.168.0.0/24".parse().unwrap(), "192.168.1.0/24".parse().unwrap()];
-/// let result = gather(&nets);
-/// assert_eq!(result, vec!["192.168.0.0/23".parse().unwrap()]);
-///
-/// // Overlapping networks are reduced to the supernet
-/// let nets = vec!["10.0.0.0/16".parse().unwrap(), "10.0.1.0/24".parse().unwrap()];
-/// let result = gather(&nets);
-/// assert_eq!(result, vec!["10.0.0.0/16".parse().unwrap()]);
-/// ```
-fn gather(nets: &Vec<IpNet>) -> Vec<IpNet> {
-    IpNet::aggregate(nets)
-}
+use diag::note;
+use diff::ColorChoice;
+use ipnet::{IpNet, Ipv6Net};
+use lfc::format::{self, Formatter, HexCase, Ipv6Form};
+use lfc::setfile;
+use lfc::source::{self, FileSource, InputSource, Record};
+use lfc::space::{self, Family};
+use lfc::{
+    Aggregator, AggregatorBuilder, Embedded, Event, HostBits, Mapped, Parser, gather, parse_nets,
+    record_nets, spill,
+};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, io, str};
 
 fn print_help() {
     print!(
@@ -113,272 +112,4970 @@ fn print_help() {
 Little Fluffy Clouds (lfc) - IP Network Aggregation Tool
 
 USAGE:
-    lfc [OPTIONS]
+    lfc [OPTIONS] [FILE]...
+    lfc diff [OPTIONS] OLD NEW
+    lfc delta --current CURRENT [OPTIONS] [FILE]...
+    lfc plan --current CURRENT [OPTIONS] [FILE]...
+    lfc serve [--unix PATH] [--listen ADDRESS] [OPTIONS] [FILE]...
+    lfc bench [--count N] [--profile PROFILE]
+    lfc gen [--count N] [--family FAMILY] [--prefixlen-range RANGE] [--seed S]
+    lfc selftest [--count N] [--seed S]
+    lfc formats
+    lfc nat64 [--prefix PREFIX] [--reverse] [OPTIONS] [FILE]...
+    lfc explode --to /LEN [--max-output N] [OPTIONS] [FILE]...
+    lfc anonymize [--v4 /LEN] [--v6 /LEN] [OPTIONS] [FILE]...
+    lfc hosts [--max N] [OPTIONS] [FILE]...
+    lfc info NETWORK...
+    lfc tui [OPTIONS] [FILE]...
+    lfc ptr-zones [--skeleton] [OPTIONS] [FILE]...
+    lfc push PROVIDER [--dry-run] [OPTIONS] [FILE]...
+    lfc fetch FEED [OPTIONS]
+    lfc irr-check --origin ASN [--irr-sources SOURCES] [OPTIONS] [FILE]...
+    lfc geoip update|status [--geoip-config FILE] [--data-dir DIR]
+    lfc by-country [--format json] [OPTIONS] [FILE]...
+    lfc by-asn [--format json] [OPTIONS] [FILE]...
+    lfc resolve --list NAME=FILE:PRIORITY... [--format json] [OPTIONS]
+    lfc lint [--v4 /LEN] [--v6 /LEN] [--format json] [OPTIONS] [FILE]...
+
+COMMANDS:
+    diff          Show the networks added and removed between two lists
+    delta         Print the commands that update a set to the aggregated input
+    plan          Print the networks to add to and remove from a deployed list
+    serve         Answer aggregate, diff, and lookup requests on a socket
+    bench         Time parsing and aggregating a generated list
+    gen           Print a reproducible list of random networks
+    selftest      Check aggregation against a slow reference implementation
+    formats       List the output formats
+    nat64         Translate networks to and from their NAT64 form
+    explode       Split the aggregated networks into networks of one size
+    anonymize     Widen the aggregated networks so none is too specific
+    hosts         Print every address in the aggregated networks
+    info          Describe a network, like ipcalc
+    tui           Browse the aggregated networks as a tree
+    ptr-zones     Print the reverse DNS zones for the aggregated networks
+    push          Update a list at a provider, like Cloudflare, to match
+    fetch         Print a service's published ranges, like Googlebot's
+    irr-check     Check the aggregated networks against an AS's IRR routes
+    geoip         Download the GeoLite2 country database
+    by-country    Group the aggregated networks by country, with their share
+    by-asn        Group the aggregated networks by origin AS, with its name
+    resolve       Work out which of several overlapping lists decides where
+    lint          Point out suspicious entries in a list
 
 OPTIONS:
-    -h, --help    Print help information
+    -h, --help          Print help information
+    -q, --quiet         Don't print hints or warnings to stderr
+        --stdin         Read networks from stdin, even if it's a terminal
+        --color WHEN    Color diff output: auto, always, or never [default: auto]
+        --diff-against FILE
+                        Print a unified diff from FILE, a previously saved
+                        result, to the new result instead of the result itself
+        --cache-dir DIR Reuse results from DIR when the input hasn't changed
+        --data-dir DIR  Keep downloaded RIR, bogons, and GeoLite2 data in DIR
+                        [default: $XDG_CACHE_HOME/lfc or ~/.cache/lfc]
+        --normalize-mapped[=v4|v6]
+                        Convert IPv4-mapped IPv6 networks, like
+                        ::ffff:192.0.2.0/120, to IPv4, or with =v6, convert
+                        IPv4 networks to their mapped IPv6 form
+        --extract-embedded[=also|instead]
+                        Add the IPv4 space inside 6to4 (2002::/16) and
+                        Teredo (2001::/32) networks, or with =instead,
+                        replace those networks with it
+        --zones HOW     What to do with IPv6 zone IDs, like fe80::1%eth0/64:
+                        reject them as bad input, strip them, or group
+                        the networks by zone [default: reject]
+        --default-prefixlen-v4 LEN
+        --default-prefixlen-v6 LEN
+                        Take a bare address of the family, with no /prefix
+                        length, as the /LEN network that contains it,
+                        instead of rejecting it
+        --truncate-host-bits
+                        Clear the host bits of networks like 192.0.2.77/24,
+                        with a warning, instead of rejecting them
+        --merge-slack PERCENT
+                        Also merge networks into a supernet that's at most
+                        PERCENT new space, like 5%, and report the space
+                        that was added
+        --max-coverage PERCENT
+                        Refuse to write a result that covers more than
+                        PERCENT of the IPv4 or IPv6 space, like 5%
+        --min-prefixlen-v4 LEN
+        --min-prefixlen-v6 LEN
+                        Refuse to write a result with any network shorter
+                        than /LEN, like 8 for IPv4 or 19 for IPv6
+        --force         Write the result anyway, with a warning, when it's
+                        over --max-coverage or has networks shorter than
+                        --min-prefixlen-v4 or --min-prefixlen-v6
+        --report KIND   Before writing the result, report on it to stderr:
+                        special, which networks are or include IANA
+                        special-purpose space, by block, or slaac, which
+                        IPv6 host entries look autoconfigured and are
+                        better blocked as their /64; may be given more than
+                        once
+        --no-merge-adjacent
+                        Only remove duplicates and networks inside other
+                        networks, without merging neighbors
+        --dedupe-only   Only sort the networks and remove duplicates, without
+                        removing networks inside others or merging any
+        --keep-structure
+                        Keep networks that are inside other networks, while
+                        still merging neighbors
+        --no-merge-across REGIONS
+                        Never merge networks into one that's partly inside
+                        and partly outside a region. REGIONS is a file of
+                        networks, builtin:rfc1918, builtin:rir, or
+                        builtin:special. Can be given more than once
+        --scope-aware   Never merge special-purpose space, like
+                        100.64.0.0/10, with the routable space next to it;
+                        the same as --no-merge-across builtin:special
+        --respect-allocations
+                        Never merge networks into one that spans two RIR
+                        allocations or assignments
+        --rir RIRS      Keep only the space that these regional registries
+                        delegated, like arin,ripe: afrinic, apnic, arin,
+                        lacnic, or ripe
+        --drop CATEGORIES
+                        Leave special-purpose space out of the result:
+                        multicast, loopback, link-local, cgnat, doc,
+                        benchmarking, ula, private, reserved, or bogons for
+                        all of them; may be given more than once
+        --drop-bogons PROFILE
+                        Leave a martians list out of the result: rfc1918,
+                        rfc6890, iana-special, fullbogons, or a file of
+                        networks; may be given more than once
+        --sample N      Keep only N of the resulting networks, picked at
+                        random but the same for the same --seed
+        --sample-weighted
+                        With --sample, pick networks in proportion to how
+                        many addresses they have
+        --strict        Reject networks that aren't written canonically:
+                        with host bits set, leading zeros, or IPv6 that
+                        isn't in RFC 5952 form. Every bad line is reported
+        --baseline FILE With --format html-report, also show what changed
+                        from FILE, an earlier result
+        --annotate [KIND]
+                        With --format markdown, add a note to each network:
+                        special, whether it is or includes IANA
+                        special-purpose space, or asn, the AS that
+                        originates it [default: special]
+        --provider PROVIDER
+                        Where --annotate asn and `lfc by-asn` look up
+                        origins: cymru, Team Cymru's bulk whois
+                        [default: cymru]
+        --toml-key KEY  With --format toml, put the networks under KEY, a
+                        dotted path like firewall.blocklist.networks
+                        [default: networks]
+        --toml-stats    With --format toml, add a stats table with how many
+                        networks and addresses there are
+        --xml-root NAME With --format xml, name the root element NAME
+                        [default: networks]
+        --xml-element NAME
+                        With --format xml, name each network's element NAME
+                        [default: network]
+        --xml-attribute NAME
+                        With --format xml, put each network in an attribute
+                        named NAME instead of in the element's text
+        --intrange-split
+                        With --format intrange, write IPv6 addresses as
+                        their high and low 64 bits
+        --bits-hex      With --format bits, write addresses in hexadecimal
+                        instead of binary
+        --dnsmasq-option OPTION
+                        With --format dnsmasq, the option to write for each
+                        network: ignore-address or bogus-nxdomain [default:
+                        ignore-address]
+        --action ACTION With --format unbound, the access-control action
+                        for each network, like deny or allow [default:
+                        refuse]. With --format istio, the policy's action:
+                        ALLOW, DENY, or AUDIT [default: DENY]. With
+                        --format windows-firewall, the rules' action:
+                        Block or Allow [default: Block]. With --format
+                        iproute, the route type: blackhole, unreachable,
+                        or prohibit [default: blackhole]. With --format
+                        bsd-route, the route flag: blackhole or reject
+                        [default: blackhole]
+        --except FILE   With --format bind-acl, negate the networks in
+                        FILE, which are inside the list's, so the ACL
+                        doesn't match them
+        --envoy-principal PRINCIPAL
+                        With --format envoy, the RBAC principal for each
+                        network: source_ip, direct_remote_ip, or remote_ip
+                        [default: source_ip]
+        --bpf-pin PATH  With --format bpf-map, update the maps pinned at
+                        PATH_v4 and PATH_v6 [default: /sys/fs/bpf/lfc]
+        --bpf-value HEX With --format bpf-map, the value for each network,
+                        in hex bytes [default: 01000000]
+        --lang LANG     With --format codegen, the language of the code: c
+                        or rust [default: c]
+        --firewalld-family FAMILY
+                        With --format firewalld, the family of the set:
+                        inet or inet6 [default: the networks' family]
+        --windows-direction DIRECTION
+                        With --format windows-firewall, the direction the
+                        rules match: Inbound or Outbound [default: Inbound]
+        --frr-ge LEN    With --format frr, match the networks at least LEN
+                        long inside each entry
+        --frr-le LEN    With --format frr, match the networks up to LEN
+                        long inside each entry
+        --table TABLE   With --format iproute, the routing table for the
+                        routes, by number or name [default: main]
+        --istio-selector LABEL=VALUE
+                        With --format istio, apply the policy only to
+                        workloads with this label. Can be repeated
+        --istio-remote  With --format istio, match the networks as
+                        remoteIpBlocks, from X-Forwarded-For
+        --save-set FILE Also save the result to FILE as a binary set file
+        --load-set FILE Add the networks in a set file saved by --save-set
+                        to the input
+        --low-memory    Sort the input in temporary files instead of in
+                        memory, for inputs too big to fit
+        --state-dir DIR Save each result in DIR and report what changed since
+                        the last run
+        --name NAME     Name of the list to track in the state directory
+                        [default: default]
+        --webhook URL   POST a JSON summary to URL when the state directory
+                        shows that the list changed
+        --webhook-secret SECRET
+                        Sign webhook bodies with HMAC-SHA256 using SECRET,
+                        which other users can see; prefer the file, or
+                        LFC_WEBHOOK_SECRET in the environment
+        --webhook-secret-file FILE
+                        Sign webhook bodies with the secret in FILE
+        --webhook-retries N
+                        Retry failed webhooks N times [default: 3]
+        --syslog        Log each prefix the state directory shows was added
+                        or removed to syslog
+        --metrics-file FILE
+                        Write Prometheus metrics about the result to FILE,
+                        for node_exporter's textfile collector
+        --output FILE   Write the result to FILE instead of stdout, replacing
+                        it only once it's complete
+        --refresh INTERVAL
+                        Keep running, and read the input and update the
+                        outputs again every INTERVAL, like 15m or 1h
+        --now TIME      Leave out the entries that have expired by TIME,
+                        like 2025-09-19T12:34:56Z, instead of by now
+        --expire-after INTERVAL
+                        Take the timestamp after an entry as when it was
+                        added, and expire it INTERVAL later, like 7d
+        --rpki SOURCE   Check each network in the result against the RPKI
+                        VRPs from an RTR cache, like rtr://validator:3323,
+                        or a JSON or CSV file or URL of them
+        --origin ASN    The AS that announces the networks, like AS64500,
+                        for --rpki or `lfc irr-check`
+        --rpki-drop     Leave the networks that are RPKI invalid out of the
+                        result instead of just warning about them
+        --irr-server HOST[:PORT]
+                        The IRR server `lfc irr-check` asks for route
+                        objects [default: whois.radb.net]
+        --irr-sources SOURCES
+                        The IRR databases `lfc irr-check` asks for route
+                        objects from, like RADB,RIPE [default: every one the
+                        server mirrors]
+        --geoip-config FILE
+                        The GeoIP.conf with the MaxMind LicenseKey that
+                        `lfc geoip update` downloads with, unless
+                        MAXMIND_LICENSE_KEY is set [default: /etc/GeoIP.conf]
+        --asn ASN       The AS whose announced prefixes `lfc fetch ripestat`
+                        downloads, like AS64500. Can be repeated, or list
+                        several separated by commas
+        --since TIME    With `lfc fetch ripestat`, the prefixes announced
+                        since TIME, like 2024-01-31 or 2024-01-31T12:00:00Z
+                        [default: two weeks ago]
+        --until TIME    With `lfc fetch ripestat`, the prefixes announced
+                        until TIME [default: now]
+        --unix PATH     The Unix domain socket for `lfc serve` to listen on
+        --listen ADDRESS
+                        The TCP address, like 127.0.0.1:8080, for `lfc serve`
+                        to answer REST API requests on
+        --current FILE  The deployed list that `lfc delta` or `lfc plan`
+                        updates. For `lfc delta`, the output of `ipset list`
+                        or `ipset save` works too
+        --set NAME      Name of the ipset to update [default: the name in
+                        the --current listing]
+        --prefix PREFIX The NAT64 prefix for `lfc nat64`: an IPv6 /32, /40,
+                        /48, /56, /64, or /96 [default: 64:ff9b::/96]
+        --reverse       Translate NAT64 networks back to IPv4 in
+                        `lfc nat64`
+        --to /LEN       The size of the networks `lfc explode` splits
+                        networks into
+        --max-output N  The most networks `lfc explode` prints before it
+                        refuses to run at all [default: 1_000_000]
+        --v4 /LEN       The longest IPv4 prefix `lfc anonymize` leaves
+                        [default: /24], or the shortest `lfc lint` doesn't
+                        warn about [default: /8]
+        --v6 /LEN       The longest IPv6 prefix `lfc anonymize` leaves
+                        [default: /48], or the shortest `lfc lint` doesn't
+                        warn about [default: /32]
+        --max N         The most addresses `lfc hosts` prints before it
+                        refuses to run at all [default: 65_536]
+        --skeleton      Write a skeleton zone file for each zone in
+                        `lfc ptr-zones` instead of only its name
+        --list NAME     The list `lfc push cloudflare` updates
+        --list NAME=FILE:PRIORITY
+                        A list for `lfc resolve`, and the decision it makes,
+                        like allow or block; may be given more than once
+        --service SID   The Fastly service `lfc push fastly` updates
+        --acl NAME      The ACL `lfc push fastly` updates
+        --list-id ID    The network list `lfc push akamai` updates
+        --akamai-mode MODE
+                        How `lfc push akamai` updates the list: replace or
+                        append [default: replace]
+        --akamai-activate NETWORK
+                        Activate the list on staging or production after
+                        `lfc push akamai` updates it
+        --dry-run       Print what `lfc push` would change without changing
+                        anything
+        --format FORMAT Output format. For the aggregated list, one of those
+                        `lfc formats` lists. For `lfc delta`: ipset-delta.
+                        For `lfc plan`: text or json [default: text]
+        --ipv6-form FORM
+                        Write IPv6 networks compressed, like 2001:db8::/32,
+                        or expanded to all eight groups [default:
+                        compressed]
+        --ipv6-case CASE
+                        Write IPv6 hex digits in lower or upper case
+                        [default: lower]
+        --script FILE   Pass input lines and output networks through the
+                        Rhai script in FILE
+        --count N       How many networks `lfc bench` or `lfc gen` generates,
+                        or how many lists `lfc selftest` checks [default:
+                        1_000_000 for bench, 100 for gen, 10_000 for
+                        selftest]
+        --profile PROFILE
+                        The kind of list `lfc bench` generates: bgp, random,
+                        or dense [default: bgp]
+        --family FAMILY Which networks `lfc gen` prints: v4, v6, or mixed
+                        [default: v4]
+        --prefixlen-range RANGE
+                        Prefix lengths for `lfc gen`, inclusive, like 16..28
+                        [default: 16..32 for IPv4, 32..64 for IPv6]. With
+                        --format openbgpd, the lengths each entry matches
+        --seed S        Seed for `lfc gen`, `lfc bench`, `lfc selftest`, and
+                        --sample, so the same seed always generates or picks
+                        the same networks
 
 DESCRIPTION:
-    Reads IP networks in CIDR notation from stdin and outputs an aggregated,
-    minimized list of networks. Adjacent networks are merged into larger CIDR
-    blocks where possible, and overlapping or duplicate entries are removed.
+    Reads IP networks in CIDR notation from the given files, or from stdin if
+    no files are given, and outputs an aggregated, minimized list of networks.
+    Adjacent networks are merged into larger CIDR blocks where possible, and
+    overlapping or duplicate entries are removed. When several files are
+    given, they're parsed on separate threads. A file name that starts with
+    http:// or https:// is downloaded instead, and gzipped input is
+    decompressed.
+
+    Only networks are ever written to stdout. Hints, warnings, and other
+    diagnostics go to stderr, so the output is always safe to pipe. They're
+    one per line unless `--format` picks another format; `lfc formats` lists
+    the choices.
+
+    `--script` loads a Rhai script that can define `fn input(line)`, called
+    with each input line before it's parsed, and `fn output(net)`, called
+    with each aggregated network. Each returns a string to use in its place,
+    or `()` to drop it, so output can be tagged or rewritten into whatever
+    lines another tool expects. Scripts need lfc built with the `scripting`
+    feature.
+
+    IPv6 networks are written in the RFC 5952 form unless `--ipv6-form` or
+    `--ipv6-case` say otherwise, for appliances that only accept expanded
+    addresses or for diffing against vendor exports. They apply to every
+    `--format`.
+
+    `lfc diff` aggregates both lists and prints the networks that were
+    removed (-) and added (+). When stdout is a terminal, the changes are
+    colored and grouped by address family. Otherwise they're printed as plain
+    `-net` and `+net` lines.
+
+    `--diff-against` is for reviewing updates to a saved list. Its output is
+    a standard unified diff that `patch -p0` can apply to update FILE.
+
+    With `--cache-dir`, results are saved under a hash of the input and the
+    options that affect them. Running over the same input again skips
+    parsing and aggregating and reuses the saved result. It can't be used
+    with `--merge-slack`, whose report of the space it added comes from
+    aggregating.
+
+    Feeds that mix sources often list the same space as both IPv4 and
+    IPv4-mapped IPv6. `--normalize-mapped` converts the mapped networks to
+    IPv4 before aggregating, so they merge with their IPv4 neighbors instead
+    of appearing twice. `--normalize-mapped=v6` goes the other way, for
+    IPv6-only consumers.
+
+    `--extract-embedded` carries decisions about IPv6 transition addresses
+    over to IPv4. A 6to4 network holds its site's IPv4 address right after
+    the 2002: prefix, and a Teredo address ends with its client's IPv4
+    address, so blocking one should usually block the other. Teredo
+    networks shorter than /96 don't say which client they're for, and are
+    left as they are.
+
+    IPv6 zone IDs, like the %eth0 in fe80::1%eth0/64, are errors unless
+    `--zones` says otherwise. `--zones strip` drops them, so the networks
+    aggregate with everything else. `--zones group` aggregates each zone's
+    networks on their own and prints them after the rest, with their zone
+    IDs, like fe80::%eth0/64. Grouped networks are only printed; state,
+    diffs, metrics, and set files only see the networks without zones.
+
+    Some feeds list one representative host per subnet instead of the
+    subnet. `--default-prefixlen-v4` and `--default-prefixlen-v6` widen a
+    bare address of that family to the network around it, so with
+    `--default-prefixlen-v4 24`, 192.0.2.77 becomes 192.0.2.0/24. Without
+    them, a bare address is an error, since it's more often a typo.
+
+    A network with host bits set, like 192.168.1.77/24, is an error too,
+    since it's unclear whether 192.168.1.0/24 or 192.168.1.77/32 was meant.
+    `--truncate-host-bits` takes it as the network, 192.168.1.0/24, and
+    warns with how many networks it changed and where the first one was.
+
+    `--merge-slack` trades accuracy for a shorter list. Networks that nearly
+    fill a supernet are replaced by it, as long as the space the supernet
+    adds is no more than PERCENT of it. With `--merge-slack 5%`, 31 of the
+    32 /24s in a /19 become the /19. Each piece of added space is reported
+    on stderr, so nothing is covered by surprise.
+
+    `--max-coverage` is a guardrail for lists that feed firewalls. When the
+    result covers more than PERCENT of the IPv4 or IPv6 address space, lfc
+    stops before writing anything or pushing it to a provider, so a feed
+    that suddenly lists 0.0.0.0/1 can't block half the internet. With
+    `--refresh`, it leaves the outputs as they were and tries again at the
+    next interval instead. `--force` writes the result anyway, after a
+    warning, for the run where the big list is intended.
+
+    `--min-prefixlen-v4` and `--min-prefixlen-v6` guard the result the
+    same way, for routers that must never install anything broader than an
+    RIR allocation. Each network in the result that's shorter is reported,
+    whether it was in the input or merged from networks that weren't, and
+    lfc stops, unless `--force` makes them warnings. `--respect-allocations`
+    keeps merges from crossing allocations in the first place.
+
+    `--report special` is for reviewing a list before it's published. It
+    sorts the networks in the result by the blocks of the IPv4 and IPv6
+    Special-Purpose Address Registries they're in or include, like `lfc
+    info` does for one network, and writes that to stderr, leaving the
+    result itself alone. `--drop` and `--drop-bogons` take out whatever
+    shouldn't be there.
+
+    `--report slaac` lists the IPv6 /128s in the result whose interface
+    IDs look like a host picked them itself: made from its MAC address,
+    with ff:fe in the middle, or random, like the temporary addresses that
+    hosts replace every day or so. Blocking one of those is futile, since
+    the host moves on to another address in the same /64, so each is
+    listed with the /64 to block instead. Spotting a random interface ID
+    is a guess, and an address an administrator picked at random is
+    listed too.
+
+    `--no-merge-adjacent` keeps every network that isn't a duplicate or
+    inside another one exactly as it was, without merging neighbors into
+    bigger networks, for lists where each entry has to match a real
+    allocation.
+
+    `--dedupe-only` goes further and only removes exact duplicates, so lfc
+    can put a list into a canonical form, sorted and with any host bits
+    cleared, without changing what its entries are.
+
+    `--keep-structure` merges neighbors as usual, but keeps the networks
+    that are inside other networks instead of removing them, so the result
+    shows how the list is organized. `--format tree` draws it, with each
+    network indented under the network that holds it.
+
+    `--format dot` writes a Graphviz graph of the aggregation instead of the
+    list: the resulting networks in bold, with the networks they were made
+    from under them, down to the input networks as leaves. Render it with
+    Graphviz, like `dot -Tsvg`.
+
+    `--format html-report` writes a single HTML page with no outside files,
+    for attaching to a change ticket: counts of networks and addresses,
+    charts of the prefix lengths, and every network with the inputs it came
+    from. With `--baseline FILE`, it also lists what changed since FILE, an
+    earlier result. `--name` names the report.
+
+    `--format markdown` writes a table of the networks, their sizes, and
+    their families, for pasting into wikis and pull requests. `--annotate`
+    adds a Notes column saying which networks are IANA special-purpose
+    space, like documentation or private-use blocks, the same way
+    `lfc info` does. `--annotate asn` notes the AS that originates each
+    network's first address instead, with its name and the BGP prefix
+    that covers the address. Every network is looked up at once, in one
+    session with Team Cymru's bulk whois, so even thousands take seconds.
+
+    `--format toml` writes the networks as a TOML array, under the key
+    `--toml-key` names, so the result can go straight into an application's
+    config file. The part of the key before the last dot is a table, so
+    `--toml-key firewall.blocklist.networks` writes a `[firewall.blocklist]`
+    table with a `networks` array in it. `--toml-stats` adds a `stats` table
+    next to the array. Address counts too big for a TOML integer, like most
+    IPv6 ones, are written as strings.
+
+    `--format xml` writes an XML document with an element for each network
+    inside a root element, for appliances and older APIs that only take XML.
+    `--xml-root` and `--xml-element` rename the elements, and
+    `--xml-attribute` puts each network in an attribute instead, so the
+    output can match whatever schema the consumer expects.
+
+    `--format protobuf` writes one serialized `lfc.v1.Result` Protocol
+    Buffers message, with each network's address as bytes and its prefix
+    length as a number, so typed consumers don't have to parse text. The
+    schema is `lfc.proto` in lfc's source, and `lfc::format::PROTO` in the
+    library.
+
+    `--format arrow` writes an Apache Arrow IPC file, which Polars, pandas,
+    and DuckDB can read or memory-map without parsing. It has a row for each
+    network, with its text, its first and last addresses as 16 bytes each
+    (IPv4 as IPv4-mapped IPv6 addresses), its prefix length, and its family
+    as 4 or 6.
+
+    `--format intrange` writes each network's first and last addresses as
+    integers, separated by a comma, the way most IP lookup tables in
+    databases store ranges. IPv6 addresses are 128-bit numbers, which many
+    databases can't hold, so `--intrange-split` writes each one as its high
+    and low 64 bits instead, for four numbers on each IPv6 line.
+
+    `--format bits` writes each network next to its address in binary, with
+    a `|` where the network bits end, so it's easy to see which bits two
+    networks share and why they didn't merge. `--bits-hex` writes the
+    addresses in hexadecimal instead, with the `|` after the last digit
+    that has any network bits.
+
+    `--format dnsmasq` writes an `ignore-address=` line for each network,
+    so dnsmasq, as on OpenWrt, drops DNS answers pointing into the list.
+    `--dnsmasq-option bogus-nxdomain` turns those answers into NXDOMAIN
+    instead. dnsmasq's `ipset=`, `nftset=`, and `address=` options take
+    domains rather than networks, so there's no way to write them from a
+    list of networks; load the list into the set directly instead.
+
+    `--format unbound` writes a `server:` clause with an `access-control`
+    line for each network, IPv4 and IPv6 alike, for an Unbound config file
+    to include. The lines refuse queries from the networks, or take the
+    Unbound action that `--action` names instead.
+
+    `--format bind-acl` writes an `acl` statement for named.conf with the
+    networks, named by `--name`, or `lfc` without it, so the list can be
+    used wherever BIND takes an address match list. `--except FILE` carves
+    the networks in FILE out of the list's: they're negated with `!` ahead
+    of the rest, since BIND uses the first element that matches.
+
+    `--format envoy` writes the `principals` of an Envoy RBAC policy, with
+    a `source_ip` CidrRange for each network, so edge proxies can block the
+    same lists as everything else. It's JSON, which is also YAML, so it
+    fits either kind of Envoy config. Newer Envoys deprecate `source_ip`;
+    `--envoy-principal direct_remote_ip` writes what replaced it, and
+    `--envoy-principal remote_ip` matches the client in `X-Forwarded-For`.
+
+    `--format bpf-map` writes a `bpftool map update` command for each
+    network, adding it to an LPM trie map that an XDP or tc filter looks
+    client addresses up in. A trie's keys are all one size, so IPv4 networks
+    go to the map pinned at `--bpf-pin` with `_v4` added, and IPv6 ones to
+    the one with `_v6`. Keys are a little-endian prefix length followed by
+    the address, for x86-64 and arm64 hosts. Each value is a 32-bit 1, or
+    the bytes `--bpf-value` gives.
+
+    `--format codegen` writes a C header, or a Rust module with `--lang
+    rust`, with a sorted table of each family's networks and a function
+    that finds the longest network containing an address, for building a
+    small list into firmware or an agent. The names start with `--name`, or
+    `lfc` without it, so more than one list can go into the same program.
+
+    `--format firewalld` writes a `hash:net` ipset for
+    /etc/firewalld/ipsets/NAME.xml, where the file's name names the set, so
+    RHEL-family hosts can use the list natively. A set holds one family, so
+    a list with both needs `--firewalld-family inet` or `inet6`, and the
+    networks of the other family are left out. Sets of more than 65,536
+    networks get a `maxelem` big enough for them.
+
+    `--format windows-firewall` writes a PowerShell script that keeps
+    Windows Firewall rules blocking the networks as their `-RemoteAddress`,
+    a thousand networks to a rule. The rules are named `--name`, or `lfc`
+    without it, then `-1`, `-2`, and so on, so running the script again
+    with a newer list updates the rules with `Set-NetFirewallRule`, creates
+    any more it needs with `New-NetFirewallRule`, and removes the ones left
+    over from a longer list. `--action Allow` allows the networks instead,
+    and `--windows-direction Outbound` matches outbound connections.
+
+    `--format iproute` writes `ip route` commands that null-route the
+    networks, `ip -6` ones for IPv6, to pipe into a shell. They first flush
+    the table's routes of the same type, then add each network with `ip
+    route replace`, so the table ends up holding just the newer list.
+    `--action` picks another type of route, like unreachable, and `--table`
+    puts the routes in another table than main. Every route of the type in
+    the table is flushed, so a table of the list's own keeps other routes
+    safe.
+
+    `--format bsd-route` writes a `route add` command for each network,
+    through the loopback address with `-blackhole`, for FreeBSD and OpenBSD
+    hosts. `--action reject` answers the networks' packets with an ICMP
+    unreachable instead of dropping them quietly.
+
+    `--format frr` writes numbered `ip prefix-list` and `ipv6 prefix-list`
+    lines permitting the networks, in a list named by `--name` or `lfc`,
+    for FRR or Quagga's vtysh. `--frr-ge` and `--frr-le` let each entry
+    match the longer networks inside it as well, with `ge` and `le`. An
+    entry gets them only where they're longer than it and fit its family,
+    so `--frr-le 24` lets a /16 match /24s inside it and leaves a /24, or
+    an IPv6 network, exact.
+
+    `--format openbgpd` writes a `prefix-set` block for bgpd.conf, named by
+    `--name` or `lfc`, with an entry for each network. `--prefixlen-range`
+    lets each entry match the longer networks inside it as well, with a
+    `prefixlen` range cut to the lengths between the entry's own and its
+    family's addresses, so `--prefixlen-range 16..24` lets a /8 match /16s
+    to /24s and a /20 match /20s to /24s.
+
+    `--format istio` writes an AuthorizationPolicy manifest, named by
+    `--name` or `lfc`, with a rule matching the networks' `ipBlocks`. It
+    denies them, or takes the action `--action` names instead.
+    `--istio-selector` limits the policy to workloads with the given
+    labels, and `--istio-remote` matches `remoteIpBlocks` instead, for
+    clients behind a load balancer. An empty list gets a policy without
+    any rules, which matches nothing, rather than an empty `ipBlocks`,
+    which Istio treats as matching everyone.
+
+    `--no-merge-across` keeps merged networks from spanning the edge of a
+    region, for policies that keep some space apart. `builtin:rfc1918`
+    keeps private IPv4 space from merging with the public space next to
+    it, and `builtin:rir` keeps merged networks inside a single allocation
+    from IANA to a regional registry: an IPv4 /8, or one of the IPv6 blocks
+    in IANA's registry. `builtin:special` keeps the blocks of the IANA
+    special-purpose registries apart, like 100.64.0.0/10 and 192.0.2.0/24,
+    since a route or rule that covers one of them along with globally
+    routable space means something it shouldn't; `--scope-aware` is the
+    same. Networks that already span an edge are left as they are.
+
+    `--respect-allocations` is the same, with every allocation and
+    assignment in the regional registries' delegated-stats files as a
+    region, so that two neighboring blocks that went to unrelated
+    organizations never merge into one network that looks like a single
+    holder's. The files are kept and refreshed like `--rir`'s.
+
+    `--rir` keeps only the parts of the result that the named regional
+    registries have allocated or assigned, for policies scoped to a
+    region. A network that spans space from several registries is cut
+    down to the networks inside the named ones. Which registry delegated
+    what comes from their daily delegated-stats files, which are kept in
+    the rir directory of `--data-dir`, or of ~/.cache/lfc without one,
+    and downloaded again once they're a day old.
+
+    `--drop` leaves the chosen kinds of special-purpose space out of the
+    result, cutting networks that span it down to the parts outside it.
+    Each kind can be chosen on its own, since what doesn't belong depends
+    on where a list is used: a firewall in front of a carrier-grade NAT
+    needs cgnat kept, and a lab may want private space. doc is the
+    documentation blocks, like 192.0.2.0/24 and 2001:db8::/32, private is
+    the RFC 1918 blocks, reserved is 0.0.0.0/8, 240.0.0.0/4, and ::, and
+    bogons is every kind at once.
+
+    `--drop-bogons` leaves out a whole martians list instead, so the set is
+    one that can be named in a change review and swapped per environment.
+    rfc1918 is the private blocks, rfc6890 is the special-purpose blocks
+    as RFC 6890 listed them, and iana-special is those of IANA's current
+    registries that `lfc info` knows, including multicast. fullbogons is
+    Team Cymru's lists of special-purpose and unallocated space, which
+    changes as space is allocated; it's kept in the bogons directory of
+    `--data-dir`, or of ~/.cache/lfc without one, and refreshed daily
+    like `--rir`'s files. Anything else is a file or URL of networks, one
+    per line, with `#` comments.
+
+    `--sample` keeps a random subset of the result, for canary deployments
+    and load tests. The same input, N, and `--seed` always pick the same
+    networks, so a canary can be rolled out again exactly, and a different
+    seed picks a different subset. Every network is as likely as any other
+    unless `--sample-weighted` makes a /16 as likely as 256 /24s.
+
+    `--strict` is for linting lists that are kept by hand. On top of the
+    usual checks, it rejects anything lfc wouldn't write itself: host bits,
+    leading zeros like 010.0.0.0/8 or 10.0.0.0/08, and IPv6 text that isn't
+    in the RFC 5952 form, like 2001:DB8:0::/48. It reports every bad line
+    with what it should say instead, then exits with an error.
+
+    `--save-set` saves the result as the address ranges it covers, in a
+    compact binary form that `--load-set` reads back far faster than
+    parsing the text again, for daemons like `lfc serve` that restart often
+    with big lists. Without any files, `--load-set` doesn't read stdin.
+
+    `--low-memory` sorts the parsed networks in runs of about a million,
+    saved in the system's temporary directory ($TMPDIR), and merges the runs
+    at the end. Memory use then depends on the size of the result rather
+    than the size of the input. It's slower, so only use it when you must.
+
+    With `--state-dir`, each result is saved under its `--name`, and the
+    prefixes and addresses added and removed since the previous run are
+    reported on stderr. The result itself is still printed as usual. Add
+    `--webhook` to also POST the added and removed prefixes, counts, and a
+    timestamp as JSON whenever the list changes. If the webhook can't be
+    delivered, lfc exits with an error without saving the new result, so
+    the next run notices the same change and tries again. With
+    a secret from `--webhook-secret-file`, LFC_WEBHOOK_SECRET, or
+    `--webhook-secret`, the body's signature is sent in an
+    `X-Lfc-Signature: sha256=<hex>` header.
+
+    `--syslog` logs one message per added or removed prefix, like
+    `list=NAME action=add prefix=10.0.0.0/23 source=feed.txt`, for an
+    audit trail of changes. Like webhooks, a failure to log stops lfc
+    before it saves the new result.
+
+    `--refresh` keeps lfc running as a feed-sync daemon. Every INTERVAL, it
+    downloads its URL inputs again, aggregates them, and compares the
+    result with the state directory, which it needs. When the list changed,
+    it rewrites `--output`, which it also needs, and the other outputs, and
+    reports the change as usual, through `--webhook` and `--syslog` too.
+    When it didn't, only `--metrics-file` is rewritten. A download that
+    fails is reported and tried again at the next interval. SIGHUP starts
+    the next pass right away, and the interval over from there.
+
+    An entry can expire, for a blocklist that cleans itself up. A line with
+    a timestamp after its network, like `192.0.2.0/24 2025-09-19T12:34:56Z`
+    or `192.0.2.0/24 expires=1758285296`, is left out of the input once
+    that time has passed, or once `--now` has, and the line is otherwise
+    read as its network alone. The timestamp is RFC 3339 in UTC, a date, or
+    seconds since the Unix epoch. With `--expire-after`, it's when the
+    entry was added instead, so a feed can list when it last saw each
+    address and let them age out. When any entry is still to expire,
+    aggregating says when the next one does on stderr, and in
+    `--metrics-file`, so cron or `--refresh` can be timed to match.
+
+    `--rpki` checks each network in the result against RPKI before it's
+    written, so a prefix list doesn't contradict what routers that validate
+    origins will accept. The validated ROA payloads come from an
+    RPKI-to-Router cache, like Routinator or StayRTR, at `rtr://HOST:PORT`,
+    or from a file or URL of the JSON or CSV that validators export. A
+    network is invalid if ROAs cover it but none lets `--origin` announce
+    it at its length. Each invalid network is reported on stderr, along
+    with a count of valid, invalid, and not found networks, and
+    `--rpki-drop` also leaves them out of the result.
+
+    `lfc serve` aggregates the given files once, then listens on a Unix
+    socket. Each request and response is a 4-byte big-endian length followed
+    by that much UTF-8 text. Requests are `aggregate` followed by a list of
+    networks on the next lines, `list` for the loaded list, or
+    `lookup ADDRESS` for the loaded network containing ADDRESS. Responses
+    start with an `ok` or `error MESSAGE` line, followed by any networks.
+
+    With `--listen`, `lfc serve` also answers a JSON REST API over HTTP:
+    POST /aggregate, POST /diff, GET /lookup?address=ADDRESS, and GET, PUT,
+    and DELETE on /lists/NAME to manage named lists. The files given on the
+    command line become the list named by `--name`. GET /openapi.json
+    describes the whole API, for generating clients.
+
+    Under systemd, `lfc serve` can be socket-activated: it answers on the
+    sockets the `.socket` unit passes in, the REST API on TCP ones and the
+    Unix socket protocol on Unix ones, along with any `--unix` and
+    `--listen` of its own. With `Type=notify`, it tells systemd when it's
+    ready, and with `WatchdogSec=`, keeps telling the watchdog it's alive.
+    SIGHUP makes it read its files again, so `systemctl reload` can pick
+    up a changed list without dropping requests. If they can't be read,
+    it keeps the list it had.
+
+    `--metrics-file` writes gauges for the prefix and address counts per
+    family, the time of the run, and how long aggregation took, labeled
+    with the `--name` of the list, and how many times `--refresh` has
+    failed to read the sources. `lfc serve --listen` answers with the same
+    gauges for each of its lists at GET /metrics.
+
+    `lfc delta` compares an existing ipset with the aggregated input and
+    prints only the `add` and `del` commands needed to turn one into the
+    other, in the format read by `ipset restore`. Additions come first, so
+    the set never drops coverage it should keep, and it never needs to be
+    flushed.
+
+    `lfc plan` does the same comparison without assuming anything about
+    where the list is deployed. It prints `add NET` and `remove NET` lines,
+    or with `--format json`, an object with `add` and `remove` arrays and a
+    summary, for feeding into your own deployment tooling.
+
+    `lfc nat64` translates IPv4 networks into the IPv6 networks a NAT64
+    translator uses for them under `--prefix`, following RFC 6052, so one
+    IPv4 list can be enforced on an IPv6-only network. The prefix defaults
+    to the well-known 64:ff9b::/96. With `--reverse`, networks under the
+    prefix are translated back to IPv4 instead. Anything else passes
+    through, and the result is aggregated as usual.
+
+    `lfc explode` aggregates the input, then splits each network into the
+    /LEN networks that make it up, for tools that score or look up
+    networks of one size, like per-/24 reputation. Networks that are
+    already /LEN or smaller are printed as they are. If the result would
+    have more than `--max-output` networks, it prints nothing and exits
+    with an error saying how many there would have been.
+
+    `lfc anonymize` aggregates the input, then widens every network longer
+    than `--v4` or `--v6` to the network of that length around it, zeroing
+    the host bits, and aggregates again. A list derived from user traffic
+    can then be shared without naming any single host or subscriber, only
+    the /24 or /48 it's in.
+
+    `lfc lint` reads the input without aggregating it and reports each
+    line that's suspicious, like a compiler, as an error, a warning, or a
+    notice. Errors are lines that aren't networks, and 0.0.0.0/0 or ::/0,
+    which match everything. Warnings are networks shorter than `--v4` or
+    `--v6`, special-purpose space like that `lfc info` describes, host
+    bits that are set, and networks inside or the same as another one in
+    the input, which is named. Notices are networks that aren't written the
+    way `--strict` wants. With `--format json`, it prints a JSON object
+    with how many there are of each, and a list of findings with their
+    severity, source, line, text, and message. It exits with 1 if there
+    are any errors.
+
+    `lfc hosts` prints every address in the aggregated networks, one per
+    line, for tools that only take single addresses. That includes the
+    first and last address of each network, since after aggregating they're
+    usually ordinary hosts from the input. Like `lfc explode`, it refuses to
+    print anything if there are more than `--max` addresses.
+
+    `lfc info` describes each network on its command line: its network and
+    broadcast addresses, its first and last usable hosts, how many addresses
+    it has, its netmask and wildcard mask, its address in binary, and which
+    block in IANA's special-purpose registries it belongs to, if any. It
+    parses networks the way aggregating does, except that host bits are
+    kept and a bare address is a /32 or /128 unless
+    `--default-prefixlen-v4` or `--default-prefixlen-v6` says otherwise.
+
+    `lfc tui` shows the aggregated networks as a tree in the terminal. Each
+    branch is the smallest network holding everything under it, with a count
+    of the addresses under it. Arrow keys move and expand, `/` searches for a
+    network or address, space marks a branch or network, and `x` exits and
+    prints the marked networks in the chosen `--format`, while `q` exits
+    without printing anything. It needs lfc built with the `tui` feature.
+
+    `lfc ptr-zones` prints the in-addr.arpa and ip6.arpa zones that hold the
+    reverse DNS for the aggregated networks. Reverse zones end on octet
+    boundaries for IPv4 and nibble boundaries for IPv6, so a network that
+    doesn't, like a /22, is split into the zones that make it up. IPv4
+    networks smaller than a /24 get RFC 2317 classless zones, like
+    64/26.2.0.192.in-addr.arpa. With `--skeleton`, each zone is a stanza
+    with $ORIGIN, SOA, and NS records to fill in, and classless zones also
+    get the NS and CNAME records their /24's zone needs to delegate them.
+
+    `lfc push` reads a list back from a provider, works out the networks
+    to add and remove to make it match the aggregated input, prints them
+    like `lfc plan` does, and sends only those changes. New networks are
+    added before old ones are removed, so nothing in both is ever left out
+    in between. With `--dry-run`, it only prints the changes. The provider
+    is:
+
+        cloudflare  The account's IP list named by `--list`. The API token
+                    comes from CLOUDFLARE_API_TOKEN and needs permission to
+                    edit the account's lists, and the account ID comes from
+                    CLOUDFLARE_ACCOUNT_ID.
+        fastly      The ACL named by `--acl` in the active version of the
+                    service `--service`. Its entries are updated in
+                    batches, without a new version, and negated entries are
+                    left alone. The API token comes from FASTLY_API_TOKEN.
+        akamai      The network list with the ID `--list-id`. With
+                    `--akamai-mode append`, the networks it doesn't have
+                    are added and nothing is removed. The list is only
+                    activated with `--akamai-activate`. Requests are signed
+                    with EdgeGrid, from the API client's credentials in
+                    AKAMAI_HOST, AKAMAI_CLIENT_TOKEN, AKAMAI_CLIENT_SECRET,
+                    and AKAMAI_ACCESS_TOKEN.
+
+    `lfc fetch` downloads the ranges a service publishes as JSON and
+    aggregates them like any other input, for building allowlists. The
+    feeds are:
+
+        github-meta  Every range in GitHub's meta API, for hooks, actions,
+                     pages, and the rest
+        googlebot    The ranges Googlebot crawls from
+        ripestat     The prefixes each `--asn` announced, as RIPEstat saw
+                     them. `--since` and `--until` look back in time
+
+    `lfc irr-check` aggregates the input, then asks an IRR server for the
+    route and route6 objects registered for `--origin` and prints each
+    network that doesn't match one: those inside a route object or covering
+    some, as mismatched, and those that don't overlap any, as unregistered.
+    It exits with an error if there are any, so a list can be checked
+    before it's published. `--dedupe-only` checks the networks as they're
+    listed rather than merged. The server is RADb's unless `--irr-server`
+    names another, and `--irr-sources` limits the route objects to those in
+    some of its databases.
+
+    `lfc geoip update` downloads MaxMind's GeoLite2 Country database into
+    the geoip directory of `--data-dir`, or of ~/.cache/lfc without one,
+    replacing the copy already there. The database needs a free MaxMind
+    license key, from MAXMIND_LICENSE_KEY or the LicenseKey line of the
+    GeoIP.conf that geoipupdate uses. `lfc by-country` reads the copy
+    there, and refuses to run if there's none or it's more than 30 days
+    old, so run the update from cron. `lfc geoip status` says how big and
+    how old the copy is.
+
+    `lfc by-country` aggregates the input, splits the networks where the
+    GeoLite2 blocks they overlap start and end, and prints a section for
+    each country, by code, with its networks and how many IPv4 and IPv6
+    addresses they have, as numbers and as shares of the whole list.
+    Networks that GeoLite2 doesn't place in any country are under ZZ. The
+    section headers are comments, so the output still works as a list.
+    With `--format json`, it prints a JSON object keyed by country code
+    instead.
+
+    `lfc by-asn` aggregates the input, looks up the AS that originates
+    each network like `--annotate asn` does, and prints a section for each
+    AS, in order of number, with its name, its networks, and the same
+    totals. Networks whose first address isn't announced come last, as
+    unannounced. A network goes with the origin of its first address, so
+    use `--dedupe-only` to keep networks that span several ASes as they're
+    listed. With `--format json`, it prints a JSON object keyed like
+    AS64500, with each AS's name.
+
+    `lfc resolve` aggregates each `--list` on its own and works out the
+    policy they add up to. Where lists overlap, the one with the highest
+    priority decides, and where they have the same priority, the one given
+    first does. It prints a section for each decision, highest priority
+    first, with the networks it decides and the same totals as
+    `lfc by-country`, so every address that's in any list is in exactly
+    one section. Lists with the same NAME make the same decision, so they
+    share a section. With `--format json`, it prints a JSON object keyed
+    by NAME.
+
+    `lfc bench` generates a list, then times parsing and aggregating it
+    separately. The list is always generated from the same seed, so the
+    numbers from different builds and releases are comparable on the same
+    machine.
+
+    `lfc gen` prints uniformly random networks from anywhere in the address
+    space, for load testing and fuzzing whatever consumes lfc's output. The
+    same `--seed` and options always print the same list.
+
+    `lfc selftest` aggregates random lists every way lfc can and checks
+    that each result is sorted, has no host bits, has nothing left to merge,
+    covers exactly the same addresses as its input, and matches a slow
+    reference implementation. If any check fails, it prints the smallest
+    input it could find that still fails, and exits with an error. Each run
+    uses a new seed unless you give one, and prints it so the run can be
+    repeated.
 
 EXAMPLES:
     cat networks.txt | lfc
+    lfc networks.txt more-networks.txt
     echo -e '192.168.0.0/24\\n192.168.1.0/24' | lfc
+    lfc diff yesterday.txt today.txt
+    lfc --diff-against blocklist.txt new-feed.txt > blocklist.patch
+    lfc --state-dir /var/lib/lfc --name spamhaus drop.txt > drop-aggregated.txt
+    lfc delta --current <(ipset list blocklist) new.txt | ipset restore
+    lfc plan --current deployed.txt --format json new.txt
+    lfc serve --unix /run/lfc.sock blocklist.txt
+    lfc --save-set blocklist.lfcset blocklist.txt > /dev/null
+    lfc serve --unix /run/lfc.sock --load-set blocklist.lfcset
+    lfc serve --listen 127.0.0.1:8080 --name blocklist blocklist.txt
+    lfc bench --count 5_000_000 --profile bgp
+    lfc gen --count 10000 --family mixed --prefixlen-range 16..28 --seed 42
+    lfc selftest --count 100_000
+    lfc --format json networks.txt
+    lfc --format yaml networks.txt > group_vars/blocklist.yml
+    lfc --format msgpack bgp-full.txt > bgp-full.msgpack
+    lfc --format protobuf bgp-full.txt > bgp-full.pb
+    lfc --format arrow bgp-full.txt > bgp-full.arrow
+    lfc --format intrange --intrange-split bgp-full.txt > ranges.csv
+    lfc --dedupe-only --format bits list.txt
+    lfc --format dnsmasq blocklist.txt > /etc/dnsmasq.d/blocklist.conf
+    lfc --format unbound --action refuse abusers.txt > /etc/unbound/abusers.conf
+    lfc --format bind-acl --name blocked abusers.txt > /etc/bind/blocked.acl
+    lfc --format envoy --envoy-principal direct_remote_ip blocklist.txt
+    lfc --format bpf-map --bpf-pin /sys/fs/bpf/xdp/deny blocklist.txt | sh
+    lfc --format codegen --lang rust --name deny blocklist.txt > src/deny.rs
+    lfc --format firewalld --firewalld-family inet drop.txt > /etc/firewalld/ipsets/drop.xml
+    lfc --format windows-firewall --name abusers abusers.txt > abusers.ps1
+    lfc --format iproute --action blackhole --table 100 drop.txt | sh
+    lfc --format bsd-route --action reject drop.txt | sh
+    lfc --format frr --name AS64500-IN --frr-le 24 customer.txt | vtysh -f /dev/stdin
+    lfc --format openbgpd --name customers --prefixlen-range 0..24 customers.txt
+    lfc --format istio --name deny-abusers --istio-selector app=ingress abusers.txt
+    lfc --keep-structure --format tree routes.txt
+    lfc --format dot routes.txt | dot -Tsvg > routes.svg
+    lfc --format html-report --baseline deployed.txt feed-*.txt > report.html
+    lfc --format markdown --annotate allowlist.txt
+    lfc --format markdown --annotate asn --provider cymru abusers.txt
+    lfc --format toml --toml-key firewall.blocklist.networks drop.txt
+    lfc --format xml --xml-root acl --xml-element entry --xml-attribute prefix drop.txt
+    lfc nat64 --prefix 2001:db8:64::/96 blocklist.txt
+    lfc explode --to /24 --max-output 1_000_000 blocklist.txt
+    lfc hosts --max 65536 blocklist.txt
+    lfc info 203.0.113.0/26
+    lfc tui blocklist.txt > selected.txt
+    lfc ptr-zones --skeleton customer-blocks.txt > reverse.zones
+    lfc lint --v4 /16 --format json blocklist.txt > lint.json
+    lfc push cloudflare --list my_blocklist --dry-run feeds/*.txt
+    lfc push fastly --service SU1Z0isxPaozGVKXdv0eY --acl blocklist feeds/*.txt
+    lfc push akamai --list-id 12345_BLOCKLIST --akamai-activate staging feeds/*.txt
+    lfc fetch googlebot > googlebot.txt
+    lfc fetch ripestat --asn AS64500,AS64501 --since 2024-01-01 --until 2024-02-01
+    lfc irr-check --origin AS64500 --irr-sources RADB,RIPE --dedupe-only customer.txt
+    lfc geoip update --data-dir /var/lib/lfc --geoip-config /usr/local/etc/GeoIP.conf
+    lfc by-country --data-dir /var/lib/lfc --format json blocklist.txt
+    lfc by-asn --dedupe-only abusers.txt
+    lfc resolve --list allow=allow.txt:100 --list block=block.txt:50
+    lfc --refresh 1h --state-dir /var/lib/lfc --output /etc/nginx/deny.txt https://example.com/feed.txt
+    lfc --expire-after 7d --metrics-file /var/lib/node_exporter/lfc.prom sightings.txt
+    lfc --rpki rtr://validator:3323 --origin AS64500 --rpki-drop --format frr customer.txt
+    lfc --script tag.rhai networks.txt
+    lfc --default-prefixlen-v4 24 --default-prefixlen-v6 64 hosts.txt
+    lfc --strict prefix-lists/*.txt > /dev/null
+    lfc --merge-slack 5% routes.txt
+    lfc --max-coverage 1% --output /etc/nginx/deny.txt feeds/*.txt
+    lfc --min-prefixlen-v4 8 --min-prefixlen-v6 19 --format frr customer.txt
+    lfc --report special --output /etc/nginx/deny.txt feeds/*.txt
+    lfc --report slaac --quiet abusers.txt > /dev/null
+    lfc --no-merge-across builtin:rfc1918 --no-merge-across sites.txt routes.txt
+    lfc --scope-aware --format frr routes.txt
+    lfc --rir arin,lacnic feeds/*.txt > americas.txt
+    lfc --drop multicast,loopback,link-local,doc --format unbound feeds/*.txt
+    lfc --drop-bogons fullbogons --drop-bogons /etc/lfc/martians.txt --format frr customer.txt
+    lfc --sample 1000 --seed 42 --format iproute blocklist.txt > canary.sh
+    lfc --respect-allocations --data-dir /var/lib/lfc abusers.txt
 "
     );
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// The subcommand to run.
+#[derive(Debug, Default, PartialEq)]
+enum Command {
+    /// Aggregate the input networks. This is what lfc does without a subcommand.
+    #[default]
+    Aggregate,
+    /// Compare two lists of networks.
+    Diff,
+    /// Work out the commands to update a set of networks in place.
+    Delta,
+    /// Work out a vendor-neutral plan to update a list of networks.
+    Plan,
+    /// Answer requests on a socket.
+    Serve,
+    /// Time parsing and aggregating a synthetic list.
+    Bench,
+    /// Print random networks.
+    Gen,
+    /// Check the aggregator against a reference implementation.
+    Selftest,
+    /// List the output formats.
+    Formats,
+    /// Translate networks to and from their NAT64 form.
+    Nat64,
+    /// Split networks into networks of one size.
+    Explode,
+    /// Widen networks that are too specific.
+    Anonymize,
+    /// Print every address in the networks.
+    Hosts,
+    /// Describe networks.
+    Info,
+    /// Browse the networks in the terminal.
+    Tui,
+    /// Print the networks' reverse DNS zones.
+    PtrZones,
+    /// Update a list at a provider.
+    Push,
+    /// Print a service's published ranges.
+    Fetch,
+    /// Check networks against an AS's route objects.
+    IrrCheck,
+    /// Manage the GeoLite2 database.
+    Geoip,
+    /// Group networks by country.
+    ByCountry,
+    /// Group networks by origin AS.
+    ByAsn,
+    /// Resolve overlapping lists by priority.
+    Resolve,
+    /// Point out suspicious entries.
+    Lint,
+}
 
-    if args.len() > 1 {
-        let arg = &args[1];
-        if arg == "-h" || arg == "--help" {
-            print_help();
-            return;
-        } else {
-            eprintln!("error: unrecognized argument '{}'", arg);
-            eprintln!();
-            eprintln!("Usage: lfc [OPTIONS]");
-            eprintln!();
-            eprintln!("For more information, try '--help'.");
-            std::process::exit(1);
+impl Command {
+    /// Looks up a subcommand by the name used on the command line.
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "diff" => Some(Command::Diff),
+            "delta" => Some(Command::Delta),
+            "plan" => Some(Command::Plan),
+            "serve" => Some(Command::Serve),
+            "bench" => Some(Command::Bench),
+            "gen" => Some(Command::Gen),
+            "selftest" => Some(Command::Selftest),
+            "formats" => Some(Command::Formats),
+            "nat64" => Some(Command::Nat64),
+            "explode" => Some(Command::Explode),
+            "anonymize" => Some(Command::Anonymize),
+            "hosts" => Some(Command::Hosts),
+            "info" => Some(Command::Info),
+            "tui" => Some(Command::Tui),
+            "ptr-zones" => Some(Command::PtrZones),
+            "push" => Some(Command::Push),
+            "fetch" => Some(Command::Fetch),
+            "irr-check" => Some(Command::IrrCheck),
+            "geoip" => Some(Command::Geoip),
+            "by-country" => Some(Command::ByCountry),
+            "by-asn" => Some(Command::ByAsn),
+            "resolve" => Some(Command::Resolve),
+            "lint" => Some(Command::Lint),
+            _ => None,
         }
     }
+}
 
-    let stdin_contents = io::read_to_string(io::stdin()).unwrap();
-    let nets = parse_nets(stdin_contents.lines());
+/// What `--annotate` notes about each network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Annotation {
+    /// Whether it is or includes IANA special-purpose space.
+    Special,
+    /// The AS that originates it.
+    Asn,
+}
+
+impl str::FromStr for Annotation {
+    type Err = String;
 
-    for n in gather(&nets) {
-        println!("{}", n);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "special" => Ok(Annotation::Special),
+            "asn" => Ok(Annotation::Asn),
+            _ => Err(format!(
+                "invalid --annotate '{}' (expected special or asn)",
+                s
+            )),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// What `--report` writes about the result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Report {
+    /// Which networks are in, or include, IANA special-purpose space.
+    Special,
+    /// Which IPv6 host entries look like SLAAC picked them.
+    Slaac,
+}
 
-    #[test]
-    fn test_parse_nets_single_network() {
-        let input = "192.168.1.0/24";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "192.168.1.0/24");
-    }
+impl str::FromStr for Report {
+    type Err = String;
 
-    #[test]
-    fn test_parse_nets_multiple_networks() {
-        let input = "192.168.1.0/24\n10.0.0.0/8\n172.16.0.0/12";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 3);
-        assert_eq!(result[0].to_string(), "192.168.1.0/24");
-        assert_eq!(result[1].to_string(), "10.0.0.0/8");
-        assert_eq!(result[2].to_string(), "172.16.0.0/12");
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "special" => Ok(Report::Special),
+            "slaac" => Ok(Report::Slaac),
+            _ => Err(format!(
+                "invalid --report '{}' (expected special or slaac)",
+                s
+            )),
+        }
     }
+}
 
-    #[test]
-    fn test_parse_nets_with_whitespace() {
-        let input = "  192.168.1.0/24  \n  10.0.0.0/8  ";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].to_string(), "192.168.1.0/24");
-        assert_eq!(result[1].to_string(), "10.0.0.0/8");
-    }
+/// Command line options.
+#[derive(Debug, PartialEq)]
+struct Options {
+    command: Command,
+    /// Print the help text and exit.
+    help: bool,
+    /// Suppress non-fatal diagnostics.
+    quiet: bool,
+    /// Read from stdin even when it's a terminal.
+    stdin: bool,
+    /// When to color diff output.
+    color: ColorChoice,
+    /// A saved result to print a unified diff against.
+    diff_against: Option<String>,
+    /// Where to remember results between runs.
+    state_dir: Option<String>,
+    /// The name of the list being tracked in the state directory.
+    name: Option<String>,
+    /// The current contents of the set that `lfc delta` or `lfc plan` updates.
+    current: Option<String>,
+    /// The name of the ipset that `lfc delta` updates.
+    set: Option<String>,
+    /// The output format.
+    format: Option<String>,
+    /// How to write IPv6 networks in the output.
+    style: format::Style,
+    /// A script with input and output hooks.
+    script: Option<String>,
+    /// Where to POST a summary when the state directory shows a change.
+    webhook: Option<String>,
+    /// The key for signing webhook bodies.
+    webhook_secret: Option<String>,
+    /// The file with the key for signing webhook bodies.
+    webhook_secret_file: Option<String>,
+    /// How many times to retry a failed webhook.
+    webhook_retries: u32,
+    /// Log each change to syslog.
+    syslog: bool,
+    /// Where to write Prometheus metrics.
+    metrics_file: Option<String>,
+    /// Where to write the result instead of stdout.
+    output: Option<String>,
+    /// How often to aggregate the input again.
+    refresh: Option<Duration>,
+    /// When entries have to expire by to be left out, instead of now.
+    now: Option<u64>,
+    /// How long after its timestamp an entry expires.
+    expire_after: Option<Duration>,
+    /// Where to get the RPKI VRPs to check the result against.
+    rpki: Option<String>,
+    /// The AS that announces the networks.
+    origin: Option<u32>,
+    /// Leave RPKI invalid networks out of the result.
+    rpki_drop: bool,
+    /// Where to cache results.
+    cache_dir: Option<String>,
+    /// Where to keep downloaded data.
+    data_dir: Option<String>,
+    /// What to do with IPv4-mapped IPv6 networks.
+    mapped: Mapped,
+    /// What to do with the IPv4 space in 6to4 and Teredo networks.
+    embedded: Embedded,
+    /// The NAT64 prefix for `lfc nat64`.
+    nat64_prefix: Option<Ipv6Net>,
+    /// Translate NAT64 networks back to IPv4.
+    reverse: bool,
+    /// The registries whose space `--rir` keeps.
+    rirs: Vec<delegated::Rir>,
+    /// The kinds of special-purpose space `--drop` leaves out.
+    drop: Vec<bogons::Category>,
+    /// The martians lists `--drop-bogons` leaves out.
+    drop_bogons: Vec<String>,
+    /// How many networks `--sample` keeps.
+    sample: Option<usize>,
+    /// Pick networks for `--sample` by how many addresses they have.
+    sample_weighted: bool,
+    /// The prefix length `lfc explode` splits networks into.
+    explode_to: Option<u8>,
+    /// The most networks `lfc explode` prints.
+    max_output: Option<usize>,
+    /// The longest IPv4 prefix `lfc anonymize` leaves, or the shortest
+    /// `lfc lint` accepts.
+    len_v4: Option<u8>,
+    /// The longest IPv6 prefix `lfc anonymize` leaves, or the shortest
+    /// `lfc lint` accepts.
+    len_v6: Option<u8>,
+    /// The most addresses `lfc hosts` prints.
+    max_hosts: Option<usize>,
+    /// Write skeleton zone files in `lfc ptr-zones`.
+    skeleton: bool,
+    /// The provider `lfc push` updates a list at.
+    provider: Option<String>,
+    /// The list `lfc push` updates, or the lists `lfc resolve` resolves,
+    /// as NAME=FILE:PRIORITY.
+    lists: Vec<String>,
+    /// The feed `lfc fetch` downloads.
+    feed: Option<String>,
+    /// The ASes whose prefixes `lfc fetch ripestat` downloads.
+    asns: Vec<u32>,
+    /// When `lfc fetch ripestat` starts looking at announcements.
+    since: Option<String>,
+    /// When `lfc fetch ripestat` stops looking at announcements.
+    until: Option<String>,
+    /// The IRR server `lfc irr-check` asks.
+    irr_server: Option<String>,
+    /// The IRR databases `lfc irr-check` asks about.
+    irr_sources: Vec<String>,
+    /// What `lfc geoip` does: update or status.
+    geoip_action: Option<String>,
+    /// The GeoIP.conf with the MaxMind license key.
+    geoip_config: Option<String>,
+    /// The Fastly service whose ACL `lfc push fastly` updates.
+    service: Option<String>,
+    /// The Fastly ACL `lfc push fastly` updates.
+    acl: Option<String>,
+    /// The Akamai network list `lfc push akamai` updates.
+    list_id: Option<String>,
+    /// How `lfc push akamai` updates the list.
+    akamai_mode: Option<push::akamai::Mode>,
+    /// The Akamai network `lfc push akamai` activates the list on.
+    akamai_activate: Option<push::akamai::Network>,
+    /// Only print what `lfc push` would change.
+    dry_run: bool,
+    /// What to do with IPv6 zone IDs.
+    zones: zones::Zones,
+    /// The prefix length for bare IPv4 addresses.
+    default_prefix_len_v4: Option<u8>,
+    /// The prefix length for bare IPv6 addresses.
+    default_prefix_len_v6: Option<u8>,
+    /// Clear host bits instead of rejecting networks that have them.
+    truncate_host_bits: bool,
+    /// Reject networks that aren't written canonically.
+    strict: bool,
+    /// How much of a merged network can be space that wasn't in the input,
+    /// as a fraction.
+    merge_slack: f64,
+    /// The most of either family's address space the result can cover, as
+    /// a percentage.
+    max_coverage: Option<f64>,
+    /// The shortest IPv4 network the result can have.
+    min_prefix_len_v4: Option<u8>,
+    /// The shortest IPv6 network the result can have.
+    min_prefix_len_v6: Option<u8>,
+    /// Write a result over `--max-coverage` or under the minimum prefix
+    /// lengths anyway.
+    force: bool,
+    /// What to report about the result before writing it.
+    report: Vec<Report>,
+    /// Region lists that merged networks can't straddle.
+    no_merge_across: Vec<String>,
+    /// Keep special-purpose space from merging with the space next to it.
+    scope_aware: bool,
+    /// Keep merged networks from straddling RIR allocations.
+    respect_allocations: bool,
+    /// Leave adjacent networks unmerged.
+    no_merge_adjacent: bool,
+    /// Only remove duplicates, leaving networks inside others too.
+    dedupe_only: bool,
+    /// Keep networks inside others, but still merge neighbors.
+    keep_structure: bool,
+    /// A set file to add to the input.
+    load_set: Option<String>,
+    /// An earlier result for `--format html-report` to compare with.
+    baseline: Option<String>,
+    /// What to note about each network in `--format markdown`.
+    annotate: Option<Annotation>,
+    /// Where `--annotate asn` and `lfc by-asn` look up origins.
+    asn_provider: Option<asn::Provider>,
+    /// The dotted key `--format toml` puts the networks under.
+    toml_key: Option<String>,
+    /// Add stats to `--format toml`.
+    toml_stats: bool,
+    /// The root element's name for `--format xml`.
+    xml_root: Option<String>,
+    /// The name of each network's element for `--format xml`.
+    xml_element: Option<String>,
+    /// The attribute `--format xml` puts networks in, instead of text.
+    xml_attribute: Option<String>,
+    /// Split IPv6 addresses into halves in `--format intrange`.
+    intrange_split: bool,
+    /// Write hexadecimal in `--format bits`.
+    bits_hex: bool,
+    /// The option `--format dnsmasq` writes.
+    dnsmasq_option: Option<String>,
+    /// The action `--format unbound`, `istio`, `windows-firewall`,
+    /// `iproute`, or `bsd-route` takes.
+    action: Option<String>,
+    /// The networks `--format bind-acl` negates.
+    except: Option<String>,
+    /// The RBAC principal `--format envoy` writes.
+    envoy_principal: Option<String>,
+    /// Where `--format bpf-map`'s maps are pinned.
+    bpf_pin: Option<String>,
+    /// The value `--format bpf-map` gives each network, in hex.
+    bpf_value: Option<String>,
+    /// The language `--format codegen` writes.
+    lang: Option<String>,
+    /// The family of the set `--format firewalld` writes.
+    firewalld_family: Option<String>,
+    /// The direction `--format windows-firewall`'s rules match.
+    windows_direction: Option<String>,
+    /// The least length `--format frr`'s entries match.
+    frr_ge: Option<u8>,
+    /// The greatest length `--format frr`'s entries match.
+    frr_le: Option<u8>,
+    /// The routing table `--format iproute` adds routes to.
+    table: Option<String>,
+    /// The labels of the workloads `--format istio` applies to.
+    istio_selector: Vec<String>,
+    /// Match remote addresses in `--format istio`.
+    istio_remote: bool,
+    /// Where to save the result as a set file.
+    save_set: Option<String>,
+    /// How many networks to generate.
+    count: Option<usize>,
+    /// What kind of networks `lfc bench` generates.
+    profile: synth::Profile,
+    /// Which families `lfc gen` generates.
+    family: synth::FamilyChoice,
+    /// The prefix lengths `lfc gen` generates, or `--format openbgpd`'s
+    /// entries match.
+    prefix_lengths: Option<(u8, u8)>,
+    /// The seed for generating networks.
+    seed: Option<u64>,
+    /// Sort the input on disk.
+    low_memory: bool,
+    /// The Unix domain socket that `lfc serve` listens on.
+    unix: Option<String>,
+    /// The TCP address that `lfc serve` listens on for REST requests.
+    listen: Option<String>,
+    /// Files to read networks from.
+    files: Vec<String>,
+}
 
-    #[test]
-    fn test_parse_nets_with_empty_lines() {
-        let input = "192.168.1.0/24\n\n10.0.0.0/8\n\n\n172.16.0.0/12";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 3);
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            command: Command::default(),
+            help: false,
+            quiet: false,
+            stdin: false,
+            color: ColorChoice::default(),
+            diff_against: None,
+            state_dir: None,
+            name: None,
+            current: None,
+            set: None,
+            format: None,
+            style: format::Style::default(),
+            script: None,
+            webhook: None,
+            webhook_secret: None,
+            webhook_secret_file: None,
+            webhook_retries: 3,
+            syslog: false,
+            metrics_file: None,
+            output: None,
+            refresh: None,
+            now: None,
+            expire_after: None,
+            rpki: None,
+            origin: None,
+            rpki_drop: false,
+            cache_dir: None,
+            data_dir: None,
+            mapped: Mapped::Keep,
+            embedded: Embedded::Ignore,
+            nat64_prefix: None,
+            reverse: false,
+            rirs: Vec::new(),
+            drop: Vec::new(),
+            drop_bogons: Vec::new(),
+            sample: None,
+            sample_weighted: false,
+            explode_to: None,
+            max_output: None,
+            len_v4: None,
+            len_v6: None,
+            max_hosts: None,
+            skeleton: false,
+            provider: None,
+            lists: Vec::new(),
+            feed: None,
+            asns: Vec::new(),
+            since: None,
+            until: None,
+            irr_server: None,
+            irr_sources: Vec::new(),
+            geoip_action: None,
+            geoip_config: None,
+            service: None,
+            acl: None,
+            list_id: None,
+            akamai_mode: None,
+            akamai_activate: None,
+            dry_run: false,
+            zones: zones::Zones::default(),
+            default_prefix_len_v4: None,
+            default_prefix_len_v6: None,
+            truncate_host_bits: false,
+            strict: false,
+            merge_slack: 0.0,
+            max_coverage: None,
+            min_prefix_len_v4: None,
+            min_prefix_len_v6: None,
+            force: false,
+            report: Vec::new(),
+            no_merge_across: Vec::new(),
+            scope_aware: false,
+            respect_allocations: false,
+            no_merge_adjacent: false,
+            dedupe_only: false,
+            keep_structure: false,
+            load_set: None,
+            baseline: None,
+            annotate: None,
+            asn_provider: None,
+            toml_key: None,
+            toml_stats: false,
+            xml_root: None,
+            xml_element: None,
+            xml_attribute: None,
+            intrange_split: false,
+            bits_hex: false,
+            dnsmasq_option: None,
+            action: None,
+            except: None,
+            envoy_principal: None,
+            bpf_pin: None,
+            bpf_value: None,
+            lang: None,
+            firewalld_family: None,
+            windows_direction: None,
+            frr_ge: None,
+            frr_le: None,
+            table: None,
+            istio_selector: Vec::new(),
+            istio_remote: false,
+            save_set: None,
+            count: None,
+            profile: synth::Profile::default(),
+            family: synth::FamilyChoice::default(),
+            prefix_lengths: None,
+            seed: None,
+            low_memory: false,
+            unix: None,
+            listen: None,
+            files: Vec::new(),
+        }
     }
+}
 
-    #[test]
-    fn test_parse_nets_ipv6() {
-        let input = "2001:db8::/32\nfe80::/10";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].to_string(), "2001:db8::/32");
-        assert_eq!(result[1].to_string(), "fe80::/10");
+/// Parses the command line arguments (excluding the program name).
+///
+/// Options that take a value accept it either as the next argument or after
+/// an `=`, like `--color never` or `--color=never`.
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("option '{}' requires a value", name))
+        };
+        match name {
+            "-h" | "--help" => options.help = true,
+            "-q" | "--quiet" => options.quiet = true,
+            "--stdin" => options.stdin = true,
+            "--color" => options.color = value()?.parse()?,
+            "--diff-against" => options.diff_against = Some(value()?),
+            "--state-dir" => options.state_dir = Some(value()?),
+            "--name" => options.name = Some(value()?),
+            "--current" => options.current = Some(value()?),
+            "--set" => options.set = Some(value()?),
+            "--format" => options.format = Some(value()?),
+            "--ipv6-form" => {
+                options.style.ipv6_form = match value()?.as_str() {
+                    "compressed" => Ipv6Form::Compressed,
+                    "expanded" => Ipv6Form::Expanded,
+                    form => {
+                        return Err(format!(
+                            "invalid --ipv6-form '{}' (expected compressed or expanded)",
+                            form
+                        ));
+                    }
+                }
+            }
+            "--ipv6-case" => {
+                options.style.hex_case = match value()?.as_str() {
+                    "lower" => HexCase::Lower,
+                    "upper" => HexCase::Upper,
+                    case => {
+                        return Err(format!(
+                            "invalid --ipv6-case '{}' (expected lower or upper)",
+                            case
+                        ));
+                    }
+                }
+            }
+            "--script" => options.script = Some(value()?),
+            "--webhook" => options.webhook = Some(value()?),
+            "--syslog" => options.syslog = true,
+            "--unix" => options.unix = Some(value()?),
+            "--listen" => options.listen = Some(value()?),
+            "--cache-dir" => options.cache_dir = Some(value()?),
+            "--data-dir" => options.data_dir = Some(value()?),
+            "--normalize-mapped" => {
+                options.mapped = match inline_value.as_deref() {
+                    None | Some("v4") => Mapped::ToV4,
+                    Some("v6") => Mapped::ToV6,
+                    Some(to) => {
+                        return Err(format!(
+                            "invalid --normalize-mapped '{}' (expected v4 or v6)",
+                            to
+                        ));
+                    }
+                }
+            }
+            "--extract-embedded" => {
+                options.embedded = match inline_value.as_deref() {
+                    None | Some("also") => Embedded::Also,
+                    Some("instead") => Embedded::Instead,
+                    Some(how) => {
+                        return Err(format!(
+                            "invalid --extract-embedded '{}' (expected also or instead)",
+                            how
+                        ));
+                    }
+                }
+            }
+            "--zones" => options.zones = value()?.parse()?,
+            "--v4" => {
+                let len = value()?;
+                let len = len.strip_prefix('/').unwrap_or(&len);
+                options.len_v4 = Some(parse_prefix_len(name, len, 32)?)
+            }
+            "--v6" => {
+                let len = value()?;
+                let len = len.strip_prefix('/').unwrap_or(&len);
+                options.len_v6 = Some(parse_prefix_len(name, len, 128)?)
+            }
+            "--default-prefixlen-v4" => {
+                options.default_prefix_len_v4 = Some(parse_prefix_len(name, &value()?, 32)?)
+            }
+            "--default-prefixlen-v6" => {
+                options.default_prefix_len_v6 = Some(parse_prefix_len(name, &value()?, 128)?)
+            }
+            "--load-set" => options.load_set = Some(value()?),
+            "--baseline" => options.baseline = Some(value()?),
+            "--annotate" => {
+                // The kind is optional, so the next argument is only taken
+                // as one if it names one.
+                let kind = inline_value
+                    .clone()
+                    .or_else(|| args.next_if(|next| next.parse::<Annotation>().is_ok()));
+                options.annotate = Some(match kind {
+                    Some(kind) => kind.parse()?,
+                    None => Annotation::Special,
+                });
+            }
+            "--provider" => options.asn_provider = Some(value()?.parse()?),
+            "--toml-key" => options.toml_key = Some(value()?),
+            "--toml-stats" => options.toml_stats = true,
+            "--xml-root" => options.xml_root = Some(value()?),
+            "--xml-element" => options.xml_element = Some(value()?),
+            "--xml-attribute" => options.xml_attribute = Some(value()?),
+            "--intrange-split" => options.intrange_split = true,
+            "--bits-hex" => options.bits_hex = true,
+            "--dnsmasq-option" => options.dnsmasq_option = Some(value()?),
+            "--action" => options.action = Some(value()?),
+            "--except" => options.except = Some(value()?),
+            "--envoy-principal" => options.envoy_principal = Some(value()?),
+            "--bpf-pin" => options.bpf_pin = Some(value()?),
+            "--bpf-value" => options.bpf_value = Some(value()?),
+            "--lang" => options.lang = Some(value()?),
+            "--firewalld-family" => options.firewalld_family = Some(value()?),
+            "--windows-direction" => options.windows_direction = Some(value()?),
+            flag @ ("--frr-ge" | "--frr-le") => {
+                let len = value()?;
+                let len = Some(
+                    len.strip_prefix('/')
+                        .unwrap_or(&len)
+                        .parse()
+                        .ok()
+                        .filter(|&len| len <= 128)
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid {} '{}' (expected a prefix length like /24)",
+                                flag, len
+                            )
+                        })?,
+                );
+                if flag == "--frr-ge" {
+                    options.frr_ge = len;
+                } else {
+                    options.frr_le = len;
+                }
+            }
+            "--table" => options.table = Some(value()?),
+            "--istio-selector" => options.istio_selector.push(value()?),
+            "--istio-remote" => options.istio_remote = true,
+            "--save-set" => options.save_set = Some(value()?),
+            "--low-memory" => options.low_memory = true,
+            "--truncate-host-bits" => options.truncate_host_bits = true,
+            "--strict" => options.strict = true,
+            "--no-merge-across" => options.no_merge_across.push(value()?),
+            "--respect-allocations" => options.respect_allocations = true,
+            "--scope-aware" => options.scope_aware = true,
+            "--rir" => {
+                for rir in value()?.split(',') {
+                    options.rirs.push(rir.trim().parse()?);
+                }
+            }
+            "--drop" => options.drop.extend(bogons::parse(&value()?)?),
+            "--drop-bogons" => options.drop_bogons.push(value()?),
+            "--no-merge-adjacent" => options.no_merge_adjacent = true,
+            "--dedupe-only" => options.dedupe_only = true,
+            "--keep-structure" => options.keep_structure = true,
+            "--merge-slack" => {
+                let slack = value()?;
+                options.merge_slack = slack
+                    .strip_suffix('%')
+                    .unwrap_or(&slack)
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|percent| (0.0..100.0).contains(percent))
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid --merge-slack '{}' (expected a percentage below 100, like 5%)",
+                            slack
+                        )
+                    })?
+                    / 100.0
+            }
+            "--max-coverage" => {
+                let coverage = value()?;
+                options.max_coverage = Some(
+                    coverage
+                        .strip_suffix('%')
+                        .unwrap_or(&coverage)
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid --max-coverage '{}' (expected a percentage, like 5%)",
+                                coverage
+                            )
+                        })?,
+                )
+            }
+            "--min-prefixlen-v4" => {
+                options.min_prefix_len_v4 = Some(parse_prefix_len(name, &value()?, 32)?)
+            }
+            "--min-prefixlen-v6" => {
+                options.min_prefix_len_v6 = Some(parse_prefix_len(name, &value()?, 128)?)
+            }
+            "--force" => options.force = true,
+            "--report" => options.report.push(value()?.parse()?),
+            "--reverse" => options.reverse = true,
+            "--to" => {
+                let to = value()?;
+                options.explode_to = Some(
+                    to.strip_prefix('/')
+                        .unwrap_or(&to)
+                        .parse()
+                        .ok()
+                        .filter(|&len| len <= 128)
+                        .ok_or_else(|| {
+                            format!("invalid --to '{}' (expected a prefix length like /24)", to)
+                        })?,
+                )
+            }
+            "--max" => {
+                let max = value()?;
+                options.max_hosts = Some(
+                    max.replace('_', "")
+                        .parse()
+                        .map_err(|_| format!("invalid --max '{}'", max))?,
+                )
+            }
+            "--skeleton" => options.skeleton = true,
+            "--list" => options.lists.push(value()?),
+            "--service" => options.service = Some(value()?),
+            "--asn" => {
+                for asn in value()?.split(',') {
+                    options
+                        .asns
+                        .push(rpki::parse_asn(asn.trim()).ok_or_else(|| {
+                            format!("invalid --asn '{}' (expected like AS64500)", asn)
+                        })?);
+                }
+            }
+            flag @ ("--since" | "--until") => {
+                let time = value()?;
+                let time = fetch::parse_time(&time).map(str::to_string).ok_or_else(|| {
+                    format!(
+                        "invalid {} '{}' (expected a date like 2024-01-31 or 2024-01-31T12:00:00Z)",
+                        flag, time
+                    )
+                })?;
+                if flag == "--since" {
+                    options.since = Some(time);
+                } else {
+                    options.until = Some(time);
+                }
+            }
+            "--irr-server" => options.irr_server = Some(value()?),
+            "--geoip-config" => options.geoip_config = Some(value()?),
+            "--irr-sources" => {
+                options.irr_sources = value()?
+                    .split(',')
+                    .map(|source| source.trim().to_uppercase())
+                    .filter(|source| !source.is_empty())
+                    .collect()
+            }
+            "--acl" => options.acl = Some(value()?),
+            "--list-id" => options.list_id = Some(value()?),
+            "--akamai-mode" => options.akamai_mode = Some(value()?.parse()?),
+            "--akamai-activate" => options.akamai_activate = Some(value()?.parse()?),
+            "--dry-run" => options.dry_run = true,
+            "--max-output" => {
+                let max = value()?;
+                options.max_output = Some(
+                    max.replace('_', "")
+                        .parse()
+                        .map_err(|_| format!("invalid --max-output '{}'", max))?,
+                )
+            }
+            "--prefix" => {
+                let prefix = value()?;
+                let parsed = prefix
+                    .parse()
+                    .ok()
+                    .filter(space::is_nat64_prefix)
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid NAT64 prefix '{}' (expected an IPv6 /32, /40, /48, /56, /64, or /96)",
+                            prefix
+                        )
+                    })?;
+                options.nat64_prefix = Some(parsed)
+            }
+            "--metrics-file" => options.metrics_file = Some(value()?),
+            "--webhook-secret" => options.webhook_secret = Some(value()?),
+            "--webhook-secret-file" => options.webhook_secret_file = Some(value()?),
+            "--profile" => options.profile = value()?.parse()?,
+            "--family" => options.family = value()?.parse()?,
+            "--prefixlen-range" => options.prefix_lengths = Some(synth::parse_lengths(&value()?)?),
+            "--seed" => {
+                let seed = value()?;
+                options.seed = Some(
+                    seed.parse()
+                        .map_err(|_| format!("invalid seed '{}'", seed))?,
+                )
+            }
+            "--sample" => {
+                let count = value()?;
+                options.sample = Some(
+                    count
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| format!("invalid --sample '{}'", count))?,
+                )
+            }
+            "--sample-weighted" => options.sample_weighted = true,
+            "--count" => {
+                let count = value()?;
+                // Allow 5_000_000 the way Rust does.
+                options.count = Some(
+                    count
+                        .replace('_', "")
+                        .parse()
+                        .map_err(|_| format!("invalid count '{}'", count))?,
+                )
+            }
+            "--output" => options.output = Some(value()?),
+            "--refresh" => options.refresh = Some(clock::parse_interval(&value()?)?),
+            "--now" => {
+                let now = value()?;
+                options.now = Some(clock::parse_timestamp(&now).ok_or_else(|| {
+                    format!(
+                        "invalid --now '{}' (expected like 2025-09-19T12:34:56Z)",
+                        now
+                    )
+                })?)
+            }
+            "--expire-after" => options.expire_after = Some(clock::parse_interval(&value()?)?),
+            "--rpki" => options.rpki = Some(value()?),
+            "--origin" => {
+                let origin = value()?;
+                options.origin = Some(rpki::parse_asn(&origin).ok_or_else(|| {
+                    format!("invalid --origin '{}' (expected like AS64500)", origin)
+                })?)
+            }
+            "--rpki-drop" => options.rpki_drop = true,
+            "--webhook-retries" => {
+                let retries = value()?;
+                options.webhook_retries = retries
+                    .parse()
+                    .map_err(|_| format!("invalid number of retries '{}'", retries))?
+            }
+            _ if options.command == Command::Aggregate
+                && options.files.is_empty()
+                && Command::from_name(name).is_some() =>
+            {
+                options.command = Command::from_name(name).unwrap()
+            }
+            _ if options.command == Command::Push
+                && options.provider.is_none()
+                && !arg.starts_with('-') =>
+            {
+                options.provider = Some(arg)
+            }
+            _ if options.command == Command::Fetch
+                && options.feed.is_none()
+                && !arg.starts_with('-') =>
+            {
+                options.feed = Some(arg)
+            }
+            _ if options.command == Command::Geoip
+                && options.geoip_action.is_none()
+                && !arg.starts_with('-') =>
+            {
+                options.geoip_action = Some(arg)
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unrecognized argument '{}'", arg));
+            }
+            _ => options.files.push(arg),
+        }
     }
+    Ok(options)
+}
 
-    #[test]
-    fn test_parse_nets_mixed_ipv4_ipv6() {
-        let input = "192.168.1.0/24\n2001:db8::/32";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].to_string(), "192.168.1.0/24");
-        assert_eq!(result[1].to_string(), "2001:db8::/32");
+/// Parses the value of an option that's a prefix length no longer than
+/// `longest`.
+fn parse_prefix_len(name: &str, value: &str, longest: u8) -> Result<u8, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&len| len <= longest)
+        .ok_or_else(|| format!("invalid {} '{}' (expected 0 to {})", name, value, longest))
+}
+
+/// Opens all of the input named by the options.
+///
+/// Files are read in the order given. Stdin is read if `--stdin` was given or
+/// if there are no files. If stdin is an interactive terminal and the user
+/// didn't explicitly ask for it, print a hint so it doesn't look like we're
+/// hanging.
+fn read_input(options: &Options) -> lfc::Result<Vec<Box<dyn InputSource>>> {
+    // Opening a URL is mostly waiting for it to download, so the sources
+    // are opened together. If several can't be, the first one's error is
+    // returned.
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    let mut inputs = in_parallel(&options.files, workers, |file| source::open(file))
+        .into_iter()
+        .collect::<lfc::Result<Vec<_>>>()?;
+
+    // A set file is input too, so it stands in for stdin.
+    if options.stdin || (options.files.is_empty() && options.load_set.is_none()) {
+        if !options.stdin && io::stdin().is_terminal() {
+            note!("reading from terminal; pipe a list or pass a file, Ctrl-D to end");
+        }
+        inputs.push(Box::new(FileSource::stdin()?));
+    }
+    // A line that isn't text would stop aggregating partway through, so
+    // it's reported now instead, while it can still be.
+    for input in &inputs {
+        if input.text().is_err()
+            && let Some(Err(err)) = input.records().find(Result::is_err)
+        {
+            return Err(err);
+        }
+    }
+    if options.zones == zones::Zones::Strip {
+        inputs = zones::strip(inputs)?;
     }
+    if options.strict {
+        check_strict(options, &inputs)?;
+    }
+    Ok(inputs)
+}
 
-    #[test]
-    #[should_panic(expected = "Unable to parse")]
-    fn test_parse_nets_invalid_input() {
-        let input = "not-an-ip-address";
-        parse_nets(input.lines());
+/// Reports every line of the inputs that `--strict` rejects, instead of
+/// stopping at the first one like parsing does, and returns an error if
+/// there were any.
+fn check_strict(options: &Options, inputs: &[Box<dyn InputSource>]) -> lfc::Result<()> {
+    let mut bad = 0;
+    for err in aggregator(options)
+        .map_err(lfc::Error::Config)?
+        .parser()
+        .records(input_records(inputs))
+        .filter_map(Result::err)
+    {
+        eprintln!("error: {}", err);
+        bad += 1;
+    }
+    match bad {
+        0 => Ok(()),
+        1 => Err(lfc::Error::Config("--strict found 1 bad line".to_string())),
+        _ => Err(lfc::Error::Config(format!(
+            "--strict found {} bad lines",
+            bad
+        ))),
     }
+}
 
-    #[test]
-    #[should_panic(expected = "Unable to parse")]
-    fn test_parse_nets_ip_without_cidr() {
-        let input = "192.168.1.1";
-        parse_nets(input.lines());
+/// Returns the records of all of the inputs, one after the other, leaving
+/// out the entries that have expired and the timestamps of the rest.
+fn input_records<'a, I>(inputs: I) -> impl Iterator<Item = Record<'a>>
+where
+    I: IntoIterator<Item = &'a Box<dyn InputSource>>,
+{
+    inputs
+        .into_iter()
+        .flat_map(|input| input.records())
+        .map(|record| record.unwrap_or_else(|err| fail(err)))
+        .filter_map(unexpired)
+}
+
+/// `--now` and `--expire-after` in seconds, for [`unexpired`].
+static EXPIRY: OnceLock<(Option<u64>, u64)> = OnceLock::new();
+
+/// The soonest time an entry that hasn't expired yet will, for
+/// [`take_next_expiry`], or u64::MAX if none has a timestamp.
+static NEXT_EXPIRY: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Returns the record with just its network, or `None` if it's expired.
+fn unexpired(record: Record) -> Option<Record> {
+    let &(now, after) = EXPIRY.get_or_init(|| (None, 0));
+    let Some((text, expires)) = expiry::split(record.text, after) else {
+        return Some(record);
+    };
+    if expires <= now.unwrap_or_else(clock::now) {
+        return None;
     }
+    NEXT_EXPIRY.fetch_min(expires, Ordering::Relaxed);
+    Some(Record { text, ..record })
+}
 
-    #[test]
-    fn test_parse_nets_empty_input() {
-        let input = "";
-        let result = parse_nets(input.lines());
-        assert_eq!(result.len(), 0);
+/// Returns when the next entry read since the last call expires, if any
+/// of them will.
+fn take_next_expiry() -> Option<u64> {
+    Some(NEXT_EXPIRY.swap(u64::MAX, Ordering::Relaxed)).filter(|&next| next != u64::MAX)
+}
+
+/// Returns the lines of all of the inputs, one after the other.
+fn input_lines<'a, I>(inputs: I) -> impl Iterator<Item = &'a str>
+where
+    I: IntoIterator<Item = &'a Box<dyn InputSource>>,
+{
+    input_records(inputs).map(|record| record.text)
+}
+
+/// Parses and aggregates all of the inputs like [`gather_lines`].
+///
+/// With more than one input, each is parsed and aggregated on its own by a
+/// small pool of worker threads, and then their results are aggregated
+/// together. Aggregating each input first means the final step only sees
+/// what's left of each one, which is usually much less.
+///
+/// Returns an error if any non-empty line cannot be parsed as a valid IP
+/// network. If several inputs have a bad line, the one from the first of
+/// them is returned, the same as parsing them one after the other.
+fn gather_inputs(
+    inputs: &[Box<dyn InputSource>],
+    aggregator: &Aggregator,
+) -> lfc::Result<Vec<IpNet>> {
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    gather_inputs_with(inputs, aggregator, workers)
+}
+
+/// Does the work of [`gather_inputs`] with up to `workers` threads.
+fn gather_inputs_with(
+    inputs: &[Box<dyn InputSource>],
+    aggregator: &Aggregator,
+    workers: usize,
+) -> lfc::Result<Vec<IpNet>> {
+    let workers = workers.min(inputs.len());
+    if workers <= 1 {
+        let result = aggregator.aggregate_records(input_records(inputs));
+        warn_truncated();
+        return result;
     }
 
-    #[test]
-    fn test_gather_overlapping_networks() {
-        let nets = vec![
-            "10.0.0.0/16".parse().unwrap(),
-            "10.0.1.0/24".parse().unwrap(),
-            "10.0.2.0/24".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "10.0.0.0/16");
+    // The parts are aggregated together at the end, and that's when any
+    // slack belongs.
+    let exact = aggregator.exact();
+    let parts = in_parallel(inputs, workers, |input| {
+        exact.aggregate_records(input_records([input]))
+    })
+    .into_iter()
+    .collect::<lfc::Result<Vec<_>>>();
+    warn_truncated();
+    Ok(aggregator.aggregate(parts?.iter().flatten()))
+}
+
+/// Returns the results of `work` on each of the items, in order. Up to
+/// `workers` threads each take the next item that's left until there are
+/// none.
+fn in_parallel<T, R, F>(items: &[T], workers: usize, work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = workers.min(items.len());
+    if workers <= 1 {
+        return items.iter().map(work).collect();
     }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break results;
+                        };
+                        results.push((index, work(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
 
-    #[test]
-    fn test_gather_adjacent_networks() {
-        let nets = vec![
-            "192.168.0.0/24".parse().unwrap(),
-            "192.168.1.0/24".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "192.168.0.0/23");
+/// Exits with a usage error.
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    eprintln!();
+    eprintln!("Usage: lfc [OPTIONS] [FILE]...");
+    eprintln!("       lfc diff [OPTIONS] OLD NEW");
+    eprintln!("       lfc delta --current CURRENT [OPTIONS] [FILE]...");
+    eprintln!("       lfc plan --current CURRENT [OPTIONS] [FILE]...");
+    eprintln!("       lfc serve [--unix PATH] [--listen ADDRESS] [OPTIONS] [FILE]...");
+    eprintln!("       lfc bench [--count N] [--profile PROFILE]");
+    eprintln!("       lfc gen [--count N] [--family FAMILY] [--prefixlen-range RANGE] [--seed S]");
+    eprintln!("       lfc selftest [--count N] [--seed S]");
+    eprintln!("       lfc formats");
+    eprintln!("       lfc nat64 [--prefix PREFIX] [--reverse] [OPTIONS] [FILE]...");
+    eprintln!("       lfc explode --to /LEN [--max-output N] [OPTIONS] [FILE]...");
+    eprintln!("       lfc anonymize [--v4 /LEN] [--v6 /LEN] [OPTIONS] [FILE]...");
+    eprintln!("       lfc hosts [--max N] [OPTIONS] [FILE]...");
+    eprintln!("       lfc info NETWORK...");
+    eprintln!("       lfc tui [OPTIONS] [FILE]...");
+    eprintln!("       lfc ptr-zones [--skeleton] [OPTIONS] [FILE]...");
+    eprintln!("       lfc push PROVIDER [--dry-run] [OPTIONS] [FILE]...");
+    eprintln!("       lfc fetch FEED [OPTIONS]");
+    eprintln!("       lfc irr-check --origin ASN [--irr-sources SOURCES] [OPTIONS] [FILE]...");
+    eprintln!("       lfc geoip update|status [--geoip-config FILE] [--data-dir DIR]");
+    eprintln!("       lfc by-country [--format json] [OPTIONS] [FILE]...");
+    eprintln!("       lfc by-asn [--format json] [OPTIONS] [FILE]...");
+    eprintln!("       lfc resolve --list NAME=FILE:PRIORITY... [--format json] [OPTIONS]");
+    eprintln!("       lfc lint [--v4 /LEN] [--v6 /LEN] [--format json] [OPTIONS] [FILE]...");
+    eprintln!();
+    eprintln!("For more information, try '--help'.");
+    std::process::exit(1);
+}
+
+/// Prints an error and exits.
+fn fail<E: std::fmt::Display>(err: E) -> ! {
+    eprintln!("error: {}", err);
+    std::process::exit(1);
+}
+
+/// Exits if writing to stdout failed for any reason other than the reader
+/// going away.
+fn check_output(result: io::Result<()>) {
+    written(result).unwrap_or_else(|err| fail(err));
+}
+
+/// Returns an error if writing failed for any reason other than the reader
+/// going away.
+fn written(result: io::Result<()>) -> Result<(), String> {
+    match result {
+        // It's not an error if whoever's reading our output stops early.
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err.to_string()),
+        _ => Ok(()),
     }
+}
 
-    #[test]
-    fn test_gather_four_adjacent_networks() {
-        let nets = vec![
-            "10.0.0.0/24".parse().unwrap(),
-            "10.0.1.0/24".parse().unwrap(),
-            "10.0.2.0/24".parse().unwrap(),
-            "10.0.3.0/24".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "10.0.0.0/22");
+/// Runs `lfc diff`.
+fn run_diff(options: &Options) {
+    let [old, new] = options.files.as_slice() else {
+        usage_error("diff needs exactly two files: OLD and NEW");
+    };
+    let aggregator = aggregator(options).unwrap_or_else(|err| fail(err));
+    let inputs = [old, new].map(|file| source::open(file).unwrap_or_else(|err| fail(err)));
+    if options.strict {
+        check_strict(options, &inputs).unwrap_or_else(|err| fail(err));
     }
+    let [old, new] = inputs.map(|input| {
+        aggregator
+            .aggregate_records(input_records([&input]))
+            .unwrap_or_else(|err| fail(err))
+    });
+    warn_truncated();
+    let changes = diff::diff(&old, &new);
 
-    #[test]
-    fn test_gather_non_adjacent_networks() {
-        let nets = vec![
-            "192.168.1.0/24".parse().unwrap(),
-            "192.168.3.0/24".parse().unwrap(),
-            "10.0.0.0/8".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 3);
-        assert!(result.contains(&"10.0.0.0/8".parse().unwrap()));
-        assert!(result.contains(&"192.168.1.0/24".parse().unwrap()));
-        assert!(result.contains(&"192.168.3.0/24".parse().unwrap()));
+    let stdout = io::stdout();
+    let color = options.color.enabled(stdout.is_terminal());
+    let mut out = io::BufWriter::new(stdout.lock());
+    let result = if color {
+        diff::render_pretty(&mut out, &changes, true)
+    } else {
+        diff::render_plain(&mut out, &changes)
+    };
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc delta`.
+fn run_delta(options: &Options) {
+    let Some(current) = &options.current else {
+        usage_error("delta needs --current FILE");
+    };
+    match options.format.as_deref() {
+        None | Some("ipset-delta") => {}
+        Some(format) => usage_error(&format!("unknown delta format '{}'", format)),
     }
 
-    #[test]
-    fn test_gather_duplicate_networks() {
-        let nets = vec![
-            "192.168.1.0/24".parse().unwrap(),
-            "192.168.1.0/24".parse().unwrap(),
-            "192.168.1.0/24".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "192.168.1.0/24");
+    let current = source::open(current).unwrap_or_else(|err| fail(err));
+    let text = current.text().unwrap_or_else(|err| fail(err));
+    let current = ipset::parse_current(text).unwrap_or_else(|err| fail(err));
+    let Some(name) = options.set.as_ref().or(current.name.as_ref()) else {
+        fail("the current set doesn't say what it's called; use --set NAME");
+    };
+
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let mut desired = gather_inputs(
+        &inputs,
+        &aggregator(options).unwrap_or_else(|err| fail(err)),
+    )
+    .unwrap_or_else(|err| fail(err));
+    if let Some(family) = current.family {
+        let before = desired.len();
+        desired.retain(|net| Family::of(net) == family);
+        if desired.len() != before {
+            note!(
+                "warning: skipping {} networks that aren't {} because {} is an {} set",
+                before - desired.len(),
+                family,
+                name,
+                family
+            );
+        }
     }
 
-    #[test]
-    fn test_gather_ipv6_adjacent() {
-        let nets = vec![
-            "2001:db8::/33".parse().unwrap(),
-            "2001:db8:8000::/33".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "2001:db8::/32");
+    let changes = diff::diff(&current.members, &desired);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    check_output(ipset::write_delta(&mut out, name, &changes).and_then(|_| out.flush()));
+}
+
+/// Runs `lfc plan`.
+fn run_plan(options: &Options) {
+    let Some(current) = &options.current else {
+        usage_error("plan needs --current FILE");
+    };
+    let current = source::open(current).unwrap_or_else(|err| fail(err));
+    let current = record_nets(input_records([&current]))
+        .collect::<lfc::Result<Vec<_>>>()
+        .unwrap_or_else(|err| fail(err));
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let desired = gather_inputs(
+        &inputs,
+        &aggregator(options).unwrap_or_else(|err| fail(err)),
+    )
+    .unwrap_or_else(|err| fail(err));
+    let plan = plan::Plan::new(&current, &desired);
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = match options.format.as_deref() {
+        None | Some("text") => plan.write_text(&mut out),
+        Some("json") => plan.write_json(&mut out),
+        Some(format) => usage_error(&format!("unknown plan format '{}'", format)),
+    };
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc serve`.
+fn run_serve(options: &Options) {
+    let passed = systemd::sockets().unwrap_or_else(|err| fail(err));
+    if options.unix.is_none() && options.listen.is_none() && passed.is_empty() {
+        usage_error("serve needs --unix PATH, --listen ADDRESS, or both");
     }
+    let hangups = hangup::hangups().unwrap_or_else(|err| fail(err));
+    let started = Instant::now();
+    let nets = if options.files.is_empty() && options.load_set.is_none() {
+        Vec::new()
+    } else {
+        let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+        aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err))
+    };
+    let name = options.name.as_deref().unwrap_or(state::DEFAULT_NAME);
+    state::check_name(name).unwrap_or_else(|err| fail(err));
+    let count = nets.len();
+    let store = Arc::new(serve::Store::new(name, nets, started.elapsed()));
 
-    #[test]
-    fn test_gather_ipv6_non_adjacent() {
-        let nets = vec![
-            "2001:db8::/32".parse().unwrap(),
-            "2001:dba::/32".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 2);
+    let mut servers = Vec::new();
+    if let Some(address) = &options.listen {
+        let listener = TcpListener::bind(address)
+            .unwrap_or_else(|err| fail(format!("unable to listen on {}: {}", address, err)));
+        note!("serving {} networks over HTTP on {}", count, address);
+        let store = Arc::clone(&store);
+        servers.push(thread::spawn(move || rest::run(listener, store)));
+    }
+    if let Some(path) = &options.unix {
+        servers.push(serve_unix(path, Arc::clone(&store), count));
+    }
+    for socket in passed {
+        let store = Arc::clone(&store);
+        servers.push(match socket {
+            systemd::Socket::Tcp(listener) => {
+                note!(
+                    "serving {} networks over HTTP on a socket from systemd",
+                    count
+                );
+                thread::spawn(move || rest::run(listener, store))
+            }
+            #[cfg(unix)]
+            systemd::Socket::Unix(listener) => {
+                note!("serving {} networks on a socket from systemd", count);
+                thread::spawn(move || serve::run(listener, store))
+            }
+        });
     }
 
-    #[test]
-    fn test_gather_mixed_ipv4_ipv6() {
-        let nets = vec![
-            "192.168.0.0/24".parse().unwrap(),
-            "192.168.1.0/24".parse().unwrap(),
-            "2001:db8::/33".parse().unwrap(),
-            "2001:db8:8000::/33".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 2);
-        assert!(result.contains(&"192.168.0.0/23".parse().unwrap()));
-        assert!(result.contains(&"2001:db8::/32".parse().unwrap()));
+    if let Err(err) = systemd::notify("READY=1") {
+        note!("warning: unable to tell systemd lfc is ready: {}", err);
     }
+    if let Some(interval) = systemd::watchdog() {
+        thread::spawn(move || {
+            loop {
+                thread::sleep(interval);
+                if let Err(err) = systemd::notify("WATCHDOG=1") {
+                    note!("warning: unable to ping the systemd watchdog: {}", err);
+                }
+            }
+        });
+    }
+    thread::scope(|scope| {
+        scope.spawn(move || reload_on_hangup(options, &store, &hangups));
+        for server in servers {
+            match server.join() {
+                Ok(result) => result.unwrap_or_else(|err| fail(err)),
+                Err(_) => fail("the server crashed"),
+            }
+        }
+    });
+}
 
-    #[test]
-    fn test_gather_empty() {
-        let nets = vec![];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 0);
+/// Reads the sources of `lfc serve` again into its list each time it's sent
+/// SIGHUP. Requests already being answered keep the list they started
+/// with, and the list stays as it was if the sources can't be read.
+fn reload_on_hangup(options: &Options, store: &serve::Store, hangups: &Receiver<()>) {
+    let name = options.name.as_deref().unwrap_or(state::DEFAULT_NAME);
+    while hangup::wait(hangups, None) {
+        if options.files.is_empty() && options.load_set.is_none() {
+            note!("SIGHUP: there are no sources to reload");
+            continue;
+        }
+        forget_allocations();
+        let started = Instant::now();
+        let inputs = match read_input(options) {
+            Ok(inputs) => inputs,
+            Err(err) => {
+                metrics::fetch_failed();
+                note!("error: {}; keeping the list as it was", err);
+                continue;
+            }
+        };
+        match aggregate_with_set(options, &inputs) {
+            Ok(nets) => {
+                note!("SIGHUP: reloaded {} networks", nets.len());
+                store.put(name, nets, started.elapsed());
+            }
+            Err(err) => note!("error: {}; keeping the list as it was", err),
+        }
     }
+}
 
-    #[test]
-    fn test_gather_single_network() {
-        let nets = vec!["10.0.0.0/8".parse().unwrap()];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "10.0.0.0/8");
+/// Starts answering requests on the Unix socket at the path.
+#[cfg(unix)]
+fn serve_unix(
+    path: &str,
+    store: Arc<serve::Store>,
+    count: usize,
+) -> thread::JoinHandle<io::Result<()>> {
+    let listener = serve::bind(Path::new(path)).unwrap_or_else(|err| fail(err));
+    note!("serving {} networks on {}", count, path);
+    thread::spawn(move || serve::run(listener, store))
+}
+
+#[cfg(not(unix))]
+fn serve_unix(
+    _path: &str,
+    _store: Arc<serve::Store>,
+    _count: usize,
+) -> thread::JoinHandle<io::Result<()>> {
+    fail("Unix domain sockets are only supported on Unix");
+}
+
+/// Runs `lfc bench`.
+fn run_bench(options: &Options) {
+    let count = options.count.unwrap_or(1_000_000);
+    let seed = options.seed.unwrap_or(synth::DEFAULT_SEED);
+    let mut text = String::new();
+    options
+        .profile
+        .generate(count, &mut synth::Rng::new(seed), &mut text);
+
+    let started = Instant::now();
+    let nets = parse_nets(text.lines()).unwrap_or_else(|err| fail(err));
+    let parsing = started.elapsed().as_secs_f64();
+    let started = Instant::now();
+    let result = gather(&nets);
+    let aggregating = started.elapsed().as_secs_f64();
+
+    let parser = if cfg!(feature = "fast-parse") {
+        "fast-parse"
+    } else {
+        "ipnet"
+    };
+    let megabytes = text.len() as f64 / 1e6;
+    let report = format!(
+        "\
+profile    {}
+parser     {}
+input      {} networks, {:.1} MB
+parse      {:.3} s, {:.2} M networks/s, {:.1} MB/s
+aggregate  {:.3} s, {:.2} M networks/s
+result     {} networks
+",
+        options.profile,
+        parser,
+        count,
+        megabytes,
+        parsing,
+        count as f64 / parsing / 1e6,
+        megabytes / parsing,
+        aggregating,
+        count as f64 / aggregating / 1e6,
+        result.len()
+    );
+    check_output(io::stdout().write_all(report.as_bytes()));
+}
+
+/// Runs `lfc gen`.
+fn run_gen(options: &Options) {
+    let random = synth::Random {
+        family: options.family,
+        lengths: options.prefix_lengths,
+    };
+    let mut rng = synth::Rng::new(options.seed.unwrap_or(synth::DEFAULT_SEED));
+    let nets = random
+        .networks(&mut rng)
+        .unwrap_or_else(|err| usage_error(&err));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = nets
+        .take(options.count.unwrap_or(100))
+        .try_for_each(|net| writeln!(out, "{}", net));
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc selftest`.
+fn run_selftest(options: &Options) {
+    let count = options.count.unwrap_or(10_000);
+    let seed = options.seed.unwrap_or_else(|| {
+        let started = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+        started.map_or(synth::DEFAULT_SEED, |elapsed| elapsed.as_nanos() as u64)
+    });
+    let result = selftest::run(count, seed);
+    let mut out = io::stdout().lock();
+    match result {
+        Ok(()) => check_output(writeln!(out, "ok: {} lists, seed {}", count, seed)),
+        Err(failure) => {
+            check_output(write!(out, "{}", failure));
+            fail(format!(
+                "self-test failed with seed {}; please report this with the output above",
+                seed
+            ));
+        }
     }
+}
 
-    #[test]
-    fn test_gather_complex_aggregation() {
-        let nets = vec![
-            "192.168.0.0/25".parse().unwrap(),
-            "192.168.0.128/25".parse().unwrap(),
-            "192.168.1.0/25".parse().unwrap(),
-            "192.168.1.128/25".parse().unwrap(),
-        ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 1);
-        assert_eq!(result[0].to_string(), "192.168.0.0/23");
-    }
-
-    #[test]
-    fn test_gather_alignment_issues() {
-        let nets = vec![
-            "30.0.33.0/24".parse().unwrap(),
-            "30.0.34.0/24".parse().unwrap(),
-            "10.0.7.0/24".parse().unwrap(),
-            "10.0.8.0/24".parse().unwrap(),
-            "10.0.9.0/24".parse().unwrap(),
-            "20.0.14.0/23".parse().unwrap(),
-            "20.0.16.0/23".parse().unwrap(),
-            "20.0.18.0/23".parse().unwrap(),
-            "20.0.20.0/23".parse().unwrap(),
-            "30.0.32.0/20".parse().unwrap(),
+/// Runs `lfc formats`.
+fn run_formats() {
+    let formats = format::Registry::default();
+    let width = formats
+        .iter()
+        .map(|formatter| formatter.name().len())
+        .max()
+        .unwrap_or(0);
+    let mut out = io::stdout().lock();
+    let result = formats.iter().try_for_each(|formatter| {
+        writeln!(
+            out,
+            "{:<width$}  {}",
+            formatter.name(),
+            formatter.description(),
+            width = width
+        )
+    });
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Returns everything besides the input itself that affects the result of
+/// aggregating it, for keying the cache.
+fn cache_context(options: &Options) -> Result<Vec<String>, String> {
+    let mut context = vec![format!("lfc {}", env!("CARGO_PKG_VERSION"))];
+    if options.mapped != Mapped::Keep {
+        context.push(format!("mapped {:?}", options.mapped));
+    }
+    if options.embedded != Embedded::Ignore {
+        context.push(format!("embedded {:?}", options.embedded));
+    }
+    if options.default_prefix_len_v4.is_some() || options.default_prefix_len_v6.is_some() {
+        context.push(format!(
+            "default prefix lengths {:?} {:?}",
+            options.default_prefix_len_v4, options.default_prefix_len_v6
+        ));
+    }
+    if options.merge_slack > 0.0 {
+        context.push(format!("merge slack {}", options.merge_slack));
+    }
+    if options.no_merge_adjacent {
+        context.push("no merge adjacent".to_string());
+    }
+    if options.dedupe_only {
+        context.push("dedupe only".to_string());
+    }
+    if options.keep_structure {
+        context.push("keep structure".to_string());
+    }
+    // The regions themselves, since a file of them can change.
+    if keeps_regions(options) {
+        context.push(format!("no merge across {:?}", no_merge_across(options)?));
+    }
+    // Without it, host bits are an error that a cached result would hide.
+    if options.truncate_host_bits {
+        context.push("truncate host bits".to_string());
+    }
+    Ok(context)
+}
+
+/// How many networks `--truncate-host-bits` cleared the host bits of, and
+/// where the first one was.
+static TRUNCATED: Mutex<(usize, Option<String>)> = Mutex::new((0, None));
+
+/// Counts a network whose host bits were cleared, for [`warn_truncated`].
+fn count_truncated(record: &Record, net: IpNet) {
+    let mut truncated = TRUNCATED.lock().unwrap();
+    truncated.0 += 1;
+    truncated
+        .1
+        .get_or_insert_with(|| format!("{} on {}", net, record.provenance));
+}
+
+/// Warns about the networks whose host bits were cleared since the last
+/// warning, if there were any.
+fn warn_truncated() {
+    let (count, first) = std::mem::take(&mut *TRUNCATED.lock().unwrap());
+    if let Some(first) = first {
+        note!(
+            "warning: cleared the host bits of {} network{}, starting with {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            first
+        );
+    }
+}
+
+/// Returns whether there are any regions that merged networks can't
+/// straddle.
+fn keeps_regions(options: &Options) -> bool {
+    !options.no_merge_across.is_empty() || options.respect_allocations || options.scope_aware
+}
+
+/// Returns the regions from every `--no-merge-across`, the special-purpose
+/// blocks with `--scope-aware`, and every RIR allocation with
+/// `--respect-allocations`.
+fn no_merge_across(options: &Options) -> Result<Vec<IpNet>, String> {
+    let mut regions = Vec::new();
+    for spec in &options.no_merge_across {
+        regions.extend(regions::load(spec)?);
+    }
+    if options.scope_aware {
+        regions.extend(regions::load("builtin:special")?);
+    }
+    if options.respect_allocations {
+        let mut allocations = ALLOCATIONS.lock().unwrap();
+        if allocations.is_none() {
+            *allocations = Some(delegated::allocations(options.data_dir.as_deref())?);
+        }
+        regions.extend(allocations.iter().flatten());
+    }
+    Ok(regions)
+}
+
+/// Every RIR allocation, loaded once however many aggregators need them,
+/// until [`forget_allocations`].
+static ALLOCATIONS: Mutex<Option<Vec<IpNet>>> = Mutex::new(None);
+
+/// Makes the next aggregator load the RIR allocations again, for reloading
+/// on SIGHUP.
+fn forget_allocations() {
+    *ALLOCATIONS.lock().unwrap() = None;
+}
+
+/// Reports the space that `--merge-slack` added to the list.
+fn report_filled(event: &Event) {
+    if let Event::Filled { gap, into } = event {
+        note!("merge slack added {} to make {}", gap, into);
+    }
+}
+
+/// The aggregation events, kept for `--format dot`.
+static EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+/// Keeps an aggregation event for `--format dot`.
+fn record_event(event: &Event) {
+    report_filled(event);
+    EVENTS.lock().unwrap().push(*event);
+}
+
+/// Returns whether the output is a graph of the aggregation, which needs
+/// its events.
+fn graphing(options: &Options) -> bool {
+    options.command == Command::Aggregate && options.format.as_deref() == Some("dot")
+}
+
+/// Returns the parser taking bare addresses as `--default-prefixlen-v4`
+/// and `--default-prefixlen-v6` say to.
+fn default_prefix_lens(options: &Options, mut parser: Parser) -> Parser {
+    if let Some(len) = options.default_prefix_len_v4 {
+        parser = parser.default_prefix_len(Family::V4, len);
+    }
+    if let Some(len) = options.default_prefix_len_v6 {
+        parser = parser.default_prefix_len(Family::V6, len);
+    }
+    parser
+}
+
+/// Returns an aggregator configured by the options, or an error if the
+/// regions it can't merge across can't be loaded.
+fn aggregator(options: &Options) -> Result<Aggregator, String> {
+    let parser = if options.truncate_host_bits {
+        Parser::new()
+            .host_bits(HostBits::Truncate)
+            .on_truncate(count_truncated)
+    } else {
+        Parser::new().host_bits(HostBits::Reject)
+    }
+    .strict(options.strict);
+    let parser = default_prefix_lens(options, parser);
+    let mut builder = AggregatorBuilder::new()
+        .mapped(options.mapped)
+        .embedded(options.embedded)
+        .merge_adjacent(!options.no_merge_adjacent && !options.dedupe_only)
+        .absorb_subnets(!options.dedupe_only && !options.keep_structure)
+        .merge_slack(options.merge_slack)
+        .no_merge_across(no_merge_across(options)?)
+        .parser(parser);
+    if graphing(options) {
+        builder = builder.on_event(record_event);
+    } else if options.merge_slack > 0.0 {
+        builder = builder.on_event(report_filled);
+    }
+    Ok(builder
+        .build()
+        .unwrap_or_else(|err| usage_error(&err.to_string())))
+}
+
+/// Parses and aggregates the input, on disk if `--low-memory` was given.
+fn aggregate(options: &Options, inputs: &[Box<dyn InputSource>]) -> Result<Vec<IpNet>, String> {
+    let aggregator = aggregator(options)?;
+    if !options.low_memory {
+        return gather_inputs(inputs, &aggregator).map_err(|err| err.to_string());
+    }
+    let mut error = None;
+    let nets = aggregator
+        .parser()
+        .records(input_records(inputs))
+        .map_while(|net| net.map_err(|err| error = Some(err)).ok())
+        .flat_map(|net| aggregator.prepare(net));
+    let result = spill::gather(nets);
+    warn_truncated();
+    if let Some(err) = error {
+        return Err(err.to_string());
+    }
+    let mut result = result.map_err(|err| format!("unable to sort on disk: {}", err))?;
+    // Sorting on disk only merges exactly.
+    if options.merge_slack > 0.0 {
+        result = aggregator.aggregate(&result);
+    }
+    Ok(result)
+}
+
+/// Parses and aggregates the input, using the cache directory if there is
+/// one.
+fn aggregate_cached(
+    options: &Options,
+    inputs: &[Box<dyn InputSource>],
+) -> Result<Vec<IpNet>, String> {
+    let Some(dir) = &options.cache_dir else {
+        return aggregate(options, inputs);
+    };
+    let dir = Path::new(dir);
+    let key = cache::key(input_lines(inputs), &cache_context(options)?);
+    if let Some(result) = cache::load(dir, &key).map_err(|err| err.to_string())? {
+        note!("using cached result {}", key);
+        return Ok(result);
+    }
+    let result = aggregate(options, inputs)?;
+    cache::save(dir, &key, &result).map_err(|err| err.to_string())?;
+    Ok(result)
+}
+
+/// Aggregates the inputs along with the `--load-set` file, if there is one,
+/// keeps the space that the `--rir` registries delegated, leaves out what
+/// `--drop` and `--drop-bogons` say to, and then keeps the `--sample`. A set
+/// file is already aggregated, so on its own it's used as is.
+fn aggregate_with_set(
+    options: &Options,
+    inputs: &[Box<dyn InputSource>],
+) -> Result<Vec<IpNet>, String> {
+    let result = match &options.load_set {
+        None => aggregate_cached(options, inputs)?,
+        Some(path) => {
+            let set = setfile::load(Path::new(path)).map_err(|err| err.to_string())?;
+            if inputs.is_empty() {
+                set
+            } else {
+                aggregator(options)?
+                    .aggregate(set.iter().chain(&aggregate_cached(options, inputs)?))
+            }
+        }
+    };
+    let result = drop_special(options, keep_rirs(options, result)?)?;
+    Ok(keep_sample(options, result))
+}
+
+/// Keeps the `--sample` of the networks, if there is one.
+fn keep_sample(options: &Options, nets: Vec<IpNet>) -> Vec<IpNet> {
+    let Some(count) = options.sample else {
+        return nets;
+    };
+    let mut rng = synth::Rng::new(options.seed.unwrap_or(synth::DEFAULT_SEED));
+    sample::sample(&nets, count, options.sample_weighted, &mut rng)
+}
+
+/// Keeps the parts of the networks that the `--rir` registries delegated,
+/// if there are any.
+fn keep_rirs(options: &Options, nets: Vec<IpNet>) -> Result<Vec<IpNet>, String> {
+    if options.rirs.is_empty() {
+        return Ok(nets);
+    }
+    let mut delegated = Vec::new();
+    for rir in &options.rirs {
+        delegated.extend(rir.delegations(options.data_dir.as_deref())?);
+    }
+    let kept = delegated::intersect(&nets, &delegated);
+    note!(
+        "--rir kept {} networks from {} in the result",
+        kept.len(),
+        nets.len()
+    );
+    Ok(kept)
+}
+
+/// Leaves the special-purpose space that `--drop` and `--drop-bogons`
+/// name out of the networks, if they name any.
+fn drop_special(options: &Options, nets: Vec<IpNet>) -> Result<Vec<IpNet>, String> {
+    if options.drop.is_empty() && options.drop_bogons.is_empty() {
+        return Ok(nets);
+    }
+    let mut blocks = bogons::blocks(&options.drop);
+    for spec in &options.drop_bogons {
+        blocks.extend(bogons::profile(spec, options.data_dir.as_deref())?);
+    }
+    let kept = bogons::drop(&nets, &blocks);
+    if kept != nets {
+        note!(
+            "dropping bogons left {} networks from {} in the result",
+            kept.len(),
+            nets.len()
+        );
+    }
+    Ok(kept)
+}
+
+/// Returns the formatter that `--format` picked, or exits if there isn't
+/// one by that name.
+fn output_format<'a>(formats: &'a format::Registry, options: &Options) -> &'a dyn Formatter {
+    let name = options.format.as_deref().unwrap_or("text");
+    formats.get(name).unwrap_or_else(|| {
+        usage_error(&format!(
+            "unknown format '{}'; `lfc formats` lists them",
+            name
+        ))
+    })
+}
+
+/// Writes the networks to stdout with the `--format` formatter, or the one
+/// [`output::configure`] builds for them from the options, if it takes any.
+fn print_nets(
+    options: &Options,
+    formatter: &dyn Formatter,
+    nets: &[IpNet],
+    inputs: &[Box<dyn InputSource>],
+) {
+    let context = output::Context {
+        result: nets,
+        inputs,
+    };
+    let configured = output::configure(options, &context).unwrap_or_else(|err| fail(err));
+    let formatter = configured.as_deref().unwrap_or(formatter);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    check_output(
+        formatter
+            .write(&mut out, nets, &options.style)
+            .and_then(|_| out.flush()),
+    );
+}
+
+/// Runs `lfc explode`.
+fn run_explode(options: &Options) {
+    let Some(to) = options.explode_to else {
+        usage_error("explode needs --to /LEN");
+    };
+    let max = options.max_output.unwrap_or(1_000_000);
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+
+    let mut total: u128 = 0;
+    for net in &nets {
+        if to > net.max_prefix_len() {
+            usage_error(&format!(
+                "--to /{} is longer than /{}, the longest {} prefix",
+                to,
+                net.max_prefix_len(),
+                Family::of(net)
+            ));
+        }
+        total = total.saturating_add(space::subnet_count(net, to));
+    }
+    if total > max as u128 {
+        fail(format!(
+            "exploding into /{}s would print {} networks, more than --max-output {}",
+            to,
+            space::Count(total),
+            max
+        ));
+    }
+    let exploded: Vec<IpNet> = nets
+        .iter()
+        .flat_map(|net| net.subnets(to.max(net.prefix_len())).unwrap())
+        .collect();
+    print_nets(options, formatter, &exploded, &inputs);
+}
+
+/// Returns the network around `net` that's no longer than `v4` or `v6`,
+/// for its family.
+fn anonymize(net: &IpNet, v4: u8, v6: u8) -> IpNet {
+    let longest = match net {
+        IpNet::V4(_) => v4,
+        IpNet::V6(_) => v6,
+    };
+    if net.prefix_len() <= longest {
+        return *net;
+    }
+    IpNet::new(net.addr(), longest).unwrap().trunc()
+}
+
+/// Runs `lfc anonymize`.
+fn run_anonymize(options: &Options) {
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let (v4, v6) = (options.len_v4.unwrap_or(24), options.len_v6.unwrap_or(48));
+    let widened = nets
+        .iter()
+        .filter(|net| anonymize(net, v4, v6) != **net)
+        .count();
+    let result = aggregator(options)
+        .unwrap_or_else(|err| fail(err))
+        .exact()
+        .aggregate(nets.iter().map(|net| anonymize(net, v4, v6)));
+    note!(
+        "anonymize: widened {} of {} networks to /{} or /{}",
+        widened,
+        nets.len(),
+        v4,
+        v6
+    );
+    print_nets(options, formatter, &result, &inputs);
+}
+
+/// Runs `lfc hosts`.
+fn run_hosts(options: &Options) {
+    if options.format.is_some() {
+        usage_error("hosts only prints plain addresses");
+    }
+    let max = options.max_hosts.unwrap_or(65_536);
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+
+    let total = nets.iter().fold(0u128, |total, net| {
+        total.saturating_add(space::subnet_count(net, net.max_prefix_len()))
+    });
+    if total > max as u128 {
+        fail(format!(
+            "the networks have {} addresses, more than --max {}",
+            space::Count(total),
+            max
+        ));
+    }
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = nets
+        .iter()
+        .flat_map(|net| net.subnets(net.max_prefix_len()).unwrap())
+        .try_for_each(|host| writeln!(out, "{}", options.style.address(host.addr())));
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc info`.
+fn run_info(options: &Options) {
+    if options.files.is_empty() {
+        usage_error("info needs at least one NETWORK");
+    }
+    let parser = Parser::new()
+        .default_prefix_len(Family::V4, options.default_prefix_len_v4.unwrap_or(32))
+        .default_prefix_len(Family::V6, options.default_prefix_len_v6.unwrap_or(128));
+    let nets: Vec<IpNet> = options
+        .files
+        .iter()
+        .map(|text| {
+            parser.parse(text).unwrap_or_else(|| {
+                usage_error(&format!("'{}' isn't an IP network or address", text))
+            })
+        })
+        .collect();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = nets.iter().enumerate().try_for_each(|(i, net)| {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        info::write(&mut out, net, &options.style)
+    });
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc ptr-zones`.
+fn run_ptr_zones(options: &Options) {
+    if options.format.is_some() {
+        usage_error("ptr-zones only prints zone names or zone files");
+    }
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = nets
+        .iter()
+        .flat_map(ptr::split)
+        .enumerate()
+        .try_for_each(|(i, zone)| {
+            if !options.skeleton {
+                return writeln!(out, "{}", ptr::name(&zone));
+            }
+            if i > 0 {
+                writeln!(out)?;
+            }
+            ptr::write_skeleton(&mut out, &zone, &options.style)
+        });
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc push`.
+fn run_push(options: &Options) {
+    if options.format.is_some() {
+        usage_error("push prints its changes like `lfc plan`");
+    }
+    let Some(provider) = &options.provider else {
+        usage_error("push needs a PROVIDER: cloudflare, fastly, or akamai");
+    };
+    let push: push::Push = match provider.as_str() {
+        "cloudflare" => {
+            let [list] = options.lists.as_slice() else {
+                usage_error("push cloudflare needs one --list NAME");
+            };
+            let cloudflare =
+                push::cloudflare::Cloudflare::from_env().unwrap_or_else(|err| fail(err));
+            Box::new(move |nets| cloudflare.push(list, nets, options.dry_run))
+        }
+        "fastly" => {
+            let (Some(service), Some(acl)) = (&options.service, &options.acl) else {
+                usage_error("push fastly needs --service SID and --acl NAME");
+            };
+            let fastly = push::fastly::Fastly::from_env(service).unwrap_or_else(|err| fail(err));
+            Box::new(move |nets| fastly.push(acl, nets, options.dry_run))
+        }
+        "akamai" => {
+            let Some(id) = &options.list_id else {
+                usage_error("push akamai needs --list-id ID");
+            };
+            let akamai = push::akamai::Akamai::from_env().unwrap_or_else(|err| fail(err));
+            let mode = options.akamai_mode.unwrap_or_default();
+            Box::new(move |nets| {
+                akamai.push(id, nets, mode, options.akamai_activate, options.dry_run)
+            })
+        }
+        _ => usage_error(&format!(
+            "unknown provider '{}'; try cloudflare, fastly, or akamai",
+            provider
+        )),
+    };
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    write_report(options, &nets);
+    check_limits(options, &nets).unwrap_or_else(|err| fail(err));
+    let plan = push(&nets).unwrap_or_else(|err| fail(err));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    check_output(plan.write_text(&mut out).and_then(|_| out.flush()));
+    if !options.dry_run {
+        note!(
+            "{} added, {} removed, {} unchanged",
+            plan.add.len(),
+            plan.remove.len(),
+            plan.unchanged
+        );
+    }
+}
+
+/// Runs `lfc irr-check`.
+fn run_irr_check(options: &Options) {
+    let Some(origin) = options.origin else {
+        usage_error("irr-check needs --origin to know whose route objects to check");
+    };
+    if options.format.is_some() {
+        usage_error("irr-check only prints the networks that don't match");
+    }
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let server = options.irr_server.as_deref().unwrap_or(irr::SERVER);
+    let routes = irr::routes(server, origin, &options.irr_sources).unwrap_or_else(|err| fail(err));
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let (mut registered, mut mismatched, mut unregistered) = (0, 0, 0);
+    let result = nets.iter().try_for_each(|net| {
+        let check = irr::check(net, &routes);
+        match check {
+            irr::Check::Registered => {
+                registered += 1;
+                return Ok(());
+            }
+            irr::Check::Unregistered => unregistered += 1,
+            _ => mismatched += 1,
+        }
+        writeln!(out, "{} {}", options.style.display(net), check)
+    });
+    check_output(result.and_then(|_| out.flush()));
+    note!(
+        "irr-check: {} registered, {} mismatched, {} unregistered, against {} route objects for AS{}",
+        registered,
+        mismatched,
+        unregistered,
+        routes.len(),
+        origin
+    );
+    if mismatched + unregistered > 0 {
+        fail(format!(
+            "{} networks don't match AS{}'s route objects",
+            mismatched + unregistered,
+            origin
+        ));
+    }
+}
+
+/// Runs `lfc geoip`.
+fn run_geoip(options: &Options) {
+    if !options.files.is_empty() || options.stdin {
+        usage_error("geoip doesn't read networks");
+    }
+    let dir = geoip::dir(options.data_dir.as_deref()).unwrap_or_else(|err| fail(err));
+    match options.geoip_action.as_deref() {
+        Some("update") => {
+            let config = options.geoip_config.as_deref().unwrap_or(geoip::CONFIG);
+            let key = geoip::license_key(config).unwrap_or_else(|err| fail(err));
+            let count = geoip::update(&dir, &key).unwrap_or_else(|err| fail(err));
+            note!("geoip: saved {} blocks in {}", count, dir.display());
+        }
+        Some("status") => {
+            let database = geoip::Database::open(&dir).unwrap_or_else(|err| fail(err));
+            let mut out = io::stdout().lock();
+            check_output(writeln!(
+                out,
+                "{}: {} blocks, downloaded {} days ago",
+                dir.display(),
+                database.len(),
+                database.age().as_secs() / 86_400
+            ));
+        }
+        _ => usage_error("geoip needs an action: update or status"),
+    }
+}
+
+/// Returns whether a command that prints text or JSON prints JSON.
+fn json_output(options: &Options, command: &str) -> bool {
+    match options.format.as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => usage_error(&format!("{} only prints text or json", command)),
+    }
+}
+
+/// Prints a rollup to stdout.
+fn write_rollup(options: &Options, rollup: &rollup::Rollup, json: bool) {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = if json {
+        rollup.write_json(&mut out)
+    } else {
+        rollup.write_text(&mut out, &options.style)
+    };
+    check_output(result.and_then(|_| out.flush()));
+}
+
+/// Runs `lfc by-country`.
+fn run_by_country(options: &Options) {
+    let json = json_output(options, "by-country");
+    let dir = geoip::dir(options.data_dir.as_deref()).unwrap_or_else(|err| fail(err));
+    let database = geoip::Database::open(&dir).unwrap_or_else(|err| fail(err));
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let mut countries: BTreeMap<String, Vec<IpNet>> = BTreeMap::new();
+    for net in &nets {
+        for (part, country) in database.split(net) {
+            countries
+                .entry(country.unwrap_or("ZZ").to_string())
+                .or_default()
+                .push(part);
+        }
+    }
+    // Neighboring blocks in the same country merge back together.
+    let exact = aggregator(options).unwrap_or_else(|err| fail(err)).exact();
+    for nets in countries.values_mut() {
+        *nets = exact.aggregate(nets.iter().copied());
+    }
+    let rollup = rollup::Rollup::new(
+        countries
+            .into_iter()
+            .map(|(key, nets)| rollup::Group {
+                key,
+                name: None,
+                nets,
+            })
+            .collect(),
+    );
+    write_rollup(options, &rollup, json);
+}
+
+/// Runs `lfc by-asn`.
+fn run_by_asn(options: &Options) {
+    let json = json_output(options, "by-asn");
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let origins = options
+        .asn_provider
+        .unwrap_or_default()
+        .origins(&nets)
+        .unwrap_or_else(|err| fail(err));
+    let mut ases: BTreeMap<u32, rollup::Group> = BTreeMap::new();
+    let mut unannounced = Vec::new();
+    for net in nets {
+        let Some(origin) = origins.get(&net) else {
+            unannounced.push(net);
+            continue;
+        };
+        ases.entry(origin.asn)
+            .or_insert_with(|| rollup::Group {
+                key: format!("AS{}", origin.asn),
+                name: Some(origin.name.clone()),
+                nets: Vec::new(),
+            })
+            .nets
+            .push(net);
+    }
+    let mut groups: Vec<rollup::Group> = ases.into_values().collect();
+    if !unannounced.is_empty() {
+        groups.push(rollup::Group {
+            key: "unannounced".to_string(),
+            name: None,
+            nets: unannounced,
+        });
+    }
+    write_rollup(options, &rollup::Rollup::new(groups), json);
+}
+
+/// Runs `lfc lint`.
+fn run_lint(options: &Options) {
+    let json = json_output(options, "lint");
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let records: Vec<Record> = input_records(&inputs).collect();
+    let parser = default_prefix_lens(options, Parser::new());
+    let shortest = [options.len_v4.unwrap_or(8), options.len_v6.unwrap_or(32)];
+    let findings = lint::lint(&records, &parser, shortest);
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let result = if json {
+        lint::write_json(&mut out, &findings)
+    } else {
+        lint::write_text(&mut out, &findings)
+    };
+    check_output(result.and_then(|_| out.flush()));
+    let [notices, warnings, errors] = lint::counts(&findings);
+    note!(
+        "lint: {} error{}, {} warning{}, {} notice{}",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" },
+        notices,
+        if notices == 1 { "" } else { "s" }
+    );
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Runs `lfc resolve`.
+fn run_resolve(options: &Options) {
+    let json = json_output(options, "resolve");
+    if !options.files.is_empty() || options.stdin {
+        usage_error("resolve reads its lists from --list NAME=FILE:PRIORITY");
+    }
+    if options.lists.is_empty() {
+        usage_error("resolve needs at least one --list NAME=FILE:PRIORITY");
+    }
+    let lists: Vec<resolve::List> = options
+        .lists
+        .iter()
+        .map(|list| list.parse().unwrap_or_else(|err: String| usage_error(&err)))
+        .collect();
+    let aggregator = aggregator(options).unwrap_or_else(|err| fail(err));
+    let inputs: Vec<_> = lists
+        .iter()
+        .map(|list| source::open(&list.file).unwrap_or_else(|err| fail(err)))
+        .collect();
+    if options.strict {
+        check_strict(options, &inputs).unwrap_or_else(|err| fail(err));
+    }
+    let nets: Vec<_> = lists
+        .iter()
+        .zip(&inputs)
+        .map(|(list, input)| {
+            let nets = aggregator
+                .aggregate_records(input_records([input]))
+                .unwrap_or_else(|err| fail(err));
+            (list, nets)
+        })
+        .collect();
+    warn_truncated();
+    let mut groups: Vec<rollup::Group> = Vec::new();
+    for (list, nets) in resolve::resolve(&nets) {
+        match groups.iter_mut().find(|group| group.key == list.name) {
+            Some(group) => group.nets.extend(nets),
+            None => groups.push(rollup::Group {
+                key: list.name.clone(),
+                name: None,
+                nets,
+            }),
+        }
+    }
+    let exact = aggregator.exact();
+    for group in &mut groups {
+        group.nets = exact.aggregate(group.nets.iter().copied());
+    }
+    write_rollup(options, &rollup::Rollup::new(groups), json);
+}
+
+/// Runs `lfc fetch`.
+fn run_fetch(options: &Options) {
+    let Some(name) = &options.feed else {
+        usage_error("fetch needs a FEED: github-meta, googlebot, or ripestat");
+    };
+    if !options.files.is_empty() || options.stdin {
+        usage_error("fetch only reads its FEED");
+    }
+    let inputs: Vec<Box<dyn InputSource>> = if name == fetch::RIPESTAT {
+        if options.asns.is_empty() {
+            usage_error("fetch ripestat needs --asn to know whose prefixes to download");
+        }
+        fetch::ripestat(
+            &options.asns,
+            options.since.as_deref(),
+            options.until.as_deref(),
+        )
+        .unwrap_or_else(|err| fail(err))
+        .into_iter()
+        .map(|input| Box::new(input) as Box<dyn InputSource>)
+        .collect()
+    } else {
+        let feed = fetch::find(name).unwrap_or_else(|| {
+            usage_error(&format!(
+                "unknown feed '{}'; try github-meta, googlebot, or ripestat",
+                name
+            ))
+        });
+        vec![Box::new(feed.fetch().unwrap_or_else(|err| fail(err)))]
+    };
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    print_nets(options, formatter, &nets, &inputs);
+}
+
+/// Runs `lfc tui`.
+fn run_tui(options: &Options) {
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregate_with_set(options, &inputs).unwrap_or_else(|err| fail(err));
+    let Some(selection) = tui::run(&nets, &options.style).unwrap_or_else(|err| fail(err)) else {
+        return;
+    };
+    print_nets(options, formatter, &selection, &inputs);
+}
+
+/// Runs `lfc nat64`.
+fn run_nat64(options: &Options) {
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let prefix = options
+        .nat64_prefix
+        .unwrap_or_else(|| "64:ff9b::/96".parse().unwrap());
+    let aggregator = aggregator(options).unwrap_or_else(|err| fail(err));
+    let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+    let nets = aggregator
+        .parser()
+        .records(input_records(&inputs))
+        .map(|net| net.unwrap_or_else(|err| fail(err)))
+        .map(|net| match net {
+            IpNet::V4(v4) if !options.reverse => IpNet::V6(space::to_nat64(&prefix, &v4)),
+            IpNet::V6(v6) if options.reverse => {
+                space::from_nat64(&prefix, &v6).map_or(net, IpNet::V4)
+            }
+            _ => net,
+        });
+    let result = aggregator.aggregate(nets);
+    warn_truncated();
+    print_nets(options, formatter, &result, &inputs);
+}
+
+/// Runs the default command: aggregate the input and print the result, or,
+/// with `--refresh`, keep doing that every interval.
+fn run_aggregate(options: &Options) {
+    let Some(interval) = options.refresh else {
+        let inputs = read_input(options).unwrap_or_else(|err| fail(err));
+        aggregate_inputs(options, inputs, false).unwrap_or_else(|err| fail(err));
+        return;
+    };
+    let hangups = hangup::hangups().unwrap_or_else(|err| fail(err));
+    let mut skip_unchanged = false;
+    loop {
+        let started = Instant::now();
+        match read_input(options) {
+            Ok(inputs) => match aggregate_inputs(options, inputs, skip_unchanged) {
+                Ok(()) => skip_unchanged = true,
+                Err(err) => note!("error: {}; leaving the outputs as they are", err),
+            },
+            Err(err) => {
+                metrics::fetch_failed();
+                note!("error: {}; trying again in {:?}", err, interval);
+            }
+        }
+        // SIGHUP starts the next pass now, and the interval over from it.
+        if hangup::wait(&hangups, Some(interval.saturating_sub(started.elapsed()))) {
+            note!("reloading on SIGHUP");
+            forget_allocations();
+        }
+    }
+}
+
+/// Checks the result against the RPKI VRPs from the source, reporting each
+/// network that the `--origin` can't announce, and leaving them out with
+/// `--rpki-drop`.
+fn rpki_check(options: &Options, source: &str, result: Vec<IpNet>) -> Result<Vec<IpNet>, String> {
+    let vrps = rpki::Vrps::load(source)?;
+    let origin = options.origin.expect("--rpki needs --origin");
+    let (mut valid, mut invalid, mut not_found) = (0, 0, 0);
+    let result = result
+        .into_iter()
+        .filter(|net| match vrps.validate(net, origin) {
+            rpki::State::Valid => {
+                valid += 1;
+                true
+            }
+            rpki::State::NotFound => {
+                not_found += 1;
+                true
+            }
+            rpki::State::Invalid => {
+                invalid += 1;
+                note!("{} is RPKI invalid for AS{}", net, origin);
+                !options.rpki_drop
+            }
+        })
+        .collect();
+    note!(
+        "rpki: {} valid, {} invalid{}, {} not found, against {} VRPs",
+        valid,
+        invalid,
+        if options.rpki_drop && invalid > 0 {
+            " and left out"
+        } else {
+            ""
+        },
+        not_found,
+        vrps.len()
+    );
+    Ok(result)
+}
+
+/// Writes what each `--report` asked for about the result to stderr. It
+/// was asked for, so unlike a note, `--quiet` doesn't silence it.
+fn write_report(options: &Options, result: &[IpNet]) {
+    let mut err = io::stderr().lock();
+    for report in &options.report {
+        check_output(match report {
+            Report::Special => info::write_report(&mut err, result, &options.style),
+            Report::Slaac => slaac::write_report(&mut err, result, &options.style),
+        });
+    }
+}
+
+/// Checks the result against `--max-coverage` and the minimum prefix
+/// lengths, or warns about it with `--force`.
+fn check_limits(options: &Options, result: &[IpNet]) -> Result<(), String> {
+    check_coverage(options, result)?;
+    check_prefix_lens(options, result)
+}
+
+/// Reports each network in the result that's shorter than
+/// `--min-prefixlen-v4` or `--min-prefixlen-v6`, and returns an error if
+/// there are any, unless there's `--force`.
+fn check_prefix_lens(options: &Options, result: &[IpNet]) -> Result<(), String> {
+    let level = if options.force { "warning" } else { "error" };
+    let mut short = 0;
+    for net in result {
+        let (min, name) = match Family::of(net) {
+            Family::V4 => (options.min_prefix_len_v4, "--min-prefixlen-v4"),
+            Family::V6 => (options.min_prefix_len_v6, "--min-prefixlen-v6"),
+        };
+        if let Some(min) = min.filter(|&min| net.prefix_len() < min) {
+            note!("{}: {} is shorter than {} {}", level, net, name, min);
+            short += 1;
+        }
+    }
+    match short {
+        0 => Ok(()),
+        _ if options.force => Ok(()),
+        1 => {
+            Err("1 network in the result is too short; use --force to write it anyway".to_string())
+        }
+        _ => Err(format!(
+            "{} networks in the result are too short; use --force to write them anyway",
+            short
+        )),
+    }
+}
+
+/// Checks that the result covers no more of either family's address space
+/// than `--max-coverage`, or warns that it does with `--force`.
+fn check_coverage(options: &Options, result: &[IpNet]) -> Result<(), String> {
+    let Some(max) = options.max_coverage else {
+        return Ok(());
+    };
+    for family in [Family::V4, Family::V6] {
+        let bits = match family {
+            Family::V4 => 32,
+            Family::V6 => 128,
+        };
+        let coverage = space::size(&space::ranges(result, family)) as f64 * 100.0 / 2f64.powi(bits);
+        if coverage <= max {
+            continue;
+        }
+        let message = format!(
+            "the result covers {:.1}% of the {} space, more than --max-coverage {}%",
+            coverage, family, max
+        );
+        if !options.force {
+            return Err(format!("{}; use --force to write it anyway", message));
+        }
+        note!("warning: {}", message);
+    }
+    Ok(())
+}
+
+/// Aggregates the inputs and writes the result everywhere it goes. With
+/// `skip_unchanged`, nothing is written if the state directory shows that
+/// the result didn't change, except for metrics.
+///
+/// Returns an error instead of exiting, so that `--refresh` can keep the
+/// outputs it last wrote and try again. Outputs that were already written
+/// stay written, but the state isn't saved, so the next run sees the same
+/// change.
+fn aggregate_inputs(
+    options: &Options,
+    mut inputs: Vec<Box<dyn InputSource>>,
+    skip_unchanged: bool,
+) -> Result<(), String> {
+    let formats = format::Registry::default();
+    let formatter = output_format(&formats, options);
+    let mut script = options
+        .script
+        .as_deref()
+        .map(script::Script::load)
+        .transpose()?;
+    if let Some(script) = &mut script {
+        // Filter first, so that the cache sees what's actually aggregated.
+        inputs = script.filter_inputs(inputs)?;
+    }
+    let mut zoned = BTreeMap::new();
+    if options.zones == zones::Zones::Group {
+        (inputs, zoned) = zones::group(inputs).map_err(|err| err.to_string())?;
+    }
+    let started = Instant::now();
+    let mut result = aggregate_with_set(options, &inputs)?;
+    let duration = started.elapsed();
+    let next_expiry = take_next_expiry();
+    if let Some(next) = next_expiry {
+        note!("the next entry expires at {}", clock::rfc3339(next));
+    }
+    if let Some(source) = &options.rpki {
+        result = rpki_check(options, source, result)?;
+    }
+    write_report(options, &result);
+    check_limits(options, &result)?;
+
+    let name = options.name.as_deref().unwrap_or(state::DEFAULT_NAME);
+    if let Some(path) = &options.metrics_file {
+        let metrics = metrics::render(&[metrics::List {
+            name,
+            nets: &result,
+            duration,
+            timestamp: clock::now(),
+            next_expiry,
+        }]);
+        metrics::write(Path::new(path), &metrics).map_err(|err| err.to_string())?;
+    }
+
+    // Work out what changed before printing anything, so that a bad state
+    // directory doesn't leave us with half a job done.
+    let state = options.state_dir.as_ref().map(|dir| {
+        let path = state::path(Path::new(dir), name)?;
+        let previous = state::load(&path).map_err(|err| err.to_string())?;
+        let changes = match previous {
+            Some(previous) => {
+                let summary = state::Summary::new(&previous, &result);
+                if summary.is_empty() {
+                    note!("{}: no changes", name);
+                } else {
+                    note!("{}: {}", name, summary);
+                }
+                diff::diff(&previous, &result)
+            }
+            None => {
+                note!(
+                    "{}: no previous result; saving {} prefixes",
+                    name,
+                    result.len()
+                );
+                Vec::new()
+            }
+        };
+        Ok::<_, String>((path, changes))
+    });
+    let state = state.transpose()?;
+    if skip_unchanged
+        && state
+            .as_ref()
+            .is_some_and(|(_, changes)| changes.is_empty())
+    {
+        return Ok(());
+    }
+    if let Some(path) = &options.save_set {
+        setfile::save(Path::new(path), &result).map_err(|err| err.to_string())?;
+    }
+
+    let context = output::Context {
+        result: &result,
+        inputs: &inputs,
+    };
+    let configured = output::configure(options, &context)?;
+    let formatter = configured.as_deref().unwrap_or(formatter);
+    // A file is written under a temporary name and renamed into place, so
+    // that nothing ever reads half of it.
+    let output = options
+        .output
+        .as_ref()
+        .map(|path| (format!("{}.tmp", path), path));
+    let mut out: Box<dyn Write> = match &output {
+        Some((tmp, _)) => Box::new(io::BufWriter::new(
+            File::create(tmp).map_err(|err| format!("unable to write {}: {}", tmp, err))?,
+        )),
+        None => Box::new(io::BufWriter::new(io::stdout().lock())),
+    };
+    if let Some(saved) = &options.diff_against {
+        let old = source::open(saved).map_err(|err| err.to_string())?;
+        let old: Vec<&str> = input_lines([&old]).collect();
+        let new: Vec<String> = result.iter().map(IpNet::to_string).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        written(diff::write_unified(&mut out, saved, &old, &new, 3).and_then(|_| out.flush()))?;
+    } else if let Some(lines) = script
+        .as_mut()
+        .map(|script| script.output(&result))
+        .transpose()?
+        .flatten()
+    {
+        written(
+            lines
+                .iter()
+                .try_for_each(|line| writeln!(out, "{}", line))
+                .and_then(|_| out.flush()),
+        )?;
+    } else {
+        written(
+            formatter
+                .write(&mut out, &result, &options.style)
+                .and_then(|_| out.flush()),
+        )?;
+    }
+    for (zone, inputs) in &zoned {
+        let nets = gather_inputs(inputs, &aggregator(options)?).map_err(|err| err.to_string())?;
+        written(zones::write(&mut out, zone, &nets, &options.style).and_then(|_| out.flush()))?;
+    }
+    drop(out);
+    if let Some((tmp, path)) = output {
+        fs::rename(&tmp, path).map_err(|err| format!("unable to write {}: {}", path, err))?;
+    }
+
+    if let Some((path, changes)) = state {
+        // Notify before saving, so that if the notification fails, the next
+        // run sees the same change and tries again.
+        if let (Some(url), false) = (&options.webhook, changes.is_empty()) {
+            let body = webhook::payload(name, &changes, result.len(), clock::now());
+            // Read for every notification, so that a rotated secret is used.
+            let secret = webhook::secret(
+                options.webhook_secret.as_deref(),
+                options.webhook_secret_file.as_deref(),
+            )?;
+            webhook::send(url, &body, secret.as_deref(), options.webhook_retries)?;
+        }
+        if options.syslog && !changes.is_empty() {
+            let source = if options.files.is_empty() {
+                "stdin".to_string()
+            } else {
+                options.files.join(",")
+            };
+            syslog::log_changes(name, &source, &changes).map_err(|err| err.to_string())?;
+        }
+        state::save(&path, &result).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => usage_error(&message),
+    };
+
+    if options.help {
+        print_help();
+        return;
+    }
+    diag::set_quiet(options.quiet);
+    EXPIRY
+        .set((
+            options.now,
+            options.expire_after.map_or(0, |after| after.as_secs()),
+        ))
+        .unwrap();
+    if options.webhook.is_some() && options.state_dir.is_none() {
+        usage_error("--webhook needs --state-dir to know when the list changes");
+    }
+    if options.webhook_secret.is_some() && options.webhook_secret_file.is_some() {
+        usage_error("--webhook-secret and --webhook-secret-file both give the secret; pick one");
+    }
+    if options.syslog && options.state_dir.is_none() {
+        usage_error("--syslog needs --state-dir to know when the list changes");
+    }
+    if (options.output.is_some() || options.refresh.is_some())
+        && options.command != Command::Aggregate
+    {
+        usage_error("--output and --refresh only work when aggregating");
+    }
+    if options.refresh.is_some() {
+        if options.state_dir.is_none() {
+            usage_error("--refresh needs --state-dir to know when the list changes");
+        }
+        if options.output.is_none() {
+            usage_error("--refresh needs --output to have a file to rewrite");
+        }
+        if options.stdin
+            || options.files.iter().any(|file| file == "-")
+            || !options.files.iter().any(|file| source::is_url(file))
+        {
+            usage_error("--refresh needs a URL to download again");
+        }
+    }
+    if (options.rpki.is_some() || options.rpki_drop) && options.command != Command::Aggregate {
+        usage_error("--rpki and --rpki-drop only work when aggregating");
+    }
+    if options.origin.is_some() && options.rpki.is_none() && options.command != Command::IrrCheck {
+        usage_error("--origin only works with --rpki or irr-check");
+    }
+    if (!options.asns.is_empty() || options.since.is_some() || options.until.is_some())
+        && (options.command != Command::Fetch || options.feed.as_deref() != Some(fetch::RIPESTAT))
+    {
+        usage_error("--asn, --since, and --until only work with fetch ripestat");
+    }
+    if (options.irr_server.is_some() || !options.irr_sources.is_empty())
+        && options.command != Command::IrrCheck
+    {
+        usage_error("--irr-server and --irr-sources only work with irr-check");
+    }
+    if options.geoip_config.is_some() && options.command != Command::Geoip {
+        usage_error("--geoip-config only works with geoip update");
+    }
+    if options.rpki.is_some() && options.origin.is_none() {
+        usage_error("--rpki needs --origin to know which AS announces the networks");
+    }
+    if options.rpki_drop && options.rpki.is_none() {
+        usage_error("--rpki-drop needs --rpki");
+    }
+    if options.zones == zones::Zones::Group
+        && (options.command != Command::Aggregate
+            || options
+                .format
+                .as_deref()
+                .is_some_and(|format| format != "text"))
+    {
+        usage_error("--zones group only works when aggregating to text");
+    }
+    if options.low_memory && (options.no_merge_adjacent || options.dedupe_only) {
+        usage_error("--low-memory always merges adjacent networks");
+    }
+    if let Err(err) = output::check(&options) {
+        usage_error(&err);
+    }
+    if options.asn_provider.is_some()
+        && options.annotate != Some(Annotation::Asn)
+        && options.command != Command::ByAsn
+    {
+        usage_error("--provider only works with --annotate asn or by-asn");
+    }
+    if (options.len_v4.is_some() || options.len_v6.is_some())
+        && !matches!(options.command, Command::Anonymize | Command::Lint)
+    {
+        usage_error("--v4 and --v6 only work with anonymize and lint");
+    }
+    if let (Some(ge), Some(le)) = (options.frr_ge, options.frr_le)
+        && ge > le
+    {
+        usage_error("--frr-ge can't be longer than --frr-le");
+    }
+    if (options.service.is_some() || options.acl.is_some() || options.dry_run)
+        && options.command != Command::Push
+    {
+        usage_error("--service, --acl, and --dry-run only work with lfc push");
+    }
+    if !options.lists.is_empty() && !matches!(options.command, Command::Push | Command::Resolve) {
+        usage_error("--list only works with lfc push and lfc resolve");
+    }
+    if (options.list_id.is_some()
+        || options.akamai_mode.is_some()
+        || options.akamai_activate.is_some())
+        && options.provider.as_deref() != Some("akamai")
+    {
+        usage_error(
+            "--list-id, --akamai-mode, and --akamai-activate only work with lfc push akamai",
+        );
+    }
+    if options.skeleton && options.command != Command::PtrZones {
+        usage_error("--skeleton only works with lfc ptr-zones");
+    }
+    if graphing(&options) && (options.low_memory || options.cache_dir.is_some()) {
+        usage_error("--format dot needs every merge, which --low-memory and --cache-dir skip");
+    }
+    if options.merge_slack > 0.0 && options.cache_dir.is_some() {
+        usage_error(
+            "--merge-slack reports the space it adds while merging, which --cache-dir skips",
+        );
+    }
+    if options.low_memory && options.keep_structure {
+        usage_error("--low-memory always removes networks inside other networks");
+    }
+    if (!options.rirs.is_empty()
+        || !options.drop.is_empty()
+        || !options.drop_bogons.is_empty()
+        || options.sample.is_some())
+        && matches!(
+            options.command,
+            Command::Diff
+                | Command::Delta
+                | Command::Plan
+                | Command::Bench
+                | Command::Gen
+                | Command::Selftest
+                | Command::Formats
+                | Command::Nat64
+                | Command::Info
+                | Command::Geoip
+                | Command::Resolve
+                | Command::Lint
+        )
+    {
+        usage_error(
+            "--rir, --drop, --drop-bogons, and --sample only work with commands that aggregate their input",
+        );
+    }
+    if (options.now.is_some() || options.expire_after.is_some())
+        && matches!(
+            options.command,
+            Command::Bench
+                | Command::Gen
+                | Command::Selftest
+                | Command::Formats
+                | Command::Info
+                | Command::Fetch
+                | Command::Geoip
+        )
+    {
+        usage_error("--now and --expire-after only work with commands that read lists");
+    }
+    let limited = options.max_coverage.is_some()
+        || options.min_prefix_len_v4.is_some()
+        || options.min_prefix_len_v6.is_some();
+    if limited && !matches!(options.command, Command::Aggregate | Command::Push) {
+        usage_error(
+            "--max-coverage, --min-prefixlen-v4, and --min-prefixlen-v6 only work when aggregating or with lfc push",
+        );
+    }
+    if options.force && !limited {
+        usage_error(
+            "--force only overrides --max-coverage, --min-prefixlen-v4, and --min-prefixlen-v6",
+        );
+    }
+    if !options.report.is_empty() && !matches!(options.command, Command::Aggregate | Command::Push)
+    {
+        usage_error("--report only works when aggregating or with lfc push");
+    }
+    if options.sample_weighted && options.sample.is_none() {
+        usage_error("--sample-weighted needs --sample to know how many to pick");
+    }
+    if options.low_memory && keeps_regions(&options) {
+        usage_error(
+            "--low-memory merges without looking at --no-merge-across, --respect-allocations, or --scope-aware",
+        );
+    }
+    if options.strict && options.truncate_host_bits {
+        usage_error("--strict rejects host bits, so --truncate-host-bits can't clear them");
+    }
+    if options.strict && options.zones == zones::Zones::Group {
+        usage_error("--strict can't check networks with zone IDs; use --zones strip");
+    }
+
+    match options.command {
+        Command::Aggregate => run_aggregate(&options),
+        Command::Diff => run_diff(&options),
+        Command::Delta => run_delta(&options),
+        Command::Plan => run_plan(&options),
+        Command::Serve => run_serve(&options),
+        Command::Bench => run_bench(&options),
+        Command::Gen => run_gen(&options),
+        Command::Selftest => run_selftest(&options),
+        Command::Formats => run_formats(),
+        Command::Nat64 => run_nat64(&options),
+        Command::Explode => run_explode(&options),
+        Command::Anonymize => run_anonymize(&options),
+        Command::Hosts => run_hosts(&options),
+        Command::Info => run_info(&options),
+        Command::Tui => run_tui(&options),
+        Command::PtrZones => run_ptr_zones(&options),
+        Command::Push => run_push(&options),
+        Command::Fetch => run_fetch(&options),
+        Command::IrrCheck => run_irr_check(&options),
+        Command::Geoip => run_geoip(&options),
+        Command::ByCountry => run_by_country(&options),
+        Command::ByAsn => run_by_asn(&options),
+        Command::Resolve => run_resolve(&options),
+        Command::Lint => run_lint(&options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_defaults() {
+        let options = parse_args(args(&[])).unwrap();
+        assert_eq!(options, Options::default());
+    }
+
+    #[test]
+    fn test_parse_args_stdin_and_files() {
+        let options = parse_args(args(&["--stdin", "a.txt", "-", "b.txt"])).unwrap();
+        assert!(options.stdin);
+        assert!(!options.help);
+        assert_eq!(options.files, vec!["a.txt", "-", "b.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_quiet() {
+        assert!(parse_args(args(&["-q"])).unwrap().quiet);
+        assert!(parse_args(args(&["--quiet"])).unwrap().quiet);
+    }
+
+    #[test]
+    fn test_parse_args_unrecognized() {
+        assert_eq!(
+            parse_args(args(&["--bogus"])),
+            Err("unrecognized argument '--bogus'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_diff() {
+        let options = parse_args(args(&["diff", "--color", "never", "old", "new"])).unwrap();
+        assert_eq!(options.command, Command::Diff);
+        assert_eq!(options.color, ColorChoice::Never);
+        assert_eq!(options.files, vec!["old", "new"]);
+    }
+
+    #[test]
+    fn test_parse_args_inline_value() {
+        let options = parse_args(args(&["diff", "--color=always"])).unwrap();
+        assert_eq!(options.color, ColorChoice::Always);
+    }
+
+    #[test]
+    fn test_parse_args_diff_against() {
+        let options = parse_args(args(&["--diff-against", "saved.txt", "new.txt"])).unwrap();
+        assert_eq!(options.diff_against.as_deref(), Some("saved.txt"));
+        assert_eq!(options.files, vec!["new.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_state_dir() {
+        let options = parse_args(args(&["--state-dir", "/tmp/lfc", "--name=feed"])).unwrap();
+        assert_eq!(options.state_dir.as_deref(), Some("/tmp/lfc"));
+        assert_eq!(options.name.as_deref(), Some("feed"));
+    }
+
+    #[test]
+    fn test_parse_args_delta() {
+        let options = parse_args(args(&[
+            "delta",
+            "--current",
+            "/dev/fd/63",
+            "--format",
+            "ipset-delta",
+            "new.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Delta);
+        assert_eq!(options.current.as_deref(), Some("/dev/fd/63"));
+        assert_eq!(options.format.as_deref(), Some("ipset-delta"));
+        assert_eq!(options.files, vec!["new.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_plan() {
+        let options = parse_args(args(&["plan", "--current", "a", "--format=json"])).unwrap();
+        assert_eq!(options.command, Command::Plan);
+        assert_eq!(options.current.as_deref(), Some("a"));
+        assert_eq!(options.format.as_deref(), Some("json"));
+    }
+
+    #[test]
+    fn test_parse_args_webhook() {
+        let options = parse_args(args(&[
+            "--webhook",
+            "https://example.com/hook",
+            "--webhook-secret=s3kr1t",
+            "--webhook-retries",
+            "5",
+        ]))
+        .unwrap();
+        assert_eq!(options.webhook.as_deref(), Some("https://example.com/hook"));
+        assert_eq!(options.webhook_secret.as_deref(), Some("s3kr1t"));
+        assert_eq!(options.webhook_retries, 5);
+        let options = parse_args(args(&["--webhook-secret-file", "/etc/lfc/webhook.key"])).unwrap();
+        assert_eq!(
+            options.webhook_secret_file.as_deref(),
+            Some("/etc/lfc/webhook.key")
+        );
+        assert_eq!(Options::default().webhook_retries, 3);
+        assert!(parse_args(args(&["--webhook-retries", "lots"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_metrics_file() {
+        let options = parse_args(args(&["--metrics-file", "/var/lib/node/lfc.prom"])).unwrap();
+        assert_eq!(
+            options.metrics_file.as_deref(),
+            Some("/var/lib/node/lfc.prom")
+        );
+    }
+
+    #[test]
+    fn test_parse_args_refresh() {
+        let options = parse_args(args(&[
+            "--refresh",
+            "1h",
+            "--output",
+            "/etc/lfc/feed.txt",
+            "https://example.com/feed.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.refresh, Some(Duration::from_secs(3600)));
+        assert_eq!(options.output.as_deref(), Some("/etc/lfc/feed.txt"));
+        assert!(parse_args(args(&["--refresh", "soon"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rpki() {
+        let options = parse_args(args(&[
+            "--rpki",
+            "rtr://validator:3323",
+            "--origin=AS64500",
+            "--rpki-drop",
+        ]))
+        .unwrap();
+        assert_eq!(options.rpki.as_deref(), Some("rtr://validator:3323"));
+        assert_eq!(options.origin, Some(64500));
+        assert!(options.rpki_drop);
+        assert!(parse_args(args(&["--origin", "ASX"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_syslog() {
+        assert!(parse_args(args(&["--syslog"])).unwrap().syslog);
+        assert!(!Options::default().syslog);
+    }
+
+    #[test]
+    fn test_parse_args_serve() {
+        let options = parse_args(args(&["serve", "--unix", "/run/lfc.sock", "list.txt"])).unwrap();
+        assert_eq!(options.command, Command::Serve);
+        assert_eq!(options.unix.as_deref(), Some("/run/lfc.sock"));
+        assert_eq!(options.files, vec!["list.txt"]);
+
+        let options = parse_args(args(&["serve", "--listen", "127.0.0.1:8080"])).unwrap();
+        assert_eq!(options.listen.as_deref(), Some("127.0.0.1:8080"));
+    }
+
+    /// Makes an in-memory input of each list.
+    fn inputs(lists: &[&str]) -> Vec<Box<dyn InputSource>> {
+        lists
+            .iter()
+            .enumerate()
+            .map(|(i, list)| {
+                Box::new(source::MemorySource::new(&format!("list{}", i), *list))
+                    as Box<dyn InputSource>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gather_inputs() {
+        let lists = [
+            "10.0.0.0/24\n",
+            "10.0.1.0/24\n2001:db8::/33",
+            "2001:db8:8000::/33",
+            "",
+        ];
+        let inputs = inputs(&lists);
+        let expected = lfc::gather_lines(lists.join("\n").lines()).unwrap();
+        assert_eq!(
+            gather_inputs_with(&inputs, &Aggregator::default(), 1).unwrap(),
+            expected
+        );
+        assert_eq!(
+            gather_inputs_with(&inputs, &Aggregator::default(), 3).unwrap(),
+            expected
+        );
+        assert_eq!(
+            gather_inputs_with(&inputs, &Aggregator::default(), 8).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_aggregate_inputs_keeps_outputs_on_bad_line() {
+        let dir = std::env::temp_dir().join(format!("lfc-refresh-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("deny.txt");
+        let options = parse_args(args(&[
+            "--refresh",
+            "1h",
+            "--state-dir",
+            dir.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ]))
+        .unwrap();
+        let good = aggregate_inputs(&options, inputs(&["10.0.0.0/24\n10.0.1.0/24\n"]), false);
+        let first = fs::read_to_string(&output);
+        let bad = aggregate_inputs(&options, inputs(&["10.0.0.0/24\nbogus\n"]), true);
+        let second = fs::read_to_string(&output);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(good, Ok(()));
+        assert_eq!(first.unwrap(), "10.0.0.0/23\n");
+        assert!(bad.unwrap_err().contains("\"bogus\""));
+        assert_eq!(second.unwrap(), "10.0.0.0/23\n");
+    }
+
+    #[test]
+    fn test_aggregate_inputs_bind_acl_except() {
+        let dir = std::env::temp_dir().join(format!("lfc-except-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let except = dir.join("except.txt");
+        let output = dir.join("blocked.acl");
+        fs::write(&except, "10.0.0.53/32\n").unwrap();
+        let options = parse_args(args(&[
+            "--format",
+            "bind-acl",
+            "--except",
+            except.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+        ]))
+        .unwrap();
+        let result = aggregate_inputs(&options, inputs(&["10.0.0.0/24\n10.0.1.0/24\n"]), false);
+        let written = fs::read_to_string(&output);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            written.unwrap(),
+            "acl \"lfc\" {\n    !10.0.0.53/32;\n    10.0.0.0/23;\n};\n"
+        );
+        assert_eq!(
+            output::check(&parse_args(args(&["--except", "x.txt"])).unwrap()),
+            Err("--except only works with --format bind-acl".to_string())
+        );
+    }
+
+    #[test]
+    fn test_in_parallel_keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+        for workers in [1, 3, 8] {
+            assert_eq!(
+                in_parallel(&items, workers, |item| item * 2),
+                (0..100).map(|item| item * 2).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_gather_inputs_reports_first_error() {
+        let lists = ["10.0.0.0/24\n", "bad1\n", "bad2\n", "bad3\n"];
+        for workers in [1, 3] {
+            let err =
+                gather_inputs_with(&inputs(&lists), &Aggregator::default(), workers).unwrap_err();
+            assert!(
+                err.to_string()
+                    .starts_with("Unable to parse \"bad1\" as an IP network on list1 line 1")
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_args_cache_dir() {
+        let options = parse_args(args(&["--cache-dir", "/var/cache/lfc"])).unwrap();
+        assert_eq!(options.cache_dir.as_deref(), Some("/var/cache/lfc"));
+    }
+
+    #[test]
+    fn test_parse_args_low_memory() {
+        assert!(!parse_args(args(&[])).unwrap().low_memory);
+        assert!(parse_args(args(&["--low-memory"])).unwrap().low_memory);
+    }
+
+    #[test]
+    fn test_parse_args_bench() {
+        let options =
+            parse_args(args(&["bench", "--count", "5_000_000", "--profile=dense"])).unwrap();
+        assert_eq!(options.command, Command::Bench);
+        assert_eq!(options.count, Some(5_000_000));
+        assert_eq!(options.profile, synth::Profile::Dense);
+        assert!(parse_args(args(&["bench", "--count", "lots"])).is_err());
+        assert!(parse_args(args(&["bench", "--profile", "ospf"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_gen() {
+        let options = parse_args(args(&[
+            "gen",
+            "--count",
+            "10",
+            "--family",
+            "mixed",
+            "--prefixlen-range",
+            "16..28",
+            "--seed",
+            "42",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Gen);
+        assert_eq!(options.count, Some(10));
+        assert_eq!(options.family, synth::FamilyChoice::Mixed);
+        assert_eq!(options.prefix_lengths, Some((16, 28)));
+        assert_eq!(options.seed, Some(42));
+        assert!(parse_args(args(&["gen", "--family", "v5"])).is_err());
+        assert!(parse_args(args(&["gen", "--prefixlen-range", "28..16"])).is_err());
+        assert!(parse_args(args(&["gen", "--seed", "-1"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_selftest() {
+        let options = parse_args(args(&["selftest", "--count", "50", "--seed", "7"])).unwrap();
+        assert_eq!(options.command, Command::Selftest);
+        assert_eq!(options.count, Some(50));
+        assert_eq!(options.seed, Some(7));
+    }
+
+    #[test]
+    fn test_parse_args_formats() {
+        let options = parse_args(args(&["formats"])).unwrap();
+        assert_eq!(options.command, Command::Formats);
+        let options = parse_args(args(&["--format", "json", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Aggregate);
+        assert_eq!(options.format.as_deref(), Some("json"));
+    }
+
+    #[test]
+    fn test_parse_args_script() {
+        let options = parse_args(args(&["--script", "tag.rhai", "a.txt"])).unwrap();
+        assert_eq!(options.script.as_deref(), Some("tag.rhai"));
+        assert_eq!(options.files, vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_normalize_mapped() {
+        let options = parse_args(args(&["--normalize-mapped", "a.txt"])).unwrap();
+        assert_eq!(options.mapped, Mapped::ToV4);
+        assert_eq!(options.files, vec!["a.txt"]);
+        let options = parse_args(args(&["--normalize-mapped=v6"])).unwrap();
+        assert_eq!(options.mapped, Mapped::ToV6);
+        assert!(parse_args(args(&["--normalize-mapped=v5"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_extract_embedded() {
+        let options = parse_args(args(&["--extract-embedded", "a.txt"])).unwrap();
+        assert_eq!(options.embedded, Embedded::Also);
+        assert_eq!(options.files, vec!["a.txt"]);
+        let options = parse_args(args(&["--extract-embedded=instead"])).unwrap();
+        assert_eq!(options.embedded, Embedded::Instead);
+        assert!(parse_args(args(&["--extract-embedded=both"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_nat64() {
+        let options = parse_args(args(&["nat64", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Nat64);
+        assert_eq!(options.nat64_prefix, None);
+        assert!(!options.reverse);
+        let options = parse_args(args(&[
+            "nat64",
+            "--prefix",
+            "2001:db8:100::/40",
+            "--reverse",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.nat64_prefix,
+            Some("2001:db8:100::/40".parse().unwrap())
+        );
+        assert!(options.reverse);
+        assert!(parse_args(args(&["nat64", "--prefix", "64:ff9b::/95"])).is_err());
+        assert!(parse_args(args(&["nat64", "--prefix", "192.0.2.0/24"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_ipv6_style() {
+        let options = parse_args(args(&[])).unwrap();
+        assert_eq!(options.style, format::Style::default());
+        let options = parse_args(args(&["--ipv6-form", "expanded", "--ipv6-case=upper"])).unwrap();
+        assert_eq!(options.style.ipv6_form, Ipv6Form::Expanded);
+        assert_eq!(options.style.hex_case, HexCase::Upper);
+        assert!(parse_args(args(&["--ipv6-form", "short"])).is_err());
+        assert!(parse_args(args(&["--ipv6-case", "title"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_zones() {
+        let options = parse_args(args(&[])).unwrap();
+        assert_eq!(options.zones, zones::Zones::Reject);
+        let options = parse_args(args(&["--zones", "group", "a.txt"])).unwrap();
+        assert_eq!(options.zones, zones::Zones::Group);
+        assert!(parse_args(args(&["--zones=keep"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_default_prefix_len() {
+        let options = parse_args(args(&[
+            "--default-prefixlen-v4",
+            "24",
+            "--default-prefixlen-v6=64",
+        ]))
+        .unwrap();
+        assert_eq!(options.default_prefix_len_v4, Some(24));
+        assert_eq!(options.default_prefix_len_v6, Some(64));
+        assert!(parse_args(args(&["--default-prefixlen-v4", "33"])).is_err());
+        assert!(parse_args(args(&["--default-prefixlen-v6", "/64"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_truncate_host_bits() {
+        assert!(!parse_args(args(&[])).unwrap().truncate_host_bits);
+        assert!(
+            parse_args(args(&["--truncate-host-bits", "a.txt"]))
+                .unwrap()
+                .truncate_host_bits
+        );
+    }
+
+    #[test]
+    fn test_parse_args_strict() {
+        assert!(!parse_args(args(&[])).unwrap().strict);
+        let options = parse_args(args(&["diff", "--strict", "old", "new"])).unwrap();
+        assert!(options.strict);
+        assert_eq!(options.command, Command::Diff);
+    }
+
+    #[test]
+    fn test_parse_args_merge_slack() {
+        assert_eq!(parse_args(args(&[])).unwrap().merge_slack, 0.0);
+        let options = parse_args(args(&["--merge-slack", "5%"])).unwrap();
+        assert_eq!(options.merge_slack, 0.05);
+        let options = parse_args(args(&["--merge-slack=12.5"])).unwrap();
+        assert_eq!(options.merge_slack, 0.125);
+        assert!(parse_args(args(&["--merge-slack", "100%"])).is_err());
+        assert!(parse_args(args(&["--merge-slack", "-1%"])).is_err());
+        assert!(parse_args(args(&["--merge-slack", "lots"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_max_coverage() {
+        let options = parse_args(args(&["--max-coverage", "5%", "--force"])).unwrap();
+        assert_eq!(options.max_coverage, Some(5.0));
+        assert!(options.force);
+        let options = parse_args(args(&["--max-coverage=0.5"])).unwrap();
+        assert_eq!(options.max_coverage, Some(0.5));
+        assert!(parse_args(args(&["--max-coverage", "0%"])).is_err());
+        assert!(parse_args(args(&["--max-coverage", "101%"])).is_err());
+        assert!(parse_args(args(&["--max-coverage", "some"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_report() {
+        assert_eq!(parse_args(args(&[])).unwrap().report, Vec::new());
+        let options =
+            parse_args(args(&["--report", "special", "--report=slaac", "feed.txt"])).unwrap();
+        assert_eq!(options.report, vec![Report::Special, Report::Slaac]);
+        assert_eq!(options.files, vec!["feed.txt"]);
+        assert!(parse_args(args(&["--report=rir"])).is_err());
+        assert!(parse_args(args(&["--report"])).is_err());
+    }
+
+    #[test]
+    fn test_check_coverage() {
+        let nets: Vec<IpNet> = vec![
+            "0.0.0.0/1".parse().unwrap(),
+            "2001:db8::/32".parse().unwrap(),
         ];
-        let result = gather(&nets);
-        assert_eq!(result.len(), 6);
-        assert!(result.contains(&"10.0.7.0/24".parse().unwrap()));
-        assert!(result.contains(&"10.0.8.0/23".parse().unwrap()));
-        assert!(result.contains(&"20.0.14.0/23".parse().unwrap()));
-        assert!(result.contains(&"20.0.16.0/22".parse().unwrap()));
-        assert!(result.contains(&"20.0.20.0/23".parse().unwrap()));
-        assert!(result.contains(&"30.0.32.0/20".parse().unwrap()));
+        let mut options = parse_args(args(&["--max-coverage", "50%"])).unwrap();
+        assert_eq!(check_coverage(&options, &nets), Ok(()));
+        options.max_coverage = Some(5.0);
+        assert_eq!(
+            check_coverage(&options, &nets),
+            Err("the result covers 50.0% of the IPv4 space, more than --max-coverage 5%; use --force to write it anyway".to_string())
+        );
+        options.force = true;
+        assert_eq!(check_coverage(&options, &nets), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_args_min_prefix_len() {
+        let options =
+            parse_args(args(&["--min-prefixlen-v4", "8", "--min-prefixlen-v6=19"])).unwrap();
+        assert_eq!(options.min_prefix_len_v4, Some(8));
+        assert_eq!(options.min_prefix_len_v6, Some(19));
+        assert!(parse_args(args(&["--min-prefixlen-v4", "33"])).is_err());
+        assert!(parse_args(args(&["--min-prefixlen-v6", "/19"])).is_err());
+    }
+
+    #[test]
+    fn test_check_prefix_lens() {
+        let nets: Vec<IpNet> = ["10.0.0.0/7", "192.0.2.0/24", "2000::/16", "2001:db8::/32"]
+            .iter()
+            .map(|net| net.parse().unwrap())
+            .collect();
+        let mut options = parse_args(args(&["--min-prefixlen-v4", "7"])).unwrap();
+        assert_eq!(check_prefix_lens(&options, &nets), Ok(()));
+        options.min_prefix_len_v4 = Some(8);
+        options.min_prefix_len_v6 = Some(19);
+        assert_eq!(
+            check_prefix_lens(&options, &nets),
+            Err(
+                "2 networks in the result are too short; use --force to write them anyway"
+                    .to_string()
+            )
+        );
+        options.force = true;
+        assert_eq!(check_prefix_lens(&options, &nets), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_args_no_merge_adjacent() {
+        assert!(!parse_args(args(&[])).unwrap().no_merge_adjacent);
+        assert!(
+            parse_args(args(&["--no-merge-adjacent"]))
+                .unwrap()
+                .no_merge_adjacent
+        );
+    }
+
+    #[test]
+    fn test_parse_args_baseline() {
+        let options = parse_args(args(&[
+            "--format",
+            "html-report",
+            "--baseline=deployed.txt",
+            "a.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.baseline.as_deref(), Some("deployed.txt"));
+        assert_eq!(options.files, vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_annotate() {
+        assert_eq!(parse_args(args(&[])).unwrap().annotate, None);
+        let options = parse_args(args(&["--format", "markdown", "--annotate"])).unwrap();
+        assert_eq!(options.annotate, Some(Annotation::Special));
+        assert_eq!(options.format.as_deref(), Some("markdown"));
+        let options = parse_args(args(&["--annotate", "list.txt"])).unwrap();
+        assert_eq!(options.annotate, Some(Annotation::Special));
+        assert_eq!(options.files, vec!["list.txt"]);
+        let options = parse_args(args(&["--annotate", "asn", "--provider", "cymru"])).unwrap();
+        assert_eq!(options.annotate, Some(Annotation::Asn));
+        assert_eq!(options.asn_provider, Some(asn::Provider::Cymru));
+        assert!(parse_args(args(&["--annotate=whois"])).is_err());
+        assert!(parse_args(args(&["--provider", "whois"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_toml() {
+        let options = parse_args(args(&[
+            "--format=toml",
+            "--toml-key",
+            "firewall.blocklist.networks",
+            "--toml-stats",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.toml_key.as_deref(),
+            Some("firewall.blocklist.networks")
+        );
+        assert!(options.toml_stats);
+        assert!(parse_args(args(&["--toml-key"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_xml() {
+        let options = parse_args(args(&[
+            "--format",
+            "xml",
+            "--xml-root=acl",
+            "--xml-element",
+            "entry",
+            "--xml-attribute",
+            "prefix",
+        ]))
+        .unwrap();
+        assert_eq!(options.xml_root.as_deref(), Some("acl"));
+        assert_eq!(options.xml_element.as_deref(), Some("entry"));
+        assert_eq!(options.xml_attribute.as_deref(), Some("prefix"));
+    }
+
+    #[test]
+    fn test_parse_args_intrange_split() {
+        assert!(!parse_args(args(&[])).unwrap().intrange_split);
+        let options = parse_args(args(&["--format", "intrange", "--intrange-split"])).unwrap();
+        assert!(options.intrange_split);
+    }
+
+    #[test]
+    fn test_parse_args_bits_hex() {
+        assert!(!parse_args(args(&[])).unwrap().bits_hex);
+        assert!(
+            parse_args(args(&["--format=bits", "--bits-hex"]))
+                .unwrap()
+                .bits_hex
+        );
+    }
+
+    #[test]
+    fn test_parse_args_dnsmasq_option() {
+        let options = parse_args(args(&[
+            "--format",
+            "dnsmasq",
+            "--dnsmasq-option",
+            "bogus-nxdomain",
+        ]))
+        .unwrap();
+        assert_eq!(options.dnsmasq_option.as_deref(), Some("bogus-nxdomain"));
+        assert!(parse_args(args(&["--dnsmasq-option"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_action() {
+        let options = parse_args(args(&["--format=unbound", "--action=deny"])).unwrap();
+        assert_eq!(options.action.as_deref(), Some("deny"));
+        assert_eq!(parse_args(args(&[])).unwrap().action, None);
+    }
+
+    #[test]
+    fn test_parse_args_envoy_principal() {
+        let options = parse_args(args(&[
+            "--format",
+            "envoy",
+            "--envoy-principal",
+            "direct_remote_ip",
+        ]))
+        .unwrap();
+        assert_eq!(options.envoy_principal.as_deref(), Some("direct_remote_ip"));
+        assert!(parse_args(args(&["--envoy-principal"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_bpf() {
+        let options = parse_args(args(&[
+            "--format",
+            "bpf-map",
+            "--bpf-pin",
+            "/sys/fs/bpf/xdp/deny",
+            "--bpf-value",
+            "01",
+        ]))
+        .unwrap();
+        assert_eq!(options.bpf_pin.as_deref(), Some("/sys/fs/bpf/xdp/deny"));
+        assert_eq!(options.bpf_value.as_deref(), Some("01"));
+        assert!(parse_args(args(&["--bpf-pin"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_lang() {
+        let options = parse_args(args(&["--format", "codegen", "--lang", "rust"])).unwrap();
+        assert_eq!(options.lang.as_deref(), Some("rust"));
+        assert!(parse_args(args(&["--lang"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_firewalld_family() {
+        let options = parse_args(args(&[
+            "--format",
+            "firewalld",
+            "--firewalld-family",
+            "inet6",
+        ]))
+        .unwrap();
+        assert_eq!(options.firewalld_family.as_deref(), Some("inet6"));
+        assert!(parse_args(args(&["--firewalld-family"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_windows_direction() {
+        let options = parse_args(args(&[
+            "--format=windows-firewall",
+            "--windows-direction",
+            "Outbound",
+        ]))
+        .unwrap();
+        assert_eq!(options.windows_direction.as_deref(), Some("Outbound"));
+        assert!(parse_args(args(&["--windows-direction"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_frr() {
+        let options =
+            parse_args(args(&["--format=frr", "--frr-ge", "/20", "--frr-le=24"])).unwrap();
+        assert_eq!(options.frr_ge, Some(20));
+        assert_eq!(options.frr_le, Some(24));
+        assert!(parse_args(args(&["--frr-le", "129"])).is_err());
+        assert!(parse_args(args(&["--frr-ge"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_table() {
+        let options = parse_args(args(&[
+            "--format=iproute",
+            "--action=blackhole",
+            "--table",
+            "100",
+        ]))
+        .unwrap();
+        assert_eq!(options.action.as_deref(), Some("blackhole"));
+        assert_eq!(options.table.as_deref(), Some("100"));
+        assert!(parse_args(args(&["--table"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_istio() {
+        let options = parse_args(args(&[
+            "--format=istio",
+            "--istio-selector",
+            "app=ingress",
+            "--istio-selector=tier=edge",
+            "--istio-remote",
+        ]))
+        .unwrap();
+        assert_eq!(options.istio_selector, vec!["app=ingress", "tier=edge"]);
+        assert!(options.istio_remote);
+    }
+
+    #[test]
+    fn test_parse_args_keep_structure() {
+        assert!(!parse_args(args(&[])).unwrap().keep_structure);
+        assert!(
+            parse_args(args(&["--keep-structure", "--format", "tree"]))
+                .unwrap()
+                .keep_structure
+        );
+    }
+
+    #[test]
+    fn test_parse_args_anonymize() {
+        let options = parse_args(args(&["anonymize", "--v4", "/24", "--v6=56", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Anonymize);
+        assert_eq!(options.len_v4, Some(24));
+        assert_eq!(options.len_v6, Some(56));
+        assert_eq!(options.files, vec!["a.txt"]);
+        assert!(parse_args(args(&["anonymize", "--v4", "/33"])).is_err());
+        assert!(parse_args(args(&["anonymize", "--v6", "big"])).is_err());
+    }
+
+    #[test]
+    fn test_anonymize() {
+        let anonymize = |net: &str| super::anonymize(&net.parse().unwrap(), 24, 48).to_string();
+        assert_eq!(anonymize("192.0.2.77/32"), "192.0.2.0/24");
+        assert_eq!(anonymize("192.0.2.128/25"), "192.0.2.0/24");
+        assert_eq!(anonymize("10.0.0.0/8"), "10.0.0.0/8");
+        assert_eq!(anonymize("2001:db8:1:2::5/128"), "2001:db8:1::/48");
+        assert_eq!(anonymize("2001:db8::/32"), "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_parse_args_explode() {
+        let options = parse_args(args(&[
+            "explode",
+            "--to",
+            "/24",
+            "--max-output=1_000_000",
+            "a.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Explode);
+        assert_eq!(options.explode_to, Some(24));
+        assert_eq!(options.max_output, Some(1_000_000));
+        assert_eq!(
+            parse_args(args(&["--to", "64"])).unwrap().explode_to,
+            Some(64)
+        );
+        assert!(parse_args(args(&["--to", "/129"])).is_err());
+        assert!(parse_args(args(&["--max-output", "lots"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_tui() {
+        let options = parse_args(args(&["tui", "--format", "json", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Tui);
+        assert_eq!(options.files, vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_ptr_zones() {
+        let options = parse_args(args(&["ptr-zones", "--skeleton", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::PtrZones);
+        assert!(options.skeleton);
+        assert!(!parse_args(args(&["ptr-zones"])).unwrap().skeleton);
+    }
+
+    #[test]
+    fn test_parse_args_push() {
+        let options = parse_args(args(&[
+            "push",
+            "cloudflare",
+            "--list",
+            "my_blocklist",
+            "--dry-run",
+            "a.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Push);
+        assert_eq!(options.provider.as_deref(), Some("cloudflare"));
+        assert_eq!(options.lists, vec!["my_blocklist"]);
+        assert!(options.dry_run);
+        assert_eq!(options.files, vec!["a.txt"]);
+        assert_eq!(parse_args(args(&["push"])).unwrap().provider, None);
+        let options = parse_args(args(&[
+            "push",
+            "fastly",
+            "--service",
+            "SU1Z0",
+            "--acl",
+            "blocklist",
+        ]))
+        .unwrap();
+        assert_eq!(options.provider.as_deref(), Some("fastly"));
+        assert_eq!(options.service.as_deref(), Some("SU1Z0"));
+        assert_eq!(options.acl.as_deref(), Some("blocklist"));
+        let options = parse_args(args(&[
+            "push",
+            "akamai",
+            "--list-id",
+            "12345_BLOCKLIST",
+            "--akamai-mode",
+            "append",
+            "--akamai-activate",
+            "production",
+        ]))
+        .unwrap();
+        assert_eq!(options.list_id.as_deref(), Some("12345_BLOCKLIST"));
+        assert_eq!(options.akamai_mode, Some(push::akamai::Mode::Append));
+        assert_eq!(
+            options.akamai_activate,
+            Some(push::akamai::Network::Production)
+        );
+        assert!(parse_args(args(&["push", "akamai", "--akamai-mode", "merge"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_irr_check() {
+        let options = parse_args(args(&[
+            "irr-check",
+            "--origin",
+            "AS64500",
+            "--irr-server=rr.ntt.net",
+            "--irr-sources",
+            "radb, RIPE",
+            "customer.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::IrrCheck);
+        assert_eq!(options.origin, Some(64500));
+        assert_eq!(options.irr_server.as_deref(), Some("rr.ntt.net"));
+        assert_eq!(options.irr_sources, vec!["RADB", "RIPE"]);
+        assert_eq!(options.files, vec!["customer.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_geoip() {
+        let options = parse_args(args(&[
+            "geoip",
+            "update",
+            "--geoip-config",
+            "/usr/local/etc/GeoIP.conf",
+            "--data-dir=/var/lib/lfc",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Geoip);
+        assert_eq!(options.geoip_action.as_deref(), Some("update"));
+        assert_eq!(
+            options.geoip_config.as_deref(),
+            Some("/usr/local/etc/GeoIP.conf")
+        );
+        assert_eq!(options.data_dir.as_deref(), Some("/var/lib/lfc"));
+        assert_eq!(options.cache_dir, None);
+        assert!(options.files.is_empty());
+        assert_eq!(parse_args(args(&["geoip"])).unwrap().geoip_action, None);
+    }
+
+    #[test]
+    fn test_parse_args_by_country() {
+        let options =
+            parse_args(args(&["by-country", "--format", "json", "blocklist.txt"])).unwrap();
+        assert_eq!(options.command, Command::ByCountry);
+        assert_eq!(options.format.as_deref(), Some("json"));
+        assert_eq!(options.files, vec!["blocklist.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_by_asn() {
+        let options = parse_args(args(&["by-asn", "--provider", "cymru", "abusers.txt"])).unwrap();
+        assert_eq!(options.command, Command::ByAsn);
+        assert_eq!(options.asn_provider, Some(asn::Provider::Cymru));
+        assert_eq!(options.files, vec!["abusers.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_fetch_ripestat() {
+        let options = parse_args(args(&[
+            "fetch",
+            "ripestat",
+            "--asn",
+            "AS64500, 64501",
+            "--asn=AS64502",
+            "--since",
+            "2024-01-01",
+            "--until=2024-02-01T12:00:00Z",
+        ]))
+        .unwrap();
+        assert_eq!(options.feed.as_deref(), Some("ripestat"));
+        assert_eq!(options.asns, vec![64500, 64501, 64502]);
+        assert_eq!(options.since.as_deref(), Some("2024-01-01"));
+        assert_eq!(options.until.as_deref(), Some("2024-02-01T12:00:00Z"));
+        assert!(parse_args(args(&["fetch", "ripestat", "--asn", "RIPE"])).is_err());
+        assert!(parse_args(args(&["fetch", "ripestat", "--since", "yesterday"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_fetch() {
+        let options = parse_args(args(&["fetch", "googlebot", "--format", "json"])).unwrap();
+        assert_eq!(options.command, Command::Fetch);
+        assert_eq!(options.feed.as_deref(), Some("googlebot"));
+        assert_eq!(options.format.as_deref(), Some("json"));
+        assert!(options.files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_args_info() {
+        let options = parse_args(args(&["info", "203.0.113.0/26", "2001:db8::1"])).unwrap();
+        assert_eq!(options.command, Command::Info);
+        assert_eq!(options.files, vec!["203.0.113.0/26", "2001:db8::1"]);
+    }
+
+    #[test]
+    fn test_parse_args_hosts() {
+        let options = parse_args(args(&["hosts", "--max", "65_536", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Hosts);
+        assert_eq!(options.max_hosts, Some(65536));
+        assert!(parse_args(args(&["hosts", "--max=-1"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_dedupe_only() {
+        assert!(!parse_args(args(&[])).unwrap().dedupe_only);
+        assert!(parse_args(args(&["--dedupe-only"])).unwrap().dedupe_only);
+    }
+
+    #[test]
+    fn test_parse_args_respect_allocations() {
+        assert!(!parse_args(args(&[])).unwrap().respect_allocations);
+        let options = parse_args(args(&["--respect-allocations", "abusers.txt"])).unwrap();
+        assert!(options.respect_allocations);
+        assert_eq!(options.files, vec!["abusers.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_scope_aware() {
+        assert!(!parse_args(args(&[])).unwrap().scope_aware);
+        let options = parse_args(args(&["--scope-aware", "routes.txt"])).unwrap();
+        assert!(options.scope_aware);
+        assert!(keeps_regions(&options));
+        let special = no_merge_across(&options).unwrap();
+        assert!(special.contains(&"192.0.2.0/24".parse().unwrap()));
+        assert_eq!(special, regions::load("builtin:special").unwrap());
+    }
+
+    #[test]
+    fn test_parse_args_no_merge_across() {
+        let options = parse_args(args(&[
+            "--no-merge-across",
+            "builtin:rir",
+            "--no-merge-across=sites.txt",
+            "routes.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.no_merge_across, vec!["builtin:rir", "sites.txt"]);
+        assert_eq!(options.files, vec!["routes.txt"]);
+        // A region file that's gone is an error, not an exit, so that a
+        // server reloading on SIGHUP can keep going.
+        assert!(no_merge_across(&options).is_err());
+        assert!(aggregator(&options).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_rir() {
+        let options = parse_args(args(&["--rir", "arin, RIPE", "--rir=lacnic"])).unwrap();
+        assert_eq!(
+            options.rirs,
+            vec![
+                delegated::Rir::Arin,
+                delegated::Rir::Ripe,
+                delegated::Rir::Lacnic
+            ]
+        );
+        assert!(parse_args(args(&["--rir", "iana"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_drop() {
+        let options = parse_args(args(&["--drop", "multicast,loopback", "--drop=cgnat"])).unwrap();
+        assert_eq!(
+            options.drop,
+            vec![
+                bogons::Category::Multicast,
+                bogons::Category::Loopback,
+                bogons::Category::Cgnat
+            ]
+        );
+        assert!(parse_args(args(&["--drop", "martians"])).is_err());
+        let options = parse_args(args(&[
+            "--drop-bogons",
+            "rfc6890",
+            "--drop-bogons=martians.txt",
+        ]))
+        .unwrap();
+        assert_eq!(options.drop_bogons, vec!["rfc6890", "martians.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_resolve() {
+        let options = parse_args(args(&[
+            "resolve",
+            "--list",
+            "allow=allow.txt:100",
+            "--list=block=block.txt:50",
+        ]))
+        .unwrap();
+        assert_eq!(options.command, Command::Resolve);
+        assert_eq!(
+            options.lists,
+            vec!["allow=allow.txt:100", "block=block.txt:50"]
+        );
+        assert!(options.files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_args_expiry() {
+        let options = parse_args(args(&[
+            "--now",
+            "2025-09-19T12:34:56Z",
+            "--expire-after=7d",
+        ]))
+        .unwrap();
+        assert_eq!(options.now, Some(1_758_285_296));
+        assert_eq!(options.expire_after, Some(Duration::from_secs(604_800)));
+        assert!(parse_args(args(&["--now", "tomorrow"])).is_err());
+        assert!(parse_args(args(&["--expire-after", "1w"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_lint() {
+        let options =
+            parse_args(args(&["lint", "--v4", "/16", "--format", "json", "a.txt"])).unwrap();
+        assert_eq!(options.command, Command::Lint);
+        assert_eq!(options.len_v4, Some(16));
+        assert_eq!(options.len_v6, None);
+        assert_eq!(options.files, vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_parse_args_sample() {
+        let options = parse_args(args(&[
+            "--sample",
+            "1_000",
+            "--seed=42",
+            "--sample-weighted",
+        ]))
+        .unwrap();
+        assert_eq!(options.sample, Some(1000));
+        assert_eq!(options.seed, Some(42));
+        assert!(options.sample_weighted);
+        assert!(parse_args(args(&["--sample", "some"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_set_files() {
+        let options = parse_args(args(&["--save-set", "out.lfcset", "a.txt"])).unwrap();
+        assert_eq!(options.save_set.as_deref(), Some("out.lfcset"));
+        let options = parse_args(args(&["serve", "--load-set=out.lfcset"])).unwrap();
+        assert_eq!(options.command, Command::Serve);
+        assert_eq!(options.load_set.as_deref(), Some("out.lfcset"));
+        assert!(options.files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_args_missing_value() {
+        assert!(parse_args(args(&["--color"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_args_diff_only_as_first_word() {
+        let options = parse_args(args(&["a.txt", "diff"])).unwrap();
+        assert_eq!(options.command, Command::Aggregate);
+        assert_eq!(options.files, vec!["a.txt", "diff"]);
     }
 }
@@ -0,0 +1,219 @@
+//! Prometheus metrics.
+//!
+//! When lfc runs and exits, `--metrics-file` writes the metrics in the
+//! Prometheus text format for node_exporter's textfile collector to pick
+//! up. `lfc serve --listen` answers with them at `/metrics` instead, for
+//! each of its lists.
+
+use ipnet::IpNet;
+use lfc::space::{self, Family};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How many times reading the sources has failed since lfc started.
+static FETCH_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Counts a failure to read the sources, for `lfc_fetch_errors`.
+pub fn fetch_failed() {
+    FETCH_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// What the metrics say about one aggregated list.
+pub struct List<'a> {
+    pub name: &'a str,
+    pub nets: &'a [IpNet],
+    /// How long parsing and aggregating it took.
+    pub duration: Duration,
+    /// When it was aggregated, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// When the next entry in its input expires, if one does.
+    pub next_expiry: Option<u64>,
+}
+
+/// Writes the help and type lines that come before a metric's samples.
+fn header(out: &mut String, metric: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", metric, help);
+    let _ = writeln!(out, "# TYPE {} gauge", metric);
+}
+
+/// Renders the metrics for the lists, and how many times reading the
+/// sources has failed.
+pub fn render(lists: &[List]) -> String {
+    let mut out = String::new();
+    let names: Vec<String> = lists
+        .iter()
+        .map(|list| {
+            list.name
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        })
+        .collect();
+    let label = |name: &str, family: Option<Family>| match family {
+        Some(family) => format!("{{list=\"{}\",family=\"{}\"}}", name, family),
+        None => format!("{{list=\"{}\"}}", name),
+    };
+
+    header(
+        &mut out,
+        "lfc_prefixes",
+        "Number of prefixes in the aggregated list.",
+    );
+    for (list, name) in lists.iter().zip(&names) {
+        for family in [Family::V4, Family::V6] {
+            let count = list
+                .nets
+                .iter()
+                .filter(|net| Family::of(net) == family)
+                .count();
+            let _ = writeln!(out, "lfc_prefixes{} {}", label(name, Some(family)), count);
+        }
+    }
+
+    header(
+        &mut out,
+        "lfc_addresses",
+        "Number of addresses covered by the aggregated list.",
+    );
+    for (list, name) in lists.iter().zip(&names) {
+        for family in [Family::V4, Family::V6] {
+            let size = space::size(&space::ranges(list.nets, family));
+            let _ = writeln!(
+                out,
+                "lfc_addresses{} {}",
+                label(name, Some(family)),
+                size as f64
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "lfc_last_update_timestamp_seconds",
+        "When the list was last aggregated.",
+    );
+    for (list, name) in lists.iter().zip(&names) {
+        let _ = writeln!(
+            out,
+            "lfc_last_update_timestamp_seconds{} {}",
+            label(name, None),
+            list.timestamp
+        );
+    }
+
+    header(
+        &mut out,
+        "lfc_aggregation_duration_seconds",
+        "How long parsing and aggregating took.",
+    );
+    for (list, name) in lists.iter().zip(&names) {
+        let _ = writeln!(
+            out,
+            "lfc_aggregation_duration_seconds{} {}",
+            label(name, None),
+            list.duration.as_secs_f64()
+        );
+    }
+
+    if lists.iter().any(|list| list.next_expiry.is_some()) {
+        header(
+            &mut out,
+            "lfc_next_expiry_timestamp_seconds",
+            "When the next entry in the input expires.",
+        );
+    }
+    for (list, name) in lists.iter().zip(&names) {
+        if let Some(next_expiry) = list.next_expiry {
+            let _ = writeln!(
+                out,
+                "lfc_next_expiry_timestamp_seconds{} {}",
+                label(name, None),
+                next_expiry
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "lfc_fetch_errors",
+        "How many times reading the sources has failed since lfc started.",
+    );
+    let _ = writeln!(
+        out,
+        "lfc_fetch_errors {}",
+        FETCH_ERRORS.load(Ordering::Relaxed)
+    );
+    out
+}
+
+/// Writes the metrics to a file, atomically so the collector never reads a
+/// partial file.
+pub fn write(path: &Path, metrics: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, metrics)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let result: Vec<IpNet> = vec![
+            "10.0.0.0/23".parse().unwrap(),
+            "192.168.0.0/24".parse().unwrap(),
+            "2001:db8::/126".parse().unwrap(),
+        ];
+        let metrics = render(&[List {
+            name: "feed",
+            nets: &result,
+            duration: Duration::from_millis(250),
+            timestamp: 1_758_285_296,
+            next_expiry: Some(1_758_300_000),
+        }]);
+        assert!(metrics.contains("lfc_prefixes{list=\"feed\",family=\"IPv4\"} 2\n"));
+        assert!(metrics.contains("lfc_prefixes{list=\"feed\",family=\"IPv6\"} 1\n"));
+        assert!(metrics.contains("lfc_addresses{list=\"feed\",family=\"IPv4\"} 768\n"));
+        assert!(metrics.contains("lfc_addresses{list=\"feed\",family=\"IPv6\"} 4\n"));
+        assert!(metrics.contains("lfc_last_update_timestamp_seconds{list=\"feed\"} 1758285296\n"));
+        assert!(metrics.contains("lfc_aggregation_duration_seconds{list=\"feed\"} 0.25\n"));
+        assert!(metrics.contains("lfc_next_expiry_timestamp_seconds{list=\"feed\"} 1758300000\n"));
+        assert!(metrics.contains("# TYPE lfc_fetch_errors gauge\nlfc_fetch_errors "));
+    }
+
+    #[test]
+    fn test_render_escapes_list_name() {
+        let metrics = render(&[List {
+            name: "a\"b",
+            nets: &[],
+            duration: Duration::ZERO,
+            timestamp: 0,
+            next_expiry: None,
+        }]);
+        assert!(metrics.contains("{list=\"a\\\"b\"}"));
+    }
+
+    #[test]
+    fn test_render_lists() {
+        let list = |name| List {
+            name,
+            nets: &[],
+            duration: Duration::ZERO,
+            timestamp: 0,
+            next_expiry: None,
+        };
+        let metrics = render(&[list("a"), list("b")]);
+        assert_eq!(metrics.matches("# TYPE lfc_prefixes gauge\n").count(), 1);
+        assert!(metrics.contains(
+            "lfc_last_update_timestamp_seconds{list=\"a\"} 0\n\
+             lfc_last_update_timestamp_seconds{list=\"b\"} 0\n"
+        ));
+        assert!(!metrics.contains("lfc_next_expiry"));
+    }
+}
@@ -0,0 +1,618 @@
+//! The output formats that take options of their own.
+//!
+//! Each one has an entry in [`CONFIGURED`] under its `--format` name, with
+//! the options that only it takes and the function that builds it from
+//! them. Formats without an entry come from the registry as they are.
+//! Adding a configurable format means adding its entry and its options to
+//! `Options`; nothing else picks a formatter by which options were given.
+
+use crate::{Annotation, Options, info};
+use ipnet::IpNet;
+use lfc::format::{self, Formatter};
+use lfc::setfile;
+use lfc::source::{self, InputSource};
+use lfc::space::Family;
+use std::path::Path;
+
+/// What a formatter can be built from besides the options.
+#[derive(Default)]
+pub struct Context<'a> {
+    /// The aggregated networks that will be written.
+    pub result: &'a [IpNet],
+    /// The inputs they came from.
+    pub inputs: &'a [Box<dyn InputSource>],
+}
+
+/// Builds a formatter from the options and the context.
+type Build = fn(&Options, &Context) -> Result<Box<dyn Formatter>, String>;
+
+/// Returns whether an option was given.
+type Given = fn(&Options) -> bool;
+
+/// A format that takes options.
+struct Configured {
+    /// Its `--format` name.
+    name: &'static str,
+    /// The options that only it, or other formats listing them too, takes,
+    /// with whether each was given.
+    options: &'static [(&'static str, Given)],
+    build: Build,
+}
+
+/// The formats that take options, in the registry's order.
+const CONFIGURED: &[Configured] = &[
+    Configured {
+        name: "dot",
+        options: &[],
+        build: dot,
+    },
+    Configured {
+        name: "html-report",
+        options: &[("--baseline", |options| options.baseline.is_some())],
+        build: html_report,
+    },
+    Configured {
+        name: "markdown",
+        options: &[("--annotate", |options| options.annotate.is_some())],
+        build: markdown,
+    },
+    Configured {
+        name: "toml",
+        options: &[
+            ("--toml-key", |options| options.toml_key.is_some()),
+            ("--toml-stats", |options| options.toml_stats),
+        ],
+        build: toml,
+    },
+    Configured {
+        name: "xml",
+        options: &[
+            ("--xml-root", |options| options.xml_root.is_some()),
+            ("--xml-element", |options| options.xml_element.is_some()),
+            ("--xml-attribute", |options| options.xml_attribute.is_some()),
+        ],
+        build: xml,
+    },
+    Configured {
+        name: "intrange",
+        options: &[("--intrange-split", |options| options.intrange_split)],
+        build: intrange,
+    },
+    Configured {
+        name: "bits",
+        options: &[("--bits-hex", |options| options.bits_hex)],
+        build: bits,
+    },
+    Configured {
+        name: "dnsmasq",
+        options: &[("--dnsmasq-option", |options| {
+            options.dnsmasq_option.is_some()
+        })],
+        build: dnsmasq,
+    },
+    Configured {
+        name: "unbound",
+        options: &[("--action", |options| options.action.is_some())],
+        build: unbound,
+    },
+    Configured {
+        name: "bind-acl",
+        options: &[("--except", |options| options.except.is_some())],
+        build: bind_acl,
+    },
+    Configured {
+        name: "envoy",
+        options: &[("--envoy-principal", |options| {
+            options.envoy_principal.is_some()
+        })],
+        build: envoy,
+    },
+    Configured {
+        name: "istio",
+        options: &[
+            ("--action", |options| options.action.is_some()),
+            ("--istio-selector", |options| {
+                !options.istio_selector.is_empty()
+            }),
+            ("--istio-remote", |options| options.istio_remote),
+        ],
+        build: istio,
+    },
+    Configured {
+        name: "bpf-map",
+        options: &[
+            ("--bpf-pin", |options| options.bpf_pin.is_some()),
+            ("--bpf-value", |options| options.bpf_value.is_some()),
+        ],
+        build: bpf_map,
+    },
+    Configured {
+        name: "codegen",
+        options: &[("--lang", |options| options.lang.is_some())],
+        build: codegen,
+    },
+    Configured {
+        name: "firewalld",
+        options: &[("--firewalld-family", |options| {
+            options.firewalld_family.is_some()
+        })],
+        build: firewalld,
+    },
+    Configured {
+        name: "windows-firewall",
+        options: &[
+            ("--action", |options| options.action.is_some()),
+            ("--windows-direction", |options| {
+                options.windows_direction.is_some()
+            }),
+        ],
+        build: windows_firewall,
+    },
+    Configured {
+        name: "iproute",
+        options: &[
+            ("--action", |options| options.action.is_some()),
+            ("--table", |options| options.table.is_some()),
+        ],
+        build: iproute,
+    },
+    Configured {
+        name: "bsd-route",
+        options: &[("--action", |options| options.action.is_some())],
+        build: bsd_route,
+    },
+    Configured {
+        name: "frr",
+        options: &[
+            ("--frr-ge", |options| options.frr_ge.is_some()),
+            ("--frr-le", |options| options.frr_le.is_some()),
+        ],
+        build: frr,
+    },
+    Configured {
+        name: "openbgpd",
+        options: &[],
+        build: openbgpd,
+    },
+];
+
+/// Returns the `--format` name, which is text if there isn't one.
+fn format_name(options: &Options) -> &str {
+    options.format.as_deref().unwrap_or("text")
+}
+
+/// Checks that every format's option that was given is one that the format
+/// being written takes.
+pub fn check(options: &Options) -> Result<(), String> {
+    let format = format_name(options);
+    for (option, given) in CONFIGURED.iter().flat_map(|entry| entry.options) {
+        if !given(options) {
+            continue;
+        }
+        let takers: Vec<&str> = CONFIGURED
+            .iter()
+            .filter(|entry| entry.options.iter().any(|(name, _)| name == option))
+            .map(|entry| entry.name)
+            .collect();
+        if takers.contains(&format) {
+            continue;
+        }
+        let takers = match takers.as_slice() {
+            [only] => only.to_string(),
+            [rest @ .., last] => format!("{}, or {}", rest.join(", "), last),
+            [] => unreachable!(),
+        };
+        return Err(format!("{} only works with --format {}", option, takers));
+    }
+    Ok(())
+}
+
+/// Returns the formatter that `--format` names, built from the options, or
+/// `None` if it doesn't take any and the registry's will do.
+pub fn configure(
+    options: &Options,
+    context: &Context,
+) -> Result<Option<Box<dyn Formatter>>, String> {
+    let name = format_name(options);
+    CONFIGURED
+        .iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| (entry.build)(options, context))
+        .transpose()
+}
+
+/// Returns the `--name` option, or lfc if there isn't one.
+fn name(options: &Options) -> &str {
+    options.name.as_deref().unwrap_or("lfc")
+}
+
+/// Builds `--format dot` from the aggregation's events.
+fn dot(_: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let events = std::mem::take(&mut *crate::EVENTS.lock().unwrap());
+    Ok(Box::new(format::Dot::new(events)))
+}
+
+/// Builds `--format html-report`, with each input as a source, and the
+/// `--baseline` result to compare with, if there is one.
+fn html_report(options: &Options, context: &Context) -> Result<Box<dyn Formatter>, String> {
+    let exact = crate::aggregator(options)?.exact();
+    let mut sources = context
+        .inputs
+        .iter()
+        .map(|input| {
+            let nets = exact
+                .aggregate_records(crate::input_records([input]))
+                .map_err(|err| err.to_string())?;
+            Ok((input.name().to_string(), nets))
+        })
+        .collect::<Result<Vec<(String, Vec<IpNet>)>, String>>()?;
+    if let Some(path) = &options.load_set {
+        let set = setfile::load(Path::new(path)).map_err(|err| err.to_string())?;
+        sources.push((path.clone(), set));
+    }
+    let mut report = format::HtmlReport::default().sources(sources);
+    if let Some(name) = &options.name {
+        report = report.title(format!("lfc report: {}", name));
+    }
+    if let Some(path) = &options.baseline {
+        let baseline = source::open(path).map_err(|err| err.to_string())?;
+        let baseline = crate::record_nets(crate::input_records([&baseline]))
+            .collect::<lfc::Result<Vec<_>>>()
+            .map_err(|err| err.to_string())?;
+        report = report.baseline(path.clone(), baseline);
+    }
+    Ok(Box::new(report))
+}
+
+/// Builds `--format markdown`, with the notes `--annotate` asks for.
+fn markdown(options: &Options, context: &Context) -> Result<Box<dyn Formatter>, String> {
+    let table = format::Markdown::default();
+    let notes = match options.annotate {
+        None => return Ok(Box::new(table)),
+        Some(Annotation::Special) => context
+            .result
+            .iter()
+            .filter_map(|net| info::describe(net).map(|note| (*net, note)))
+            .collect(),
+        Some(Annotation::Asn) => options
+            .asn_provider
+            .unwrap_or_default()
+            .origins(context.result)?
+            .into_iter()
+            .map(|(net, origin)| (net, origin.to_string()))
+            .collect(),
+    };
+    Ok(Box::new(table.notes(notes)))
+}
+
+/// Builds `--format toml` with the `--toml-key` and `--toml-stats` options.
+fn toml(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let path = options.toml_key.as_deref().unwrap_or("networks");
+    let toml = format::Toml::default()
+        .key(path)
+        .ok_or_else(|| format!("--toml-key {:?} has an empty part", path))?
+        .stats(options.toml_stats);
+    Ok(Box::new(toml))
+}
+
+/// Builds `--format xml` with the names the options set.
+fn xml(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let bad = |option: &str, name: &str| format!("{} {:?} isn't a valid XML name", option, name);
+    let mut xml = format::Xml::default();
+    if let Some(name) = &options.xml_root {
+        xml = xml.root(name).ok_or_else(|| bad("--xml-root", name))?;
+    }
+    if let Some(name) = &options.xml_element {
+        xml = xml
+            .element(name)
+            .ok_or_else(|| bad("--xml-element", name))?;
+    }
+    if let Some(name) = &options.xml_attribute {
+        xml = xml
+            .attribute(name)
+            .ok_or_else(|| bad("--xml-attribute", name))?;
+    }
+    Ok(Box::new(xml))
+}
+
+/// Builds `--format intrange` with the `--intrange-split` option.
+fn intrange(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    Ok(Box::new(
+        format::IntRange::default().split(options.intrange_split),
+    ))
+}
+
+/// Builds `--format bits` with the `--bits-hex` option.
+fn bits(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    Ok(Box::new(format::Bits::default().hex(options.bits_hex)))
+}
+
+/// Builds `--format dnsmasq` with the `--dnsmasq-option` option.
+fn dnsmasq(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut dnsmasq = format::Dnsmasq::default();
+    if let Some(option) = &options.dnsmasq_option {
+        dnsmasq = dnsmasq.option(option).ok_or_else(|| {
+            format!(
+                "--dnsmasq-option '{}' isn't ignore-address or bogus-nxdomain",
+                option
+            )
+        })?;
+    }
+    Ok(Box::new(dnsmasq))
+}
+
+/// Builds `--format unbound` with the `--action` option.
+fn unbound(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut unbound = format::Unbound::default();
+    if let Some(action) = &options.action {
+        unbound = unbound
+            .action(action)
+            .ok_or_else(|| format!("--action '{}' isn't an Unbound action", action))?;
+    }
+    Ok(Box::new(unbound))
+}
+
+/// Builds `--format bind-acl`, named by `--name`, negating the networks in
+/// the `--except` file.
+fn bind_acl(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let name = name(options);
+    let mut acl = format::BindAcl::default()
+        .name(name)
+        .ok_or_else(|| format!("--name '{}' can't name a BIND ACL", name))?;
+    if let Some(path) = &options.except {
+        let except = source::open(path).map_err(|err| err.to_string())?;
+        let except = crate::record_nets(crate::input_records([&except]))
+            .collect::<lfc::Result<Vec<_>>>()
+            .map_err(|err| err.to_string())?;
+        acl = acl.except(except);
+    }
+    Ok(Box::new(acl))
+}
+
+/// Builds `--format envoy` with the `--envoy-principal` option.
+fn envoy(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut envoy = format::Envoy::default();
+    if let Some(principal) = &options.envoy_principal {
+        envoy = envoy.principal(principal).ok_or_else(|| {
+            format!(
+                "--envoy-principal '{}' isn't source_ip, direct_remote_ip, or remote_ip",
+                principal
+            )
+        })?;
+    }
+    Ok(Box::new(envoy))
+}
+
+/// Builds `--format istio`, named by `--name`, with the `--action`,
+/// `--istio-selector`, and `--istio-remote` options.
+fn istio(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let name = name(options);
+    let mut istio = format::Istio::default()
+        .name(name)
+        .ok_or_else(|| format!("--name '{}' can't name a Kubernetes object", name))?
+        .remote(options.istio_remote);
+    if let Some(action) = &options.action {
+        istio = istio
+            .action(action)
+            .ok_or_else(|| format!("--action '{}' isn't ALLOW, DENY, or AUDIT", action))?;
+    }
+    for selector in &options.istio_selector {
+        istio = selector
+            .split_once('=')
+            .and_then(|(label, value)| istio.label(label, value))
+            .ok_or_else(|| format!("--istio-selector '{}' isn't LABEL=VALUE", selector))?;
+    }
+    Ok(Box::new(istio))
+}
+
+/// Builds `--format bpf-map` with the `--bpf-pin` and `--bpf-value`
+/// options.
+fn bpf_map(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut bpf = format::BpfMap::default();
+    if let Some(pin) = &options.bpf_pin {
+        bpf = bpf
+            .pin(pin)
+            .ok_or_else(|| format!("--bpf-pin '{}' isn't a plain path", pin))?;
+    }
+    if let Some(value) = &options.bpf_value {
+        bpf = bpf
+            .value(value)
+            .ok_or_else(|| format!("--bpf-value '{}' isn't hex bytes", value))?;
+    }
+    Ok(Box::new(bpf))
+}
+
+/// Builds `--format codegen` in the `--lang` language, with names prefixed
+/// by `--name`.
+fn codegen(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let lang = options.lang.as_deref().unwrap_or("c");
+    let name = name(options);
+    let codegen = format::Codegen::default()
+        .lang(lang)
+        .ok_or_else(|| format!("--lang '{}' isn't c or rust", lang))?
+        .prefix(name)
+        .ok_or_else(|| format!("--name '{}' isn't an identifier", name))?;
+    Ok(Box::new(codegen))
+}
+
+/// Builds `--format firewalld` for the networks, with the
+/// `--firewalld-family` option, which they need if they're of both
+/// families. Warns about the networks the family leaves out.
+fn firewalld(options: &Options, context: &Context) -> Result<Box<dyn Formatter>, String> {
+    let nets = context.result;
+    let Some(name) = &options.firewalld_family else {
+        if nets
+            .iter()
+            .any(|net| Family::of(net) != Family::of(&nets[0]))
+        {
+            return Err(
+                "a firewalld ipset holds one family; pick inet or inet6 with --firewalld-family"
+                    .to_string(),
+            );
+        }
+        return Ok(Box::new(format::Firewalld::default()));
+    };
+    let firewalld = format::Firewalld::default()
+        .family(name)
+        .ok_or_else(|| format!("--firewalld-family '{}' isn't inet or inet6", name))?;
+    let family = if name == "inet" {
+        Family::V4
+    } else {
+        Family::V6
+    };
+    let skipped = nets.iter().filter(|net| Family::of(net) != family).count();
+    if skipped > 0 {
+        crate::diag::note!(
+            "warning: skipping {} networks that aren't {} because the set is {}",
+            skipped,
+            family,
+            name
+        );
+    }
+    Ok(Box::new(firewalld))
+}
+
+/// Builds `--format windows-firewall`, named by `--name`, with the
+/// `--action` and `--windows-direction` options.
+fn windows_firewall(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let name = name(options);
+    let mut windows = format::WindowsFirewall::default()
+        .name(name)
+        .ok_or_else(|| format!("--name '{}' can't name a firewall rule", name))?;
+    if let Some(action) = &options.action {
+        windows = windows
+            .action(action)
+            .ok_or_else(|| format!("--action '{}' isn't Block or Allow", action))?;
+    }
+    if let Some(direction) = &options.windows_direction {
+        windows = windows.direction(direction).ok_or_else(|| {
+            format!(
+                "--windows-direction '{}' isn't Inbound or Outbound",
+                direction
+            )
+        })?;
+    }
+    Ok(Box::new(windows))
+}
+
+/// Builds `--format iproute` with the `--action` and `--table` options.
+fn iproute(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut iproute = format::IpRoute::default();
+    if let Some(action) = &options.action {
+        iproute = iproute.action(action).ok_or_else(|| {
+            format!(
+                "--action '{}' isn't blackhole, unreachable, or prohibit",
+                action
+            )
+        })?;
+    }
+    if let Some(table) = &options.table {
+        iproute = iproute
+            .table(table)
+            .ok_or_else(|| format!("--table '{}' isn't a routing table", table))?;
+    }
+    Ok(Box::new(iproute))
+}
+
+/// Builds `--format bsd-route` with the `--action` option.
+fn bsd_route(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let mut bsd = format::BsdRoute::default();
+    if let Some(action) = &options.action {
+        bsd = bsd
+            .action(action)
+            .ok_or_else(|| format!("--action '{}' isn't blackhole or reject", action))?;
+    }
+    Ok(Box::new(bsd))
+}
+
+/// Builds `--format frr`, named by `--name`, with the `--frr-ge` and
+/// `--frr-le` options.
+fn frr(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let name = name(options);
+    let mut frr = format::Frr::default()
+        .name(name)
+        .ok_or_else(|| format!("--name '{}' can't name a prefix list", name))?;
+    if let Some(ge) = options.frr_ge {
+        frr = frr.ge(ge);
+    }
+    if let Some(le) = options.frr_le {
+        frr = frr.le(le);
+    }
+    Ok(Box::new(frr))
+}
+
+/// Builds `--format openbgpd`, named by `--name`, with the
+/// `--prefixlen-range` option.
+fn openbgpd(options: &Options, _: &Context) -> Result<Box<dyn Formatter>, String> {
+    let name = name(options);
+    let mut set = format::OpenBgpd::default()
+        .name(name)
+        .ok_or_else(|| format!("--name '{}' can't name a prefix set", name))?;
+    if let Some((low, high)) = options.prefix_lengths {
+        set = set.lengths(low, high);
+    }
+    Ok(Box::new(set))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_args;
+
+    fn options(args: &[&str]) -> Options {
+        parse_args(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check(&options(&["--format", "toml", "--toml-stats"])),
+            Ok(())
+        );
+        assert_eq!(
+            check(&options(&["--format", "json", "--toml-stats"])),
+            Err("--toml-stats only works with --format toml".to_string())
+        );
+        assert_eq!(
+            check(&options(&["--format", "iproute", "--action", "prohibit"])),
+            Ok(())
+        );
+        assert_eq!(
+            check(&options(&["--action", "deny"])),
+            Err(
+                "--action only works with --format unbound, istio, windows-firewall, iproute, or bsd-route"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(&options(&[
+                "explode",
+                "--format",
+                "dnsmasq",
+                "--dnsmasq-option",
+                "bogus-nxdomain"
+            ])),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_configure() {
+        let context = Context::default();
+        assert!(
+            configure(&options(&["--format", "json"]), &context)
+                .unwrap()
+                .is_none()
+        );
+        let formatter = configure(&options(&["--format", "bits", "--bits-hex"]), &context);
+        assert_eq!(formatter.unwrap().unwrap().name(), "bits");
+        let formatter = configure(
+            &options(&["--format", "unbound", "--action", "nope"]),
+            &context,
+        );
+        assert_eq!(
+            formatter.err(),
+            Some("--action 'nope' isn't an Unbound action".to_string())
+        );
+    }
+}
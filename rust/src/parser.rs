@@ -0,0 +1,389 @@
+//! Turning lines of text into networks, with the knobs exposed.
+//!
+//! [`record_nets`](crate::record_nets) takes every line as a network in CIDR
+//! notation and nothing else. A [`Parser`] can also take some lines that
+//! aren't quite that, like a bare address in a feed that lists one host per
+//! subnet, and [`Aggregator::aggregate_records`](crate::Aggregator) parses
+//! with the one it was built with.
+
+use crate::source::Record;
+use crate::space::Family;
+use crate::{Error, Result};
+use ipnet::IpNet;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// What to do with networks that have host bits set, like `192.0.2.77/24`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HostBits {
+    /// Return them as they are. Aggregating clears the host bits anyway.
+    #[default]
+    Keep,
+    /// Treat them as bad input, since the address may be a typo for a
+    /// different network.
+    Reject,
+    /// Clear the host bits, so `192.0.2.77/24` becomes `192.0.2.0/24`.
+    Truncate,
+}
+
+/// The signature of an [`on_truncate`](Parser::on_truncate) callback.
+type TruncateFn = dyn Fn(&Record, IpNet) + Send + Sync;
+
+/// A function that's called with each record whose host bits were cleared.
+#[derive(Clone)]
+struct OnTruncate(Arc<TruncateFn>);
+
+impl fmt::Debug for OnTruncate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("OnTruncate")
+    }
+}
+
+/// Parses networks the way its settings say to.
+///
+/// The default parser only takes networks in CIDR notation, like
+/// [`record_nets`](crate::record_nets).
+///
+/// ```
+/// use ipnet::IpNet;
+/// use lfc::Parser;
+/// use lfc::space::Family;
+///
+/// let parser = Parser::new().default_prefix_len(Family::V4, 24);
+/// assert_eq!(parser.parse("192.0.2.77"), "192.0.2.0/24".parse::<IpNet>().ok());
+/// assert_eq!(parser.parse("2001:db8::1"), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    default_v4: Option<u8>,
+    default_v6: Option<u8>,
+    host_bits: HostBits,
+    strict: bool,
+    on_truncate: Option<OnTruncate>,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a bare address of the family, with no `/len`, as the `/len`
+    /// network that contains it. Without this, a bare address is an error.
+    pub fn default_prefix_len(mut self, family: Family, len: u8) -> Self {
+        match family {
+            Family::V4 => self.default_v4 = Some(len),
+            Family::V6 => self.default_v6 = Some(len),
+        }
+        self
+    }
+
+    /// What to do with networks that have host bits set. Bare addresses
+    /// widened by [`default_prefix_len`](Self::default_prefix_len) don't
+    /// count; clearing their host bits is the point.
+    pub fn host_bits(mut self, host_bits: HostBits) -> Self {
+        self.host_bits = host_bits;
+        self
+    }
+
+    /// Whether to reject networks that aren't written the way lfc writes
+    /// them: with host bits set, leading zeros in an IPv4 octet or the
+    /// prefix length, or IPv6 text that isn't in the RFC 5952 form, like
+    /// `2001:DB8:0::/48`. This overrides [`host_bits`](Self::host_bits).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Calls `callback` with each record that [`HostBits::Truncate`] cleared
+    /// the host bits of, and the network as it was written, so they can be
+    /// counted or reported.
+    pub fn on_truncate<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Record, IpNet) + Send + Sync + 'static,
+    {
+        self.on_truncate = Some(OnTruncate(Arc::new(callback)));
+        self
+    }
+
+    /// The prefix length bare addresses of the family get, if they're
+    /// allowed.
+    pub(crate) fn default_len(&self, family: Family) -> Option<u8> {
+        match family {
+            Family::V4 => self.default_v4,
+            Family::V6 => self.default_v6,
+        }
+    }
+
+    /// Parses a single network, or returns `None` if it isn't one or the
+    /// settings reject it.
+    pub fn parse(&self, text: &str) -> Option<IpNet> {
+        self.check(text).ok().map(|(net, _)| net)
+    }
+
+    /// Parses a network and applies the settings to it. Returns the network
+    /// to use and, if its host bits were cleared, the network as written.
+    /// Otherwise returns why it was rejected, or `None` if it didn't parse
+    /// at all.
+    fn check(&self, text: &str) -> std::result::Result<(IpNet, Option<IpNet>), Option<String>> {
+        let Some(net) = crate::parse_net(text) else {
+            let net = self.parse_bare(text).ok_or(None)?;
+            if self.strict {
+                let address: IpAddr = text.parse().unwrap();
+                if let Some(reason) = noncanonical(text, &address.to_string(), address.is_ipv4()) {
+                    return Err(Some(reason));
+                }
+            }
+            return Ok((net, None));
+        };
+        let truncated = net.trunc();
+        if truncated != net {
+            match self.host_bits {
+                _ if self.strict => {}
+                HostBits::Keep => return Ok((net, None)),
+                HostBits::Truncate => return Ok((truncated, Some(net))),
+                HostBits::Reject => {}
+            }
+            return Err(Some(format!(
+                "host bits are set; the network is {}",
+                truncated
+            )));
+        }
+        if self.strict
+            && let Some(reason) =
+                noncanonical(text, &net.to_string(), Family::of(&net) == Family::V4)
+        {
+            return Err(Some(reason));
+        }
+        Ok((net, None))
+    }
+
+    /// Parses the text of a record, explaining why if it's bad.
+    fn parse_record(&self, record: &Record, text: &str) -> Result<IpNet> {
+        match self.check(text) {
+            Ok((net, truncated)) => {
+                if let (Some(callback), Some(written)) = (&self.on_truncate, truncated) {
+                    (callback.0)(record, written);
+                }
+                Ok(net)
+            }
+            Err(Some(reason)) => Err(Error::parse_because(record, text, 0, reason)),
+            Err(None) => Err(Error::parse(record, text)),
+        }
+    }
+
+    /// Parses a bare address into the network around it, if there's a
+    /// default prefix length for its family.
+    fn parse_bare(&self, text: &str) -> Option<IpNet> {
+        let address: IpAddr = text.parse().ok()?;
+        let family = match address {
+            IpAddr::V4(_) => Family::V4,
+            IpAddr::V6(_) => Family::V6,
+        };
+        let len = self.default_len(family)?;
+        IpNet::new(address, len).ok().map(|net| net.trunc())
+    }
+
+    /// Parses records like [`record_nets`](crate::record_nets), naming the
+    /// source and line of any that are bad.
+    pub fn records<'a, I: IntoIterator<Item = Record<'a>>>(
+        self,
+        records: I,
+    ) -> impl Iterator<Item = Result<IpNet>> {
+        records.into_iter().filter_map(move |record| {
+            let text = record.text.trim();
+            if text.is_empty() {
+                return None;
+            }
+            Some(self.parse_record(&record, text))
+        })
+    }
+}
+
+/// Explains how `text` differs from the canonical way to write what it
+/// parsed to, if it does.
+fn noncanonical(text: &str, canonical: &str, ipv4: bool) -> Option<String> {
+    if text == canonical {
+        return None;
+    }
+    let reason = match (text.rsplit_once('/'), canonical.rsplit_once('/')) {
+        (Some((_, len)), Some((_, expected))) if len != expected => {
+            "the prefix length has leading zeros"
+        }
+        _ if ipv4 => "an octet has leading zeros",
+        _ => "not in RFC 5952 form",
+    };
+    Some(format!("{}; write it as {}", reason, canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Provenance;
+    use std::sync::Mutex;
+
+    fn net(text: &str) -> Option<IpNet> {
+        Some(text.parse().unwrap())
+    }
+
+    #[test]
+    fn test_default_parser() {
+        let parser = Parser::new();
+        assert_eq!(parser.parse("10.0.0.0/8"), net("10.0.0.0/8"));
+        assert_eq!(parser.parse("10.0.0.1"), None);
+        assert_eq!(parser.parse("2001:db8::1"), None);
+    }
+
+    #[test]
+    fn test_default_prefix_len() {
+        let parser = Parser::new()
+            .default_prefix_len(Family::V4, 24)
+            .default_prefix_len(Family::V6, 64);
+        assert_eq!(parser.parse("192.0.2.77"), net("192.0.2.0/24"));
+        assert_eq!(parser.parse("2001:db8::1:2:3:4"), net("2001:db8::/64"));
+        // Networks with a prefix length are left alone, host bits and all.
+        assert_eq!(parser.parse("192.0.2.77/25"), net("192.0.2.77/25"));
+        assert_eq!(parser.parse("bogus"), None);
+
+        let parser = Parser::new().default_prefix_len(Family::V6, 48);
+        assert_eq!(parser.parse("10.0.0.1"), None);
+        assert_eq!(parser.parse("2001:db8:1:2::"), net("2001:db8:1::/48"));
+
+        // A length that doesn't fit the family can't make a network.
+        let parser = Parser::new().default_prefix_len(Family::V4, 33);
+        assert_eq!(parser.parse("10.0.0.1"), None);
+    }
+
+    #[test]
+    fn test_records() {
+        let records = ["10.0.0.1", "", "10.0.0.0/8", "2001:db8::1"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| Record {
+                text,
+                provenance: Provenance {
+                    source: None,
+                    line: index + 1,
+                },
+            });
+        let parsed: Vec<_> = Parser::new()
+            .default_prefix_len(Family::V4, 16)
+            .records(records)
+            .collect();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].as_ref().ok().copied(), net("10.0.0.0/16"));
+        assert_eq!(parsed[1].as_ref().ok().copied(), net("10.0.0.0/8"));
+        assert!(
+            parsed[2]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("line 4")
+        );
+    }
+
+    fn records(lines: &'static [&'static str]) -> impl Iterator<Item = Record<'static>> {
+        lines.iter().enumerate().map(|(index, text)| Record {
+            text,
+            provenance: Provenance {
+                source: None,
+                line: index + 1,
+            },
+        })
+    }
+
+    #[test]
+    fn test_host_bits() {
+        assert_eq!(Parser::new().parse("192.0.2.77/24"), net("192.0.2.77/24"));
+        let parser = Parser::new().host_bits(HostBits::Reject);
+        assert_eq!(parser.parse("192.0.2.77/24"), None);
+        assert_eq!(parser.parse("192.0.2.0/24"), net("192.0.2.0/24"));
+        let error = parser
+            .clone()
+            .records(records(&["10.0.0.0/8", "  192.0.2.77/24"]))
+            .nth(1)
+            .unwrap()
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Unable to parse \"192.0.2.77/24\" as an IP network on line 2, column 3: \
+             host bits are set; the network is 192.0.2.0/24."
+        );
+        // Widening a bare address isn't a host bits problem.
+        let parser = parser.default_prefix_len(Family::V4, 24);
+        assert_eq!(parser.parse("192.0.2.77"), net("192.0.2.0/24"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_by_parser = Arc::clone(&seen);
+        let parser = Parser::new()
+            .host_bits(HostBits::Truncate)
+            .on_truncate(move |record, net| {
+                seen_by_parser
+                    .lock()
+                    .unwrap()
+                    .push((record.provenance.line, net))
+            });
+        let parsed: Vec<_> = parser
+            .records(records(&["192.0.2.77/24", "10.0.0.0/8", "2001:db8::1/32"]))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            parsed,
+            ["192.0.2.0/24", "10.0.0.0/8", "2001:db8::/32"]
+                .map(|net| net.parse::<IpNet>().unwrap())
+        );
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (1, "192.0.2.77/24".parse().unwrap()),
+                (3, "2001:db8::1/32".parse().unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let parser = Parser::new().strict(true);
+        assert_eq!(parser.parse("10.0.0.0/8"), net("10.0.0.0/8"));
+        assert_eq!(parser.parse("2001:db8::/32"), net("2001:db8::/32"));
+        assert_eq!(
+            parser.parse("::ffff:192.0.2.0/120"),
+            net("::ffff:192.0.2.0/120")
+        );
+        let reason = |text: &'static str| match parser.check(text) {
+            Err(Some(reason)) => reason,
+            other => panic!("{} wasn't rejected: {:?}", text, other),
+        };
+        assert_eq!(
+            reason("010.0.0.0/8"),
+            "an octet has leading zeros; write it as 10.0.0.0/8"
+        );
+        assert_eq!(
+            reason("10.0.0.0/08"),
+            "the prefix length has leading zeros; write it as 10.0.0.0/8"
+        );
+        assert_eq!(
+            reason("2001:DB8:0::/48"),
+            "not in RFC 5952 form; write it as 2001:db8::/48"
+        );
+        assert_eq!(
+            reason("2001:0db8:0:0:0:0:0:1/128"),
+            "not in RFC 5952 form; write it as 2001:db8::1/128"
+        );
+        assert_eq!(
+            reason("10.0.0.1/8"),
+            "host bits are set; the network is 10.0.0.0/8"
+        );
+        // Even when the host bits would otherwise be cleared.
+        let truncating = parser.clone().host_bits(HostBits::Truncate);
+        assert_eq!(truncating.parse("10.0.0.1/8"), None);
+        // Bare addresses have to be canonical too.
+        let parser = parser.default_prefix_len(Family::V6, 64);
+        assert_eq!(parser.parse("2001:db8::1"), net("2001:db8::/64"));
+        assert_eq!(parser.parse("2001:DB8::1"), None);
+    }
+}
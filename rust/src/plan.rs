@@ -0,0 +1,133 @@
+//! Vendor-neutral plans for updating a deployed list.
+
+use crate::diff::{self, Change};
+use ipnet::IpNet;
+use std::io::{self, Write};
+
+/// The networks to add to and remove from a deployed list to make it match
+/// the desired one.
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+    pub add: Vec<IpNet>,
+    pub remove: Vec<IpNet>,
+    /// How many deployed networks stay as they are.
+    pub unchanged: usize,
+}
+
+impl Plan {
+    /// Plans the update from the `current` entries to the `desired` ones.
+    /// Entries are compared exactly, so `current` shouldn't be aggregated
+    /// first: every entry that's really deployed needs to be accounted for.
+    pub fn new(current: &[IpNet], desired: &[IpNet]) -> Plan {
+        let mut plan = Plan::default();
+        for change in diff::diff(current, desired) {
+            match change {
+                Change::Added(net) => plan.add.push(net),
+                Change::Removed(net) => plan.remove.push(net),
+            }
+        }
+        let mut desired: Vec<IpNet> = desired.iter().map(IpNet::trunc).collect();
+        desired.sort_unstable();
+        desired.dedup();
+        plan.unchanged = desired.len() - plan.add.len();
+        plan
+    }
+
+    /// Writes the plan as `add NET` and `remove NET` lines, additions first.
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for net in &self.add {
+            writeln!(out, "add {}", net)?;
+        }
+        for net in &self.remove {
+            writeln!(out, "remove {}", net)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the plan as a JSON object.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"add\": {},", json_list(&self.add))?;
+        writeln!(out, "  \"remove\": {},", json_list(&self.remove))?;
+        writeln!(
+            out,
+            "  \"summary\": {{\"add\": {}, \"remove\": {}, \"unchanged\": {}}}",
+            self.add.len(),
+            self.remove.len(),
+            self.unchanged
+        )?;
+        writeln!(out, "}}")
+    }
+}
+
+/// Formats networks as a JSON array of strings. Networks never contain
+/// anything that needs escaping.
+fn json_list(nets: &[IpNet]) -> String {
+    let items: Vec<String> = nets.iter().map(|net| format!("\"{}\"", net)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn plan() -> Plan {
+        Plan::new(
+            &nets(&["10.0.0.0/24", "10.0.1.0/24", "192.168.0.0/16"]),
+            &nets(&["10.0.0.0/23", "192.168.0.0/16", "2001:db8::/32"]),
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        assert_eq!(
+            plan(),
+            Plan {
+                add: nets(&["10.0.0.0/23", "2001:db8::/32"]),
+                remove: nets(&["10.0.0.0/24", "10.0.1.0/24"]),
+                unchanged: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_write_text() {
+        let mut out = Vec::new();
+        plan().write_text(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+add 10.0.0.0/23
+add 2001:db8::/32
+remove 10.0.0.0/24
+remove 10.0.1.0/24
+"
+        );
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut out = Vec::new();
+        plan().write_json(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{
+  "add": ["10.0.0.0/23", "2001:db8::/32"],
+  "remove": ["10.0.0.0/24", "10.0.1.0/24"],
+  "summary": {"add": 2, "remove": 2, "unchanged": 1}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_write_json_empty() {
+        let mut out = Vec::new();
+        Plan::new(&[], &[]).write_json(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\"add\": [],"));
+    }
+}
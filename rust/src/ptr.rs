@@ -0,0 +1,145 @@
+//! Reverse DNS zones for `lfc ptr-zones`.
+//!
+//! Reverse zones are cut on octet boundaries for IPv4 and on nibble
+//! boundaries for IPv6, so a network that doesn't end on one is split into
+//! the networks that do. The exception is an IPv4 network inside a /24,
+//! which gets an RFC 2317 classless zone like `64/26.2.0.192.in-addr.arpa`
+//! that the /24's zone delegates to, with a CNAME for each address.
+
+use ipnet::{IpNet, Ipv4Net};
+use lfc::format::Style;
+use std::io::{self, Write};
+
+/// The name server and contact that skeleton zones use, to be replaced.
+const SOA: &str = "ns1.example. hostmaster.example. ( 1 3600 900 604800 3600 )";
+const NS: &str = "ns1.example.";
+
+/// Returns the networks that have a reverse zone each, which together cover
+/// the network.
+pub fn split(net: &IpNet) -> Vec<IpNet> {
+    let (step, classless) = match net {
+        IpNet::V4(_) => (8, 24),
+        IpNet::V6(_) => (4, 128),
+    };
+    let len = net.prefix_len();
+    if len.is_multiple_of(step) || len > classless {
+        return vec![*net];
+    }
+    net.subnets(len.next_multiple_of(step)).unwrap().collect()
+}
+
+/// Returns the name of a network's reverse zone, without the trailing dot.
+/// The network is one that [`split`] returned.
+pub fn name(net: &IpNet) -> String {
+    let mut labels: Vec<String> = match net {
+        IpNet::V4(net) => {
+            let octets = net.network().octets();
+            let whole = usize::from(net.prefix_len() / 8);
+            let mut labels: Vec<String> = octets[..whole].iter().rev().map(u8::to_string).collect();
+            if let Some(label) = classless_label(net) {
+                labels.insert(0, label);
+            }
+            labels.push("in-addr".into());
+            labels
+        }
+        IpNet::V6(net) => {
+            let value = u128::from(net.network());
+            let mut labels: Vec<String> = (0..net.prefix_len() / 4)
+                .rev()
+                .map(|i| format!("{:x}", (value >> (124 - 4 * i)) & 0xf))
+                .collect();
+            labels.push("ip6".into());
+            labels
+        }
+    };
+    labels.push("arpa".into());
+    labels.join(".")
+}
+
+/// Returns the RFC 2317 label for a network inside a /24, like `64/26`.
+fn classless_label(net: &Ipv4Net) -> Option<String> {
+    (!net.prefix_len().is_multiple_of(8))
+        .then(|| format!("{}/{}", net.network().octets()[3], net.prefix_len()))
+}
+
+/// Writes a skeleton zone file for a network's reverse zone, with example
+/// names for the name server and contact. A classless zone also gets the
+/// records its parent zone needs to delegate to it.
+pub fn write_skeleton(out: &mut dyn Write, net: &IpNet, style: &Style) -> io::Result<()> {
+    writeln!(out, "; {}", style.display(net))?;
+    writeln!(out, "$ORIGIN {}.", name(net))?;
+    writeln!(out, "$TTL 3600")?;
+    writeln!(out, "@\tIN\tSOA\t{}", SOA)?;
+    writeln!(out, "@\tIN\tNS\t{}", NS)?;
+    let IpNet::V4(v4) = net else {
+        return Ok(());
+    };
+    let Some(label) = classless_label(v4) else {
+        return Ok(());
+    };
+    let parent = Ipv4Net::new(v4.network(), 24).unwrap().trunc();
+    writeln!(out)?;
+    writeln!(out, "; In the parent zone, {}.", name(&parent.into()))?;
+    writeln!(out, "{}\tIN\tNS\t{}", label, NS)?;
+    for host in v4.subnets(32).unwrap() {
+        let octet = host.network().octets()[3];
+        writeln!(out, "{}\tIN\tCNAME\t{}.{}", octet, octet, label)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones(text: &str) -> Vec<String> {
+        split(&text.parse().unwrap()).iter().map(name).collect()
+    }
+
+    #[test]
+    fn test_zones() {
+        assert_eq!(zones("192.0.2.0/24"), ["2.0.192.in-addr.arpa"]);
+        assert_eq!(zones("10.0.0.0/8"), ["10.in-addr.arpa"]);
+        assert_eq!(zones("0.0.0.0/0"), ["in-addr.arpa"]);
+        assert_eq!(
+            zones("198.51.100.0/23"),
+            ["100.51.198.in-addr.arpa", "101.51.198.in-addr.arpa"]
+        );
+        assert_eq!(zones("192.0.2.64/26"), ["64/26.2.0.192.in-addr.arpa"]);
+        assert_eq!(zones("192.0.2.7/32"), ["7.2.0.192.in-addr.arpa"]);
+        assert_eq!(zones("2001:db8::/32"), ["8.b.d.0.1.0.0.2.ip6.arpa"]);
+        assert_eq!(
+            zones("2001:db8::/31"),
+            ["8.b.d.0.1.0.0.2.ip6.arpa", "9.b.d.0.1.0.0.2.ip6.arpa"]
+        );
+        assert_eq!(zones("::/0"), ["ip6.arpa"]);
+    }
+
+    #[test]
+    fn test_write_skeleton() {
+        let mut out = Vec::new();
+        write_skeleton(
+            &mut out,
+            &"192.0.2.8/30".parse().unwrap(),
+            &Style::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+; 192.0.2.8/30
+$ORIGIN 8/30.2.0.192.in-addr.arpa.
+$TTL 3600
+@\tIN\tSOA\tns1.example. hostmaster.example. ( 1 3600 900 604800 3600 )
+@\tIN\tNS\tns1.example.
+
+; In the parent zone, 2.0.192.in-addr.arpa.
+8/30\tIN\tNS\tns1.example.
+8\tIN\tCNAME\t8.8/30
+9\tIN\tCNAME\t9.8/30
+10\tIN\tCNAME\t10.8/30
+11\tIN\tCNAME\t11.8/30
+"
+        );
+    }
+}
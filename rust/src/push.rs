@@ -0,0 +1,207 @@
+//! Pushing the aggregated list to a provider's own list, for `lfc push`.
+//!
+//! Each provider's list is read back and [planned](Plan) against the
+//! result, so only the networks that changed are sent, and entries that
+//! stay aren't churned. New networks are added before old ones are
+//! removed, so nothing that's in both the old and new lists is ever left
+//! out in between.
+
+pub mod akamai;
+pub mod cloudflare;
+pub mod fastly;
+
+use crate::plan::Plan;
+use ipnet::IpNet;
+use lfc::Parser;
+use lfc::space::Family;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Pushes the desired networks to a provider's list and returns the plan
+/// it carried out.
+pub type Push<'a> = Box<dyn Fn(&[IpNet]) -> Result<Plan, String> + 'a>;
+
+/// A JSON API client that sends every request with the same headers.
+pub struct Client {
+    agent: ureq::Agent,
+    base: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Client {
+    /// Returns a client for the API at `base`, which paths are relative to.
+    pub fn new(base: &str, headers: Vec<(&'static str, String)>) -> Client {
+        let config = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build();
+        Client {
+            agent: config.into(),
+            base: base.to_string(),
+            headers,
+        }
+    }
+
+    /// Sends a request, with a JSON body if there is one, and returns the
+    /// response's status and JSON body. Errors from the API itself are left
+    /// for the caller, since each API reports them differently.
+    pub fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<(u16, Value), String> {
+        self.send_with(method, path, body, &[])
+    }
+
+    /// Sends a request like [`send`](Client::send), with more headers for
+    /// only this request, like a signature of it.
+    pub fn send_with(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+        headers: &[(&'static str, String)],
+    ) -> Result<(u16, Value), String> {
+        let url = format!("{}{}", self.base, path);
+        let mut request = ureq::http::Request::builder()
+            .method(method)
+            .uri(&url)
+            .header("Accept", "application/json");
+        for (name, value) in self.headers.iter().chain(headers) {
+            request = request.header(*name, value);
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header("Content-Type", "application/json");
+                body.to_string()
+            }
+            None => String::new(),
+        };
+        let request = request
+            .body(body)
+            .map_err(|err| format!("{} {}: {}", method, url, err))?;
+        let mut response = self
+            .agent
+            .run(request)
+            .map_err(|err| format!("{} {}: {}", method, url, err))?;
+        let status = response.status().as_u16();
+        let text = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|err| format!("{} {}: {}", method, url, err))?;
+        let json = if text.trim().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).map_err(|err| {
+                format!(
+                    "{} {} returned {} with bad JSON: {}",
+                    method, url, status, err
+                )
+            })?
+        };
+        Ok((status, json))
+    }
+}
+
+/// Returns the value of an environment variable that a provider needs.
+pub fn env(name: &str) -> Result<String, String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| format!("set {} in the environment", name))
+}
+
+/// Parses a network from a provider, where a bare address is a host.
+pub fn parse_entry(text: &str) -> Result<IpNet, String> {
+    Parser::new()
+        .default_prefix_len(Family::V4, 32)
+        .default_prefix_len(Family::V6, 128)
+        .parse(text)
+        .ok_or_else(|| format!("the list has '{}', which isn't a network", text))
+}
+
+/// A provider's list entries, each with the provider's ID for it.
+pub struct Entries(pub Vec<(IpNet, String)>);
+
+impl Entries {
+    /// Plans the update from these entries to the desired networks, and
+    /// returns the plan with the IDs of the entries it removes.
+    pub fn plan(&self, desired: &[IpNet]) -> (Plan, Vec<String>) {
+        let current: Vec<IpNet> = self.0.iter().map(|(net, _)| *net).collect();
+        let plan = Plan::new(&current, desired);
+        let mut ids: HashMap<IpNet, Vec<&str>> = HashMap::new();
+        for (net, id) in &self.0 {
+            ids.entry(net.trunc()).or_default().push(id);
+        }
+        let removed = plan
+            .remove
+            .iter()
+            .flat_map(|net| ids.get(net).into_iter().flatten())
+            .map(|id| id.to_string())
+            .collect();
+        (plan, removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_plan() {
+        let entries = Entries(vec![
+            (net("192.0.2.0/24"), "a".to_string()),
+            (net("198.51.100.0/24"), "b".to_string()),
+            (net("198.51.100.0/24"), "c".to_string()),
+        ]);
+        let (plan, removed) = entries.plan(&[net("192.0.2.0/24"), net("203.0.113.0/24")]);
+        assert_eq!(plan.add, vec![net("203.0.113.0/24")]);
+        assert_eq!(plan.remove, vec![net("198.51.100.0/24")]);
+        assert_eq!(removed, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_send() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).contains("{\"id\":\"a\"}") {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+            let body = "{\"success\":false}";
+            write!(
+                stream,
+                "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        let client = Client::new(&base, vec![("Authorization", "Bearer t".to_string())]);
+        let body = serde_json::json!({"id": "a"});
+        let (status, response) = client.send("DELETE", "/items", Some(&body)).unwrap();
+        assert_eq!(status, 404);
+        assert_eq!(response, serde_json::json!({"success": false}));
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.starts_with("delete /items http/1.1\r\n"));
+        assert!(request.contains("authorization: bearer t\r\n"));
+        assert!(request.contains("content-type: application/json\r\n"));
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(parse_entry("192.0.2.1"), Ok(net("192.0.2.1/32")));
+        assert_eq!(parse_entry("2001:db8::/64"), Ok(net("2001:db8::/64")));
+        assert!(parse_entry("example.com").is_err());
+    }
+}
@@ -0,0 +1,455 @@
+//! Akamai Network Lists, through the Network Lists API, version 2.
+//!
+//! Requests are signed with EdgeGrid, from the API client's credentials in
+//! `AKAMAI_HOST`, `AKAMAI_CLIENT_TOKEN`, `AKAMAI_CLIENT_SECRET`, and
+//! `AKAMAI_ACCESS_TOKEN`, the same names Akamai's own tools read. A list
+//! only reaches the edge once it's activated, which is a separate, slower
+//! step, so it's only done when it's asked for.
+
+use super::{Client, Entries};
+use crate::clock;
+use crate::plan::Plan;
+use hmac::{Hmac, KeyInit, Mac};
+use ipnet::IpNet;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How a list is updated.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    /// Replace the whole list with the aggregated networks.
+    #[default]
+    Replace,
+    /// Only add the networks that aren't in the list yet, and never remove
+    /// any.
+    Append,
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "replace" => Ok(Mode::Replace),
+            "append" => Ok(Mode::Append),
+            _ => Err(format!(
+                "invalid Akamai mode '{}' (expected replace or append)",
+                s
+            )),
+        }
+    }
+}
+
+/// The Akamai network a list is activated on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Network {
+    Staging,
+    Production,
+}
+
+impl std::str::FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "staging" => Ok(Network::Staging),
+            "production" => Ok(Network::Production),
+            _ => Err(format!(
+                "invalid Akamai network '{}' (expected staging or production)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Network::Staging => write!(f, "STAGING"),
+            Network::Production => write!(f, "PRODUCTION"),
+        }
+    }
+}
+
+/// An API client's credentials, which sign each request.
+struct Credentials {
+    host: String,
+    client_token: String,
+    client_secret: String,
+    access_token: String,
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes as padded standard Base64.
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// How much of a POST body EdgeGrid signs, which is the `max_body` Akamai's
+/// own clients use.
+const MAX_BODY: usize = 131072;
+
+/// Returns the Base64 HMAC-SHA256 of the message.
+fn hmac_base64(key: &[u8], message: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    base64(&mac.finalize().into_bytes())
+}
+
+/// Formats seconds since the Unix epoch as an EdgeGrid timestamp, like
+/// `20250919T12:34:56+0000`.
+fn timestamp(secs: u64) -> String {
+    clock::rfc3339(secs).replace('-', "").replace('Z', "+0000")
+}
+
+/// Returns a nonce in the form of a UUID that's never repeated, from the
+/// time, the process, and a counter.
+fn nonce() -> String {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    let n = nanos
+        ^ u128::from(std::process::id()) << 96
+        ^ u128::from(COUNT.fetch_add(1, Ordering::Relaxed)) << 64;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        n >> 96,
+        n >> 80 & 0xffff,
+        n >> 64 & 0xffff,
+        n >> 48 & 0xffff,
+        n & 0xffff_ffff_ffff
+    )
+}
+
+impl Credentials {
+    /// Returns the `Authorization` header for a request, signed with
+    /// EdgeGrid. Only POST bodies are part of the signature, and only
+    /// their first [`MAX_BODY`] bytes.
+    fn authorization(
+        &self,
+        method: &str,
+        path: &str,
+        body: &str,
+        timestamp: &str,
+        nonce: &str,
+    ) -> String {
+        let header = format!(
+            "EG1-HMAC-SHA256 client_token={};access_token={};timestamp={};nonce={};",
+            self.client_token, self.access_token, timestamp, nonce
+        );
+        let content_hash = if method == "POST" && !body.is_empty() {
+            let body = body.as_bytes();
+            base64(&Sha256::digest(&body[..body.len().min(MAX_BODY)]))
+        } else {
+            String::new()
+        };
+        let data = [
+            method,
+            "https",
+            &self.host,
+            path,
+            "",
+            &content_hash,
+            &header,
+        ]
+        .join("\t");
+        let key = hmac_base64(self.client_secret.as_bytes(), timestamp.as_bytes());
+        let signature = hmac_base64(key.as_bytes(), data.as_bytes());
+        format!("{}signature={}", header, signature)
+    }
+}
+
+/// Returns the response if it was successful, or the problem Akamai
+/// reported if it wasn't.
+fn check(status: u16, response: Value) -> Result<Value, String> {
+    if status < 400 {
+        return Ok(response);
+    }
+    match (response["title"].as_str(), response["detail"].as_str()) {
+        (Some(title), Some(detail)) => Err(format!("Akamai: {}: {}", title, detail)),
+        (Some(title), None) => Err(format!("Akamai: {}", title)),
+        _ => Err(format!("Akamai returned HTTP {}", status)),
+    }
+}
+
+/// Returns the networks in a network list, each with its own text as its
+/// ID, since elements are removed by value.
+fn parse_list(list: &Value) -> Result<Entries, String> {
+    if list["type"].as_str() != Some("IP") {
+        return Err(format!(
+            "the Akamai network list '{}' isn't an IP list",
+            list["name"].as_str().unwrap_or_default()
+        ));
+    }
+    let entries = list["list"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|element| {
+            let text = element.as_str().unwrap_or_default();
+            Ok((super::parse_entry(text)?, text.to_string()))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Entries(entries))
+}
+
+/// Returns a network list with its elements replaced by the networks, for
+/// updating it. The sync point has to be the one that was read, so that a
+/// change made since then isn't overwritten.
+fn replacement(list: &Value, desired: &[IpNet]) -> Value {
+    let mut update = json!({
+        "name": list["name"],
+        "type": list["type"],
+        "syncPoint": list["syncPoint"],
+        "list": desired.iter().map(IpNet::to_string).collect::<Vec<_>>(),
+    });
+    if let Some(description) = list["description"].as_str() {
+        update["description"] = json!(description);
+    }
+    update
+}
+
+/// The account's network lists.
+pub struct Akamai {
+    client: Client,
+    credentials: Credentials,
+}
+
+impl Akamai {
+    /// Returns the network lists of the API client whose credentials are
+    /// in the environment.
+    pub fn from_env() -> Result<Akamai, String> {
+        let credentials = Credentials {
+            host: super::env("AKAMAI_HOST")?
+                .trim_start_matches("https://")
+                .trim_end_matches('/')
+                .to_string(),
+            client_token: super::env("AKAMAI_CLIENT_TOKEN")?,
+            client_secret: super::env("AKAMAI_CLIENT_SECRET")?,
+            access_token: super::env("AKAMAI_ACCESS_TOKEN")?,
+        };
+        Ok(Akamai {
+            client: Client::new(&format!("https://{}", credentials.host), Vec::new()),
+            credentials,
+        })
+    }
+
+    /// Sends a signed request about the list and returns the response.
+    fn call(
+        &self,
+        method: &str,
+        id: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value, String> {
+        let path = format!("/network-list/v2/network-lists/{}{}", id, path);
+        let text = body.map(Value::to_string).unwrap_or_default();
+        let authorization = self.credentials.authorization(
+            method,
+            &path,
+            &text,
+            &timestamp(clock::now()),
+            &nonce(),
+        );
+        let (status, response) =
+            self.client
+                .send_with(method, &path, body, &[("Authorization", authorization)])?;
+        check(status, response)
+    }
+
+    /// Updates the network list with the ID to hold the desired networks,
+    /// or, in append mode, to hold them as well, and returns the plan. With
+    /// `activate`, the list is then activated on that network. With
+    /// `dry_run`, nothing is changed.
+    pub fn push(
+        &self,
+        id: &str,
+        desired: &[IpNet],
+        mode: Mode,
+        activate: Option<Network>,
+        dry_run: bool,
+    ) -> Result<Plan, String> {
+        let list = self.call("GET", id, "?includeElements=true", None)?;
+        let (mut plan, _) = parse_list(&list)?.plan(desired);
+        if mode == Mode::Append {
+            plan.unchanged += plan.remove.len();
+            plan.remove.clear();
+        }
+        if dry_run {
+            return Ok(plan);
+        }
+        if mode == Mode::Append && !plan.add.is_empty() {
+            let add: Vec<String> = plan.add.iter().map(IpNet::to_string).collect();
+            self.call("POST", id, "/append", Some(&json!({ "list": add })))?;
+        } else if mode == Mode::Replace && (!plan.add.is_empty() || !plan.remove.is_empty()) {
+            self.call("PUT", id, "", Some(&replacement(&list, desired)))?;
+        }
+        if let Some(network) = activate {
+            let body = json!({
+                "comments": "Updated by lfc push",
+                "notificationRecipients": [],
+            });
+            self.call(
+                "POST",
+                id,
+                &format!("/environments/{}/activate", network),
+                Some(&body),
+            )?;
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(1_758_285_296), "20250919T12:34:56+0000");
+    }
+
+    #[test]
+    fn test_nonce() {
+        let (first, second) = (nonce(), nonce());
+        assert_ne!(first, second);
+        assert_eq!(first.len(), 36);
+        assert_eq!(first.matches('-').count(), 4);
+    }
+
+    #[test]
+    fn test_authorization() {
+        let credentials = Credentials {
+            host: "akab-h.luna.akamaiapis.net".to_string(),
+            client_token: "akab-c".to_string(),
+            client_secret: "secret".to_string(),
+            access_token: "akab-a".to_string(),
+        };
+        let header = "EG1-HMAC-SHA256 client_token=akab-c;access_token=akab-a;\
+                      timestamp=20250919T12:34:56+0000;nonce=n;";
+        let path = "/network-list/v2/network-lists/1_X/append";
+        assert_eq!(
+            credentials.authorization(
+                "POST",
+                path,
+                "{\"list\":[\"192.0.2.0/24\"]}",
+                "20250919T12:34:56+0000",
+                "n"
+            ),
+            format!(
+                "{}signature=TDfqnldJTI+PHZjzL9mkLLVf8qmtL4bc7mS67ku+qEk=",
+                header
+            )
+        );
+        assert_eq!(
+            credentials.authorization("GET", path, "", "20250919T12:34:56+0000", "n"),
+            format!(
+                "{}signature=gIvk6wGNWqzY88bZhvl3LkabU2p/KQ3For3RDemqNEg=",
+                header
+            )
+        );
+        let long = format!("{}{}", "x".repeat(MAX_BODY), "y".repeat(1000));
+        assert_eq!(
+            credentials.authorization("POST", path, &long, "20250919T12:34:56+0000", "n"),
+            format!(
+                "{}signature=0e+Bhs3ewxrrsFouJgW4Z5qIy1LSc1Hl0+nJ9BAPEWo=",
+                header
+            )
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(200, json!({})), Ok(json!({})));
+        assert_eq!(
+            check(
+                409,
+                json!({"title": "Conflict", "detail": "Stale syncPoint"})
+            ),
+            Err("Akamai: Conflict: Stale syncPoint".to_string())
+        );
+        assert_eq!(
+            check(502, Value::Null),
+            Err("Akamai returned HTTP 502".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let list = json!({
+            "name": "blocklist",
+            "type": "IP",
+            "list": ["192.0.2.7", "198.51.100.0/24"],
+        });
+        assert_eq!(
+            parse_list(&list).unwrap().0,
+            vec![
+                ("192.0.2.7/32".parse().unwrap(), "192.0.2.7".to_string()),
+                (
+                    "198.51.100.0/24".parse().unwrap(),
+                    "198.51.100.0/24".to_string()
+                ),
+            ]
+        );
+        assert!(parse_list(&json!({"name": "countries", "type": "GEO"})).is_err());
+    }
+
+    #[test]
+    fn test_replacement() {
+        let list = json!({
+            "name": "blocklist",
+            "type": "IP",
+            "syncPoint": 4,
+            "description": "Feeds",
+            "list": ["192.0.2.7"],
+        });
+        assert_eq!(
+            replacement(&list, &["198.51.100.0/24".parse().unwrap()]),
+            json!({
+                "name": "blocklist",
+                "type": "IP",
+                "syncPoint": 4,
+                "description": "Feeds",
+                "list": ["198.51.100.0/24"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_mode() {
+        assert_eq!("append".parse(), Ok(Mode::Append));
+        assert_eq!("replace".parse(), Ok(Mode::Replace));
+        assert!("merge".parse::<Mode>().is_err());
+        assert_eq!("production".parse(), Ok(Network::Production));
+        assert_eq!(Network::Staging.to_string(), "STAGING");
+    }
+}
@@ -0,0 +1,222 @@
+//! Cloudflare IP Lists, through the account's Rules Lists API.
+//!
+//! The API token comes from `CLOUDFLARE_API_TOKEN`, and needs permission to
+//! edit the account's lists, and the account from `CLOUDFLARE_ACCOUNT_ID`.
+//! Changes to a list's items are bulk operations that finish in the
+//! background, so each one is waited on before the next starts.
+
+use super::{Client, Entries};
+use crate::plan::Plan;
+use ipnet::IpNet;
+use serde_json::{Value, json};
+use std::thread;
+use std::time::Duration;
+
+const API: &str = "https://api.cloudflare.com/client/v4";
+
+/// The most items to read or send in one request.
+const PAGE: usize = 500;
+
+/// How long to wait for a bulk operation before giving up.
+const OPERATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An account's lists.
+pub struct Cloudflare {
+    client: Client,
+    account: String,
+}
+
+/// Returns the response if it was successful, or the errors Cloudflare
+/// reported if it wasn't.
+fn check(status: u16, response: Value) -> Result<Value, String> {
+    if response["success"].as_bool() == Some(true) {
+        return Ok(response);
+    }
+    let errors: Vec<String> = response["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|error| match error["code"].as_i64() {
+            Some(code) => format!("{} ({})", error["message"].as_str().unwrap_or("?"), code),
+            None => error["message"].as_str().unwrap_or("?").to_string(),
+        })
+        .collect();
+    if errors.is_empty() {
+        Err(format!("Cloudflare returned HTTP {}", status))
+    } else {
+        Err(format!("Cloudflare: {}", errors.join("; ")))
+    }
+}
+
+/// Returns the ID of the IP list with the name, from the account's lists.
+fn find_list(lists: &Value, name: &str) -> Result<String, String> {
+    let list = lists
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|list| list["name"].as_str() == Some(name))
+        .ok_or_else(|| format!("there's no Cloudflare list named '{}'", name))?;
+    if list["kind"].as_str() != Some("ip") {
+        return Err(format!("the Cloudflare list '{}' isn't an IP list", name));
+    }
+    list["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("the Cloudflare list '{}' doesn't have an ID", name))
+}
+
+/// Returns the networks and IDs of a page of list items.
+fn parse_items(items: &Value) -> Result<Vec<(IpNet, String)>, String> {
+    items
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|item| {
+            let net = super::parse_entry(item["ip"].as_str().unwrap_or_default())?;
+            let id = item["id"]
+                .as_str()
+                .ok_or("a list item doesn't have an ID")?;
+            Ok((net, id.to_string()))
+        })
+        .collect()
+}
+
+impl Cloudflare {
+    /// Returns the lists of the account in the environment.
+    pub fn from_env() -> Result<Cloudflare, String> {
+        let token = super::env("CLOUDFLARE_API_TOKEN")?;
+        let account = super::env("CLOUDFLARE_ACCOUNT_ID")?;
+        let client = Client::new(API, vec![("Authorization", format!("Bearer {}", token))]);
+        Ok(Cloudflare { client, account })
+    }
+
+    /// Sends a request about the account's lists and returns the response.
+    fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let path = format!("/accounts/{}/rules/lists{}", self.account, path);
+        let (status, response) = self.client.send(method, &path, body)?;
+        check(status, response)
+    }
+
+    /// Returns the ID of the IP list with the name.
+    fn list_id(&self, name: &str) -> Result<String, String> {
+        find_list(&self.call("GET", "", None)?["result"], name)
+    }
+
+    /// Returns every item in the list.
+    fn items(&self, list: &str) -> Result<Entries, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut path = format!("/{}/items?per_page={}", list, PAGE);
+            if let Some(cursor) = &cursor {
+                path.push_str(&format!("&cursor={}", cursor));
+            }
+            let response = self.call("GET", &path, None)?;
+            items.extend(parse_items(&response["result"])?);
+            match response["result_info"]["cursors"]["after"].as_str() {
+                Some(after) => cursor = Some(after.to_string()),
+                None => return Ok(Entries(items)),
+            }
+        }
+    }
+
+    /// Waits for a bulk operation to finish.
+    fn wait(&self, operation: &Value) -> Result<(), String> {
+        let id = operation["operation_id"]
+            .as_str()
+            .ok_or("Cloudflare didn't return a bulk operation ID")?;
+        let started = std::time::Instant::now();
+        loop {
+            let response = self.call("GET", &format!("/bulk_operations/{}", id), None)?;
+            let status = &response["result"];
+            match status["status"].as_str() {
+                Some("completed") => return Ok(()),
+                Some("failed") => {
+                    return Err(format!(
+                        "Cloudflare bulk operation {} failed: {}",
+                        id,
+                        status["error"].as_str().unwrap_or("no reason given")
+                    ));
+                }
+                _ if started.elapsed() > OPERATION_TIMEOUT => {
+                    return Err(format!("Cloudflare bulk operation {} didn't finish", id));
+                }
+                _ => thread::sleep(Duration::from_secs(1)),
+            }
+        }
+    }
+
+    /// Updates the list named `name` to hold the desired networks, and
+    /// returns the plan. With `dry_run`, nothing is changed.
+    pub fn push(&self, name: &str, desired: &[IpNet], dry_run: bool) -> Result<Plan, String> {
+        let list = self.list_id(name)?;
+        let (plan, removed) = self.items(&list)?.plan(desired);
+        if dry_run {
+            return Ok(plan);
+        }
+        for chunk in plan.add.chunks(PAGE) {
+            let items: Vec<Value> = chunk
+                .iter()
+                .map(|net| json!({ "ip": net.to_string() }))
+                .collect();
+            let body = Value::from(items);
+            self.wait(&self.call("POST", &format!("/{}/items", list), Some(&body))?["result"])?;
+        }
+        for chunk in removed.chunks(PAGE) {
+            let items: Vec<Value> = chunk.iter().map(|id| json!({ "id": id })).collect();
+            let body = json!({ "items": items });
+            self.wait(&self.call("DELETE", &format!("/{}/items", list), Some(&body))?["result"])?;
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let ok = json!({"success": true, "errors": [], "result": [1]});
+        assert_eq!(check(200, ok.clone()), Ok(ok));
+        let failed = json!({
+            "success": false,
+            "errors": [{"code": 10000, "message": "Authentication error"}],
+        });
+        assert_eq!(
+            check(403, failed),
+            Err("Cloudflare: Authentication error (10000)".to_string())
+        );
+        assert_eq!(
+            check(502, Value::Null),
+            Err("Cloudflare returned HTTP 502".to_string())
+        );
+    }
+
+    #[test]
+    fn test_find_list() {
+        let lists = json!([
+            {"id": "1", "name": "hosts", "kind": "hostname"},
+            {"id": "2", "name": "my_blocklist", "kind": "ip"},
+        ]);
+        assert_eq!(find_list(&lists, "my_blocklist"), Ok("2".to_string()));
+        assert!(find_list(&lists, "hosts").is_err());
+        assert!(find_list(&lists, "missing").is_err());
+    }
+
+    #[test]
+    fn test_parse_items() {
+        let items = json!([
+            {"id": "a", "ip": "192.0.2.1"},
+            {"id": "b", "ip": "2001:db8::/64", "comment": "lab"},
+        ]);
+        assert_eq!(
+            parse_items(&items),
+            Ok(vec![
+                ("192.0.2.1/32".parse().unwrap(), "a".to_string()),
+                ("2001:db8::/64".parse().unwrap(), "b".to_string()),
+            ])
+        );
+        assert!(parse_items(&json!([{"id": "c"}])).is_err());
+    }
+}
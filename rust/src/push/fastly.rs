@@ -0,0 +1,224 @@
+//! Fastly ACLs, through the service's ACL entries API.
+//!
+//! The API token comes from `FASTLY_API_TOKEN`. ACLs belong to a version
+//! of the service, so the ACL is looked up by name in the active version,
+//! but its entries don't, so they change without activating anything.
+//! Negated entries are exceptions that lfc doesn't write, so they're left
+//! alone.
+
+use super::{Client, Entries};
+use crate::plan::Plan;
+use ipnet::IpNet;
+use serde_json::{Value, json};
+
+const API: &str = "https://api.fastly.com";
+
+/// How many entries to read in one request.
+const PAGE: usize = 100;
+
+/// The most changes in one batch update.
+const BATCH: usize = 1000;
+
+/// A service's ACLs.
+pub struct Fastly {
+    client: Client,
+    service: String,
+}
+
+/// Returns the response if it was successful, or the error Fastly reported
+/// if it wasn't.
+fn check(status: u16, response: Value) -> Result<Value, String> {
+    if status < 400 {
+        return Ok(response);
+    }
+    match (response["msg"].as_str(), response["detail"].as_str()) {
+        (Some(msg), Some(detail)) => Err(format!("Fastly: {}: {}", msg, detail)),
+        (Some(msg), None) => Err(format!("Fastly: {}", msg)),
+        _ => Err(format!("Fastly returned HTTP {}", status)),
+    }
+}
+
+/// Returns the number of the service's active version.
+fn active_version(service: &Value) -> Result<u64, String> {
+    service["versions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|version| version["active"].as_bool() == Some(true))
+        .and_then(|version| version["number"].as_u64())
+        .ok_or_else(|| "the Fastly service doesn't have an active version".to_string())
+}
+
+/// Returns whether an entry is negated, which the API writes as a number,
+/// a string, or a boolean.
+fn negated(entry: &Value) -> bool {
+    match &entry["negated"] {
+        Value::Bool(negated) => *negated,
+        Value::Number(number) => number.as_u64() != Some(0),
+        Value::String(text) => text != "0" && !text.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns the networks and IDs of a page of ACL entries, without the
+/// negated ones.
+fn parse_entries(entries: &Value) -> Result<Vec<(IpNet, String)>, String> {
+    entries
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|entry| !negated(entry))
+        .map(|entry| {
+            let ip = entry["ip"].as_str().unwrap_or_default();
+            let text = match entry["subnet"].as_u64() {
+                Some(subnet) => format!("{}/{}", ip, subnet),
+                None => ip.to_string(),
+            };
+            let net = super::parse_entry(&text)?;
+            let id = entry["id"]
+                .as_str()
+                .ok_or("an ACL entry doesn't have an ID")?;
+            Ok((net, id.to_string()))
+        })
+        .collect()
+}
+
+/// Returns the batch operations that make the plan's changes, creating
+/// entries before deleting them.
+fn operations(plan: &Plan, removed: &[String]) -> Vec<Value> {
+    let create = plan.add.iter().map(|net| {
+        json!({
+            "op": "create",
+            "ip": net.network().to_string(),
+            "subnet": net.prefix_len(),
+        })
+    });
+    let delete = removed.iter().map(|id| json!({ "op": "delete", "id": id }));
+    create.chain(delete).collect()
+}
+
+impl Fastly {
+    /// Returns the ACLs of the service, with the token in the environment.
+    pub fn from_env(service: &str) -> Result<Fastly, String> {
+        let token = super::env("FASTLY_API_TOKEN")?;
+        Ok(Fastly {
+            client: Client::new(API, vec![("Fastly-Key", token)]),
+            service: service.to_string(),
+        })
+    }
+
+    /// Sends a request about the service and returns the response.
+    fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let path = format!("/service/{}{}", self.service, path);
+        let (status, response) = self.client.send(method, &path, body)?;
+        check(status, response)
+    }
+
+    /// Returns the ID of the ACL with the name in the active version.
+    fn acl_id(&self, name: &str) -> Result<String, String> {
+        let version = active_version(&self.call("GET", "", None)?)?;
+        let acl = self.call("GET", &format!("/version/{}/acl/{}", version, name), None)?;
+        acl["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("the Fastly ACL '{}' doesn't have an ID", name))
+    }
+
+    /// Returns every entry in the ACL, except the negated ones.
+    fn entries(&self, acl: &str) -> Result<Entries, String> {
+        let mut entries = Vec::new();
+        for page in 1.. {
+            let path = format!("/acl/{}/entries?page={}&per_page={}", acl, page, PAGE);
+            let response = self.call("GET", &path, None)?;
+            entries.extend(parse_entries(&response)?);
+            if response.as_array().is_none_or(|page| page.len() < PAGE) {
+                break;
+            }
+        }
+        Ok(Entries(entries))
+    }
+
+    /// Updates the ACL named `name` to hold the desired networks, and
+    /// returns the plan. With `dry_run`, nothing is changed.
+    pub fn push(&self, name: &str, desired: &[IpNet], dry_run: bool) -> Result<Plan, String> {
+        let acl = self.acl_id(name)?;
+        let (plan, removed) = self.entries(&acl)?.plan(desired);
+        if dry_run {
+            return Ok(plan);
+        }
+        for batch in operations(&plan, &removed).chunks(BATCH) {
+            let body = json!({ "entries": batch });
+            self.call("PATCH", &format!("/acl/{}/entries", acl), Some(&body))?;
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check(200, json!({"status": "ok"})),
+            Ok(json!({"status": "ok"}))
+        );
+        assert_eq!(
+            check(
+                404,
+                json!({"msg": "Record not found", "detail": "Cannot find acl"})
+            ),
+            Err("Fastly: Record not found: Cannot find acl".to_string())
+        );
+        assert_eq!(
+            check(500, Value::Null),
+            Err("Fastly returned HTTP 500".to_string())
+        );
+    }
+
+    #[test]
+    fn test_active_version() {
+        let service = json!({"versions": [
+            {"number": 1, "active": false},
+            {"number": 2, "active": true},
+            {"number": 3, "active": false},
+        ]});
+        assert_eq!(active_version(&service), Ok(2));
+        assert!(active_version(&json!({"versions": []})).is_err());
+    }
+
+    #[test]
+    fn test_parse_entries() {
+        let entries = json!([
+            {"id": "a", "ip": "192.0.2.0", "subnet": 24, "negated": "0"},
+            {"id": "b", "ip": "192.0.2.7", "subnet": null, "negated": 0},
+            {"id": "c", "ip": "192.0.2.8", "subnet": 30, "negated": "1"},
+            {"id": "d", "ip": "2001:db8::", "subnet": 32, "negated": false},
+        ]);
+        assert_eq!(
+            parse_entries(&entries),
+            Ok(vec![
+                ("192.0.2.0/24".parse().unwrap(), "a".to_string()),
+                ("192.0.2.7/32".parse().unwrap(), "b".to_string()),
+                ("2001:db8::/32".parse().unwrap(), "d".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_operations() {
+        let plan = Plan {
+            add: vec!["203.0.113.0/24".parse().unwrap()],
+            remove: vec!["198.51.100.0/24".parse().unwrap()],
+            unchanged: 1,
+        };
+        assert_eq!(
+            operations(&plan, &["b".to_string()]),
+            vec![
+                json!({"op": "create", "ip": "203.0.113.0", "subnet": 24}),
+                json!({"op": "delete", "id": "b"}),
+            ]
+        );
+    }
+}
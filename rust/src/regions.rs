@@ -0,0 +1,139 @@
+//! Regions that `--no-merge-across` keeps aggregation from straddling.
+//!
+//! A region list is either a file of networks, one per line, or one of the
+//! built-in lists:
+//!
+//! - `builtin:rfc1918`: the private IPv4 blocks, so private space never
+//!   merges with the public space next to it.
+//! - `builtin:rir`: the blocks IANA allocates to the regional internet
+//!   registries, so no merged network spans two allocations. IANA's IPv4
+//!   registry is kept in /8s, so every IPv4 /8 is a region of its own. The
+//!   IPv6 blocks come from IANA's IPv6 Global Unicast Address Assignments
+//!   registry.
+//! - `builtin:special`: the IANA special-purpose blocks that aren't inside
+//!   another one, so special-purpose space never merges with the globally
+//!   routable space next to it. The blocks inside others are left out, so
+//!   that, say, 192.0.0.0/24 can still merge whole.
+
+use crate::info;
+use ipnet::IpNet;
+use lfc::source;
+
+const RFC1918: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"];
+
+const RIR_V6: &[&str] = &[
+    "2001::/23",
+    "2001:200::/23",
+    "2001:400::/23",
+    "2001:600::/23",
+    "2001:800::/22",
+    "2001:c00::/23",
+    "2001:e00::/23",
+    "2001:1200::/23",
+    "2001:1400::/22",
+    "2001:1800::/23",
+    "2001:1a00::/23",
+    "2001:1c00::/22",
+    "2001:2000::/19",
+    "2001:4000::/23",
+    "2001:4200::/23",
+    "2001:4400::/23",
+    "2001:4600::/23",
+    "2001:4800::/23",
+    "2001:4a00::/23",
+    "2001:4c00::/23",
+    "2001:5000::/20",
+    "2001:8000::/19",
+    "2001:a000::/20",
+    "2001:b000::/20",
+    "2003::/18",
+    "2400::/12",
+    "2600::/12",
+    "2610::/23",
+    "2620::/23",
+    "2630::/12",
+    "2800::/12",
+    "2a00::/12",
+    "2a10::/12",
+    "2c00::/12",
+];
+
+/// Loads the regions named by a `--no-merge-across` value.
+pub fn load(spec: &str) -> Result<Vec<IpNet>, String> {
+    let parse =
+        |list: &[&str]| -> Vec<IpNet> { list.iter().map(|net| net.parse().unwrap()).collect() };
+    match spec.strip_prefix("builtin:") {
+        Some("rfc1918") => Ok(parse(RFC1918)),
+        Some("rir") => {
+            let mut regions: Vec<IpNet> = (0..=255)
+                .map(|octet| format!("{}.0.0.0/8", octet).parse().unwrap())
+                .collect();
+            regions.extend(parse(RIR_V6));
+            Ok(regions)
+        }
+        Some("special") => {
+            let blocks = info::special_blocks();
+            Ok(blocks
+                .iter()
+                .filter(|block| {
+                    !blocks
+                        .iter()
+                        .any(|other| other != *block && other.contains(*block))
+                })
+                .copied()
+                .collect())
+        }
+        Some(name) => Err(format!(
+            "unknown built-in region list '{}' (expected rfc1918, rir, or special)",
+            name
+        )),
+        None => {
+            let input = source::open(spec).map_err(|err| err.to_string())?;
+            let records = input
+                .records()
+                .collect::<lfc::Result<Vec<_>>>()
+                .map_err(|err| err.to_string())?;
+            lfc::record_nets(records)
+                .collect::<lfc::Result<_>>()
+                .map_err(|err| err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtins() {
+        let rfc1918 = load("builtin:rfc1918").unwrap();
+        assert_eq!(rfc1918.len(), 3);
+        let rir = load("builtin:rir").unwrap();
+        assert_eq!(rir.len(), 256 + RIR_V6.len());
+        assert!(rir.iter().all(|net| *net == net.trunc()));
+        let special = load("builtin:special").unwrap();
+        assert!(special.contains(&"100.64.0.0/10".parse().unwrap()));
+        assert!(special.contains(&"2001::/23".parse().unwrap()));
+        assert!(!special.contains(&"2001::/32".parse().unwrap()));
+        assert!(!special.contains(&"0.0.0.0/32".parse().unwrap()));
+        assert!(load("builtin:iana").is_err());
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("lfc-regions-test-{}", std::process::id()));
+        std::fs::write(&path, "100.64.0.0/10\n\n2001:db8::/32\n").unwrap();
+        let regions = load(path.to_str().unwrap()).unwrap();
+        std::fs::write(&path, "100.64.0.0/10\nbogus\n").unwrap();
+        let error = load(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            regions,
+            vec![
+                "100.64.0.0/10".parse::<IpNet>().unwrap(),
+                "2001:db8::/32".parse().unwrap()
+            ]
+        );
+        assert!(error.contains("bogus"));
+    }
+}
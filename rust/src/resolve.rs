@@ -0,0 +1,167 @@
+//! Overlapping lists resolved by priority, for `lfc resolve`.
+//!
+//! Each list names a decision, like allow or block, and has a priority.
+//! Where lists overlap, the one with the highest priority decides, so the
+//! result says which decision applies to every part of the address space
+//! any of the lists covers: the policy the lists add up to.
+
+use ipnet::IpNet;
+use lfc::space::{self, Family};
+use std::str::FromStr;
+
+/// One of the lists `--list NAME=FILE:PRIORITY` names.
+#[derive(Debug, PartialEq, Eq)]
+pub struct List {
+    /// The decision the list makes, like allow or block.
+    pub name: String,
+    /// Where the list's networks are, as a file or URL.
+    pub file: String,
+    pub priority: i64,
+}
+
+impl FromStr for List {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid list '{}' (expected NAME=FILE:PRIORITY)", s);
+        let (name, rest) = s.split_once('=').ok_or_else(invalid)?;
+        // The file can be a URL, with colons of its own, so the priority is
+        // after the last one.
+        let (file, priority) = rest.rsplit_once(':').ok_or_else(invalid)?;
+        if name.is_empty() || file.is_empty() {
+            return Err(invalid());
+        }
+        Ok(List {
+            name: name.to_string(),
+            file: file.to_string(),
+            priority: priority.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Returns the space each list decides, in order of priority from highest,
+/// given each list's networks. Lists with the same priority go in the order
+/// they're given, so where they overlap, the first one given decides.
+pub fn resolve<'a>(lists: &[(&'a List, Vec<IpNet>)]) -> Vec<(&'a List, Vec<IpNet>)> {
+    let mut order: Vec<&(&List, Vec<IpNet>)> = lists.iter().collect();
+    order.sort_by_key(|(list, _)| std::cmp::Reverse(list.priority));
+    let mut decided = [Vec::new(), Vec::new()];
+    order
+        .into_iter()
+        .map(|(list, nets)| {
+            let mut kept = Vec::new();
+            for (i, family) in [Family::V4, Family::V6].into_iter().enumerate() {
+                let ranges = space::ranges(nets, family);
+                for (start, end) in space::subtract(&ranges, &decided[i]) {
+                    space::cover(family, start, end, &mut kept);
+                }
+                decided[i] = merge(&decided[i], &ranges);
+            }
+            (*list, kept)
+        })
+        .collect()
+}
+
+/// Returns the sorted, merged ranges covering both `a` and `b`.
+fn merge(a: &[(u128, u128)], b: &[(u128, u128)]) -> Vec<(u128, u128)> {
+    let mut all: Vec<(u128, u128)> = a.iter().chain(b).copied().collect();
+    all.sort_unstable();
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(all.len());
+    for (start, end) in all {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(nets: &[&str]) -> Vec<IpNet> {
+        nets.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    fn list(s: &str) -> List {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(
+            list("allow=allow.txt:100"),
+            List {
+                name: "allow".to_string(),
+                file: "allow.txt".to_string(),
+                priority: 100,
+            }
+        );
+        assert_eq!(
+            list("block=https://example.com/feed.txt:-5").file,
+            "https://example.com/feed.txt"
+        );
+        assert!("allow.txt:100".parse::<List>().is_err());
+        assert!("allow=allow.txt".parse::<List>().is_err());
+        assert!("allow=:100".parse::<List>().is_err());
+        assert!("=allow.txt:100".parse::<List>().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let (allow, block) = (list("allow=a:100"), list("block=b:50"));
+        let resolved = resolve(&[
+            (
+                &block,
+                nets(&["10.0.0.0/8", "192.0.2.0/24", "2001:db8::/32"]),
+            ),
+            (
+                &allow,
+                nets(&["10.1.0.0/16", "10.3.0.0/16", "198.51.100.0/24"]),
+            ),
+        ]);
+        assert_eq!(
+            resolved[0],
+            (
+                &allow,
+                nets(&["10.1.0.0/16", "10.3.0.0/16", "198.51.100.0/24"])
+            )
+        );
+        assert_eq!(resolved[1].0, &block);
+        assert_eq!(
+            resolved[1].1,
+            nets(&[
+                "10.0.0.0/16",
+                "10.2.0.0/16",
+                "10.4.0.0/14",
+                "10.8.0.0/13",
+                "10.16.0.0/12",
+                "10.32.0.0/11",
+                "10.64.0.0/10",
+                "10.128.0.0/9",
+                "192.0.2.0/24",
+                "2001:db8::/32"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_ties() {
+        let (first, second) = (list("first=a:1"), list("second=b:1"));
+        let resolved = resolve(&[
+            (&first, nets(&["192.0.2.0/25"])),
+            (&second, nets(&["192.0.2.0/24"])),
+        ]);
+        assert_eq!(resolved[0].1, nets(&["192.0.2.0/25"]));
+        assert_eq!(resolved[1].1, nets(&["192.0.2.128/25"]));
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(
+            merge(&[(0, 9)], &[(10, 19), (30, 39)]),
+            vec![(0, 19), (30, 39)]
+        );
+    }
+}
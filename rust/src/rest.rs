@@ -0,0 +1,612 @@
+//! A small REST API for `lfc serve --listen`.
+//!
+//! The API aggregates and diffs lists of networks sent to it, looks up
+//! addresses, and manages named lists. It describes itself with an OpenAPI
+//! document at `/openapi.json`, so clients can be generated rather than
+//! written by hand. Request and response bodies are JSON, except for the
+//! Prometheus metrics at `/metrics`.
+//!
+//! This is a deliberately minimal HTTP/1.1 server: one request per
+//! connection, a fixed number of connections at once, no chunked request
+//! bodies, and no TLS. Put it behind a reverse
+//! proxy if it needs to face anything other than trusted local clients.
+
+use crate::diff::{self, Change};
+use crate::metrics;
+use crate::serve::Store;
+use crate::state;
+use ipnet::IpNet;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The OpenAPI document, with the version filled in when it's served.
+const OPENAPI: &str = include_str!("openapi.json");
+
+/// The most header bytes we'll read before giving up on a request.
+const MAX_HEADERS: usize = 64 * 1024;
+
+/// The largest request body we'll read. That's a few hundred thousand
+/// networks as JSON.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// How many connections are answered at once. Others wait to be accepted.
+const WORKERS: usize = 16;
+
+#[derive(Deserialize)]
+struct NetworksBody {
+    networks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct DiffBody {
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// An HTTP response.
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Option<Body>,
+}
+
+/// The body of a response.
+#[derive(Debug, PartialEq)]
+pub enum Body {
+    Json(Value),
+    /// Prometheus metrics, in its text format.
+    Metrics(String),
+}
+
+impl Response {
+    fn ok(body: Value) -> Response {
+        Response {
+            status: 200,
+            body: Some(Body::Json(body)),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Response {
+        Response {
+            status,
+            body: Some(Body::Json(json!({ "error": message.into() }))),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Content Too Large",
+            501 => "Not Implemented",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Returns the OpenAPI document describing this API.
+pub fn openapi() -> Value {
+    let mut doc: Value = serde_json::from_str(OPENAPI).expect("openapi.json is valid JSON");
+    doc["info"]["version"] = json!(env!("CARGO_PKG_VERSION"));
+    doc
+}
+
+/// Parses and aggregates a list of networks from a request.
+fn aggregate(networks: &[String]) -> Result<Vec<IpNet>, Response> {
+    lfc::gather_lines(networks.iter().map(String::as_str))
+        .map_err(|err| Response::error(400, err.to_string()))
+}
+
+fn strings(nets: &[IpNet]) -> Vec<String> {
+    nets.iter().map(IpNet::to_string).collect()
+}
+
+/// Parses a JSON request body.
+fn body<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, Response> {
+    serde_json::from_slice(body)
+        .map_err(|err| Response::error(400, format!("invalid body: {}", err)))
+}
+
+/// Decodes a `%XX`-escaped query string component.
+fn percent_decode(s: &str) -> String {
+    let hex = |byte: u8| (byte as char).to_digit(16).map(|digit| digit as u8);
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Returns the value of a query string parameter.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .find(|(key, _)| percent_decode(key) == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Answers one request.
+pub fn route(store: &Store, method: &str, target: &str, request_body: &[u8]) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let result = match (method, segments.as_slice()) {
+        ("GET", ["openapi.json"]) => Ok(Response::ok(openapi())),
+        ("POST", ["aggregate"]) => body::<NetworksBody>(request_body)
+            .and_then(|request| aggregate(&request.networks))
+            .map(|nets| Response::ok(json!({ "networks": strings(&nets) }))),
+        ("POST", ["diff"]) => body::<DiffBody>(request_body).and_then(|request| {
+            let changes = diff::diff(&aggregate(&request.old)?, &aggregate(&request.new)?);
+            let pick = |added: bool| -> Vec<String> {
+                changes
+                    .iter()
+                    .filter(|change| matches!(change, Change::Added(_)) == added)
+                    .map(|change| change.net().to_string())
+                    .collect()
+            };
+            Ok(Response::ok(
+                json!({ "added": pick(true), "removed": pick(false) }),
+            ))
+        }),
+        ("GET", ["lookup"]) => lookup(store, query),
+        ("GET", ["lists"]) => Ok(Response::ok(json!({ "lists": store.names() }))),
+        (method, ["lists", name]) => list(store, method, name, request_body),
+        ("GET", ["metrics"]) => Ok(metrics(store)),
+        (_, ["openapi.json" | "aggregate" | "diff" | "lookup" | "lists" | "metrics"]) => Err(
+            Response::error(405, format!("{} isn't allowed here", method)),
+        ),
+        _ => Err(Response::error(404, format!("no such resource {:?}", path))),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+/// Answers `GET /metrics` with the metrics for every list.
+fn metrics(store: &Store) -> Response {
+    // A list can be deleted between listing the names and getting it.
+    let loaded: Vec<_> = store
+        .names()
+        .into_iter()
+        .filter_map(|name| Some((store.get(&name)?, name)))
+        .collect();
+    let lists: Vec<_> = loaded
+        .iter()
+        .map(|(loaded, name)| loaded.metrics(name))
+        .collect();
+    Response {
+        status: 200,
+        body: Some(Body::Metrics(metrics::render(&lists))),
+    }
+}
+
+/// Answers `GET /lookup`.
+fn lookup(store: &Store, query: &str) -> Result<Response, Response> {
+    let Some(address) = query_param(query, "address") else {
+        return Err(Response::error(400, "missing the address parameter"));
+    };
+    let addr: IpAddr = address.parse().map_err(|_| {
+        Response::error(
+            400,
+            format!("Unable to parse {:?} as an IP address.", address),
+        )
+    })?;
+    let names = match query_param(query, "list") {
+        Some(name) if store.get(&name).is_none() => {
+            return Err(Response::error(404, format!("no such list {:?}", name)));
+        }
+        Some(name) => vec![name],
+        None => store.names(),
+    };
+    let matches: Vec<Value> = names
+        .iter()
+        .filter_map(|name| {
+            let net = store.get(name)?.lookup(addr)?;
+            Some(json!({ "list": name, "network": net.to_string() }))
+        })
+        .collect();
+    Ok(Response::ok(
+        json!({ "address": addr.to_string(), "matches": matches }),
+    ))
+}
+
+/// Answers requests on `/lists/{name}`.
+fn list(
+    store: &Store,
+    method: &str,
+    name: &str,
+    request_body: &[u8],
+) -> Result<Response, Response> {
+    let name = percent_decode(name);
+    state::check_name(&name).map_err(|err| Response::error(400, err))?;
+    let not_found = || Response::error(404, format!("no such list {:?}", name));
+    match method {
+        "GET" => {
+            let loaded = store.get(&name).ok_or_else(not_found)?;
+            Ok(Response::ok(
+                json!({ "name": name, "networks": strings(loaded.nets()) }),
+            ))
+        }
+        "PUT" => {
+            let networks = body::<NetworksBody>(request_body)?.networks;
+            let started = Instant::now();
+            let nets = aggregate(&networks)?;
+            let loaded = store.put(&name, nets, started.elapsed());
+            Ok(Response::ok(
+                json!({ "name": name, "networks": strings(loaded.nets()) }),
+            ))
+        }
+        "DELETE" if store.remove(&name) => Ok(Response {
+            status: 204,
+            body: None,
+        }),
+        "DELETE" => Err(not_found()),
+        _ => Err(Response::error(
+            405,
+            format!("{} isn't allowed here", method),
+        )),
+    }
+}
+
+/// Reads a request's method, target, and body.
+fn read_request<R: BufRead>(reader: &mut R) -> Result<(String, String, Vec<u8>), Response> {
+    let bad = |message: &str| Response::error(400, message);
+    let mut line = String::new();
+    let mut header_bytes = 0;
+    let mut read_line = |line: &mut String| -> Result<(), Response> {
+        line.clear();
+        let read = reader
+            .by_ref()
+            .take((MAX_HEADERS - header_bytes) as u64)
+            .read_line(line)
+            .map_err(|_| bad("unable to read the request"))?;
+        header_bytes += read;
+        if !line.ends_with('\n') {
+            return Err(bad("request headers are too long or incomplete"));
+        }
+        Ok(())
+    };
+
+    read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(target)) = (words.next(), words.next()) else {
+        return Err(bad("malformed request line"));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+
+    let mut length = 0;
+    loop {
+        read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse().map_err(|_| bad("invalid Content-Length"))?;
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                return Err(Response::error(
+                    501,
+                    "chunked request bodies aren't supported",
+                ));
+            }
+        }
+    }
+
+    if length > MAX_BODY {
+        return Err(Response::error(413, "request body is too large"));
+    }
+    // The body grows as it arrives, rather than as big as the client says.
+    let mut body = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut body)
+        .map_err(|_| bad("unable to read the request body"))?;
+    if body.len() < length {
+        return Err(bad("request body is shorter than its Content-Length"));
+    }
+    Ok((method, target, body))
+}
+
+/// Writes a response and closes the conversation.
+fn write_response<W: Write>(out: &mut W, response: &Response) -> io::Result<()> {
+    let (content_type, body) = match &response.body {
+        Some(Body::Json(body)) => ("application/json", format!("{:#}\n", body)),
+        Some(Body::Metrics(text)) => ("text/plain; version=0.0.4", text.clone()),
+        None => ("application/json", String::new()),
+    };
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        content_type,
+        body.len(),
+        body
+    )?;
+    out.flush()
+}
+
+fn serve_connection(store: &Store, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut reader = BufReader::new(&stream);
+    let response = match read_request(&mut reader) {
+        Ok((method, target, body)) => route(store, &method, &target, &body),
+        Err(response) => response,
+    };
+    write_response(&mut &stream, &response)
+}
+
+/// Answers requests on the listener forever, with [`WORKERS`] threads. A
+/// connection that can't be accepted is skipped.
+pub fn run(listener: TcpListener, store: Arc<Store>) -> io::Result<()> {
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..WORKERS {
+        let (store, receiver) = (Arc::clone(&store), Arc::clone(&receiver));
+        thread::spawn(move || {
+            loop {
+                // The lock is only held while waiting, not while serving.
+                let Ok(stream) = receiver.lock().unwrap().recv() else {
+                    break;
+                };
+                if let Err(err) = serve_connection(&store, stream) {
+                    crate::diag::note!("warning: dropping connection: {}", err);
+                }
+            }
+        });
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                crate::diag::note!("warning: unable to accept a connection: {}", err);
+                continue;
+            }
+        };
+        if sender.send(stream).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Store {
+        Store::new(
+            "default",
+            vec![
+                "10.0.0.0/23".parse().unwrap(),
+                "2001:db8::/32".parse().unwrap(),
+            ],
+            Duration::ZERO,
+        )
+    }
+
+    /// Returns the JSON body of the response.
+    fn json(response: Response) -> Value {
+        match response.body {
+            Some(Body::Json(body)) => body,
+            body => panic!("{:?} isn't JSON", body),
+        }
+    }
+
+    fn call(store: &Store, method: &str, target: &str, body: &str) -> Response {
+        route(store, method, target, body.as_bytes())
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let response = call(
+            &store(),
+            "POST",
+            "/aggregate",
+            r#"{"networks": ["10.0.0.0/24", "10.0.1.0/24"]}"#,
+        );
+        assert_eq!(
+            response,
+            Response::ok(json!({ "networks": ["10.0.0.0/23"] }))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_bad_network() {
+        let response = call(&store(), "POST", "/aggregate", r#"{"networks": ["nope"]}"#);
+        assert_eq!(response.status, 400);
+        assert_eq!(
+            json(response)["error"],
+            "Unable to parse \"nope\" as an IP network on line 1, column 1: not an IP address."
+        );
+    }
+
+    #[test]
+    fn test_aggregate_bad_body() {
+        assert_eq!(call(&store(), "POST", "/aggregate", "[").status, 400);
+        assert_eq!(call(&store(), "POST", "/aggregate", "{}").status, 400);
+    }
+
+    #[test]
+    fn test_diff() {
+        let response = call(
+            &store(),
+            "POST",
+            "/diff",
+            r#"{"old": ["10.0.0.0/24"], "new": ["10.0.0.0/24", "10.0.1.0/24"]}"#,
+        );
+        assert_eq!(
+            response,
+            Response::ok(json!({ "added": ["10.0.0.0/23"], "removed": ["10.0.0.0/24"] }))
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let store = store();
+        store.put("other", vec!["10.0.0.0/8".parse().unwrap()], Duration::ZERO);
+        assert_eq!(
+            call(&store, "GET", "/lookup?address=10.0.1.1", ""),
+            Response::ok(json!({
+                "address": "10.0.1.1",
+                "matches": [
+                    { "list": "default", "network": "10.0.0.0/23" },
+                    { "list": "other", "network": "10.0.0.0/8" },
+                ],
+            }))
+        );
+        assert_eq!(
+            call(
+                &store,
+                "GET",
+                "/lookup?address=2001%3adb8%3A%3A1&list=default",
+                ""
+            ),
+            Response::ok(json!({
+                "address": "2001:db8::1",
+                "matches": [{ "list": "default", "network": "2001:db8::/32" }],
+            }))
+        );
+        assert_eq!(call(&store, "GET", "/lookup", "").status, 400);
+        assert_eq!(call(&store, "GET", "/lookup?address=x", "").status, 400);
+        assert_eq!(
+            call(&store, "GET", "/lookup?address=10.0.0.1&list=missing", "").status,
+            404
+        );
+    }
+
+    #[test]
+    fn test_list_management() {
+        let store = store();
+        assert_eq!(
+            call(&store, "GET", "/lists", ""),
+            Response::ok(json!({ "lists": ["default"] }))
+        );
+        assert_eq!(
+            call(
+                &store,
+                "PUT",
+                "/lists/new",
+                r#"{"networks": ["192.168.0.0/24", "192.168.1.0/24"]}"#
+            ),
+            Response::ok(json!({ "name": "new", "networks": ["192.168.0.0/23"] }))
+        );
+        assert_eq!(
+            call(&store, "GET", "/lists/new", ""),
+            Response::ok(json!({ "name": "new", "networks": ["192.168.0.0/23"] }))
+        );
+        assert_eq!(call(&store, "DELETE", "/lists/new", "").status, 204);
+        assert_eq!(call(&store, "DELETE", "/lists/new", "").status, 404);
+        assert_eq!(call(&store, "GET", "/lists/new", "").status, 404);
+        assert_eq!(call(&store, "GET", "/lists/..", "").status, 400);
+        assert_eq!(call(&store, "POST", "/lists/new", "").status, 405);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let store = store();
+        store.put("other", vec!["10.0.0.0/8".parse().unwrap()], Duration::ZERO);
+        let response = call(&store, "GET", "/metrics", "");
+        assert_eq!(response.status, 200);
+        let Some(Body::Metrics(text)) = response.body else {
+            panic!("{:?} isn't metrics", response.body);
+        };
+        assert!(text.contains("lfc_prefixes{list=\"default\",family=\"IPv4\"} 1\n"));
+        assert!(text.contains("lfc_prefixes{list=\"other\",family=\"IPv4\"} 1\n"));
+        assert!(text.contains("lfc_addresses{list=\"other\",family=\"IPv4\"} 16777216\n"));
+        assert!(text.contains("lfc_fetch_errors "));
+        assert_eq!(call(&store, "POST", "/metrics", "").status, 405);
+    }
+
+    #[test]
+    fn test_unknown_routes() {
+        assert_eq!(call(&store(), "GET", "/nowhere", "").status, 404);
+        assert_eq!(call(&store(), "GET", "/aggregate", "").status, 405);
+    }
+
+    #[test]
+    fn test_openapi() {
+        let response = call(&store(), "GET", "/openapi.json", "");
+        assert_eq!(response.status, 200);
+        let doc = json(response);
+        assert_eq!(doc["openapi"], "3.0.3");
+        assert_eq!(doc["info"]["version"], env!("CARGO_PKG_VERSION"));
+        for path in [
+            "/aggregate",
+            "/diff",
+            "/lookup",
+            "/lists",
+            "/lists/{name}",
+            "/metrics",
+        ] {
+            assert!(
+                doc["paths"].get(path).is_some(),
+                "{} isn't documented",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("2001%3Adb8%3a%3A1"), "2001:db8::1");
+        assert_eq!(percent_decode("a+b"), "a b");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /aggregate HTTP/1.1\r\nHost: x\r\nContent-Length: 4\r\n\r\nbodyextra";
+        let (method, target, body) = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(target, "/aggregate");
+        assert_eq!(body, b"body");
+
+        let chunked = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert_eq!(
+            read_request(&mut chunked.as_bytes()).unwrap_err().status,
+            501
+        );
+        let short = "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nbody";
+        assert_eq!(read_request(&mut short.as_bytes()).unwrap_err().status, 400);
+        let huge = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert_eq!(read_request(&mut huge.as_bytes()).unwrap_err().status, 413);
+        assert_eq!(read_request(&mut "GET".as_bytes()).unwrap_err().status, 400);
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut out = Vec::new();
+        write_response(&mut out, &Response::ok(json!({ "lists": [] }))).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(out.contains("Content-Length: 18\r\n"));
+        assert!(out.ends_with("\r\n\r\n{\n  \"lists\": []\n}\n"));
+    }
+}
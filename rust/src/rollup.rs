@@ -0,0 +1,216 @@
+//! Networks grouped by who or where they belong to, for `lfc by-country`
+//! and `lfc by-asn`.
+//!
+//! Each group says how many addresses of each family it has, and what
+//! share of the whole list's addresses of that family that is, which is
+//! what "how much of this blocklist is in one country" is asking.
+
+use ipnet::IpNet;
+use lfc::format::Style;
+use lfc::space::{self, Family};
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// The networks that belong to one country, AS, or the like.
+pub struct Group {
+    /// What the group is keyed by, like a country code or `AS64500`.
+    pub key: String,
+    /// A longer name for it, like the AS's.
+    pub name: Option<String>,
+    pub nets: Vec<IpNet>,
+}
+
+/// The networks in each group.
+pub struct Rollup {
+    groups: Vec<Group>,
+    totals: [u128; 2],
+}
+
+impl Rollup {
+    /// Returns the rollup of the groups, in order, which together make up
+    /// the whole list.
+    pub fn new(groups: Vec<Group>) -> Rollup {
+        let all: Vec<IpNet> = groups
+            .iter()
+            .flat_map(|group| &group.nets)
+            .copied()
+            .collect();
+        Rollup {
+            totals: sizes(&all),
+            groups,
+        }
+    }
+
+    /// Writes a section for each group: a `#` comment line with its stats,
+    /// so that the output still reads as a list, and then its networks.
+    pub fn write_text<W: Write>(&self, out: &mut W, style: &Style) -> io::Result<()> {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            write!(out, "# {}", group.key)?;
+            if let Some(name) = &group.name {
+                write!(out, " {}", name)?;
+            }
+            let plural = if group.nets.len() == 1 { "" } else { "s" };
+            write!(out, ": {} network{}", group.nets.len(), plural)?;
+            for (family, size, total) in self.stats(&group.nets) {
+                write!(
+                    out,
+                    ", {} {} addresses ({:.1}%)",
+                    size,
+                    family,
+                    share(size, total)
+                )?;
+            }
+            writeln!(out)?;
+            for net in &group.nets {
+                writeln!(out, "{}", style.display(net))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a JSON object with a member for each group, keyed like the
+    /// group, with its name, networks, and stats. The address counts can be
+    /// bigger than JSON parsers that use doubles hold exactly, but they're
+    /// written exactly anyway.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        for (i, group) in self.groups.iter().enumerate() {
+            write!(out, "  {}: {{", Value::from(group.key.as_str()))?;
+            if let Some(name) = &group.name {
+                write!(out, "\"name\": {}, ", Value::from(name.as_str()))?;
+            }
+            let items: Vec<String> = group
+                .nets
+                .iter()
+                .map(|net| format!("\"{}\"", net))
+                .collect();
+            write!(out, "\"networks\": [{}]", items.join(", "))?;
+            for (family, size, total) in self.stats(&group.nets) {
+                let family = match family {
+                    Family::V4 => "ipv4",
+                    Family::V6 => "ipv6",
+                };
+                write!(
+                    out,
+                    ", \"{}_addresses\": {}, \"{}_percent\": {:.1}",
+                    family,
+                    size,
+                    family,
+                    share(size, total)
+                )?;
+            }
+            let comma = if i + 1 < self.groups.len() { "," } else { "" };
+            writeln!(out, "}}{}", comma)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Returns the size of the networks in each family the whole list has
+    /// any addresses of, along with the list's size in it.
+    fn stats(&self, nets: &[IpNet]) -> Vec<(Family, u128, u128)> {
+        let sizes = sizes(nets);
+        [Family::V4, Family::V6]
+            .into_iter()
+            .zip(sizes.into_iter().zip(self.totals))
+            .filter(|(_, (_, total))| *total > 0)
+            .map(|(family, (size, total))| (family, size, total))
+            .collect()
+    }
+}
+
+/// Returns the number of IPv4 and IPv6 addresses in the networks.
+fn sizes(nets: &[IpNet]) -> [u128; 2] {
+    [Family::V4, Family::V6].map(|family| space::size(&space::ranges(nets, family)))
+}
+
+/// Returns `size` as a percentage of `total`.
+fn share(size: u128, total: u128) -> f64 {
+    size as f64 * 100.0 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rollup(groups: &[(&str, &[&str])]) -> Rollup {
+        Rollup::new(
+            groups
+                .iter()
+                .map(|(key, nets)| Group {
+                    key: key.to_string(),
+                    name: None,
+                    nets: nets.iter().map(|net| net.parse().unwrap()).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_write_text() {
+        let rollup = rollup(&[
+            ("AU", &["203.0.112.0/23"]),
+            ("US", &["192.0.2.0/24", "198.51.100.0/24", "2001:db8::/32"]),
+        ]);
+        let mut out = Vec::new();
+        rollup.write_text(&mut out, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+# AU: 1 network, 512 IPv4 addresses (50.0%), 0 IPv6 addresses (0.0%)
+203.0.112.0/23
+
+# US: 3 networks, 512 IPv4 addresses (50.0%), 79228162514264337593543950336 IPv6 addresses (100.0%)
+192.0.2.0/24
+198.51.100.0/24
+2001:db8::/32
+"
+        );
+    }
+
+    #[test]
+    fn test_write_json() {
+        let rollup = rollup(&[
+            ("AU", &["1.0.0.0/24"]),
+            ("ZZ", &["1.0.1.0/24", "1.0.2.0/23"]),
+        ]);
+        let mut out = Vec::new();
+        rollup.write_json(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text,
+            "\
+{
+  \"AU\": {\"networks\": [\"1.0.0.0/24\"], \"ipv4_addresses\": 256, \"ipv4_percent\": 25.0},
+  \"ZZ\": {\"networks\": [\"1.0.1.0/24\", \"1.0.2.0/23\"], \"ipv4_addresses\": 768, \"ipv4_percent\": 75.0}
+}
+"
+        );
+        assert!(serde_json::from_str::<serde_json::Value>(&text).is_ok());
+        let mut out = Vec::new();
+        Rollup::new(Vec::new()).write_json(&mut out).unwrap();
+        assert_eq!(out, b"{\n}\n");
+    }
+
+    #[test]
+    fn test_names() {
+        let rollup = Rollup::new(vec![Group {
+            key: "AS64500".to_string(),
+            name: Some("EXAMPLE \"NET\", US".to_string()),
+            nets: vec!["192.0.2.0/24".parse().unwrap()],
+        }]);
+        let mut out = Vec::new();
+        rollup.write_text(&mut out, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# AS64500 EXAMPLE \"NET\", US: 1 network, 256 IPv4 addresses (100.0%)\n192.0.2.0/24\n"
+        );
+        let mut out = Vec::new();
+        rollup.write_json(&mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["AS64500"]["name"], "EXAMPLE \"NET\", US");
+        assert_eq!(json["AS64500"]["ipv4_addresses"], 256);
+    }
+}
@@ -0,0 +1,437 @@
+//! RPKI origin validation, for `--rpki`.
+//!
+//! Validated ROA payloads (VRPs) come either from an RPKI-to-Router cache,
+//! like Routinator or StayRTR, over `rtr://HOST:PORT`, or from a file or URL
+//! of the JSON or CSV that validators export. Each network is then checked
+//! the way routers check announcements (RFC 6811): it's valid if a VRP
+//! covering it names the origin and allows its length, invalid if VRPs
+//! cover it but none does, and not found if none cover it at all.
+
+use ipnet::IpNet;
+use lfc::source;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpStream};
+use std::time::Duration;
+
+/// The port RTR caches listen on when the URL doesn't say.
+const RTR_PORT: u16 = 323;
+
+/// How long to wait for the cache before giving up.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The longest PDU we accept. Prefix PDUs are 32 bytes at most, and only
+/// error reports carry text.
+const MAX_PDU: usize = 65_536;
+
+/// The error code a cache reports when it doesn't speak our version.
+const UNSUPPORTED_VERSION: u16 = 4;
+
+/// What RPKI says about a network announced by an origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Valid,
+    Invalid,
+    NotFound,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            State::Valid => "valid",
+            State::Invalid => "invalid",
+            State::NotFound => "not found",
+        })
+    }
+}
+
+/// A validated ROA payload: the origin may announce the prefix, and any
+/// network inside it up to the maximum length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Vrp {
+    prefix: IpNet,
+    max_len: u8,
+    asn: u32,
+}
+
+impl Vrp {
+    /// Returns the VRP, or `None` if the maximum length doesn't fit the
+    /// prefix.
+    fn new(prefix: IpNet, max_len: u8, asn: u32) -> Option<Vrp> {
+        (prefix.prefix_len() <= max_len && max_len <= prefix.max_prefix_len()).then_some(Vrp {
+            prefix: prefix.trunc(),
+            max_len,
+            asn,
+        })
+    }
+}
+
+/// The VRPs, by prefix, each with the lengths and origins allowed.
+#[derive(Debug, Default)]
+pub struct Vrps {
+    by_prefix: HashMap<IpNet, Vec<(u8, u32)>>,
+}
+
+impl FromIterator<Vrp> for Vrps {
+    fn from_iter<I: IntoIterator<Item = Vrp>>(vrps: I) -> Self {
+        let mut by_prefix: HashMap<IpNet, Vec<(u8, u32)>> = HashMap::new();
+        for vrp in vrps {
+            by_prefix
+                .entry(vrp.prefix)
+                .or_default()
+                .push((vrp.max_len, vrp.asn));
+        }
+        Vrps { by_prefix }
+    }
+}
+
+impl Vrps {
+    /// Loads the VRPs from an RTR cache, if the source starts with
+    /// `rtr://`, or else from a file or URL of VRPs.
+    pub fn load(source: &str) -> Result<Vrps, String> {
+        match source.strip_prefix("rtr://") {
+            Some(address) => rtr(address),
+            None => {
+                let file = source::open(source).map_err(|err| err.to_string())?;
+                let text = std::str::from_utf8(file.bytes())
+                    .map_err(|_| format!("{} isn't text", source))?;
+                parse(text).map_err(|err| format!("{}: {}", source, err))
+            }
+        }
+    }
+
+    /// Returns how many VRPs there are.
+    pub fn len(&self) -> usize {
+        self.by_prefix.values().map(Vec::len).sum()
+    }
+
+    /// Returns the state of the network announced by the origin.
+    pub fn validate(&self, net: &IpNet, origin: u32) -> State {
+        let mut state = State::NotFound;
+        for len in 0..=net.prefix_len() {
+            let covering = IpNet::new(net.addr(), len).unwrap().trunc();
+            for &(max_len, asn) in self.by_prefix.get(&covering).into_iter().flatten() {
+                if asn == origin && net.prefix_len() <= max_len {
+                    return State::Valid;
+                }
+                state = State::Invalid;
+            }
+        }
+        state
+    }
+}
+
+/// Parses an AS number, like `AS64500` or `64500`.
+pub fn parse_asn(text: &str) -> Option<u32> {
+    let digits = text
+        .strip_prefix("AS")
+        .or_else(|| text.strip_prefix("as"))
+        .unwrap_or(text);
+    digits.parse().ok()
+}
+
+/// Parses VRPs exported as JSON, with a `roas` array like Routinator's and
+/// rpki-client's, or as CSV, with a line of `ASN,PREFIX,MAX LENGTH` and
+/// anything after for each.
+fn parse(text: &str) -> Result<Vrps, String> {
+    if text.trim_start().starts_with('{') {
+        let json: Value =
+            serde_json::from_str(text).map_err(|err| format!("not valid JSON: {}", err))?;
+        let roas = json["roas"]
+            .as_array()
+            .ok_or("the JSON doesn't have a \"roas\" array")?;
+        return roas
+            .iter()
+            .map(|roa| {
+                let asn = match &roa["asn"] {
+                    Value::String(asn) => parse_asn(asn),
+                    asn => asn.as_u64().and_then(|asn| u32::try_from(asn).ok()),
+                };
+                let prefix = roa["prefix"].as_str().and_then(|net| net.parse().ok());
+                let max_len = roa["maxLength"]
+                    .as_u64()
+                    .and_then(|len| u8::try_from(len).ok());
+                asn.zip(prefix)
+                    .zip(max_len)
+                    .and_then(|((asn, prefix), max_len)| Vrp::new(prefix, max_len, asn))
+                    .ok_or_else(|| format!("invalid ROA {}", roa))
+            })
+            .collect();
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        // A header, if there is one, doesn't start with an AS number.
+        .filter(|(number, line)| {
+            *number > 0 || parse_asn(line.split(',').next().unwrap()).is_some()
+        })
+        .map(|(number, line)| {
+            let mut fields = line.split(',').map(str::trim);
+            let asn = fields.next().and_then(parse_asn);
+            let prefix = fields.next().and_then(|net| net.parse().ok());
+            let max_len = fields.next().and_then(|len| len.parse().ok());
+            asn.zip(prefix)
+                .zip(max_len)
+                .and_then(|((asn, prefix), max_len)| Vrp::new(prefix, max_len, asn))
+                .ok_or_else(|| format!("line {}: invalid VRP '{}'", number + 1, line))
+        })
+        .collect()
+}
+
+/// Downloads the VRPs from an RTR cache at `HOST[:PORT]`, asking for
+/// version 1 of the protocol (RFC 8210) and falling back to version 0 (RFC
+/// 6810) if the cache is older.
+fn rtr(address: &str) -> Result<Vrps, String> {
+    let address = address.trim_end_matches('/');
+    let has_port = address
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| port.parse::<u16>().is_ok() && !host.ends_with(':'));
+    let address = if has_port {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, RTR_PORT)
+    };
+    for version in [1, 0] {
+        let failed = |err: io::Error| format!("rtr://{}: {}", address, err);
+        let mut stream = TcpStream::connect(&address).map_err(failed)?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(failed)?;
+        match query(&mut stream, version) {
+            Err(Rtr::Downgrade) if version > 0 => continue,
+            result => return result.map_err(|err| format!("rtr://{}: {}", address, err)),
+        }
+    }
+    unreachable!("version 0 never downgrades")
+}
+
+/// Why an RTR query failed.
+#[derive(Debug)]
+enum Rtr {
+    /// The cache wants an older version of the protocol.
+    Downgrade,
+    Failed(String),
+}
+
+impl fmt::Display for Rtr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rtr::Downgrade => f.write_str("the cache doesn't speak any version we do"),
+            Rtr::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<io::Error> for Rtr {
+    fn from(err: io::Error) -> Self {
+        Rtr::Failed(err.to_string())
+    }
+}
+
+/// Sends a Reset Query and reads the cache's whole answer, up to its End of
+/// Data.
+fn query<S: Read + Write>(stream: &mut S, version: u8) -> Result<Vrps, Rtr> {
+    stream.write_all(&[version, 2, 0, 0, 0, 0, 0, 8])?;
+    stream.flush()?;
+    let mut vrps = Vec::new();
+    loop {
+        let mut header = [0; 8];
+        stream.read_exact(&mut header)?;
+        let (kind, field) = (header[1], u16::from_be_bytes([header[2], header[3]]));
+        let length = u32::from_be_bytes(header[4..].try_into().unwrap()) as usize;
+        if !(8..=MAX_PDU).contains(&length) {
+            return Err(Rtr::Failed(format!("bad PDU length {}", length)));
+        }
+        let mut body = vec![0; length - 8];
+        stream.read_exact(&mut body)?;
+        match kind {
+            // Serial Notify, Cache Response, and Router Key.
+            0 | 3 | 9 => {}
+            4 | 6 => {
+                let vrp =
+                    prefix(kind, &body).ok_or_else(|| Rtr::Failed("bad prefix PDU".to_string()))?;
+                // The first bit of the flags says whether it's announced
+                // or withdrawn.
+                if body[0] & 1 == 1 {
+                    vrps.push(vrp);
+                } else {
+                    vrps.retain(|other| *other != vrp);
+                }
+            }
+            7 => return Ok(vrps.into_iter().collect()),
+            8 => return Err(Rtr::Failed("the cache reset the session".to_string())),
+            10 if field == UNSUPPORTED_VERSION && version > 0 => return Err(Rtr::Downgrade),
+            10 => {
+                let text = report_text(&body).unwrap_or_default();
+                return Err(Rtr::Failed(match field {
+                    2 => "the cache has no data yet".to_string(),
+                    _ if text.is_empty() => format!("the cache reported error {}", field),
+                    _ => format!("the cache reported error {}: {}", field, text),
+                }));
+            }
+            _ => return Err(Rtr::Failed(format!("unexpected PDU type {}", kind))),
+        }
+    }
+}
+
+/// Returns the VRP in the body of an IPv4 (type 4) or IPv6 (type 6) Prefix
+/// PDU: flags, prefix length, maximum length, a zero, the address, and the
+/// AS number.
+fn prefix(kind: u8, body: &[u8]) -> Option<Vrp> {
+    let (addr, rest): (IpAddr, &[u8]) = match kind {
+        4 if body.len() == 12 => (
+            Ipv4Addr::from(<[u8; 4]>::try_from(&body[4..8]).ok()?).into(),
+            &body[8..],
+        ),
+        6 if body.len() == 24 => (
+            Ipv6Addr::from(<[u8; 16]>::try_from(&body[4..20]).ok()?).into(),
+            &body[20..],
+        ),
+        _ => return None,
+    };
+    let prefix = IpNet::new(addr, body[1]).ok()?;
+    Vrp::new(prefix, body[2], u32::from_be_bytes(rest.try_into().ok()?))
+}
+
+/// Returns the text of an Error Report's body: the erroneous PDU, with its
+/// length first, then the text, with its length first.
+fn report_text(body: &[u8]) -> Option<String> {
+    let pdu = u32::from_be_bytes(body.get(..4)?.try_into().ok()?) as usize;
+    let rest = body.get(4 + pdu..)?;
+    let text = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+    Some(String::from_utf8_lossy(rest.get(4..4 + text)?).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache that answers from a script of bytes and keeps what it's sent.
+    struct Cache {
+        answer: io::Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Cache {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.answer.read(buf)
+        }
+    }
+
+    impl Write for Cache {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn cache(pdus: &[&[u8]]) -> Cache {
+        Cache {
+            answer: io::Cursor::new(pdus.concat()),
+            sent: Vec::new(),
+        }
+    }
+
+    fn vrps(text: &str) -> Vrps {
+        parse(text).unwrap()
+    }
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let vrps = vrps(
+            "ASN,IP Prefix,Max Length,Trust Anchor\nAS64500,192.0.2.0/23,24,arin\nAS64501,198.51.100.0/24,24,ripe\nAS0,203.0.113.0/24,24,apnic\n",
+        );
+        assert_eq!(vrps.len(), 3);
+        assert_eq!(vrps.validate(&net("192.0.2.0/23"), 64500), State::Valid);
+        assert_eq!(vrps.validate(&net("192.0.3.0/24"), 64500), State::Valid);
+        assert_eq!(vrps.validate(&net("192.0.3.0/25"), 64500), State::Invalid);
+        assert_eq!(
+            vrps.validate(&net("198.51.100.0/24"), 64500),
+            State::Invalid
+        );
+        assert_eq!(vrps.validate(&net("203.0.113.0/24"), 64500), State::Invalid);
+        assert_eq!(vrps.validate(&net("192.0.0.0/16"), 64500), State::NotFound);
+        assert_eq!(vrps.validate(&net("2001:db8::/32"), 64500), State::NotFound);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let vrps = vrps(
+            r#"{"metadata": {"vrps": 2}, "roas": [
+                {"asn": "AS64500", "prefix": "2001:db8::/32", "maxLength": 48, "ta": "ripe"},
+                {"asn": 64501, "prefix": "192.0.2.0/24", "maxLength": 24, "ta": "arin"}
+            ]}"#,
+        );
+        assert_eq!(vrps.validate(&net("2001:db8:1::/48"), 64500), State::Valid);
+        assert_eq!(vrps.validate(&net("192.0.2.0/24"), 64501), State::Valid);
+        assert!(
+            parse(r#"{"roas": [{"asn": 1, "prefix": "192.0.2.0/24", "maxLength": 16}]}"#).is_err()
+        );
+        assert!(parse(r#"{"vrps": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        assert_eq!(vrps("64500,192.0.2.0/24,24\n\n").len(), 1);
+        assert!(parse("AS64500,192.0.2.0/24,24\nAS64500,192.0.2.0/24\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_asn() {
+        assert_eq!(parse_asn("AS64500"), Some(64500));
+        assert_eq!(parse_asn("as4200000000"), Some(4_200_000_000));
+        assert_eq!(parse_asn("64500"), Some(64500));
+        assert_eq!(parse_asn("AS"), None);
+        assert_eq!(parse_asn("AS-1"), None);
+    }
+
+    #[test]
+    fn test_query() {
+        let mut stream = cache(&[
+            &[1, 3, 0, 7, 0, 0, 0, 8],
+            &[
+                1, 4, 0, 0, 0, 0, 0, 20, 1, 23, 24, 0, 192, 0, 2, 0, 0, 0, 0xfb, 0xf4,
+            ],
+            &[
+                1, 6, 0, 0, 0, 0, 0, 32, 1, 32, 48, 0, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0xfb, 0xf5,
+            ],
+            &[1, 9, 0, 0, 0, 0, 0, 8],
+            &[
+                1, 7, 0, 7, 0, 0, 0, 24, 0, 0, 0, 1, 0, 0, 14, 16, 0, 0, 2, 88, 0, 0, 28, 32,
+            ],
+        ]);
+        let vrps = query(&mut stream, 1).unwrap();
+        assert_eq!(stream.sent, [1, 2, 0, 0, 0, 0, 0, 8]);
+        assert_eq!(vrps.len(), 2);
+        assert_eq!(vrps.validate(&net("192.0.3.0/24"), 64500), State::Valid);
+        assert_eq!(vrps.validate(&net("2001:db8::/48"), 64501), State::Valid);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let unsupported: &[u8] = &[1, 10, 0, 4, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(matches!(
+            query(&mut cache(&[unsupported]), 1),
+            Err(Rtr::Downgrade)
+        ));
+        let no_data: &[u8] = &[0, 10, 0, 2, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0];
+        let err = query(&mut cache(&[no_data]), 0).unwrap_err().to_string();
+        assert_eq!(err, "the cache has no data yet");
+        let mut report = vec![0, 10, 0, 1, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 4];
+        report.extend(b"oops");
+        let err = query(&mut cache(&[&report]), 0).unwrap_err().to_string();
+        assert_eq!(err, "the cache reported error 1: oops");
+        let bad_length: &[u8] = &[1, 3, 0, 0, 0, 0, 0, 4];
+        assert!(query(&mut cache(&[bad_length]), 1).is_err());
+        let short: &[u8] = &[1, 3, 0, 0, 0, 0, 0, 8];
+        assert!(query(&mut cache(&[short]), 1).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Reproducible random subsets of a list, for `--sample`.
+//!
+//! The picks come from the same seeded generator as `lfc gen`, so the same
+//! list, count, and `--seed` always pick the same networks, which is what a
+//! canary deployment needs to be repeatable.
+
+use crate::synth::Rng;
+use ipnet::IpNet;
+use lfc::space;
+
+/// Returns `count` of the networks, picked at random without replacement,
+/// in the order they're listed. With `weighted`, a network's chance of
+/// being picked is in proportion to how many addresses it has, rather than
+/// the same for every network. Asking for more than there are returns them
+/// all.
+pub fn sample(nets: &[IpNet], count: usize, weighted: bool, rng: &mut Rng) -> Vec<IpNet> {
+    let mut picked: Vec<usize> = if weighted {
+        // Efraimidis and Spirakis' method: each network gets the key
+        // ln(u) / weight for a uniform u in (0, 1], and the biggest keys win.
+        let mut keys: Vec<(f64, usize)> = nets
+            .iter()
+            .enumerate()
+            .map(|(i, net)| {
+                let (start, end) = space::range(net);
+                let weight = (end - start) as f64 + 1.0;
+                (uniform(rng).ln() / weight, i)
+            })
+            .collect();
+        keys.sort_by(|a, b| b.0.total_cmp(&a.0));
+        keys.into_iter().take(count).map(|(_, i)| i).collect()
+    } else {
+        // A Fisher-Yates shuffle, stopped once the first `count` are picked.
+        let mut indices: Vec<usize> = (0..nets.len()).collect();
+        let count = count.min(indices.len());
+        for i in 0..count {
+            let j = i + rng.below((indices.len() - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices.truncate(count);
+        indices
+    };
+    picked.sort_unstable();
+    picked.into_iter().map(|i| nets[i]).collect()
+}
+
+/// Returns a uniform random number in (0, 1].
+fn uniform(rng: &mut Rng) -> f64 {
+    ((rng.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(count: usize) -> Vec<IpNet> {
+        (0..count)
+            .map(|i| format!("10.0.{}.0/24", i).parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let nets = nets(100);
+        let picked = sample(&nets, 10, false, &mut Rng::new(42));
+        assert_eq!(picked.len(), 10);
+        assert!(picked.is_sorted());
+        assert!(picked.iter().all(|net| nets.contains(net)));
+        assert_eq!(picked, sample(&nets, 10, false, &mut Rng::new(42)));
+        assert_ne!(picked, sample(&nets, 10, false, &mut Rng::new(43)));
+        assert_eq!(sample(&nets, 1000, false, &mut Rng::new(42)), nets);
+        assert_eq!(sample(&nets, 0, false, &mut Rng::new(42)), vec![]);
+    }
+
+    #[test]
+    fn test_sample_weighted() {
+        let mut nets = nets(50);
+        nets.push("11.0.0.0/8".parse().unwrap());
+        // The /8 has 65,536 times the addresses of any /24, so it's all but
+        // certain to be picked with any seed.
+        for seed in 0..20 {
+            let picked = sample(&nets, 2, true, &mut Rng::new(seed));
+            assert_eq!(picked.len(), 2);
+            assert!(picked.contains(&"11.0.0.0/8".parse().unwrap()));
+        }
+        assert_eq!(
+            sample(&nets, 5, true, &mut Rng::new(7)),
+            sample(&nets, 5, true, &mut Rng::new(7))
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! User scripts that adjust the input and output.
+//!
+//! `--script FILE` loads a [Rhai](https://rhai.rs) script that can define
+//! either or both of these functions:
+//!
+//! ```text
+//! fn input(line) { ... }   // called with each non-empty input line
+//! fn output(net) { ... }   // called with each aggregated network
+//! ```
+//!
+//! Each returns a string to use in place of what it was given, or `()` to
+//! drop it. Whatever `input` returns is parsed as a network as usual. Whatever
+//! `output` returns is printed as is, so it can be any line at all, like a
+//! tagged entry for some other tool. The script's top level runs once before
+//! any of the calls, and `print` and `debug` go to stderr so they can't mix
+//! with the output.
+//!
+//! Scripting is behind the `scripting` feature. Without it, `--script` is an
+//! error.
+
+use ipnet::IpNet;
+use lfc::source::InputSource;
+
+#[cfg(feature = "scripting")]
+pub use engine::Script;
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use super::*;
+    use lfc::source::MemorySource;
+    use rhai::{AST, CallFnOptions, Dynamic, Engine, Scope};
+    use std::fs;
+
+    /// A loaded script.
+    pub struct Script {
+        engine: Engine,
+        ast: AST,
+        scope: Scope<'static>,
+    }
+
+    impl Script {
+        /// Loads and compiles the script, and runs its top level.
+        pub fn load(path: &str) -> Result<Script, String> {
+            let source = fs::read_to_string(path)
+                .map_err(|err| format!("unable to read {:?}: {}", path, err))?;
+            Script::compile(path, &source)
+        }
+
+        fn compile(path: &str, source: &str) -> Result<Script, String> {
+            let mut engine = Engine::new();
+            engine.on_print(|text| eprintln!("{}", text));
+            engine.on_debug(|text, _, position| eprintln!("{:?}: {}", position, text));
+            let ast = engine
+                .compile(source)
+                .map_err(|err| format!("unable to load {:?}: {}", path, err))?;
+            let mut scope = Scope::new();
+            engine
+                .run_ast_with_scope(&mut scope, &ast)
+                .map_err(|err| format!("{:?} failed: {}", path, err))?;
+            Ok(Script { engine, ast, scope })
+        }
+
+        /// Returns whether the script defines the one-argument function.
+        fn defines(&self, name: &str) -> bool {
+            self.ast
+                .iter_functions()
+                .any(|function| function.name == name && function.params.len() == 1)
+        }
+
+        /// Calls the function, and returns the string it returned, or `None`
+        /// if it returned `()`.
+        fn call(&mut self, name: &str, arg: &str) -> Result<Option<String>, String> {
+            // The top level already ran when the script was loaded.
+            let options = CallFnOptions::new().eval_ast(false).rewind_scope(false);
+            let result: Dynamic = self
+                .engine
+                .call_fn_with_options(
+                    options,
+                    &mut self.scope,
+                    &self.ast,
+                    name,
+                    (arg.to_string(),),
+                )
+                .map_err(|err| format!("{}({:?}) failed: {}", name, arg, err))?;
+            if result.is_unit() {
+                return Ok(None);
+            }
+            result.into_string().map(Some).map_err(|type_name| {
+                format!(
+                    "{}({:?}) returned {}, not a string or ()",
+                    name, arg, type_name
+                )
+            })
+        }
+
+        /// Passes each non-empty line of the inputs through `input`, if the
+        /// script defines it. Dropped lines are left blank, so that line
+        /// numbers in errors still match the original input.
+        pub fn filter_inputs(
+            &mut self,
+            inputs: Vec<Box<dyn InputSource>>,
+        ) -> Result<Vec<Box<dyn InputSource>>, String> {
+            if !self.defines("input") {
+                return Ok(inputs);
+            }
+            let mut filtered: Vec<Box<dyn InputSource>> = Vec::with_capacity(inputs.len());
+            for input in inputs {
+                let mut text = String::with_capacity(input.bytes().len());
+                for record in input.records() {
+                    let record = record.map_err(|err| err.to_string())?;
+                    let line = record.text.trim();
+                    if !line.is_empty() {
+                        match self.call("input", line)? {
+                            Some(line) if line.contains('\n') => {
+                                return Err(format!(
+                                    "input({:?}) returned more than one line, on {}",
+                                    record.text, record.provenance
+                                ));
+                            }
+                            Some(line) => text.push_str(&line),
+                            None => {}
+                        }
+                    }
+                    text.push('\n');
+                }
+                filtered.push(Box::new(MemorySource::new(input.name(), text)));
+            }
+            Ok(filtered)
+        }
+
+        /// Passes each network through `output`, if the script defines it,
+        /// and returns the lines to print.
+        pub fn output(&mut self, nets: &[IpNet]) -> Result<Option<Vec<String>>, String> {
+            if !self.defines("output") {
+                return Ok(None);
+            }
+            let mut lines = Vec::with_capacity(nets.len());
+            for net in nets {
+                lines.extend(self.call("output", &net.to_string())?);
+            }
+            Ok(Some(lines))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn script(source: &str) -> Script {
+            Script::compile("test.rhai", source).unwrap()
+        }
+
+        fn lines(input: &dyn InputSource) -> Vec<&str> {
+            input.records().map(|record| record.unwrap().text).collect()
+        }
+
+        #[test]
+        fn test_filter_inputs() {
+            let mut script = script(
+                r##"
+                fn input(line) {
+                    if line.starts_with("#") { return (); }
+                    if !line.contains("/") { return line + "/32"; }
+                    line
+                }
+                "##,
+            );
+            let inputs: Vec<Box<dyn InputSource>> = vec![Box::new(MemorySource::new(
+                "list",
+                "# comment\n10.0.0.1\n\n10.1.0.0/16\n",
+            ))];
+            let inputs = script.filter_inputs(inputs).unwrap();
+            assert_eq!(inputs[0].name(), "list");
+            assert_eq!(
+                lines(inputs[0].as_ref()),
+                vec!["", "10.0.0.1/32", "", "10.1.0.0/16"]
+            );
+        }
+
+        #[test]
+        fn test_output() {
+            let mut script = script(
+                r#"
+                fn output(net) {
+                    if net.starts_with("192.168.") { return (); }
+                    `${net} # blocked`
+                }
+                "#,
+            );
+            let nets = vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "192.168.0.0/16".parse().unwrap(),
+            ];
+            assert_eq!(
+                script.output(&nets).unwrap(),
+                Some(vec!["10.0.0.0/8 # blocked".to_string()])
+            );
+        }
+
+        #[test]
+        fn test_without_hooks() {
+            let mut script = script("let x = 1;");
+            assert_eq!(script.output(&[]).unwrap(), None);
+            let inputs: Vec<Box<dyn InputSource>> =
+                vec![Box::new(MemorySource::new("list", "10.0.0.0/8"))];
+            assert_eq!(script.filter_inputs(inputs).unwrap().len(), 1);
+        }
+
+        #[test]
+        fn test_errors() {
+            assert!(
+                Script::compile("bad.rhai", "fn input(line) {")
+                    .err()
+                    .unwrap()
+                    .starts_with("unable to load \"bad.rhai\"")
+            );
+            let mut script = script("fn output(net) { 42 }");
+            assert_eq!(
+                script.output(&["10.0.0.0/8".parse().unwrap()]).unwrap_err(),
+                "output(\"10.0.0.0/8\") returned i64, not a string or ()"
+            );
+        }
+    }
+}
+
+/// Stands in for a script when lfc is built without scripting, so that
+/// callers don't need to check the feature themselves.
+#[cfg(not(feature = "scripting"))]
+pub enum Script {}
+
+#[cfg(not(feature = "scripting"))]
+impl Script {
+    pub fn load(_path: &str) -> Result<Script, String> {
+        Err("--script needs lfc built with the scripting feature".to_string())
+    }
+
+    pub fn filter_inputs(
+        &mut self,
+        _inputs: Vec<Box<dyn InputSource>>,
+    ) -> Result<Vec<Box<dyn InputSource>>, String> {
+        match *self {}
+    }
+
+    pub fn output(&mut self, _nets: &[IpNet]) -> Result<Option<Vec<String>>, String> {
+        match *self {}
+    }
+}
@@ -0,0 +1,309 @@
+//! Checking the aggregator against a slow, obviously correct one.
+//!
+//! `lfc selftest` generates small random lists that are likely to have
+//! overlaps, neighbors, duplicates, and edge cases, runs them through every
+//! way lfc can aggregate, and checks each result on its own terms and
+//! against a reference implementation that does everything the long way.
+//! When a check fails, the input is shrunk to as few networks as still
+//! fail, for a bug report.
+
+use crate::synth::{self, Rng};
+use ipnet::{IpNet, Ipv4Net};
+use lfc::space::{self, Family};
+use lfc::spill::Spill;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// A way to aggregate that's being tested.
+struct Engine {
+    name: &'static str,
+    run: fn(&[IpNet]) -> Vec<IpNet>,
+}
+
+const ENGINES: [Engine; 3] = [
+    Engine {
+        name: "gather",
+        run: |input| lfc::gather(input),
+    },
+    Engine {
+        name: "parse",
+        run: |input| {
+            let text: Vec<String> = input.iter().map(IpNet::to_string).collect();
+            lfc::gather_lines(text.iter().map(String::as_str)).unwrap()
+        },
+    },
+    Engine {
+        name: "low-memory",
+        run: |input| {
+            // Tiny runs, so that even small inputs take several merge passes.
+            let mut spill = Spill::new(2);
+            for net in input {
+                spill.push(net).unwrap();
+            }
+            spill.finish().unwrap()
+        },
+    },
+];
+
+/// A failed check, with the smallest input found that fails it.
+#[derive(Debug)]
+pub struct Failure {
+    engine: &'static str,
+    check: &'static str,
+    input: Vec<IpNet>,
+    expected: Vec<IpNet>,
+    actual: Vec<IpNet>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "FAIL: {}: {}", self.engine, self.check)?;
+        for (title, nets) in [
+            ("input", &self.input),
+            ("expected", &self.expected),
+            ("actual", &self.actual),
+        ] {
+            writeln!(f, "{}:", title)?;
+            for net in nets {
+                writeln!(f, "    {}", net)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the inclusive range of addresses the network covers, along with
+/// its family so that ranges sort in network order.
+fn range(net: &IpNet) -> (Family, u128, u128) {
+    let (start, end) = space::range(net);
+    (Family::of(net), start, end)
+}
+
+/// Returns the sorted, merged ranges the networks cover.
+fn coverage(nets: &[IpNet]) -> Vec<(Family, u128, u128)> {
+    let mut ranges: Vec<_> = nets.iter().map(range).collect();
+    ranges.sort();
+    let mut merged: Vec<(Family, u128, u128)> = Vec::new();
+    for (family, start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if last.0 == family && start <= last.2.saturating_add(1) => {
+                last.2 = last.2.max(end);
+            }
+            _ => merged.push((family, start, end)),
+        }
+    }
+    merged
+}
+
+/// Aggregates the long way: merge the covered ranges, then walk down the
+/// tree of every possible network from /0 and keep the biggest ones that fit
+/// entirely inside a range.
+pub fn reference(input: &[IpNet]) -> Vec<IpNet> {
+    fn split(family: Family, range: (u128, u128), base: u128, prefix: u8, out: &mut Vec<IpNet>) {
+        let bits = match family {
+            Family::V4 => 32,
+            Family::V6 => 128,
+        };
+        let host_bits = u32::from(bits - prefix);
+        let last = base + u128::MAX.checked_shr(128 - host_bits).unwrap_or(0);
+        if last < range.0 || base > range.1 {
+            return;
+        }
+        if range.0 <= base && last <= range.1 {
+            out.push(match family {
+                Family::V4 => synth::v4(base as u32, prefix),
+                Family::V6 => synth::v6(base, prefix),
+            });
+            return;
+        }
+        let half = 1u128 << (host_bits - 1);
+        split(family, range, base, prefix + 1, out);
+        split(family, range, base + half, prefix + 1, out);
+    }
+
+    let mut out = Vec::new();
+    for (family, start, end) in coverage(input) {
+        split(family, (start, end), 0, 0, &mut out);
+    }
+    out
+}
+
+/// Checks an aggregated result, and returns a description of the first
+/// problem found.
+fn check(input: &[IpNet], actual: &[IpNet]) -> Result<(), &'static str> {
+    if actual.iter().any(|net| *net != net.trunc()) {
+        return Err("a network has host bits set");
+    }
+    for pair in actual.windows(2) {
+        let (a, b) = (range(&pair[0]), range(&pair[1]));
+        if a.0 > b.0 || (a.0 == b.0 && a.1 > b.1) {
+            return Err("networks are out of order");
+        }
+        if a.0 == b.0 && a.2 >= b.1 {
+            return Err("networks overlap");
+        }
+        // Two halves of the same supernet should have been merged.
+        if a.0 == b.0
+            && pair[0].prefix_len() == pair[1].prefix_len()
+            && pair[0]
+                .supernet()
+                .is_some_and(|parent| parent.contains(&pair[1]))
+        {
+            return Err("neighbors weren't merged");
+        }
+    }
+    if coverage(actual) != coverage(input) {
+        return Err("covers different addresses than the input");
+    }
+    if actual != reference(input) {
+        return Err("differs from the reference");
+    }
+    Ok(())
+}
+
+/// Returns a small random list of networks, crowded into a small part of
+/// the address space so they're likely to interact.
+fn sample(rng: &mut Rng) -> Vec<IpNet> {
+    let count = rng.below(24) as usize;
+    let v4_base = rng.next_u64() as u32 & !0xfff;
+    let v6_base = rng.next_u128() & !0xfff;
+    let edges: [IpNet; 4] = [
+        "0.0.0.0/0".parse().unwrap(),
+        "255.255.255.255/32".parse().unwrap(),
+        "::/0".parse().unwrap(),
+        "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128"
+            .parse()
+            .unwrap(),
+    ];
+    let mut nets: Vec<IpNet> = (0..count)
+        .map(|_| {
+            let offset = rng.below(0x1000);
+            match rng.below(10) {
+                0 => edges[rng.below(4) as usize],
+                1..=3 => synth::v6(v6_base + u128::from(offset), rng.between(116, 128)),
+                _ => synth::v4(v4_base + offset as u32, rng.between(20, 32)),
+            }
+        })
+        .collect();
+    // Networks with host bits set are valid input too.
+    if let (Some(IpNet::V4(first)), true) = (nets.first(), rng.chance(20)) {
+        let address = Ipv4Addr::from(u32::from(first.network()) | 1);
+        nets[0] = IpNet::V4(Ipv4Net::new(address, first.prefix_len()).unwrap());
+    }
+    nets
+}
+
+/// Removes networks from a failing input one at a time, for as long as it
+/// keeps failing the same way.
+fn shrink(engine: &Engine, check_name: &'static str, mut input: Vec<IpNet>) -> Vec<IpNet> {
+    let mut i = 0;
+    while i < input.len() {
+        let mut smaller = input.clone();
+        smaller.remove(i);
+        if check(&smaller, &(engine.run)(&smaller)) == Err(check_name) {
+            input = smaller;
+        } else {
+            i += 1;
+        }
+    }
+    input
+}
+
+/// Runs `count` random cases through every engine.
+pub fn run(count: usize, seed: u64) -> Result<(), Box<Failure>> {
+    let mut rng = Rng::new(seed);
+    for _ in 0..count {
+        let input = sample(&mut rng);
+        for engine in &ENGINES {
+            if let Err(check_name) = check(&input, &(engine.run)(&input)) {
+                let input = shrink(engine, check_name, input);
+                return Err(Box::new(Failure {
+                    engine: engine.name,
+                    check: check_name,
+                    expected: reference(&input),
+                    actual: (engine.run)(&input),
+                    input,
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_reference() {
+        assert_eq!(
+            reference(&nets(&[
+                "10.0.0.1/32",
+                "10.0.0.2/31",
+                "10.0.0.4/32",
+                "10.0.0.0/32"
+            ])),
+            nets(&["10.0.0.0/30", "10.0.0.4/32"])
+        );
+        assert_eq!(reference(&nets(&["::/1", "8000::/1"])), nets(&["::/0"]));
+        assert_eq!(
+            reference(&nets(&["::/0", "0.0.0.0/0", "10.0.0.0/8"])),
+            nets(&["0.0.0.0/0", "::/0"])
+        );
+    }
+
+    #[test]
+    fn test_check_catches_problems() {
+        let input = nets(&["10.0.0.0/24", "10.0.1.0/24", "10.0.3.0/24"]);
+        assert_eq!(
+            check(&input, &nets(&["10.0.0.0/23", "10.0.3.0/24"])),
+            Ok(())
+        );
+        assert_eq!(
+            check(&input, &nets(&["10.0.0.1/23", "10.0.3.0/24"])),
+            Err("a network has host bits set")
+        );
+        assert_eq!(
+            check(&input, &nets(&["10.0.3.0/24", "10.0.0.0/23"])),
+            Err("networks are out of order")
+        );
+        assert_eq!(check(&input, &input), Err("neighbors weren't merged"));
+        assert_eq!(
+            check(&input, &nets(&["10.0.0.0/23"])),
+            Err("covers different addresses than the input")
+        );
+    }
+
+    #[test]
+    fn test_shrink() {
+        let broken = Engine {
+            name: "broken",
+            // Forgets about IPv6.
+            run: |input| {
+                let v4: Vec<IpNet> = input
+                    .iter()
+                    .filter(|net| matches!(net, IpNet::V4(_)))
+                    .copied()
+                    .collect();
+                lfc::gather(&v4)
+            },
+        };
+        let input = nets(&[
+            "10.0.0.0/24",
+            "2001:db8::/32",
+            "10.0.1.0/24",
+            "2001:db8::/48",
+        ]);
+        let check_name = check(&input, &(broken.run)(&input)).unwrap_err();
+        assert_eq!(shrink(&broken, check_name, input), nets(&["2001:db8::/48"]));
+    }
+
+    #[test]
+    fn test_run() {
+        run(300, 1).unwrap();
+    }
+}
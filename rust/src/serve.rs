@@ -0,0 +1,382 @@
+//! Long-running aggregation servers.
+//!
+//! `lfc serve [FILE]...` aggregates the given files once at startup and then
+//! answers requests from other tools without them having to start a new
+//! process or re-read the lists each time. The files become the list named
+//! by `--name`. It can listen on a Unix domain socket with `--unix`, which
+//! speaks the simple protocol below, and on TCP with `--listen`, which
+//! speaks the REST API in [`crate::rest`].
+//!
+//! # Unix socket protocol
+//!
+//! Each request and each response is a frame: a 4-byte big-endian length
+//! followed by that many bytes of UTF-8 text. A connection can carry any
+//! number of request/response pairs.
+//!
+//! The first line of a request is the command:
+//!
+//! - `aggregate`: the rest of the request is a list of networks. The response
+//!   is the aggregated list.
+//! - `list`: the response is the aggregated list that was loaded at startup.
+//! - `lookup ADDRESS`: the response is the network in the loaded list that
+//!   contains ADDRESS, or nothing if none does.
+//!
+//! The first line of a response is `ok` or `error MESSAGE`. The result, if
+//! any, follows with one network per line.
+
+use crate::metrics;
+use ipnet::IpNet;
+use lfc::space::{self, Family};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The largest request we'll accept, to keep a bad length prefix from
+/// making us allocate the world.
+pub const MAX_REQUEST: usize = 256 * 1024 * 1024;
+
+/// An address as an integer, tagged with its family so that IPv4 and IPv6
+/// addresses with the same bits don't compare equal.
+type Address = (Family, u128);
+
+/// An aggregated list, ready to answer lookups.
+#[derive(Debug, Default)]
+pub struct Loaded {
+    nets: Vec<IpNet>,
+    /// The first and last addresses of `nets`, sorted, for binary searching.
+    ranges: Vec<(Address, Address, IpNet)>,
+    /// When it was loaded, in seconds since the Unix epoch.
+    updated: u64,
+    /// How long aggregating it took.
+    duration: Duration,
+}
+
+impl Loaded {
+    pub fn new(nets: Vec<IpNet>, duration: Duration) -> Loaded {
+        let mut ranges: Vec<_> = nets
+            .iter()
+            .map(|net| {
+                let family = Family::of(net);
+                let (first, last) = space::range(net);
+                ((family, first), (family, last), *net)
+            })
+            .collect();
+        ranges.sort_unstable();
+        Loaded {
+            nets,
+            ranges,
+            updated: crate::clock::now(),
+            duration,
+        }
+    }
+
+    pub fn nets(&self) -> &[IpNet] {
+        &self.nets
+    }
+
+    /// Returns the list's metrics.
+    pub fn metrics<'a>(&'a self, name: &'a str) -> metrics::List<'a> {
+        metrics::List {
+            name,
+            nets: &self.nets,
+            duration: self.duration,
+            timestamp: self.updated,
+            next_expiry: None,
+        }
+    }
+
+    /// Returns the loaded network containing the address, if there is one.
+    /// The list is aggregated, so at most one network can contain it.
+    pub fn lookup(&self, addr: IpAddr) -> Option<IpNet> {
+        let key = match addr {
+            IpAddr::V4(addr) => (Family::V4, u128::from(u32::from(addr))),
+            IpAddr::V6(addr) => (Family::V6, u128::from(addr)),
+        };
+        let index = self.ranges.partition_point(|(first, _, _)| *first <= key);
+        let (_, last, net) = self.ranges.get(index.checked_sub(1)?)?;
+        (key <= *last).then_some(*net)
+    }
+}
+
+/// The named lists a server knows about, shared between its connections.
+#[derive(Debug)]
+pub struct Store {
+    /// The list that Unix socket requests use.
+    default: String,
+    lists: RwLock<BTreeMap<String, Arc<Loaded>>>,
+}
+
+impl Store {
+    /// Creates a store holding one list, which becomes the default, and
+    /// took `duration` to aggregate.
+    pub fn new(default: &str, nets: Vec<IpNet>, duration: Duration) -> Store {
+        let store = Store {
+            default: default.to_string(),
+            lists: RwLock::default(),
+        };
+        store.put(default, nets, duration);
+        store
+    }
+
+    /// Returns the names of all the lists, in order.
+    pub fn names(&self) -> Vec<String> {
+        self.lists.read().unwrap().keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Loaded>> {
+        self.lists.read().unwrap().get(name).cloned()
+    }
+
+    /// Returns the default list, or an empty one if it's been deleted.
+    pub fn default_list(&self) -> Arc<Loaded> {
+        self.get(&self.default).unwrap_or_default()
+    }
+
+    /// Adds or replaces a list. The networks should already be aggregated,
+    /// which took `duration`.
+    pub fn put(&self, name: &str, nets: Vec<IpNet>, duration: Duration) -> Arc<Loaded> {
+        let loaded = Arc::new(Loaded::new(nets, duration));
+        self.lists
+            .write()
+            .unwrap()
+            .insert(name.to_string(), Arc::clone(&loaded));
+        loaded
+    }
+
+    /// Removes a list, returning whether it existed.
+    pub fn remove(&self, name: &str) -> bool {
+        self.lists.write().unwrap().remove(name).is_some()
+    }
+}
+
+/// Answers one Unix socket request.
+pub fn handle(store: &Store, request: &str) -> String {
+    let (command, body) = request.split_once('\n').unwrap_or((request, ""));
+    let mut words = command.split_whitespace();
+    let result = match (words.next(), words.next(), words.next()) {
+        (Some("aggregate"), None, _) => {
+            lfc::gather_lines(body.lines()).map_err(|err| err.to_string())
+        }
+        (Some("list"), None, _) => Ok(store.default_list().nets().to_vec()),
+        (Some("lookup"), Some(addr), None) => match addr.parse::<IpAddr>() {
+            Ok(addr) => Ok(store.default_list().lookup(addr).into_iter().collect()),
+            Err(_) => Err(format!("Unable to parse {:?} as an IP address.", addr)),
+        },
+        _ => Err(format!("unknown command {:?}", command)),
+    };
+    match result {
+        Ok(nets) => {
+            let mut response = String::from("ok\n");
+            for net in nets {
+                response.push_str(&net.to_string());
+                response.push('\n');
+            }
+            response
+        }
+        Err(err) => format!("error {}\n", err),
+    }
+}
+
+/// Reads one frame, or returns None if the peer closed the connection
+/// cleanly between frames.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_REQUEST {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes is too large", len),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    String::from_utf8(payload)
+        .map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "frame isn't valid UTF-8"))
+}
+
+/// Writes one frame.
+pub fn write_frame<W: Write>(writer: &mut W, payload: &str) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "response is too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(payload.as_bytes())?;
+    writer.flush()
+}
+
+#[cfg(unix)]
+pub use self::unix::{bind, run};
+
+#[cfg(unix)]
+mod unix {
+    use super::{Store, handle, read_frame, write_frame};
+    use std::io;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::{fs, thread};
+
+    /// Serves requests on one connection until the client hangs up.
+    fn serve_connection(store: &Store, mut stream: UnixStream) -> io::Result<()> {
+        while let Some(request) = read_frame(&mut stream)? {
+            write_frame(&mut stream, &handle(store, &request))?;
+        }
+        Ok(())
+    }
+
+    /// Binds the socket, replacing a stale socket file left behind by a
+    /// server that's no longer running, but refusing to take over one that's
+    /// live.
+    pub fn bind(path: &Path) -> io::Result<UnixListener> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is already in use by another server", path.display()),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+        UnixListener::bind(path)
+    }
+
+    /// Answers requests on the listener forever, one thread per connection.
+    /// A connection that can't be accepted is skipped.
+    pub fn run(listener: UnixListener, store: Arc<Store>) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    crate::diag::note!("warning: unable to accept a connection: {}", err);
+                    continue;
+                }
+            };
+            let store = Arc::clone(&store);
+            thread::spawn(move || {
+                if let Err(err) = serve_connection(&store, stream) {
+                    crate::diag::note!("warning: dropping connection: {}", err);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Store {
+        Store::new(
+            "default",
+            lfc::gather_lines([
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+                "192.168.0.0/16",
+                "2001:db8::/32",
+            ])
+            .unwrap(),
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn test_lookup() {
+        let loaded = store().default_list();
+        let lookup = |addr: &str| loaded.lookup(addr.parse().unwrap());
+        assert_eq!(lookup("10.0.1.255"), Some("10.0.0.0/23".parse().unwrap()));
+        assert_eq!(lookup("10.0.2.0"), None);
+        assert_eq!(
+            lookup("192.168.77.1"),
+            Some("192.168.0.0/16".parse().unwrap())
+        );
+        assert_eq!(lookup("9.255.255.255"), None);
+        assert_eq!(
+            lookup("2001:db8::1"),
+            Some("2001:db8::/32".parse().unwrap())
+        );
+        // ::a00:1 is 10.0.0.1's bits, but it's not an IPv4 address.
+        assert_eq!(lookup("::a00:1"), None);
+    }
+
+    #[test]
+    fn test_store() {
+        let store = store();
+        store.put("other", vec!["10.0.0.0/8".parse().unwrap()], Duration::ZERO);
+        assert_eq!(store.names(), vec!["default", "other"]);
+        assert!(store.remove("default"));
+        assert!(!store.remove("default"));
+        assert!(store.default_list().nets().is_empty());
+        assert_eq!(store.get("other").unwrap().nets().len(), 1);
+    }
+
+    #[test]
+    fn test_handle() {
+        let store = store();
+        assert_eq!(
+            handle(&store, "aggregate\n10.0.0.0/25\n10.0.0.128/25\n"),
+            "ok\n10.0.0.0/24\n"
+        );
+        assert_eq!(handle(&store, "lookup 10.0.0.1"), "ok\n10.0.0.0/23\n");
+        assert_eq!(handle(&store, "lookup 172.16.0.1"), "ok\n");
+        assert!(handle(&store, "list").starts_with("ok\n10.0.0.0/23\n"));
+        assert_eq!(
+            handle(&store, "aggregate\nnonsense"),
+            "error Unable to parse \"nonsense\" as an IP network on line 1, column 1: \
+             not an IP address.\n"
+        );
+        assert_eq!(
+            handle(&store, "frobnicate"),
+            "error unknown command \"frobnicate\"\n"
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, "list").unwrap();
+        assert_eq!(buf, b"\0\0\0\x04list");
+        let mut reader = buf.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap().as_deref(), Some("list"));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_oversized_frame() {
+        let mut reader: &[u8] = &[0xff, 0xff, 0xff, 0xff];
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_over_socket() {
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("lfc-serve-test-{}.sock", std::process::id()));
+        let listener = bind(&path).unwrap();
+        std::thread::spawn(move || run(listener, Arc::new(store())));
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        write_frame(&mut client, "lookup 10.0.0.7").unwrap();
+        assert_eq!(
+            read_frame(&mut client).unwrap().unwrap(),
+            "ok\n10.0.0.0/23\n"
+        );
+        write_frame(&mut client, "aggregate\n10.0.0.0/24\n10.0.1.0/24").unwrap();
+        assert_eq!(
+            read_frame(&mut client).unwrap().unwrap(),
+            "ok\n10.0.0.0/23\n"
+        );
+
+        // A live socket can't be taken over.
+        assert!(bind(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
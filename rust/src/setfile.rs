@@ -0,0 +1,205 @@
+//! A compact binary file for an aggregated list.
+//!
+//! Parsing a big text list again every time a daemon restarts is most of its
+//! startup time. A set file stores the list as the sorted, merged integer
+//! ranges it covers instead, which load with no parsing at all. Loading
+//! turns the ranges back into the fewest networks that cover them, which is
+//! exactly the aggregated list that was saved.
+//!
+//! The layout, with every integer little-endian:
+//!
+//! ```text
+//! "LFCSET"                     magic
+//! u16                          version, currently 1
+//! u64                          number of IPv4 ranges
+//! (u32 first, u32 last)...     IPv4 ranges, sorted, not touching
+//! u64                          number of IPv6 ranges
+//! (u128 first, u128 last)...   IPv6 ranges, sorted, not touching
+//! ```
+
+use crate::space::{self, Family};
+use crate::{Error, Result};
+use ipnet::IpNet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"LFCSET";
+const VERSION: u16 = 1;
+
+/// Encodes the address space covered by the networks.
+pub fn encode(nets: &[IpNet]) -> Vec<u8> {
+    let v4 = space::ranges(nets, Family::V4);
+    let v6 = space::ranges(nets, Family::V6);
+    let mut out = Vec::with_capacity(24 + v4.len() * 8 + v6.len() * 32);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(v4.len() as u64).to_le_bytes());
+    for (first, last) in v4 {
+        out.extend_from_slice(&(first as u32).to_le_bytes());
+        out.extend_from_slice(&(last as u32).to_le_bytes());
+    }
+    out.extend_from_slice(&(v6.len() as u64).to_le_bytes());
+    for (first, last) in v6 {
+        out.extend_from_slice(&first.to_le_bytes());
+        out.extend_from_slice(&last.to_le_bytes());
+    }
+    out
+}
+
+/// Decodes a set into its aggregated networks, IPv4 first.
+pub fn decode(bytes: &[u8]) -> Result<Vec<IpNet>> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("not an lfc set file"));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version != VERSION {
+        return Err(invalid(&format!(
+            "unsupported set file version {}",
+            version
+        )));
+    }
+    let mut nets = Vec::new();
+    for family in [Family::V4, Family::V6] {
+        let count = u64::from_le_bytes(reader.array()?);
+        let mut previous: Option<u128> = None;
+        for _ in 0..count {
+            let (first, last) = match family {
+                Family::V4 => (
+                    u32::from_le_bytes(reader.array()?).into(),
+                    u32::from_le_bytes(reader.array()?).into(),
+                ),
+                Family::V6 => (
+                    u128::from_le_bytes(reader.array()?),
+                    u128::from_le_bytes(reader.array()?),
+                ),
+            };
+            // Overlapping or touching ranges would cover() into networks
+            // that aren't aggregated.
+            if first > last || previous.is_some_and(|end| first <= end.saturating_add(1)) {
+                return Err(invalid(&format!("{} ranges out of order", family)));
+            }
+            space::cover(family, first, last, &mut nets);
+            previous = Some(last);
+        }
+    }
+    if !reader.bytes.is_empty() {
+        return Err(invalid("trailing data after the IPv6 ranges"));
+    }
+    Ok(nets)
+}
+
+/// Saves the networks to a set file, replacing it atomically.
+pub fn save(path: &Path, nets: &[IpNet]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, encode(nets))
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|err| with_path(path, err))
+}
+
+/// Loads the networks from a set file.
+pub fn load(path: &Path) -> Result<Vec<IpNet>> {
+    let bytes = fs::read(path).map_err(|err| with_path(path, err))?;
+    decode(&bytes).map_err(|err| match err {
+        Error::Io(err) => with_path(path, err),
+        err => err,
+    })
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+fn with_path(path: &Path, err: io::Error) -> Error {
+    Error::Io(io::Error::new(
+        err.kind(),
+        format!("{}: {}", path.display(), err),
+    ))
+}
+
+/// Takes fixed-size pieces off the front of the file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(invalid("the set file is truncated"));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let list = nets(&[
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "192.168.0.7/32",
+            "0.0.0.0/32",
+            "2001:db8::/33",
+            "2001:db8:8000::/33",
+            "ffff::/16",
+        ]);
+        assert_eq!(decode(&encode(&list)).unwrap(), crate::gather(&list));
+        assert_eq!(decode(&encode(&[])).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_layout() {
+        let bytes = encode(&nets(&["10.0.0.0/31"]));
+        assert_eq!(&bytes[..8], b"LFCSET\x01\x00");
+        assert_eq!(&bytes[8..16], &1u64.to_le_bytes());
+        assert_eq!(&bytes[16..24], &[0, 0, 0, 10, 1, 0, 0, 10]);
+        assert_eq!(&bytes[24..], &0u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let error = |bytes: &[u8]| decode(bytes).unwrap_err().to_string();
+        let good = encode(&nets(&["10.0.0.0/24", "10.0.2.0/24"]));
+        assert_eq!(error(b"10.0.0.0/8\n"), "not an lfc set file");
+        assert_eq!(error(b"LFCSET\x02\x00"), "unsupported set file version 2");
+        assert_eq!(error(&good[..good.len() - 1]), "the set file is truncated");
+        let mut trailing = good.clone();
+        trailing.push(0);
+        assert_eq!(error(&trailing), "trailing data after the IPv6 ranges");
+        // Swap the two ranges.
+        let mut swapped = good.clone();
+        swapped[16..32].rotate_left(8);
+        assert_eq!(error(&swapped), "IPv4 ranges out of order");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("lfc-setfile-test-{}.lfcset", std::process::id()));
+        let list = nets(&["10.0.0.0/8", "2001:db8::/32"]);
+        save(&path, &list).unwrap();
+        assert_eq!(load(&path).unwrap(), list);
+        fs::remove_file(&path).unwrap();
+        assert!(
+            load(&path)
+                .unwrap_err()
+                .to_string()
+                .contains("lfc-setfile-test")
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! IPv6 host entries that look like SLAAC picked them, for
+//! `--report slaac`.
+//!
+//! A host that configures its own address takes the /64 its router
+//! advertises and adds an interface ID: one made from its MAC address, with
+//! ff:fe in the middle (EUI-64), or a random one that's replaced every day
+//! or so (a temporary address, RFC 8981). Blocking a temporary address
+//! stops working as soon as it rotates, and an EUI-64 one is just one of the
+//! host's addresses, so the /64 is what there is to block.
+//!
+//! Telling a random interface ID from one an administrator picked can only
+//! be a guess. One looks random here when every 16-bit group is set and about
+//! as many bits are set as not, which addresses like ::1 or ::53 never are.
+
+use ipnet::{IpNet, Ipv6Net};
+use lfc::format::Style;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv6Addr};
+
+/// How an address's interface ID seems to have been made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// From the MAC address.
+    Eui64([u8; 6]),
+    /// At random.
+    Temporary,
+}
+
+/// Returns how the address's interface ID seems to have been made, or
+/// `None` if it doesn't look autoconfigured.
+pub fn classify(address: Ipv6Addr) -> Option<Kind> {
+    let octets = address.octets();
+    if octets[11] == 0xff && octets[12] == 0xfe {
+        // EUI-64 flips the universal/local bit of the MAC address.
+        return Some(Kind::Eui64([
+            octets[8] ^ 0x02,
+            octets[9],
+            octets[10],
+            octets[13],
+            octets[14],
+            octets[15],
+        ]));
+    }
+    let id = u64::from_be_bytes(octets[8..].try_into().unwrap());
+    let groups_set = (0..4).all(|group| id >> (group * 16) & 0xffff != 0);
+    (groups_set && (20..=44).contains(&id.count_ones())).then_some(Kind::Temporary)
+}
+
+/// Returns the IPv6 host entries among the networks that look
+/// autoconfigured, and how.
+pub fn find(nets: &[IpNet]) -> Vec<(Ipv6Addr, Kind)> {
+    nets.iter()
+        .filter_map(|net| match net {
+            IpNet::V6(v6) if v6.prefix_len() == 128 => {
+                classify(v6.addr()).map(|kind| (v6.addr(), kind))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the /64 the address is in.
+fn subnet(address: Ipv6Addr) -> IpNet {
+    IpNet::V6(Ipv6Net::new(address, 64).unwrap().trunc())
+}
+
+/// Writes each autoconfigured-looking host entry, with the /64 to block
+/// instead, and how many there are.
+pub fn write_report(out: &mut dyn Write, nets: &[IpNet], style: &Style) -> io::Result<()> {
+    let found = find(nets);
+    let mut subnets = BTreeSet::new();
+    for (address, kind) in &found {
+        let subnet = subnet(*address);
+        let why = match kind {
+            Kind::Eui64(mac) => format!(
+                "EUI-64 from MAC {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+            ),
+            Kind::Temporary => "looks like a temporary address".to_string(),
+        };
+        writeln!(
+            out,
+            "{}: {}; widen to {}",
+            style.address(IpAddr::V6(*address)),
+            why,
+            style.display(&subnet)
+        )?;
+        subnets.insert(subnet);
+    }
+    writeln!(
+        out,
+        "{} IPv6 host entries look autoconfigured, in {} /64s",
+        found.len(),
+        subnets.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(text: &str) -> Ipv6Addr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(address("2001:db8:1:2:211:22ff:fe33:4455")),
+            Some(Kind::Eui64([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]))
+        );
+        assert_eq!(
+            classify(address("2001:db8:1:2:9a3c:51f2:7e0d:b4c1")),
+            Some(Kind::Temporary)
+        );
+        assert_eq!(classify(address("2001:db8::1")), None);
+        assert_eq!(classify(address("2001:db8::53:0:0:1")), None);
+        assert_eq!(classify(address("2001:db8::ffff:ffff:ffff:ffff")), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let nets: Vec<IpNet> = [
+            "192.0.2.1/32",
+            "2001:db8::1/128",
+            "2001:db8:1:2:211:22ff:fe33:4455/128",
+            "2001:db8:1:2:9a3c:51f2:7e0d:b4c1/128",
+            "2001:db8:1:3:9a3c:51f2:7e0d:b4c0/127",
+        ]
+        .iter()
+        .map(|net| net.parse().unwrap())
+        .collect();
+        let mut out = Vec::new();
+        write_report(&mut out, &nets, &Style::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+2001:db8:1:2:211:22ff:fe33:4455: EUI-64 from MAC 00:11:22:33:44:55; widen to 2001:db8:1:2::/64
+2001:db8:1:2:9a3c:51f2:7e0d:b4c1: looks like a temporary address; widen to 2001:db8:1:2::/64
+2 IPv6 host entries look autoconfigured, in 1 /64s
+"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Where input lists come from.
+//!
+//! Anything that can produce the bytes of a list implements [`InputSource`],
+//! and the rest of lfc only ever sees the [`Record`]s it yields: one line of
+//! text each, along with which source and line it came from. Adding a new
+//! kind of source means writing one more implementation here, without
+//! touching parsing or aggregation.
+//!
+//! Regular files are memory-mapped rather than read into a string, so a
+//! multi-gigabyte list isn't copied into memory before we start on it. The
+//! kernel pages it in as the scanner walks through it line by line. Stdin,
+//! pipes, and other things that can't be mapped are read into a buffer.
+//! [`open`] picks the right source for a name, and decompresses gzipped input
+//! from any of them.
+
+use crate::{Error, Result};
+use flate2::read::MultiGzDecoder;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::{fmt, str};
+
+/// Something that networks can be read from.
+pub trait InputSource: Send + Sync {
+    /// Names the source in errors, like a path or URL.
+    fn name(&self) -> &str;
+
+    /// Returns the whole contents of the source.
+    fn bytes(&self) -> &[u8];
+
+    /// Returns an iterator over the lines of the source.
+    fn records(&self) -> Records<'_> {
+        Records {
+            source: self.name(),
+            rest: self.bytes(),
+            line: 0,
+        }
+    }
+
+    /// Returns the whole source as text, for the small files like saved sets
+    /// that get parsed all at once.
+    fn text(&self) -> Result<&str> {
+        str::from_utf8(self.bytes()).map_err(|_| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} isn't valid UTF-8", self.name()),
+            ))
+        })
+    }
+}
+
+/// Where a record came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Provenance<'a> {
+    /// The name of the source, if the line came from one.
+    pub source: Option<&'a str>,
+    /// The line number, counting from 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Provenance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source {
+            Some(source) => write!(f, "{} line {}", source, self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
+/// One line of input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// The line, without its line ending.
+    pub text: &'a str,
+    pub provenance: Provenance<'a>,
+}
+
+/// An iterator over the lines of an [`InputSource`].
+///
+/// Like [`str::lines`], lines end with `\n` or `\r\n`, and the final line
+/// doesn't need a line ending. Each line is checked for UTF-8 on its own as
+/// it's reached, rather than checking the whole input up front.
+pub struct Records<'a> {
+    source: &'a str,
+    rest: &'a [u8],
+    line: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let (mut line, rest) = match memchr::memchr(b'\n', self.rest) {
+            Some(end) => (&self.rest[..end], &self.rest[end + 1..]),
+            None => (self.rest, &self.rest[self.rest.len()..]),
+        };
+        self.rest = rest;
+        self.line += 1;
+        if let [head @ .., b'\r'] = line {
+            line = head;
+        }
+        let provenance = Provenance {
+            source: Some(self.source),
+            line: self.line,
+        };
+        Some(match str::from_utf8(line) {
+            Ok(text) => Ok(Record { text, provenance }),
+            Err(_) => Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} isn't valid UTF-8", provenance),
+            ))),
+        })
+    }
+}
+
+/// Returns whether the name of a source is a URL to download.
+pub fn is_url(name: &str) -> bool {
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+/// Opens the named source: stdin if the name is `-`, a URL if it starts with
+/// `http://` or `https://`, and otherwise a file. Gzipped contents are
+/// decompressed, whatever the source.
+pub fn open(name: &str) -> Result<Box<dyn InputSource>> {
+    let source: Box<dyn InputSource> = if is_url(name) {
+        Box::new(UrlSource::fetch(name)?)
+    } else {
+        Box::new(FileSource::open(name)?)
+    };
+    if GzipSource::is_gzipped(source.bytes()) {
+        return Ok(Box::new(GzipSource::decompress(source.as_ref())?));
+    }
+    Ok(source)
+}
+
+/// Adds the name of the source to an I/O error.
+fn with_name(name: &str) -> impl Fn(io::Error) -> io::Error + '_ {
+    move |err| io::Error::new(err.kind(), format!("unable to read {:?}: {}", name, err))
+}
+
+/// A file, or stdin.
+pub struct FileSource {
+    name: String,
+    data: Data,
+}
+
+enum Data {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl FileSource {
+    /// Opens the named file, or stdin if the name is `-`.
+    pub fn open(name: &str) -> Result<FileSource> {
+        if name == "-" {
+            return FileSource::stdin();
+        }
+        let mut file = File::open(name).map_err(with_name(name))?;
+        let data = if file.metadata().map_err(with_name(name))?.is_file() {
+            // SAFETY: the map is only ever read, and only as bytes. If
+            // something truncates the file while we're reading it we may get
+            // SIGBUS, which is the same trade every mmap-based tool makes.
+            Data::Mapped(unsafe { Mmap::map(&file) }.map_err(with_name(name))?)
+        } else {
+            let mut buffer = Vec::new();
+            file.read_to_end(&mut buffer).map_err(with_name(name))?;
+            Data::Read(buffer)
+        };
+        Ok(FileSource {
+            name: name.to_string(),
+            data,
+        })
+    }
+
+    /// Reads all of stdin.
+    pub fn stdin() -> Result<FileSource> {
+        let mut buffer = Vec::new();
+        io::stdin().lock().read_to_end(&mut buffer)?;
+        Ok(FileSource {
+            name: "stdin".to_string(),
+            data: Data::Read(buffer),
+        })
+    }
+}
+
+impl InputSource for FileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.data {
+            Data::Mapped(map) => map,
+            Data::Read(buffer) => buffer,
+        }
+    }
+}
+
+/// A list that's already in memory.
+pub struct MemorySource {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl MemorySource {
+    pub fn new<D: Into<Vec<u8>>>(name: &str, data: D) -> MemorySource {
+        MemorySource {
+            name: name.to_string(),
+            data: data.into(),
+        }
+    }
+}
+
+impl InputSource for MemorySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// A list downloaded over HTTP or HTTPS.
+pub struct UrlSource {
+    url: String,
+    data: Vec<u8>,
+}
+
+impl UrlSource {
+    /// Downloads the whole list.
+    pub fn fetch(url: &str) -> Result<UrlSource> {
+        let fetch_error =
+            |err: ureq::Error| io::Error::other(format!("unable to fetch {:?}: {}", url, err));
+        let data = ureq::get(url)
+            .call()
+            .map_err(fetch_error)?
+            .body_mut()
+            .with_config()
+            // Lists can be far bigger than ureq's default limit.
+            .limit(u64::MAX)
+            .read_to_vec()
+            .map_err(fetch_error)?;
+        Ok(UrlSource {
+            url: url.to_string(),
+            data,
+        })
+    }
+}
+
+impl InputSource for UrlSource {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// The decompressed contents of a gzipped source.
+pub struct GzipSource {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl GzipSource {
+    /// Returns whether the bytes start like a gzip stream.
+    pub fn is_gzipped(bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0x1f, 0x8b])
+    }
+
+    /// Decompresses the whole source, which may be several gzip streams one
+    /// after the other, like `cat a.gz b.gz` makes.
+    pub fn decompress(source: &dyn InputSource) -> Result<GzipSource> {
+        let mut data = Vec::new();
+        MultiGzDecoder::new(source.bytes())
+            .read_to_end(&mut data)
+            .map_err(with_name(source.name()))?;
+        Ok(GzipSource {
+            name: source.name().to_string(),
+            data,
+        })
+    }
+}
+
+impl InputSource for GzipSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn lines(source: &dyn InputSource) -> Vec<&str> {
+        source
+            .records()
+            .map(|record| record.unwrap().text)
+            .collect()
+    }
+
+    /// Writes `contents` to a temporary file and returns its path.
+    fn temp_file(test: &str, contents: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("lfc-source-{}-{}", test, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_lines_match_str_lines() {
+        for text in [
+            "",
+            "\n",
+            "10.0.0.0/8",
+            "10.0.0.0/8\n",
+            "10.0.0.0/8\r\n192.168.0.0/16",
+            "\n\n10.0.0.0/8\n\n",
+        ] {
+            assert_eq!(
+                lines(&MemorySource::new("test", text)),
+                text.lines().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_records_provenance() {
+        let source = MemorySource::new("test", "10.0.0.0/8\n\n192.168.0.0/16");
+        let record = source.records().nth(2).unwrap().unwrap();
+        assert_eq!(record.text, "192.168.0.0/16");
+        assert_eq!(record.provenance.to_string(), "test line 3");
+    }
+
+    #[test]
+    fn test_lines_invalid_utf8() {
+        let source = MemorySource::new("test", &b"10.0.0.0/8\n\xff\xfe\n192.168.0.0/16\n"[..]);
+        let mut records = source.records();
+        assert_eq!(records.next().unwrap().unwrap().text, "10.0.0.0/8");
+        assert_eq!(
+            records.next().unwrap().unwrap_err().to_string(),
+            "test line 2 isn't valid UTF-8"
+        );
+        assert_eq!(records.next().unwrap().unwrap().text, "192.168.0.0/16");
+    }
+
+    #[test]
+    fn test_open_maps_files() {
+        let path = temp_file("maps", b"10.0.0.0/8\n10.1.0.0/16\n");
+        let source = FileSource::open(&path).unwrap();
+        assert!(matches!(source.data, Data::Mapped(_)));
+        assert_eq!(lines(&source), vec!["10.0.0.0/8", "10.1.0.0/16"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_empty_file() {
+        let path = temp_file("empty", b"");
+        let source = open(&path).unwrap();
+        assert_eq!(source.records().count(), 0);
+        assert_eq!(source.text().unwrap(), "");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_missing_file() {
+        let err = open("/nonexistent/lfc").err().unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("unable to read \"/nonexistent/lfc\"")
+        );
+    }
+
+    #[test]
+    fn test_open_decompresses() {
+        let mut both = gzip("10.0.0.0/8\n");
+        both.extend(gzip("10.1.0.0/16\n"));
+        let path = temp_file("gzip", &both);
+        let source = open(&path).unwrap();
+        assert_eq!(source.name(), path);
+        assert_eq!(lines(source.as_ref()), vec!["10.0.0.0/8", "10.1.0.0/16"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_decompress_corrupt() {
+        let mut data = gzip("10.0.0.0/8\n");
+        data.truncate(data.len() / 2);
+        let source = MemorySource::new("broken.gz", data);
+        assert!(
+            GzipSource::decompress(&source)
+                .err()
+                .unwrap()
+                .to_string()
+                .starts_with("unable to read \"broken.gz\"")
+        );
+    }
+}
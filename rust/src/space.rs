@@ -0,0 +1,483 @@
+//! Address space arithmetic.
+//!
+//! Networks are easiest to compare as inclusive ranges of integers. IPv4 and
+//! IPv6 addresses both fit in a u128, so everything here works on u128s and
+//! keeps the two families apart with [`Family`].
+//!
+//! This is part of the no_std core, so it only uses `core` and `alloc`.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+
+/// An IP address family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    pub fn of(net: &IpNet) -> Family {
+        match net {
+            IpNet::V4(_) => Family::V4,
+            IpNet::V6(_) => Family::V6,
+        }
+    }
+
+    /// Returns the number of bits in an address, which is also the longest
+    /// possible prefix.
+    pub fn max_prefix_len(self) -> u8 {
+        match self {
+            Family::V4 => 32,
+            Family::V6 => 128,
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Family::V4 => write!(f, "IPv4"),
+            Family::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+/// Returns the first and last addresses of the network as integers.
+pub fn range(net: &IpNet) -> (u128, u128) {
+    match net {
+        IpNet::V4(net) => (
+            u32::from(net.network()).into(),
+            u32::from(net.broadcast()).into(),
+        ),
+        IpNet::V6(net) => (net.network().into(), net.broadcast().into()),
+    }
+}
+
+/// Appends the fewest networks that exactly cover the inclusive range to
+/// `out`, in order.
+pub fn cover(family: Family, start: u128, end: u128, out: &mut Vec<IpNet>) {
+    let bits = match family {
+        Family::V4 => 32,
+        Family::V6 => 128,
+    };
+    let mut start = start;
+    loop {
+        // The biggest block that starts here is limited by how aligned the
+        // start is and by how much of the range is left.
+        let mut host_bits = start.trailing_zeros().min(bits);
+        while host_bits > 0 && host_mask(host_bits) > end - start {
+            host_bits -= 1;
+        }
+        let prefix = (bits - host_bits) as u8;
+        out.push(match family {
+            Family::V4 => IpNet::V4(Ipv4Net::new(Ipv4Addr::from(start as u32), prefix).unwrap()),
+            Family::V6 => IpNet::V6(Ipv6Net::new(Ipv6Addr::from(start), prefix).unwrap()),
+        });
+        let last = start + host_mask(host_bits);
+        if last == end {
+            break;
+        }
+        start = last + 1;
+    }
+}
+
+/// Returns the IPv4 network that an IPv4-mapped IPv6 network, inside
+/// `::ffff:0:0/96`, stands for.
+pub fn unmap(net: &Ipv6Net) -> Option<Ipv4Net> {
+    let address = net.network().to_ipv4_mapped()?;
+    let prefix = net.prefix_len().checked_sub(96)?;
+    Some(Ipv4Net::new(address, prefix).unwrap())
+}
+
+/// Returns the IPv4-mapped IPv6 form of an IPv4 network.
+pub fn map(net: &Ipv4Net) -> Ipv6Net {
+    Ipv6Net::new(net.network().to_ipv6_mapped(), net.prefix_len() + 96).unwrap()
+}
+
+/// Returns the IPv4 space embedded in a 6to4 network, inside `2002::/16`,
+/// or in the client address of a Teredo network, inside `2001::/32`.
+///
+/// A 6to4 network carries the site's IPv4 address in the 32 bits after
+/// `2002`, so a /48 or longer has a whole address and a /24 has a /8. A
+/// Teredo address ends with the client's IPv4 address with its bits
+/// flipped, so only a /96 or longer says anything about the client.
+pub fn embedded_v4(net: &Ipv6Net) -> Option<Ipv4Net> {
+    let bits = u128::from(net.network());
+    let len = net.prefix_len();
+    let (address, prefix) = if bits >> 112 == 0x2002 && len >= 16 {
+        ((bits >> 80) as u32, (len - 16).min(32))
+    } else if bits >> 96 == 0x2001_0000 && len >= 96 {
+        (!(bits as u32), len - 96)
+    } else {
+        return None;
+    };
+    Some(
+        Ipv4Net::new(Ipv4Addr::from(address), prefix)
+            .unwrap()
+            .trunc(),
+    )
+}
+
+/// Returns whether the network can be a NAT64 prefix. RFC 6052 allows
+/// /32, /40, /48, /56, /64, and /96.
+pub fn is_nat64_prefix(prefix: &Ipv6Net) -> bool {
+    matches!(prefix.prefix_len(), 32 | 40 | 48 | 56 | 64 | 96)
+}
+
+/// Returns where bit `i` of an IPv4 address goes in an IPv6 address under
+/// the NAT64 prefix. Bits 64 through 71 are reserved and always zero, so
+/// addresses that would cross them skip over them.
+fn nat64_bit(prefix_len: u8, i: u8) -> u8 {
+    let bit = prefix_len + i;
+    if prefix_len < 96 && bit >= 64 {
+        bit + 8
+    } else {
+        bit
+    }
+}
+
+/// Returns the IPv6 network that a NAT64 translator with the prefix uses
+/// for the IPv4 network, per RFC 6052. The prefix must pass
+/// [`is_nat64_prefix`].
+pub fn to_nat64(prefix: &Ipv6Net, net: &Ipv4Net) -> Ipv6Net {
+    let base = u128::from(prefix.network());
+    let v4 = u32::from(net.network());
+    let mut bits = base;
+    for i in 0..32 {
+        if v4 & (1 << (31 - i)) != 0 {
+            bits |= 1 << (127 - nat64_bit(prefix.prefix_len(), i));
+        }
+    }
+    let len = match net.prefix_len() {
+        0 => prefix.prefix_len(),
+        len => nat64_bit(prefix.prefix_len(), len - 1) + 1,
+    };
+    Ipv6Net::new(Ipv6Addr::from(bits), len).unwrap().trunc()
+}
+
+/// Returns the IPv4 network inside a network under the NAT64 prefix, the
+/// reverse of [`to_nat64`]. Returns `None` if the network isn't inside the
+/// prefix or sets the reserved bits.
+pub fn from_nat64(prefix: &Ipv6Net, net: &Ipv6Net) -> Option<Ipv4Net> {
+    if !prefix.contains(net) {
+        return None;
+    }
+    let bits = u128::from(net.network());
+    if prefix.prefix_len() < 96 && (bits >> 56) & 0xff != 0 {
+        return None;
+    }
+    let mut v4 = 0u32;
+    let mut len = 0;
+    for i in 0..32 {
+        let bit = nat64_bit(prefix.prefix_len(), i);
+        if bit >= net.prefix_len() {
+            break;
+        }
+        if bits & (1 << (127 - bit)) != 0 {
+            v4 |= 1 << (31 - i);
+        }
+        len += 1;
+    }
+    Some(Ipv4Net::new(Ipv4Addr::from(v4), len).unwrap())
+}
+
+fn host_mask(host_bits: u32) -> u128 {
+    u128::MAX.checked_shr(128 - host_bits).unwrap_or(0)
+}
+
+/// Returns the sorted, merged ranges covered by the networks of one family.
+/// Overlapping and adjacent ranges are combined.
+pub fn ranges(nets: &[IpNet], family: Family) -> Vec<(u128, u128)> {
+    let mut ranges: Vec<(u128, u128)> = nets
+        .iter()
+        .filter(|net| Family::of(net) == family)
+        .map(range)
+        .collect();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Returns the number of addresses in the sorted, merged ranges. The count
+/// saturates at u128::MAX, which only matters for the entire IPv6 space.
+pub fn size(ranges: &[(u128, u128)]) -> u128 {
+    ranges.iter().fold(0u128, |total, (start, end)| {
+        total.saturating_add(end - start).saturating_add(1)
+    })
+}
+
+/// Returns how many `/len` networks the network splits into, or 1 if it's
+/// already that small. Like [`size`], the count saturates at u128::MAX.
+pub fn subnet_count(net: &IpNet, len: u8) -> u128 {
+    match len.checked_sub(net.prefix_len()) {
+        None | Some(0) => 1,
+        Some(bits) => 1u128.checked_shl(bits.into()).unwrap_or(u128::MAX),
+    }
+}
+
+/// Displays a count from [`size`] or [`subnet_count`]. Those saturate at
+/// u128::MAX, which they only reach at 2^128, the number of addresses in
+/// ::/0, so that's what it's written as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Count(pub u128);
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            u128::MAX => f.pad_integral(true, "", "340282366920938463463374607431768211456"),
+            count => fmt::Display::fmt(&count, f),
+        }
+    }
+}
+
+/// Returns the number of addresses that are in both sets of sorted, merged
+/// ranges.
+pub fn overlap_size(a: &[(u128, u128)], b: &[(u128, u128)]) -> u128 {
+    let (mut i, mut j) = (0, 0);
+    let mut total = 0u128;
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start <= end {
+            total = total.saturating_add(end - start).saturating_add(1);
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    total
+}
+
+/// Returns the parts of the sorted, merged ranges `a` that aren't in `b`,
+/// which is sorted and merged too.
+pub fn subtract(a: &[(u128, u128)], b: &[(u128, u128)]) -> Vec<(u128, u128)> {
+    let mut left = Vec::new();
+    for &(start, end) in a {
+        let first = b.partition_point(|range| range.1 < start);
+        let mut start = Some(start);
+        for range in b[first..].iter().take_while(|range| range.0 <= end) {
+            let Some(from) = start else {
+                break;
+            };
+            if range.0 > from {
+                left.push((from, range.0 - 1));
+            }
+            start = range.1.checked_add(1).filter(|&next| next <= end);
+        }
+        if let Some(from) = start {
+            left.push((from, end));
+        }
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_range() {
+        assert_eq!(
+            range(&"10.0.0.0/24".parse().unwrap()),
+            (0x0a00_0000, 0x0a00_00ff)
+        );
+        assert_eq!(range(&"::/0".parse().unwrap()), (0, u128::MAX));
+    }
+
+    #[test]
+    fn test_ranges_merges_and_splits_families() {
+        let list = nets(&["10.0.1.0/24", "10.0.0.0/24", "10.0.0.128/25", "::/127"]);
+        assert_eq!(ranges(&list, Family::V4), vec![(0x0a00_0000, 0x0a00_01ff)]);
+        assert_eq!(ranges(&list, Family::V6), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_cover() {
+        let cover_of = |family, start, end| {
+            let mut out = Vec::new();
+            cover(family, start, end, &mut out);
+            out
+        };
+        assert_eq!(
+            cover_of(Family::V4, 0x0a00_0000, 0x0a00_01ff),
+            nets(&["10.0.0.0/23"])
+        );
+        assert_eq!(
+            cover_of(Family::V4, 0x0a00_0001, 0x0a00_0004),
+            nets(&["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/32"])
+        );
+        assert_eq!(
+            cover_of(Family::V4, 0, u32::MAX.into()),
+            nets(&["0.0.0.0/0"])
+        );
+        assert_eq!(cover_of(Family::V6, 0, u128::MAX), nets(&["::/0"]));
+        assert_eq!(
+            cover_of(Family::V6, 1, u128::MAX),
+            (0..128)
+                .map(|bit| {
+                    let start = 1u128 << bit;
+                    IpNet::V6(Ipv6Net::new(Ipv6Addr::from(start), (128 - bit) as u8).unwrap())
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unmap_and_map() {
+        let v6 = |text: &str| text.parse::<Ipv6Net>().unwrap();
+        let v4 = |text: &str| text.parse::<Ipv4Net>().unwrap();
+        assert_eq!(unmap(&v6("::ffff:192.0.2.0/120")), Some(v4("192.0.2.0/24")));
+        assert_eq!(unmap(&v6("::ffff:0:0/96")), Some(v4("0.0.0.0/0")));
+        // Too short to be inside the mapped space.
+        assert_eq!(unmap(&v6("::/64")), None);
+        assert_eq!(unmap(&v6("2001:db8::/120")), None);
+        assert_eq!(map(&v4("192.0.2.0/24")), v6("::ffff:192.0.2.0/120"));
+    }
+
+    #[test]
+    fn test_embedded_v4() {
+        let embedded = |text: &str| embedded_v4(&text.parse().unwrap()).map(|net| net.to_string());
+        // 6to4, with 192.0.2.1 as c000:0201.
+        assert_eq!(
+            embedded("2002:c000:201::/48").as_deref(),
+            Some("192.0.2.1/32")
+        );
+        assert_eq!(
+            embedded("2002:c000:201:1::/64").as_deref(),
+            Some("192.0.2.1/32")
+        );
+        assert_eq!(embedded("2002:c000::/40").as_deref(), Some("192.0.0.0/24"));
+        assert_eq!(embedded("2002::/16").as_deref(), Some("0.0.0.0/0"));
+        // Teredo, with the client 192.0.2.1 flipped to 3fff:fdfe.
+        assert_eq!(
+            embedded("2001:0:4136:e378:8000:63bf:3fff:fdfe/128").as_deref(),
+            Some("192.0.2.1/32")
+        );
+        assert_eq!(
+            embedded("2001:0:4136:e378:8000:63bf:3fff:0/112").as_deref(),
+            Some("192.0.0.0/16")
+        );
+        assert_eq!(embedded("2001::/32"), None);
+        assert_eq!(embedded("2001:db8::/32"), None);
+        assert_eq!(embedded("2000::/3"), None);
+    }
+
+    #[test]
+    fn test_nat64() {
+        let v6 = |text: &str| text.parse::<Ipv6Net>().unwrap();
+        let v4 = |text: &str| text.parse::<Ipv4Net>().unwrap();
+        // The examples from RFC 6052, section 2.4, for 192.0.2.33. Whatever
+        // follows the address doesn't matter to the translator, so a host
+        // is the whole network up to the end of it.
+        for (prefix, address, len) in [
+            ("2001:db8::/32", "2001:db8:c000:221::", 64),
+            ("2001:db8:100::/40", "2001:db8:1c0:2:21::", 80),
+            ("2001:db8:122::/48", "2001:db8:122:c000:2:2100::", 88),
+            ("2001:db8:122:300::/56", "2001:db8:122:3c0:0:221::", 96),
+            ("2001:db8:122:344::/64", "2001:db8:122:344:c0:2:2100:0", 104),
+            ("2001:db8:122:344::/96", "2001:db8:122:344::192.0.2.33", 128),
+            ("64:ff9b::/96", "64:ff9b::192.0.2.33", 128),
+        ] {
+            let prefix = v6(prefix);
+            let address = v6(&format!("{}/128", address));
+            assert!(is_nat64_prefix(&prefix));
+            let translated = to_nat64(&prefix, &v4("192.0.2.33/32"));
+            assert_eq!(translated.network(), address.network());
+            assert_eq!(translated.prefix_len(), len);
+            assert_eq!(from_nat64(&prefix, &address), Some(v4("192.0.2.33/32")));
+            assert_eq!(from_nat64(&prefix, &translated), Some(v4("192.0.2.33/32")));
+        }
+        // Networks that cross the reserved bits skip over them.
+        let prefix = v6("2001:db8:100::/40");
+        assert_eq!(
+            to_nat64(&prefix, &v4("192.0.2.0/24")),
+            v6("2001:db8:1c0:2::/64")
+        );
+        assert_eq!(
+            to_nat64(&prefix, &v4("192.0.2.0/25")),
+            v6("2001:db8:1c0:2::/73")
+        );
+        assert_eq!(to_nat64(&prefix, &v4("0.0.0.0/0")), prefix);
+        assert_eq!(
+            from_nat64(&prefix, &v6("2001:db8:1c0:2::/70")),
+            Some(v4("192.0.2.0/24"))
+        );
+        assert_eq!(from_nat64(&prefix, &v6("2001:db8:1c0:2:100::/72")), None);
+        assert_eq!(from_nat64(&prefix, &v6("2001:db8::/32")), None);
+        assert!(!is_nat64_prefix(&v6("64:ff9b::/95")));
+    }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(
+            size(&ranges(&nets(&["10.0.0.0/23", "10.0.4.0/24"]), Family::V4)),
+            768
+        );
+        assert_eq!(size(&ranges(&nets(&["::/0"]), Family::V6)), u128::MAX);
+    }
+
+    #[test]
+    fn test_subnet_count() {
+        let [v4, v6] = [nets(&["10.0.0.0/16"])[0], nets(&["::/0"])[0]];
+        assert_eq!(subnet_count(&v4, 24), 256);
+        assert_eq!(subnet_count(&v4, 16), 1);
+        assert_eq!(subnet_count(&v4, 8), 1);
+        assert_eq!(subnet_count(&v6, 64), 1 << 64);
+        assert_eq!(subnet_count(&v6, 128), u128::MAX);
+    }
+
+    #[test]
+    fn test_count() {
+        let v6 = nets(&["::/0"])[0];
+        assert_eq!(
+            format!("{}", Count(subnet_count(&v6, 128))),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(format!("{:>12}", Count(4294967296)), "  4294967296");
+        assert_eq!(
+            format!("{}", Count(u128::MAX - 1)),
+            (u128::MAX - 1).to_string()
+        );
+    }
+
+    #[test]
+    fn test_overlap_size() {
+        let a = ranges(&nets(&["10.0.0.0/23", "10.0.8.0/24"]), Family::V4);
+        let b = ranges(
+            &nets(&["10.0.1.0/24", "10.0.8.128/25", "10.0.9.0/24"]),
+            Family::V4,
+        );
+        assert_eq!(overlap_size(&a, &b), 384);
+        assert_eq!(overlap_size(&a, &[]), 0);
+    }
+
+    #[test]
+    fn test_subtract() {
+        assert_eq!(
+            subtract(&[(0, 99)], &[(10, 19), (30, 39)]),
+            vec![(0, 9), (20, 29), (40, 99)]
+        );
+        assert_eq!(subtract(&[(10, 20)], &[(0, 100)]), vec![]);
+        assert_eq!(subtract(&[(10, 20)], &[]), vec![(10, 20)]);
+        assert_eq!(subtract(&[(0, u128::MAX)], &[(5, u128::MAX)]), vec![(0, 4)]);
+    }
+}
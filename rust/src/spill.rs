@@ -0,0 +1,333 @@
+//! Aggregating more networks than fit in memory.
+//!
+//! With `--low-memory`, parsed networks are collected as address ranges in a
+//! fixed-size buffer. Each time it fills up, it's sorted, merged, and written
+//! to a temporary file as a run. At the end, the runs are merged back together
+//! in order, overlapping and adjacent ranges are combined as they stream past,
+//! and each combined range is turned back into the fewest networks that cover
+//! it. Memory use depends on the buffer size and the size of the result, not
+//! on the size of the input.
+
+use crate::space::{self, Family};
+use ipnet::IpNet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, process};
+
+/// How many ranges to hold in memory before spilling them to a run, about
+/// 48 MiB worth.
+pub const RUN_LENGTH: usize = 1 << 20;
+
+/// The most runs to read from at once. With more than this, runs are merged
+/// into fewer, bigger ones first, so we don't run out of file descriptors.
+const FAN_IN: usize = 64;
+
+/// A family and an inclusive range of addresses, which sorts in the same
+/// order as the networks that make it up.
+type Range = (Family, u128, u128);
+
+/// Each range is stored on disk as a family byte and two big-endian u128s.
+const RECORD: usize = 33;
+
+/// Collects networks, spilling them to disk as needed, and aggregates them.
+pub struct Spill {
+    run_length: usize,
+    buffer: Vec<Range>,
+    dir: Option<PathBuf>,
+    runs: Vec<PathBuf>,
+    /// How many runs have been written, for naming the next one.
+    written: usize,
+}
+
+impl Spill {
+    /// Creates a collector that holds up to `run_length` ranges in memory.
+    /// Nothing is written to disk unless there are more than that.
+    pub fn new(run_length: usize) -> Spill {
+        Spill {
+            run_length,
+            buffer: Vec::new(),
+            dir: None,
+            runs: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Adds a network.
+    pub fn push(&mut self, net: &IpNet) -> io::Result<()> {
+        let (start, end) = space::range(net);
+        self.buffer.push((Family::of(net), start, end));
+        if self.buffer.len() >= self.run_length {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Sorts and merges the buffer and writes it to a new run.
+    fn spill(&mut self) -> io::Result<()> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let path = self.run_path()?;
+        let mut writer = RunWriter::create(&path)?;
+        merge_sorted(sorted(buffer), |range| writer.push(range))?;
+        writer.finish()?;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// Returns a path for a new run, creating the temporary directory first
+    /// if this is the first one.
+    fn run_path(&mut self) -> io::Result<PathBuf> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => {
+                static COUNT: AtomicUsize = AtomicUsize::new(0);
+                let dir = env::temp_dir().join(format!(
+                    "lfc-spill-{}-{}",
+                    process::id(),
+                    COUNT.fetch_add(1, Ordering::Relaxed)
+                ));
+                fs::create_dir(&dir)?;
+                self.dir.insert(dir)
+            }
+        };
+        self.written += 1;
+        Ok(dir.join(format!("run-{}", self.written)))
+    }
+
+    /// Merges everything that was added and returns the aggregated networks,
+    /// in the same order as `gather`.
+    pub fn finish(mut self) -> io::Result<Vec<IpNet>> {
+        let mut result = Vec::new();
+        let mut emit = |(family, start, end): Range| {
+            space::cover(family, start, end, &mut result);
+            Ok(())
+        };
+        if self.runs.is_empty() {
+            merge_sorted(sorted(std::mem::take(&mut self.buffer)), &mut emit)?;
+            return Ok(result);
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        while self.runs.len() > FAN_IN {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(FAN_IN) {
+                let path = self.run_path()?;
+                let mut writer = RunWriter::create(&path)?;
+                merge_runs(group, |range| writer.push(range))?;
+                writer.finish()?;
+                for run in group {
+                    fs::remove_file(run)?;
+                }
+                self.runs.push(path);
+            }
+        }
+        merge_runs(&self.runs, &mut emit)?;
+        Ok(result)
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.dir {
+            // There's nothing useful to do about a temporary directory that
+            // won't go away.
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Aggregates the networks like `gather`, spilling to disk every
+/// [`RUN_LENGTH`] networks.
+pub fn gather<I: IntoIterator<Item = IpNet>>(nets: I) -> crate::Result<Vec<IpNet>> {
+    let mut spill = Spill::new(RUN_LENGTH);
+    for net in nets {
+        spill.push(&net)?;
+    }
+    Ok(spill.finish()?)
+}
+
+fn sorted(mut ranges: Vec<Range>) -> impl Iterator<Item = io::Result<Range>> {
+    ranges.sort_unstable();
+    ranges.into_iter().map(Ok)
+}
+
+/// Combines overlapping and adjacent ranges from a sorted stream and passes
+/// each combined range to `emit`.
+fn merge_sorted<I, F>(ranges: I, mut emit: F) -> io::Result<()>
+where
+    I: IntoIterator<Item = io::Result<Range>>,
+    F: FnMut(Range) -> io::Result<()>,
+{
+    let mut current: Option<Range> = None;
+    for range in ranges {
+        let (family, start, end) = range?;
+        match &mut current {
+            Some((last_family, _, last_end))
+                if *last_family == family && start <= last_end.saturating_add(1) =>
+            {
+                *last_end = (*last_end).max(end);
+            }
+            _ => {
+                if let Some(done) = current.replace((family, start, end)) {
+                    emit(done)?;
+                }
+            }
+        }
+    }
+    if let Some(done) = current {
+        emit(done)?;
+    }
+    Ok(())
+}
+
+/// Merges sorted runs into one sorted stream of combined ranges.
+fn merge_runs<F: FnMut(Range) -> io::Result<()>>(runs: &[PathBuf], emit: F) -> io::Result<()> {
+    let mut readers = runs
+        .iter()
+        .map(RunReader::open)
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+    for (index, reader) in readers.iter_mut().enumerate() {
+        if let Some(range) = reader.next()? {
+            heap.push(Reverse((range, index)));
+        }
+    }
+    let stream = std::iter::from_fn(|| {
+        let Reverse((range, index)) = heap.pop()?;
+        match readers[index].next() {
+            Ok(Some(next)) => heap.push(Reverse((next, index))),
+            Ok(None) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        Some(Ok(range))
+    });
+    merge_sorted(stream, emit)
+}
+
+struct RunWriter {
+    out: BufWriter<File>,
+}
+
+impl RunWriter {
+    fn create(path: &PathBuf) -> io::Result<RunWriter> {
+        Ok(RunWriter {
+            out: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    fn push(&mut self, (family, start, end): Range) -> io::Result<()> {
+        let mut record = [0u8; RECORD];
+        record[0] = match family {
+            Family::V4 => 4,
+            Family::V6 => 6,
+        };
+        record[1..17].copy_from_slice(&start.to_be_bytes());
+        record[17..].copy_from_slice(&end.to_be_bytes());
+        self.out.write_all(&record)
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+struct RunReader {
+    input: BufReader<File>,
+}
+
+impl RunReader {
+    fn open(path: &PathBuf) -> io::Result<RunReader> {
+        Ok(RunReader {
+            input: BufReader::new(File::open(path)?),
+        })
+    }
+
+    fn next(&mut self) -> io::Result<Option<Range>> {
+        let mut record = [0u8; RECORD];
+        match self.input.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let family = match record[0] {
+            4 => Family::V4,
+            6 => Family::V6,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "corrupt spill run",
+                ));
+            }
+        };
+        let start = u128::from_be_bytes(record[1..17].try_into().unwrap());
+        let end = u128::from_be_bytes(record[17..].try_into().unwrap());
+        Ok(Some((family, start, end)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a messy list of overlapping, adjacent, and duplicate networks of
+    /// both families.
+    fn sample() -> Vec<IpNet> {
+        let mut state: u32 = 1;
+        (0..5000)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let prefix = 20 + (state >> 8) % 13;
+                let text = if i % 5 == 0 {
+                    format!("2001:db8:{:x}::/{}", state >> 20, prefix + 20)
+                } else {
+                    format!(
+                        "10.{}.{}.0/{}",
+                        (state >> 24) % 4,
+                        (state >> 16) % 256,
+                        prefix
+                    )
+                };
+                text.parse::<IpNet>().unwrap().trunc()
+            })
+            .collect()
+    }
+
+    fn spill(nets: &[IpNet], run_length: usize) -> Vec<IpNet> {
+        let mut spill = Spill::new(run_length);
+        for net in nets {
+            spill.push(net).unwrap();
+        }
+        spill.finish().unwrap()
+    }
+
+    #[test]
+    fn test_matches_gather_in_memory() {
+        let nets = sample();
+        assert_eq!(spill(&nets, usize::MAX), crate::gather(&nets));
+        assert_eq!(spill(&[], usize::MAX), vec![]);
+    }
+
+    #[test]
+    fn test_matches_gather_with_runs() {
+        let nets = sample();
+        // Small enough runs to need more than one round of merging.
+        assert_eq!(spill(&nets, 37), crate::gather(&nets));
+        assert_eq!(spill(&nets, 1000), crate::gather(&nets));
+    }
+
+    #[test]
+    fn test_removes_runs() {
+        let mut spill = Spill::new(2);
+        for net in sample().iter().take(10) {
+            spill.push(net).unwrap();
+        }
+        let dir = spill.dir.clone().unwrap();
+        assert!(dir.exists());
+        spill.finish().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,204 @@
+//! Remembering results between runs.
+//!
+//! With `--state-dir`, each run saves its result as `<dir>/<name>.txt` and
+//! compares it to the result saved by the previous run with the same
+//! `--name`. That's enough to notice changes to a feed from a cron job.
+
+use ipnet::IpNet;
+use lfc::space::{self, Family};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The list name used when `--name` isn't given.
+pub const DEFAULT_NAME: &str = "default";
+
+/// What changed in one address family between two results.
+#[derive(Debug, Default, PartialEq)]
+pub struct FamilyChange {
+    /// Addresses in the new result that weren't in the old one.
+    pub added: u128,
+    /// Addresses in the old result that aren't in the new one.
+    pub removed: u128,
+}
+
+/// What changed between two results.
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    /// Prefixes in the new result that weren't in the old one.
+    pub prefixes_added: usize,
+    /// Prefixes in the old result that aren't in the new one.
+    pub prefixes_removed: usize,
+    pub v4: FamilyChange,
+    pub v6: FamilyChange,
+}
+
+impl Summary {
+    /// Compares two aggregated results.
+    pub fn new(old: &[IpNet], new: &[IpNet]) -> Summary {
+        let old_set: BTreeSet<&IpNet> = old.iter().collect();
+        let new_set: BTreeSet<&IpNet> = new.iter().collect();
+        let family = |family| {
+            let old = space::ranges(old, family);
+            let new = space::ranges(new, family);
+            let common = space::overlap_size(&old, &new);
+            FamilyChange {
+                added: space::size(&new) - common,
+                removed: space::size(&old) - common,
+            }
+        };
+        Summary {
+            prefixes_added: new_set.difference(&old_set).count(),
+            prefixes_removed: old_set.difference(&new_set).count(),
+            v4: family(Family::V4),
+            v6: family(Family::V6),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Summary::default()
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "+{}/-{} prefixes, IPv4 +{}/-{} addresses, IPv6 +{}/-{} addresses",
+            self.prefixes_added,
+            self.prefixes_removed,
+            space::Count(self.v4.added),
+            space::Count(self.v4.removed),
+            space::Count(self.v6.added),
+            space::Count(self.v6.removed)
+        )
+    }
+}
+
+/// Checks that a list name is safe to use as a file name or in a URL.
+pub fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid list name {:?}: use letters, digits, '-', '_', and '.'",
+            name
+        ))
+    }
+}
+
+/// Returns the path of the state file for the named list.
+pub fn path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    check_name(name)?;
+    Ok(dir.join(format!("{}.txt", name)))
+}
+
+/// Loads a saved result, or returns None if there isn't one yet.
+pub fn load(path: &Path) -> io::Result<Option<Vec<IpNet>>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} in {} isn't a network", line, path.display()),
+                )
+            })
+        })
+        .collect::<io::Result<Vec<IpNet>>>()
+        .map(Some)
+}
+
+/// Saves a result, replacing the previous one atomically so that a crash
+/// can't leave a half-written state file behind.
+pub fn save(path: &Path, nets: &[IpNet]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut contents = String::new();
+    for net in nets {
+        contents.push_str(&net.to_string());
+        contents.push('\n');
+    }
+    let tmp = path.with_extension("txt.tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|net| net.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_summary() {
+        let old = nets(&["10.0.0.0/24", "192.168.0.0/24", "2001:db8::/127"]);
+        let new = nets(&["10.0.0.0/23", "192.168.0.0/24"]);
+        let summary = Summary::new(&old, &new);
+        assert_eq!(
+            summary,
+            Summary {
+                prefixes_added: 1,
+                prefixes_removed: 2,
+                v4: FamilyChange {
+                    added: 256,
+                    removed: 0
+                },
+                v6: FamilyChange {
+                    added: 0,
+                    removed: 2
+                },
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "+1/-2 prefixes, IPv4 +256/-0 addresses, IPv6 +0/-2 addresses"
+        );
+    }
+
+    #[test]
+    fn test_summary_unchanged() {
+        let list = nets(&["10.0.0.0/8"]);
+        assert!(Summary::new(&list, &list).is_empty());
+    }
+
+    #[test]
+    fn test_path_validates_names() {
+        let dir = Path::new("/var/lib/lfc");
+        assert_eq!(
+            path(dir, "blocklist").unwrap(),
+            PathBuf::from("/var/lib/lfc/blocklist.txt")
+        );
+        assert!(path(dir, "../etc/passwd").is_err());
+        assert!(path(dir, "").is_err());
+        assert!(path(dir, ".hidden").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("lfc-state-test-{}", std::process::id()));
+        let file = path(&dir, "test").unwrap();
+        assert_eq!(load(&file).unwrap(), None);
+
+        let list = nets(&["10.0.0.0/8", "2001:db8::/32"]);
+        save(&file, &list).unwrap();
+        assert_eq!(load(&file).unwrap(), Some(list));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
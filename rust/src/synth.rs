@@ -0,0 +1,364 @@
+//! Synthetic network lists.
+//!
+//! Everything here is driven by a small seeded random number generator, so
+//! the same seed always produces the same list, on every platform and in
+//! every release. That's what makes timings from `lfc bench` comparable
+//! between versions, and lists from `lfc gen` reproducible.
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::fmt::Write;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The seed used when the caller doesn't pick one.
+pub const DEFAULT_SEED: u64 = 0x6c66_6321;
+
+/// A SplitMix64 random number generator. It's not cryptographic, but it's
+/// fast, has no bad seeds, and is simple enough to never change.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        (self.next_u64() as u128) << 64 | self.next_u64() as u128
+    }
+
+    /// Returns a number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// Returns a number in `low..=high`.
+    pub fn between(&mut self, low: u8, high: u8) -> u8 {
+        low + self.below(u64::from(high - low) + 1) as u8
+    }
+
+    /// Returns true with the given probability, in percent.
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+/// Returns the IPv4 network of that length containing the address.
+pub fn v4(address: u32, prefix: u8) -> IpNet {
+    IpNet::V4(
+        Ipv4Net::new(Ipv4Addr::from(address), prefix)
+            .unwrap()
+            .trunc(),
+    )
+}
+
+/// Returns the IPv6 network of that length containing the address.
+pub fn v6(address: u128, prefix: u8) -> IpNet {
+    IpNet::V6(
+        Ipv6Net::new(Ipv6Addr::from(address), prefix)
+            .unwrap()
+            .trunc(),
+    )
+}
+
+/// Which address families `lfc gen` generates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FamilyChoice {
+    #[default]
+    V4,
+    V6,
+    /// Half of each.
+    Mixed,
+}
+
+impl std::str::FromStr for FamilyChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v4" => Ok(FamilyChoice::V4),
+            "v6" => Ok(FamilyChoice::V6),
+            "mixed" => Ok(FamilyChoice::Mixed),
+            _ => Err(format!(
+                "invalid family '{}' (expected v4, v6, or mixed)",
+                s
+            )),
+        }
+    }
+}
+
+/// The shortest and longest prefix lengths to generate, inclusive.
+type Lengths = (u8, u8);
+
+/// Parses an inclusive range of prefix lengths like `16..28`, or a single
+/// length like `24`.
+pub fn parse_lengths(s: &str) -> Result<Lengths, String> {
+    let invalid = || format!("invalid prefix length range '{}' (expected like 16..28)", s);
+    let (low, high) = s.split_once("..").unwrap_or((s, s));
+    let low: u8 = low.parse().map_err(|_| invalid())?;
+    let high: u8 = high.parse().map_err(|_| invalid())?;
+    if low > high || high > 128 {
+        return Err(invalid());
+    }
+    Ok((low, high))
+}
+
+/// Settings for generating uniformly random networks.
+pub struct Random {
+    pub family: FamilyChoice,
+    /// IPv4 networks stop at /32 when generating both families.
+    pub lengths: Option<Lengths>,
+}
+
+impl Random {
+    /// Returns the prefix lengths for each family, or an error if the range
+    /// doesn't work for the chosen families.
+    fn lengths(&self) -> Result<(Lengths, Lengths), String> {
+        let Some((low, high)) = self.lengths else {
+            return Ok(((16, 32), (32, 64)));
+        };
+        let too_long = |length| format!("IPv4 networks can't be longer than /32, not /{}", length);
+        match self.family {
+            FamilyChoice::V4 if high > 32 => Err(too_long(high)),
+            FamilyChoice::Mixed if low > 32 => Err(too_long(low)),
+            _ => Ok(((low, high.min(32)), (low, high))),
+        }
+    }
+
+    /// Returns a generator of random networks, or an error if the settings
+    /// don't make sense together.
+    pub fn networks(self, rng: &mut Rng) -> Result<impl Iterator<Item = IpNet> + '_, String> {
+        let (v4_lengths, v6_lengths) = self.lengths()?;
+        Ok(std::iter::repeat_with(move || {
+            let is_v4 = match self.family {
+                FamilyChoice::V4 => true,
+                FamilyChoice::V6 => false,
+                FamilyChoice::Mixed => rng.chance(50),
+            };
+            if is_v4 {
+                v4(
+                    rng.next_u64() as u32,
+                    rng.between(v4_lengths.0, v4_lengths.1),
+                )
+            } else {
+                v6(rng.next_u128(), rng.between(v6_lengths.0, v6_lengths.1))
+            }
+        }))
+    }
+}
+
+/// A kind of workload for `lfc bench`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Profile {
+    /// Something like a full routing table: mostly IPv4 /24s down to /16s
+    /// spread over the public address space, runs of neighboring prefixes
+    /// that can be merged, and some IPv6.
+    #[default]
+    Bgp,
+    /// Networks of any length anywhere, which hardly merge at all.
+    Random,
+    /// Small networks packed into one /12, which merge into very few.
+    Dense,
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bgp" => Ok(Profile::Bgp),
+            "random" => Ok(Profile::Random),
+            "dense" => Ok(Profile::Dense),
+            _ => Err(format!(
+                "invalid profile '{}' (expected bgp, random, or dense)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Profile::Bgp => write!(f, "bgp"),
+            Profile::Random => write!(f, "random"),
+            Profile::Dense => write!(f, "dense"),
+        }
+    }
+}
+
+/// IPv4 prefix lengths in a routing table, very roughly, as (length, percent).
+const BGP_V4_LENGTHS: [(u8, u64); 9] = [
+    (24, 58),
+    (23, 10),
+    (22, 12),
+    (21, 5),
+    (20, 5),
+    (19, 3),
+    (18, 2),
+    (17, 1),
+    (16, 4),
+];
+
+impl Profile {
+    /// Returns the next network of this profile. `previous` is the last one
+    /// generated, which some profiles build on.
+    fn next(self, rng: &mut Rng, previous: Option<IpNet>) -> IpNet {
+        match self {
+            Profile::Bgp => {
+                // Real tables are full of neighbors, like a /22 announced as
+                // four /24s.
+                if let (Some(IpNet::V4(previous)), true) = (previous, rng.chance(30)) {
+                    let size = 1u32 << (32 - previous.prefix_len());
+                    let next = u32::from(previous.network()).wrapping_add(size);
+                    return v4(next, previous.prefix_len());
+                }
+                if rng.chance(10) {
+                    let prefix = [32, 36, 40, 44, 48, 48, 48][rng.below(7) as usize];
+                    // Global unicast, 2000::/3.
+                    let address = (1u128 << 125) | (rng.next_u128() >> 3);
+                    return v6(address, prefix);
+                }
+                let mut pick = rng.below(100);
+                let prefix = BGP_V4_LENGTHS
+                    .iter()
+                    .find(|(_, percent)| {
+                        let found = pick < *percent;
+                        pick = pick.saturating_sub(*percent);
+                        found
+                    })
+                    .map_or(24, |(prefix, _)| *prefix);
+                // Skip 0/8, 10/8, 127/8, and everything from 224/3 up.
+                let first = loop {
+                    let first = 1 + rng.below(223) as u32;
+                    if first != 10 && first != 127 {
+                        break first;
+                    }
+                };
+                v4(first << 24 | rng.below(1 << 24) as u32, prefix)
+            }
+            Profile::Random => {
+                if rng.chance(20) {
+                    v6(rng.next_u128(), rng.between(16, 128))
+                } else {
+                    v4(rng.next_u64() as u32, rng.between(8, 32))
+                }
+            }
+            Profile::Dense => v4(0x0a00_0000 | rng.below(1 << 20) as u32, rng.between(24, 28)),
+        }
+    }
+
+    /// Appends `count` networks of this profile to `out`, one per line.
+    pub fn generate(self, count: usize, rng: &mut Rng, out: &mut String) {
+        let mut previous = None;
+        for _ in 0..count {
+            let net = self.next(rng, previous);
+            writeln!(out, "{}", net).unwrap();
+            previous = Some(net);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let mut c = Rng::new(8);
+        let a: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(a, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(a, (0..4).map(|_| c.next_u64()).collect::<Vec<_>>());
+        // SplitMix64's published first output for seed 0.
+        assert_eq!(Rng::new(0).next_u64(), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn test_rng_ranges() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            assert!(rng.below(10) < 10);
+            assert!((16..=28).contains(&rng.between(16, 28)));
+        }
+        assert_eq!(rng.between(32, 32), 32);
+    }
+
+    #[test]
+    fn test_profiles() {
+        for profile in [Profile::Bgp, Profile::Random, Profile::Dense] {
+            let mut out = String::new();
+            profile.generate(1000, &mut Rng::new(DEFAULT_SEED), &mut out);
+            let nets = lfc::parse_nets(out.lines()).unwrap();
+            assert_eq!(nets.len(), 1000, "{}", profile);
+            assert!(nets.iter().all(|net| *net == net.trunc()), "{}", profile);
+            assert_eq!(profile.to_string().parse(), Ok(profile));
+        }
+    }
+
+    #[test]
+    fn test_parse_lengths() {
+        assert_eq!(parse_lengths("16..28"), Ok((16, 28)));
+        assert_eq!(parse_lengths("24"), Ok((24, 24)));
+        assert!(parse_lengths("28..16").is_err());
+        assert!(parse_lengths("16..129").is_err());
+        assert!(parse_lengths("16-28").is_err());
+    }
+
+    #[test]
+    fn test_random_networks() {
+        let settings = |family, lengths| Random { family, lengths };
+        let take = |random: Random, seed| -> Vec<IpNet> {
+            random
+                .networks(&mut Rng::new(seed))
+                .unwrap()
+                .take(500)
+                .collect()
+        };
+
+        let nets = take(settings(FamilyChoice::V4, Some((16, 28))), 1);
+        assert!(nets.iter().all(|net| matches!(net, IpNet::V4(_))));
+        assert!(nets.iter().all(|net| (16..=28).contains(&net.prefix_len())));
+        assert_eq!(nets, take(settings(FamilyChoice::V4, Some((16, 28))), 1));
+        assert_ne!(nets, take(settings(FamilyChoice::V4, Some((16, 28))), 2));
+
+        let nets = take(settings(FamilyChoice::Mixed, Some((24, 64))), 1);
+        assert!(nets.iter().any(|net| matches!(net, IpNet::V4(_))));
+        assert!(nets.iter().any(|net| matches!(net, IpNet::V6(_))));
+        assert!(nets.iter().all(|net| match net {
+            IpNet::V4(net) => (24..=32).contains(&net.prefix_len()),
+            IpNet::V6(net) => (24..=64).contains(&net.prefix_len()),
+        }));
+
+        let mut rng = Rng::new(1);
+        assert!(
+            settings(FamilyChoice::V4, Some((16, 48)))
+                .networks(&mut rng)
+                .is_err()
+        );
+        assert!(
+            settings(FamilyChoice::Mixed, Some((40, 48)))
+                .networks(&mut rng)
+                .is_err()
+        );
+        assert!(
+            settings(FamilyChoice::V6, Some((40, 48)))
+                .networks(&mut rng)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_dense_merges() {
+        let mut out = String::new();
+        Profile::Dense.generate(100_000, &mut Rng::new(DEFAULT_SEED), &mut out);
+        assert!(lfc::gather_lines(out.lines()).unwrap().len() < 1000);
+    }
+}
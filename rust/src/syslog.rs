@@ -0,0 +1,113 @@
+//! Logging list changes to syslog.
+//!
+//! With `--syslog`, every prefix that `--state-dir` sees added or removed is
+//! logged to the local syslog daemon as its own message, with `key=value`
+//! fields that are easy to search and parse:
+//!
+//! ```text
+//! lfc[1234]: list=blocklist action=add prefix=10.0.0.0/23 source=feed.txt
+//! ```
+
+use crate::diff::Change;
+use std::io;
+
+/// Where local syslog daemons listen, in the order we try them.
+#[cfg(unix)]
+const SOCKETS: &[&str] = &["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// The `user` facility at `notice` severity.
+#[cfg(unix)]
+const PRIORITY: u8 = (1 << 3) | 5;
+
+/// Formats the message logged for one change.
+pub fn message(list: &str, source: &str, change: &Change) -> String {
+    let action = match change {
+        Change::Added(_) => "add",
+        Change::Removed(_) => "remove",
+    };
+    format!(
+        "list={} action={} prefix={} source={}",
+        quote(list),
+        action,
+        change.net(),
+        quote(source)
+    )
+}
+
+/// Quotes a value if it has spaces, quotes, or backslashes in it.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains([' ', '"', '\\']) {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Sends a message for each change to the local syslog daemon.
+#[cfg(unix)]
+pub fn log_changes(list: &str, source: &str, changes: &[Change]) -> io::Result<()> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    let mut connected = Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no syslog socket found",
+    ));
+    for path in SOCKETS {
+        connected = socket.connect(path);
+        if connected.is_ok() {
+            break;
+        }
+    }
+    connected
+        .map_err(|err| io::Error::new(err.kind(), format!("unable to reach syslog: {}", err)))?;
+
+    let pid = std::process::id();
+    for change in changes {
+        let line = format!(
+            "<{}>lfc[{}]: {}",
+            PRIORITY,
+            pid,
+            message(list, source, change)
+        );
+        socket.send(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn log_changes(_list: &str, _source: &str, _changes: &[Change]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "syslog is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        assert_eq!(
+            message(
+                "blocklist",
+                "feed.txt",
+                &Change::Added("10.0.0.0/23".parse().unwrap())
+            ),
+            "list=blocklist action=add prefix=10.0.0.0/23 source=feed.txt"
+        );
+        assert_eq!(
+            message(
+                "blocklist",
+                "my feed.txt",
+                &Change::Removed("2001:db8::/32".parse().unwrap())
+            ),
+            "list=blocklist action=remove prefix=2001:db8::/32 source=\"my feed.txt\""
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("a \"b\""), "\"a \\\"b\\\"\"");
+    }
+}
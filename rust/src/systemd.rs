@@ -0,0 +1,176 @@
+//! Running `lfc serve` as a socket-activated, `Type=notify` systemd service.
+//!
+//! With socket activation, systemd opens the sockets in the unit's
+//! `.socket` file itself and passes them in as file descriptors from 3 on,
+//! with `LISTEN_FDS` saying how many there are and `LISTEN_PID` which
+//! process they're for (sd_listen_fds(3)). With `Type=notify`,
+//! `NOTIFY_SOCKET` names the datagram socket to say `READY=1` on once the
+//! server is answering, and with `WatchdogSec=`, `WATCHDOG_USEC` says how
+//! often to say `WATCHDOG=1` after that (sd_notify(3)). Outside of systemd
+//! none of these are set, and none of this does anything.
+
+use std::env;
+use std::io;
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// The first file descriptor systemd passes.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// A listening socket that systemd passed in.
+pub enum Socket {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+/// Returns how many sockets systemd passed to the process with the ID
+/// `own`, from the values of `LISTEN_PID` and `LISTEN_FDS`.
+fn listen_fds(pid: Option<&str>, fds: Option<&str>, own: u32) -> usize {
+    if pid.and_then(|pid| pid.parse().ok()) != Some(own) {
+        return 0;
+    }
+    fds.and_then(|fds| fds.parse().ok()).unwrap_or(0)
+}
+
+/// Returns the sockets systemd passed in, in the order of the `.socket`
+/// unit. Only the first call gets them.
+#[cfg(unix)]
+pub fn sockets() -> io::Result<Vec<Socket>> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    if TAKEN.swap(true, Ordering::Relaxed) {
+        return Ok(Vec::new());
+    }
+    let count = listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    (LISTEN_FDS_START..)
+        .take(count)
+        .map(|fd| {
+            // SAFETY: systemd opened these descriptors for this process,
+            // which hasn't used them for anything else, and TAKEN makes sure
+            // they're only owned once.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            // A Unix socket has no IP address, so that tells them apart.
+            let tcp = TcpListener::from(fd);
+            if tcp.local_addr().is_ok() {
+                return Ok(Socket::Tcp(tcp));
+            }
+            Ok(Socket::Unix(UnixListener::from(OwnedFd::from(tcp))))
+        })
+        .collect()
+}
+
+/// Returns the sockets systemd passed in, which is never any off Unix.
+#[cfg(not(unix))]
+pub fn sockets() -> io::Result<Vec<Socket>> {
+    Ok(Vec::new())
+}
+
+/// Tells systemd about the service's state, like `READY=1`, if it's
+/// listening.
+pub fn notify(state: &str) -> io::Result<()> {
+    match env::var_os("NOTIFY_SOCKET") {
+        Some(path) => send(&path, state),
+        None => Ok(()),
+    }
+}
+
+/// Sends the state to the notification socket at the path, which is in
+/// the abstract namespace if it starts with `@`.
+#[cfg(unix)]
+fn send(path: &std::ffi::OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match path.as_bytes() {
+        #[cfg(target_os = "linux")]
+        [b'@', name @ ..] => {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_path: &std::ffi::OsStr, _state: &str) -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns how often systemd wants to hear `WATCHDOG=1` from the process
+/// with the ID `own`, from the values of `WATCHDOG_USEC` and
+/// `WATCHDOG_PID`. That's half of its timeout, so a late ping is still in
+/// time.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own: u32) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse().ok() != Some(own)) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+/// Returns how often to tell systemd `WATCHDOG=1`, if its watchdog is on.
+pub fn watchdog() -> Option<Duration> {
+    watchdog_interval(
+        env::var("WATCHDOG_USEC").ok().as_deref(),
+        env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds() {
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42), 2);
+        assert_eq!(listen_fds(Some("41"), Some("2"), 42), 0);
+        assert_eq!(listen_fds(None, Some("2"), 42), 0);
+        assert_eq!(listen_fds(Some("42"), None, 42), 0);
+        assert_eq!(listen_fds(Some("42"), Some("x"), 42), 0);
+    }
+
+    #[test]
+    fn test_watchdog_interval() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("41"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send() {
+        use std::os::unix::net::UnixDatagram;
+
+        let path = env::temp_dir().join(format!("lfc-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        send(path.as_os_str(), "READY=1").unwrap();
+        let mut buffer = [0; 16];
+        let len = socket.recv(&mut buffer).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&buffer[..len], b"READY=1");
+    }
+}
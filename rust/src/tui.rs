@@ -0,0 +1,639 @@
+//! `lfc tui`, a terminal browser for the aggregated networks.
+//!
+//! The networks are shown as a prefix tree: each branch is the smallest
+//! network that holds everything under it, and splits where its networks
+//! do, so that related networks end up next to each other. A network that
+//! holds others in the list, as with `--dedupe-only`, is the branch over
+//! them. Every row shows how many addresses are under it. The keys are:
+//!
+//! ```text
+//! up, down, k, j        move
+//! page up, page down    move a screen at a time
+//! right, l, enter       expand, or collapse if already expanded
+//! left, h               collapse, or move to the parent
+//! space                 mark or unmark the row and everything under it
+//! /                     search for a network or address
+//! n                     search again
+//! x                     exit and print the marked networks
+//! q, esc                exit without printing anything
+//! ```
+//!
+//! The browser draws on stderr, so whatever `x` prints can be redirected.
+//! It's behind the `tui` feature. Without it, `lfc tui` is an error.
+
+use ipnet::IpNet;
+use lfc::format::Style;
+
+#[cfg(feature = "tui")]
+pub use browser::run;
+
+#[cfg(feature = "tui")]
+mod browser {
+    use super::*;
+    use crossterm::cursor::{Hide, MoveTo, Show};
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::style::{Attribute, Print, SetAttribute};
+    use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+    use crossterm::{execute, queue};
+    use lfc::space::{self, Family};
+    use std::io::{self, IsTerminal, Write};
+
+    /// A network in the tree.
+    #[derive(Debug)]
+    struct Node {
+        net: IpNet,
+        parent: Option<usize>,
+        children: Vec<usize>,
+        /// Whether the network is in the list, rather than only a branch
+        /// over networks that are.
+        listed: bool,
+        /// How many addresses the networks under this one have.
+        addresses: u128,
+    }
+
+    /// The networks as a prefix tree.
+    #[derive(Debug)]
+    struct Tree {
+        nodes: Vec<Node>,
+        roots: Vec<usize>,
+    }
+
+    impl Tree {
+        /// Builds the tree from sorted networks without duplicates.
+        fn new(nets: &[IpNet]) -> Tree {
+            let mut tree = Tree {
+                nodes: Vec::new(),
+                roots: Vec::new(),
+            };
+            let split = nets.partition_point(|net| Family::of(net) == Family::V4);
+            for family in [&nets[..split], &nets[split..]] {
+                if !family.is_empty() {
+                    let root = tree.build(family, None);
+                    tree.roots.push(root);
+                }
+            }
+            tree
+        }
+
+        /// Adds the networks and the branches over them, and returns the
+        /// topmost one.
+        fn build(&mut self, nets: &[IpNet], parent: Option<usize>) -> usize {
+            let index = self.nodes.len();
+            let net = match nets {
+                [net] => *net,
+                _ => common(&nets[0], &nets[nets.len() - 1]),
+            };
+            // A network sorts before the networks inside it, so if one of
+            // them holds the rest, it's the first.
+            let listed = nets[0] == net;
+            self.nodes.push(Node {
+                net,
+                parent,
+                children: Vec::new(),
+                listed,
+                addresses: 0,
+            });
+            if listed {
+                self.nodes[index].addresses = space::subnet_count(&net, net.max_prefix_len());
+                if nets.len() > 1 {
+                    let child = self.build(&nets[1..], Some(index));
+                    self.nodes[index].children.push(child);
+                }
+                return index;
+            }
+            // Otherwise the smallest common network splits the networks into
+            // its two halves, with some in each.
+            let half = net.subnets(net.prefix_len() + 1).unwrap().nth(1).unwrap();
+            let split = nets.partition_point(|net| net.addr() < half.addr());
+            for part in [&nets[..split], &nets[split..]] {
+                let child = self.build(part, Some(index));
+                let addresses = self.nodes[child].addresses;
+                let node = &mut self.nodes[index];
+                node.children.push(child);
+                node.addresses = node.addresses.saturating_add(addresses);
+            }
+            index
+        }
+
+        fn is_leaf(&self, node: usize) -> bool {
+            self.nodes[node].children.is_empty()
+        }
+    }
+
+    /// Returns the smallest network that holds both networks, which are
+    /// in the same family.
+    fn common(a: &IpNet, b: &IpNet) -> IpNet {
+        let longest = a.max_prefix_len();
+        let differ = space::range(a).0 ^ space::range(b).0;
+        let same = (differ.leading_zeros() - (128 - u32::from(longest))) as u8;
+        IpNet::new(a.addr(), same.min(a.prefix_len()).min(b.prefix_len()))
+            .unwrap()
+            .trunc()
+    }
+
+    /// What the browser is doing with the keyboard.
+    #[derive(Debug, PartialEq)]
+    enum Mode {
+        Browse,
+        Search,
+    }
+
+    /// The state of the browser, apart from the terminal.
+    #[derive(Debug)]
+    struct Browser {
+        tree: Tree,
+        expanded: Vec<bool>,
+        marked: Vec<bool>,
+        /// The row the cursor is on.
+        cursor: usize,
+        /// The first row on the screen.
+        top: usize,
+        mode: Mode,
+        query: String,
+        status: String,
+    }
+
+    impl Browser {
+        fn new(nets: &[IpNet]) -> Browser {
+            let tree = Tree::new(nets);
+            let mut expanded = vec![false; tree.nodes.len()];
+            for &root in &tree.roots {
+                expanded[root] = true;
+            }
+            Browser {
+                marked: vec![false; tree.nodes.len()],
+                expanded,
+                tree,
+                cursor: 0,
+                top: 0,
+                mode: Mode::Browse,
+                query: String::new(),
+                status: String::new(),
+            }
+        }
+
+        /// Returns the nodes that are showing, in order, with their depth.
+        fn rows(&self) -> Vec<(usize, usize)> {
+            let mut rows = Vec::new();
+            let mut stack: Vec<(usize, usize)> = self
+                .tree
+                .roots
+                .iter()
+                .rev()
+                .map(|&root| (root, 0))
+                .collect();
+            while let Some((node, depth)) = stack.pop() {
+                rows.push((node, depth));
+                if self.expanded[node] {
+                    let children = &self.tree.nodes[node].children;
+                    stack.extend(children.iter().rev().map(|&child| (child, depth + 1)));
+                }
+            }
+            rows
+        }
+
+        /// Returns the node under the cursor.
+        fn current(&self) -> Option<usize> {
+            self.rows().get(self.cursor).map(|&(node, _)| node)
+        }
+
+        /// Moves the cursor by `by` rows, stopping at either end.
+        fn move_by(&mut self, by: isize) {
+            let last = self.rows().len().saturating_sub(1);
+            self.cursor = self.cursor.saturating_add_signed(by).min(last);
+        }
+
+        /// Expands the current node, or collapses it if it's expanded.
+        fn toggle(&mut self) {
+            if let Some(node) = self.current().filter(|&node| !self.tree.is_leaf(node)) {
+                self.expanded[node] = !self.expanded[node];
+            }
+        }
+
+        /// Collapses the current node, or moves to its parent if there's
+        /// nothing to collapse.
+        fn collapse(&mut self) {
+            let Some(node) = self.current() else {
+                return;
+            };
+            if self.expanded[node] && !self.tree.is_leaf(node) {
+                self.expanded[node] = false;
+            } else if let Some(parent) = self.tree.nodes[node].parent {
+                self.show(parent);
+            }
+        }
+
+        fn mark(&mut self) {
+            if let Some(node) = self.current() {
+                self.marked[node] = !self.marked[node];
+            }
+        }
+
+        /// Expands everything over the node and moves the cursor to it.
+        fn show(&mut self, node: usize) {
+            let mut parent = self.tree.nodes[node].parent;
+            while let Some(index) = parent {
+                self.expanded[index] = true;
+                parent = self.tree.nodes[index].parent;
+            }
+            self.cursor = self
+                .rows()
+                .iter()
+                .position(|&(row, _)| row == node)
+                .unwrap();
+        }
+
+        /// Returns whether the node matches the query: a network or address
+        /// that it holds or that holds it, or else the start of how it's
+        /// written.
+        fn matches(&self, node: usize, query: &str, style: &Style) -> bool {
+            let net = &self.tree.nodes[node].net;
+            let wanted = query
+                .parse::<IpNet>()
+                .ok()
+                .or_else(|| query.parse::<std::net::IpAddr>().ok().map(IpNet::from));
+            match wanted {
+                Some(wanted) => {
+                    self.tree.nodes[node].listed && (net.contains(&wanted) || wanted.contains(net))
+                }
+                None => style.display(net).to_string().starts_with(query),
+            }
+        }
+
+        /// Moves to the next node after the cursor that matches the query,
+        /// going around to the top if there isn't one. Collapsed nodes are
+        /// searched too.
+        fn search(&mut self, style: &Style) {
+            if self.query.is_empty() {
+                return;
+            }
+            let count = self.tree.nodes.len();
+            // Nodes are numbered in the order the tree lists them.
+            let start = self.current().map_or(0, |node| node + 1);
+            let found = (0..count)
+                .map(|offset| (start + offset) % count)
+                .find(|&node| self.matches(node, &self.query, style));
+            match found {
+                Some(node) => {
+                    self.show(node);
+                    self.status.clear();
+                }
+                None => self.status = format!("nothing matches {}", self.query),
+            }
+        }
+
+        /// Returns the listed networks that are marked, or under something
+        /// that is.
+        fn selection(&self) -> Vec<IpNet> {
+            let mut selection = Vec::new();
+            let mut stack: Vec<(usize, bool)> = self
+                .tree
+                .roots
+                .iter()
+                .rev()
+                .map(|&root| (root, false))
+                .collect();
+            while let Some((node, inherited)) = stack.pop() {
+                let marked = inherited || self.marked[node];
+                let children = &self.tree.nodes[node].children;
+                if self.tree.nodes[node].listed && marked {
+                    selection.push(self.tree.nodes[node].net);
+                }
+                stack.extend(children.iter().rev().map(|&child| (child, marked)));
+            }
+            selection
+        }
+
+        /// Handles a key, and returns whether the browser is done, and if
+        /// so, what to print.
+        fn key(&mut self, code: KeyCode, page: usize, style: &Style) -> Option<Option<Vec<IpNet>>> {
+            let page = page.max(1) as isize;
+            if self.mode == Mode::Search {
+                match code {
+                    KeyCode::Enter => {
+                        self.mode = Mode::Browse;
+                        self.search(style);
+                    }
+                    KeyCode::Esc => self.mode = Mode::Browse,
+                    KeyCode::Backspace => {
+                        self.query.pop();
+                    }
+                    KeyCode::Char(c) => self.query.push(c),
+                    _ => {}
+                }
+                return None;
+            }
+            self.status.clear();
+            match code {
+                KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+                KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+                KeyCode::PageUp => self.move_by(-page),
+                KeyCode::PageDown => self.move_by(page),
+                KeyCode::Home => self.cursor = 0,
+                KeyCode::End => self.move_by(isize::MAX),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => self.toggle(),
+                KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+                KeyCode::Char(' ') => self.mark(),
+                KeyCode::Char('/') => {
+                    self.mode = Mode::Search;
+                    self.query.clear();
+                }
+                KeyCode::Char('n') => self.search(style),
+                KeyCode::Char('x') => {
+                    let selection = self.selection();
+                    if !selection.is_empty() {
+                        return Some(Some(selection));
+                    }
+                    self.status = "nothing is marked".to_string();
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return Some(None),
+                _ => {}
+            }
+            None
+        }
+
+        /// Draws the browser in a terminal `width` by `height`.
+        fn draw(
+            &mut self,
+            out: &mut impl Write,
+            width: u16,
+            height: u16,
+            style: &Style,
+        ) -> io::Result<()> {
+            let rows = self.rows();
+            let page = usize::from(height.saturating_sub(2)).max(1);
+            self.cursor = self.cursor.min(rows.len().saturating_sub(1));
+            if self.cursor < self.top {
+                self.top = self.cursor;
+            } else if self.cursor >= self.top + page {
+                self.top = self.cursor + 1 - page;
+            }
+            let width = usize::from(width);
+            let networks = self.tree.nodes.iter().filter(|node| node.listed).count();
+            let marked = self.selection().len();
+            let header = format!("lfc tui: {} networks, {} marked", networks, marked);
+            queue!(
+                out,
+                MoveTo(0, 0),
+                Clear(ClearType::All),
+                Print(clip(&header, width))
+            )?;
+            for (line, &(node, depth)) in rows.iter().skip(self.top).take(page).enumerate() {
+                let text = self.row(node, depth, width, style);
+                queue!(out, MoveTo(0, line as u16 + 1))?;
+                if self.top + line == self.cursor {
+                    queue!(
+                        out,
+                        SetAttribute(Attribute::Reverse),
+                        Print(text),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(out, Print(text))?;
+                }
+            }
+            let footer = match self.mode {
+                Mode::Search => format!("/{}", self.query),
+                Mode::Browse if !self.status.is_empty() => self.status.clone(),
+                Mode::Browse => "space mark  / search  n next  x print marked  q quit".to_string(),
+            };
+            queue!(
+                out,
+                MoveTo(0, height.saturating_sub(1)),
+                Print(clip(&footer, width))
+            )?;
+            out.flush()
+        }
+
+        /// Returns a row of the tree, padded to the width.
+        fn row(&self, node: usize, depth: usize, width: usize, style: &Style) -> String {
+            let marker = if self.tree.is_leaf(node) {
+                ' '
+            } else if self.expanded[node] {
+                '-'
+            } else {
+                '+'
+            };
+            let mark = if self.marked[node] { '*' } else { ' ' };
+            let net = &self.tree.nodes[node];
+            let left = format!(
+                "{}{} {} {}",
+                "  ".repeat(depth),
+                mark,
+                marker,
+                style.display(&net.net)
+            );
+            let right = format!("{} addresses", space::Count(net.addresses));
+            let gap = width
+                .saturating_sub(left.chars().count() + right.len())
+                .max(1);
+            clip(&format!("{}{}{}", left, " ".repeat(gap), right), width)
+        }
+    }
+
+    /// Cuts the text down to the width.
+    fn clip(text: &str, width: usize) -> String {
+        text.chars().take(width).collect()
+    }
+
+    /// Puts the terminal back the way it was, even after a panic.
+    struct Screen;
+
+    impl Screen {
+        fn open() -> io::Result<Screen> {
+            terminal::enable_raw_mode()?;
+            let screen = Screen;
+            execute!(io::stderr(), EnterAlternateScreen, Hide)?;
+            Ok(screen)
+        }
+    }
+
+    impl Drop for Screen {
+        fn drop(&mut self) {
+            let _ = execute!(io::stderr(), Show, LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+
+    /// Browses the networks, which are sorted and don't overlap, and
+    /// returns the ones marked for printing, or `None` if the user quit
+    /// without printing anything.
+    pub fn run(nets: &[IpNet], style: &Style) -> Result<Option<Vec<IpNet>>, String> {
+        if !io::stderr().is_terminal() {
+            return Err("lfc tui needs stderr to be a terminal".to_string());
+        }
+        if nets.is_empty() {
+            return Err("there are no networks to browse".to_string());
+        }
+        let failed = |err: io::Error| format!("terminal error: {}", err);
+        let mut browser = Browser::new(nets);
+        let _screen = Screen::open().map_err(failed)?;
+        let mut out = io::BufWriter::new(io::stderr());
+        loop {
+            let (width, height) = terminal::size().map_err(failed)?;
+            browser
+                .draw(&mut out, width, height, style)
+                .map_err(failed)?;
+            let Event::Key(key) = event::read().map_err(failed)? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(None);
+            }
+            let page = usize::from(height.saturating_sub(2));
+            if let Some(done) = browser.key(key.code, page, style) {
+                return Ok(done);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn nets(list: &[&str]) -> Vec<IpNet> {
+            list.iter().map(|net| net.parse().unwrap()).collect()
+        }
+
+        fn shown(browser: &Browser) -> Vec<String> {
+            browser
+                .rows()
+                .iter()
+                .map(|&(node, depth)| {
+                    format!("{}{}", "  ".repeat(depth), browser.tree.nodes[node].net)
+                })
+                .collect()
+        }
+
+        fn browser() -> Browser {
+            Browser::new(&nets(&[
+                "10.0.0.0/16",
+                "10.2.0.0/16",
+                "192.168.0.0/24",
+                "2001:db8::/32",
+            ]))
+        }
+
+        #[test]
+        fn test_tree() {
+            let tree = browser().tree;
+            assert_eq!(tree.roots.len(), 2);
+            let root = &tree.nodes[tree.roots[0]];
+            assert_eq!(root.net, "0.0.0.0/0".parse().unwrap());
+            assert_eq!(root.addresses, 2 * 65536 + 256);
+            let branch = &tree.nodes[root.children[0]];
+            assert_eq!(branch.net, "10.0.0.0/14".parse().unwrap());
+            assert_eq!(branch.children.len(), 2);
+            assert!(tree.is_leaf(tree.roots[1]));
+
+            // A network holding others is the branch over them.
+            let tree = Tree::new(&nets(&[
+                "10.0.0.0/8",
+                "10.0.0.0/24",
+                "10.1.0.0/24",
+                "10.2.0.0/16",
+            ]));
+            let root = &tree.nodes[tree.roots[0]];
+            assert!(root.listed);
+            assert_eq!(root.addresses, 1 << 24);
+            let branch = &tree.nodes[root.children[0]];
+            assert_eq!(branch.net, "10.0.0.0/14".parse().unwrap());
+            assert!(!branch.listed);
+            assert_eq!(tree.nodes.iter().filter(|node| node.listed).count(), 4);
+            assert_eq!(
+                common(
+                    &"10.1.0.0/16".parse().unwrap(),
+                    &"10.1.0.0/24".parse().unwrap()
+                ),
+                "10.1.0.0/16".parse::<IpNet>().unwrap()
+            );
+        }
+
+        #[test]
+        fn test_browse() {
+            let style = Style::default();
+            let mut browser = browser();
+            assert_eq!(
+                shown(&browser),
+                vec![
+                    "0.0.0.0/0",
+                    "  10.0.0.0/14",
+                    "  192.168.0.0/24",
+                    "2001:db8::/32"
+                ]
+            );
+            browser.key(KeyCode::Down, 10, &style);
+            browser.key(KeyCode::Enter, 10, &style);
+            assert_eq!(shown(&browser)[2], "    10.0.0.0/16");
+            browser.key(KeyCode::Down, 10, &style);
+            browser.key(KeyCode::Left, 10, &style);
+            assert_eq!(browser.cursor, 1);
+            browser.key(KeyCode::Left, 10, &style);
+            assert_eq!(shown(&browser).len(), 4);
+            browser.key(KeyCode::End, 10, &style);
+            assert_eq!(browser.cursor, 3);
+        }
+
+        #[test]
+        fn test_search() {
+            let style = Style::default();
+            let mut browser = browser();
+            for code in [
+                KeyCode::Char('/'),
+                KeyCode::Char('1'),
+                KeyCode::Char('0'),
+                KeyCode::Enter,
+            ] {
+                browser.key(code, 10, &style);
+            }
+            assert_eq!(browser.current(), Some(1));
+            browser.key(KeyCode::Char('n'), 10, &style);
+            assert_eq!(shown(&browser)[browser.cursor], "    10.0.0.0/16");
+            browser.query = "10.2.3.4".to_string();
+            browser.search(&style);
+            assert_eq!(shown(&browser)[browser.cursor], "    10.2.0.0/16");
+            browser.query = "172.16.0.0/12".to_string();
+            browser.search(&style);
+            assert_eq!(browser.status, "nothing matches 172.16.0.0/12");
+        }
+
+        #[test]
+        fn test_selection() {
+            let style = Style::default();
+            let mut browser = browser();
+            assert_eq!(browser.key(KeyCode::Char('x'), 10, &style), None);
+            assert_eq!(browser.status, "nothing is marked");
+            browser.key(KeyCode::Down, 10, &style);
+            browser.key(KeyCode::Char(' '), 10, &style);
+            browser.key(KeyCode::End, 10, &style);
+            browser.key(KeyCode::Char(' '), 10, &style);
+            assert_eq!(
+                browser.key(KeyCode::Char('x'), 10, &style),
+                Some(Some(nets(&["10.0.0.0/16", "10.2.0.0/16", "2001:db8::/32"])))
+            );
+            assert_eq!(browser.key(KeyCode::Char('q'), 10, &style), Some(None));
+        }
+
+        #[test]
+        fn test_draw() {
+            let mut browser = browser();
+            let mut out = Vec::new();
+            browser.draw(&mut out, 60, 3, &Style::default()).unwrap();
+            let text = String::from_utf8_lossy(&out);
+            assert!(text.contains("lfc tui: 4 networks, 0 marked"));
+            assert!(text.contains("  - 0.0.0.0/0"));
+            assert!(!text.contains("10.0.0.0/14"));
+            assert_eq!(browser.row(3, 0, 40, &Style::default()).chars().count(), 40);
+        }
+    }
+}
+
+/// Stands in for the browser when lfc is built without it, so that callers
+/// don't need to check the feature themselves.
+#[cfg(not(feature = "tui"))]
+pub fn run(_nets: &[IpNet], _style: &Style) -> Result<Option<Vec<IpNet>>, String> {
+    Err("lfc tui needs lfc built with the tui feature".to_string())
+}
@@ -0,0 +1,161 @@
+//! Change notifications over HTTP.
+//!
+//! When `--state-dir` notices that a list changed, `--webhook URL` POSTs a
+//! JSON summary of the change to URL. With a secret, the body is signed
+//! with HMAC-SHA256 and the signature is sent in the
+//! `X-Lfc-Signature: sha256=<hex>` header, the same scheme GitHub uses, so
+//! the receiver can check that the notification really came from us.
+//!
+//! The secret comes from `--webhook-secret-file` or `LFC_WEBHOOK_SECRET`,
+//! which other users can't see the way they can see a command line, or
+//! from `--webhook-secret`.
+
+use crate::clock;
+use crate::diff::Change;
+use hmac::{Hmac, KeyInit, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+/// The header that carries the body's signature.
+pub const SIGNATURE_HEADER: &str = "X-Lfc-Signature";
+
+/// The environment variable the secret can come from.
+pub const SECRET_VARIABLE: &str = "LFC_WEBHOOK_SECRET";
+
+/// Returns the secret to sign with: `--webhook-secret` if it was given,
+/// else the contents of `--webhook-secret-file` without the newline at the
+/// end, else `LFC_WEBHOOK_SECRET`, or none at all.
+pub fn secret(given: Option<&str>, file: Option<&str>) -> Result<Option<String>, String> {
+    if let Some(secret) = given {
+        return Ok(Some(secret.to_string()));
+    }
+    if let Some(path) = file {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("unable to read the webhook secret from {}: {}", path, err))?;
+        let secret = text.trim_end_matches(['\r', '\n']);
+        if secret.is_empty() {
+            return Err(format!("{} has no webhook secret in it", path));
+        }
+        return Ok(Some(secret.to_string()));
+    }
+    Ok(std::env::var(SECRET_VARIABLE)
+        .ok()
+        .filter(|secret| !secret.is_empty()))
+}
+
+/// Builds the JSON body describing a change to the named list.
+pub fn payload(name: &str, changes: &[Change], total: usize, timestamp: u64) -> String {
+    let list = |added: bool| -> Vec<String> {
+        changes
+            .iter()
+            .filter(|change| matches!(change, Change::Added(_)) == added)
+            .map(|change| change.net().to_string())
+            .collect()
+    };
+    let (added, removed) = (list(true), list(false));
+    json!({
+        "name": name,
+        "timestamp": clock::rfc3339(timestamp),
+        "counts": {
+            "added": added.len(),
+            "removed": removed.len(),
+            "total": total,
+        },
+        "added": added,
+        "removed": removed,
+    })
+    .to_string()
+}
+
+/// Returns the hex-encoded HMAC-SHA256 signature of the body.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// POSTs the body to the URL, trying again up to `retries` more times with
+/// exponential backoff if it fails.
+pub fn send(url: &str, body: &str, secret: Option<&str>, retries: u32) -> Result<(), String> {
+    let mut attempt = 0;
+    loop {
+        let mut request = ureq::post(url).header("Content-Type", "application/json");
+        if let Some(secret) = secret {
+            request = request.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, body)));
+        }
+        match request.send(body) {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt >= retries => {
+                return Err(format!(
+                    "webhook to {} failed after {} attempts: {}",
+                    url,
+                    attempt + 1,
+                    err
+                ));
+            }
+            Err(_) => {
+                thread::sleep(Duration::from_secs(1 << attempt.min(6)));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let changes = vec![
+            Change::Added("10.0.0.0/23".parse().unwrap()),
+            Change::Removed("10.0.0.0/24".parse().unwrap()),
+            Change::Removed("10.0.1.0/24".parse().unwrap()),
+        ];
+        let body: serde_json::Value =
+            serde_json::from_str(&payload("block\"list\n", &changes, 5, 1_758_285_296)).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "name": "block\"list\n",
+                "timestamp": "2025-09-19T12:34:56Z",
+                "added": ["10.0.0.0/23"],
+                "removed": ["10.0.0.0/24", "10.0.1.0/24"],
+                "counts": {"added": 1, "removed": 2, "total": 5},
+            })
+        );
+    }
+
+    #[test]
+    fn test_secret() {
+        let path = std::env::temp_dir().join(format!("lfc-webhook-{}", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, "s3kr1t\n").unwrap();
+        let from_file = secret(None, Some(path_str));
+        let given = secret(Some("given"), Some(path_str));
+        fs::write(&path, "\n").unwrap();
+        let empty = secret(None, Some(path_str));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(from_file, Ok(Some("s3kr1t".to_string())));
+        assert_eq!(given, Ok(Some("given".to_string())));
+        assert!(empty.unwrap_err().contains("has no webhook secret"));
+        assert!(secret(None, Some(path_str)).is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! IPv6 zone IDs, like the `%eth0` in `fe80::1%eth0/64`.
+//!
+//! A zone ID says which link a link-local address belongs to. It isn't part
+//! of the network, so ipnet doesn't parse it, and by default a line with one
+//! is an error like any other bad line. `--zones strip` drops the zone IDs
+//! before parsing, and `--zones group` sets those lines aside and aggregates
+//! each zone's networks on their own.
+//!
+//! Both work on the text before it's parsed. The rewritten inputs keep every
+//! line where it was, so errors still point at the right line of the
+//! original input.
+
+use ipnet::IpNet;
+use lfc::format::Style;
+use lfc::source::{InputSource, MemorySource};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// What to do with networks that have zone IDs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Zones {
+    /// Treat them as bad input.
+    #[default]
+    Reject,
+    /// Drop the zone IDs and aggregate the networks with everything else.
+    Strip,
+    /// Aggregate each zone's networks separately, and keep the zone IDs.
+    Group,
+}
+
+impl std::str::FromStr for Zones {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Zones::Reject),
+            "strip" => Ok(Zones::Strip),
+            "group" => Ok(Zones::Group),
+            _ => Err(format!(
+                "invalid zone handling '{}' (expected reject, strip, or group)",
+                s
+            )),
+        }
+    }
+}
+
+/// Splits a network like `fe80::1%eth0/64` into `fe80::1/64` and `eth0`.
+/// Returns `None` if there's no zone ID.
+pub fn split(text: &str) -> Option<(String, &str)> {
+    let (address, rest) = text.split_once('%')?;
+    let (zone, net) = match rest.split_once('/') {
+        Some((zone, prefix)) => (zone, format!("{}/{}", address, prefix)),
+        None => (rest, address.to_string()),
+    };
+    (!zone.is_empty()).then_some((net, zone))
+}
+
+/// The inputs, rewritten line by line. Each rewritten line replaces the
+/// original, and lines that `rewrite` returns `None` for are left blank.
+fn rewrite_inputs<F>(
+    inputs: &[Box<dyn InputSource>],
+    mut rewrite: F,
+) -> lfc::Result<Vec<Box<dyn InputSource>>>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut rewritten: Vec<Box<dyn InputSource>> = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let mut text = String::with_capacity(input.bytes().len());
+        for record in input.records() {
+            let record = record?;
+            if let Some(line) = rewrite(record.text) {
+                text.push_str(&line);
+            }
+            text.push('\n');
+        }
+        rewritten.push(Box::new(MemorySource::new(input.name(), text)));
+    }
+    Ok(rewritten)
+}
+
+/// Returns whether any input has a `%` in it, so that inputs without zone
+/// IDs can skip being rewritten.
+fn any_zones(inputs: &[Box<dyn InputSource>]) -> bool {
+    inputs
+        .iter()
+        .any(|input| memchr::memchr(b'%', input.bytes()).is_some())
+}
+
+/// Drops the zone IDs from every line.
+pub fn strip(inputs: Vec<Box<dyn InputSource>>) -> lfc::Result<Vec<Box<dyn InputSource>>> {
+    if !any_zones(&inputs) {
+        return Ok(inputs);
+    }
+    rewrite_inputs(&inputs, |line| {
+        Some(match split(line.trim()) {
+            Some((net, _)) => net,
+            None => line.to_string(),
+        })
+    })
+}
+
+/// The inputs without the lines that have zone IDs, and for each zone, the
+/// inputs with only that zone's lines, without their zone IDs.
+pub type Grouped = (
+    Vec<Box<dyn InputSource>>,
+    BTreeMap<String, Vec<Box<dyn InputSource>>>,
+);
+
+/// Sets the lines with zone IDs aside, by zone.
+pub fn group(inputs: Vec<Box<dyn InputSource>>) -> lfc::Result<Grouped> {
+    if !any_zones(&inputs) {
+        return Ok((inputs, BTreeMap::new()));
+    }
+    let mut names = BTreeSet::new();
+    for input in &inputs {
+        for record in input.records() {
+            if let Some((_, zone)) = split(record?.text.trim()) {
+                names.insert(zone.to_string());
+            }
+        }
+    }
+    let rest = rewrite_inputs(&inputs, |line| {
+        split(line.trim()).is_none().then(|| line.to_string())
+    })?;
+    let mut zones = BTreeMap::new();
+    for name in names {
+        let only = rewrite_inputs(&inputs, |line| {
+            split(line.trim())
+                .filter(|(_, zone)| *zone == name)
+                .map(|(net, _)| net)
+        })?;
+        zones.insert(name, only);
+    }
+    Ok((rest, zones))
+}
+
+/// Writes a zone's networks one per line, with the zone ID after the
+/// address, like `fe80::%eth0/64`.
+pub fn write(out: &mut dyn Write, zone: &str, nets: &[IpNet], style: &Style) -> io::Result<()> {
+    for net in nets {
+        let text = style.display(net).to_string();
+        match text.split_once('/') {
+            Some((address, prefix)) => writeln!(out, "{}%{}/{}", address, zone, prefix)?,
+            None => writeln!(out, "{}%{}", text, zone)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(texts: &[&str]) -> Vec<Box<dyn InputSource>> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                Box::new(MemorySource::new(&format!("list{}", i), text.to_string()))
+                    as Box<dyn InputSource>
+            })
+            .collect()
+    }
+
+    fn lines(input: &dyn InputSource) -> Vec<&str> {
+        input.records().map(|record| record.unwrap().text).collect()
+    }
+
+    #[test]
+    fn test_parse_zones() {
+        assert_eq!("strip".parse(), Ok(Zones::Strip));
+        assert_eq!("group".parse(), Ok(Zones::Group));
+        assert!("keep".parse::<Zones>().is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("fe80::1%eth0/64"),
+            Some(("fe80::1/64".to_string(), "eth0"))
+        );
+        assert_eq!(split("fe80::1%3"), Some(("fe80::1".to_string(), "3")));
+        assert_eq!(split("fe80::/64"), None);
+        assert_eq!(split("fe80::1%/64"), None);
+    }
+
+    #[test]
+    fn test_strip() {
+        let stripped = strip(inputs(&["fe80::1%eth0/64\n\n10.0.0.0/8\n"])).unwrap();
+        assert_eq!(
+            lines(stripped[0].as_ref()),
+            vec!["fe80::1/64", "", "10.0.0.0/8"]
+        );
+    }
+
+    #[test]
+    fn test_group() {
+        let (rest, zones) = group(inputs(&[
+            "fe80::%eth0/64\n10.0.0.0/8\nfe80::%eth1/64\n",
+            "fe80:0:0:1::%eth0/64\n",
+        ]))
+        .unwrap();
+        assert_eq!(lines(rest[0].as_ref()), vec!["", "10.0.0.0/8", ""]);
+        assert_eq!(lines(rest[1].as_ref()), vec![""]);
+        assert_eq!(zones.keys().collect::<Vec<_>>(), vec!["eth0", "eth1"]);
+        let eth0 = &zones["eth0"];
+        assert_eq!(eth0[0].name(), "list0");
+        assert_eq!(lines(eth0[0].as_ref()), vec!["fe80::/64", "", ""]);
+        assert_eq!(lines(eth0[1].as_ref()), vec!["fe80:0:0:1::/64"]);
+    }
+
+    #[test]
+    fn test_write() {
+        let nets = vec!["fe80::/63".parse().unwrap()];
+        let mut out = Vec::new();
+        write(&mut out, "eth0", &nets, &Style::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "fe80::%eth0/63\n");
+    }
+}